@@ -0,0 +1,186 @@
+use crate::parquet_extract::snake_case;
+use polars::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Column layout for extract-parquet, read from a TOML or JSON file (`--columns`). Without one
+/// every built-in column is written under its usual name, so an empty spec is the default
+/// schema:
+///
+/// ```toml
+/// keep = ["match_id", "puuid", "champion_name", "win", "solo_kills"]
+///
+/// [rename]
+/// champion_name = "champion"
+///
+/// [[participant]]
+/// path = "challenges.soloKills"
+/// type = "int"
+///
+/// [[team]]
+/// path = "objectives.horde.kills"
+/// name = "voidgrubs"
+/// type = "int"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnSpec {
+    /// Columns to write, in this order; every column when empty. Applied before `rename`.
+    pub keep: Vec<String>,
+    /// New output names, keyed by column name.
+    pub rename: BTreeMap<String, String>,
+    /// Raw participant fields added to `--level player` rows.
+    pub participant: Vec<FieldSpec>,
+    /// Raw team fields added to `--level team` rows, and to both sides at `--level match`.
+    pub team: Vec<FieldSpec>,
+}
+
+/// One extra column read straight from the match JSON.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldSpec {
+    /// Dot-separated path inside the participant or team object (`challenges.soloKills`).
+    pub path: String,
+    /// Output column name; the snake-cased last path segment when unset.
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: FieldType,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Int,
+    Float,
+    Bool,
+    String,
+}
+
+impl ColumnSpec {
+    /// Reads `path` as JSON when it ends in `.json`, TOML otherwise.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let raw = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        let spec: Self = if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+        {
+            serde_json::from_str(&raw)
+                .map_err(|err| format!("Invalid {}: {}", path.display(), err))?
+        } else {
+            toml::from_str(&raw).map_err(|err| format!("Invalid {}: {}", path.display(), err))?
+        };
+
+        for fields in [&spec.participant, &spec.team] {
+            let mut names = HashSet::new();
+            for field in fields {
+                if field.path.split('.').any(str::is_empty) {
+                    return Err(format!(
+                        "Invalid field path '{}' in {}",
+                        field.path,
+                        path.display()
+                    )
+                    .into());
+                }
+                if !names.insert(field.column_name()) {
+                    return Err(format!(
+                        "Column '{}' is declared twice in {}",
+                        field.column_name(),
+                        path.display()
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(spec)
+    }
+
+    /// Whether rows need the raw JSON kept around to read extra fields.
+    pub fn reads_raw_fields(&self) -> bool {
+        !self.participant.is_empty() || !self.team.is_empty()
+    }
+
+    /// Keeps and renames the columns of `df` as the spec asks.
+    pub fn apply(&self, df: DataFrame) -> Result<DataFrame, Box<dyn Error>> {
+        let mut df = if self.keep.is_empty() {
+            df
+        } else {
+            let available = df.get_column_names();
+            let missing: Vec<&str> = self
+                .keep
+                .iter()
+                .map(String::as_str)
+                .filter(|name| !available.contains(name))
+                .collect();
+            if !missing.is_empty() {
+                return Err(
+                    format!("Column spec keeps unknown columns: {}", missing.join(", ")).into(),
+                );
+            }
+            df.select(&self.keep)?
+        };
+
+        for (from, to) in &self.rename {
+            if df.get_column_index(from).is_none() {
+                return Err(format!("Column spec renames unknown column '{}'", from).into());
+            }
+            df.rename(from, to)?;
+        }
+        Ok(df)
+    }
+}
+
+impl FieldSpec {
+    pub fn column_name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| snake_case(self.path.rsplit('.').next().unwrap_or_default()))
+    }
+
+    /// The value at `path` inside `object`; `Null` when any segment is missing.
+    pub fn lookup(&self, object: Option<&Value>) -> Value {
+        object
+            .and_then(|object| {
+                self.path
+                    .split('.')
+                    .try_fold(object, |value, key| value.get(key))
+            })
+            .cloned()
+            .unwrap_or(Value::Null)
+    }
+
+    /// Builds the column from one looked-up value per row. Values of another JSON type become
+    /// nulls, except that `string` columns keep the JSON text of non-string values.
+    pub fn series(&self, values: &[Value]) -> Series {
+        let name = self.column_name();
+        match self.kind {
+            FieldType::Int => {
+                let values: Vec<Option<i64>> = values.iter().map(Value::as_i64).collect();
+                Series::new(&name, values)
+            }
+            FieldType::Float => {
+                let values: Vec<Option<f64>> = values.iter().map(Value::as_f64).collect();
+                Series::new(&name, values)
+            }
+            FieldType::Bool => {
+                let values: Vec<Option<bool>> = values.iter().map(Value::as_bool).collect();
+                Series::new(&name, values)
+            }
+            FieldType::String => {
+                let values: Vec<Option<String>> = values
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => None,
+                        Value::String(text) => Some(text.clone()),
+                        other => Some(other.to_string()),
+                    })
+                    .collect();
+                Series::new(&name, values)
+            }
+        }
+    }
+}
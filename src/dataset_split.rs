@@ -0,0 +1,393 @@
+use crate::parquet_extract::ParquetOptions;
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, NaiveDate, Utc};
+use polars::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+pub const SPLIT_MANIFEST_FILE: &str = "split_manifest.json";
+
+const SPLITS: [&str; 3] = ["train", "validation", "test"];
+
+/// Where the chronological split boundaries fall.
+#[derive(Debug, Clone, Copy)]
+pub enum SplitPlan {
+    /// Shares of the matches for train, validation and test, oldest matches first.
+    Fractions([f64; 3]),
+    /// Validation starts at the first timestamp and test at the second (epoch milliseconds).
+    Cutoffs([i64; 2]),
+}
+
+impl SplitPlan {
+    /// Accepts `TRAIN,VALIDATION,TEST` shares such as `0.7,0.15,0.15` (or `70,15,15`); they are
+    /// scaled to sum to one.
+    pub fn parse_fractions(raw: &str) -> Result<Self, String> {
+        let shares: Vec<f64> = raw
+            .split(',')
+            .map(|share| share.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("Invalid fractions '{}' (use e.g. 0.7,0.15,0.15)", raw))?;
+        let total: f64 = shares.iter().sum();
+        if shares.len() != 3
+            || shares
+                .iter()
+                .any(|share| !share.is_finite() || *share < 0.0)
+            || shares[0] <= 0.0
+        {
+            return Err(format!(
+                "Invalid fractions '{}' (three non-negative shares, train above zero)",
+                raw
+            ));
+        }
+        Ok(SplitPlan::Fractions([
+            shares[0] / total,
+            shares[1] / total,
+            shares[2] / total,
+        ]))
+    }
+
+    /// Boundaries from two `YYYY-MM-DD` dates (UTC midnight), in order.
+    pub fn from_dates(validation_from: NaiveDate, test_from: NaiveDate) -> Result<Self> {
+        if test_from < validation_from {
+            bail!(
+                "--test-from ({}) is before --validation-from ({})",
+                test_from,
+                validation_from
+            );
+        }
+        let millis = |date: NaiveDate| {
+            date.and_hms_opt(0, 0, 0)
+                .expect("midnight is a valid time")
+                .and_utc()
+                .timestamp_millis()
+        };
+        Ok(SplitPlan::Cutoffs([
+            millis(validation_from),
+            millis(test_from),
+        ]))
+    }
+}
+
+pub fn parse_date(raw: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date '{}' (use YYYY-MM-DD)", raw))
+}
+
+#[derive(Debug, Clone)]
+pub struct SplitDatasetArgs {
+    /// Parquets to split; each needs a `match_id` column.
+    pub inputs: Vec<PathBuf>,
+    /// `train/`, `validation/` and `test/` are created here, holding every input under its own
+    /// file name, next to the split manifest.
+    pub out_dir: PathBuf,
+    pub plan: SplitPlan,
+    pub parquet: ParquetOptions,
+}
+
+#[derive(Serialize)]
+struct SplitManifest {
+    created_at: String,
+    /// How the boundaries were chosen: the requested shares, or `null` for fixed dates.
+    fractions: Option<[f64; 3]>,
+    /// First start time in validation and in test; `null` when a share leaves the split empty.
+    validation_from: Option<String>,
+    test_from: Option<String>,
+    splits: Vec<SplitRange>,
+    files: Vec<SplitFile>,
+}
+
+#[derive(Serialize)]
+struct SplitRange {
+    split: &'static str,
+    matches: usize,
+    first_game: Option<String>,
+    last_game: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SplitFile {
+    input: String,
+    split: &'static str,
+    path: String,
+    rows: usize,
+}
+
+/// Splits `inputs` chronologically by `game_creation` into train, validation and test, with
+/// every match in exactly one split across all inputs so player, team and lobby files of the
+/// same match never land on both sides of a boundary. Inputs without `game_creation` take the
+/// time of their matches from the others; rows of matches with no known time are dropped and
+/// reported. Writes [`SPLIT_MANIFEST_FILE`] describing the
+/// boundaries and outputs.
+pub fn split_dataset(args: &SplitDatasetArgs) -> Result<()> {
+    if args.inputs.is_empty() {
+        bail!("No input Parquet to split");
+    }
+    let mut file_names = HashSet::new();
+    for input in &args.inputs {
+        let name = input
+            .file_name()
+            .ok_or_else(|| anyhow!("{} is not a file", input.display()))?;
+        if !file_names.insert(name.to_os_string()) {
+            bail!(
+                "Two inputs are named {}; split them into separate --out-dir",
+                name.to_string_lossy()
+            );
+        }
+    }
+
+    let frames: Vec<LazyFrame> = args
+        .inputs
+        .iter()
+        .map(|input| {
+            LazyFrame::scan_parquet(input.to_string_lossy().as_ref(), ScanArgsParquet::default())
+        })
+        .collect::<PolarsResult<_>>()?;
+
+    // Earliest known start per match, from every input that has one.
+    let mut match_times: HashMap<String, i64> = HashMap::new();
+    for (input, lf) in args.inputs.iter().zip(&frames) {
+        let schema = lf.schema()?;
+        if !schema.contains("match_id") {
+            bail!("{} has no match_id column", input.display());
+        }
+        if !schema.contains("game_creation") {
+            continue;
+        }
+        let times = lf
+            .clone()
+            .group_by([col("match_id")])
+            .agg([col("game_creation").cast(DataType::Int64).min()])
+            .collect()?;
+        for (match_id, time) in times
+            .column("match_id")?
+            .str()?
+            .into_iter()
+            .zip(times.column("game_creation")?.i64()?)
+        {
+            if let (Some(match_id), Some(time)) = (match_id, time) {
+                let entry = match_times.entry(match_id.to_string()).or_insert(time);
+                *entry = (*entry).min(time);
+            }
+        }
+    }
+    if match_times.is_empty() {
+        bail!("No input has game_creation values to split on");
+    }
+
+    let mut times: Vec<i64> = match_times.values().copied().collect();
+    times.sort_unstable();
+    let [validation_from, test_from] = match args.plan {
+        // The boundary is the start time of the first match past each share, so matches
+        // played at the same moment stay together.
+        SplitPlan::Fractions([train, validation, _]) => {
+            let at = |share: f64| {
+                let idx = (share * times.len() as f64).round() as usize;
+                times.get(idx).copied().unwrap_or(i64::MAX)
+            };
+            [at(train), at(train + validation)]
+        }
+        SplitPlan::Cutoffs(cutoffs) => cutoffs,
+    };
+    let split_of = |time: i64| {
+        if time < validation_from {
+            SPLITS[0]
+        } else if time < test_from {
+            SPLITS[1]
+        } else {
+            SPLITS[2]
+        }
+    };
+
+    let mut ranges: BTreeMap<&str, (usize, i64, i64)> = BTreeMap::new();
+    for time in &times {
+        let range = ranges
+            .entry(split_of(*time))
+            .or_insert((0, i64::MAX, i64::MIN));
+        range.0 += 1;
+        range.1 = range.1.min(*time);
+        range.2 = range.2.max(*time);
+    }
+
+    let (match_ids, splits): (Vec<&str>, Vec<&str>) = match_times
+        .iter()
+        .map(|(match_id, time)| (match_id.as_str(), split_of(*time)))
+        .unzip();
+    let assignment = DataFrame::new(vec![
+        Series::new("match_id", match_ids),
+        Series::new("__split", splits),
+    ])?
+    .lazy();
+
+    let mut files = Vec::new();
+    for (input, lf) in args.inputs.iter().zip(&frames) {
+        let rows = lf
+            .clone()
+            .select([len()])
+            .collect()?
+            .column("len")?
+            .u32()?
+            .get(0);
+        let assigned = lf.clone().join(
+            assignment.clone(),
+            [col("match_id")],
+            [col("match_id")],
+            JoinArgs::new(JoinType::Inner),
+        );
+        let mut written = 0;
+        for split in SPLITS {
+            let mut df = assigned
+                .clone()
+                .filter(col("__split").eq(lit(split)))
+                .drop(["__split"])
+                .collect()?;
+            let dir = args.out_dir.join(split);
+            fs::create_dir_all(&dir)?;
+            let path = dir.join(input.file_name().unwrap_or_default());
+            let mut file = File::create(&path)?;
+            args.parquet.writer(&mut file).finish(&mut df)?;
+            written += df.height();
+            files.push(SplitFile {
+                input: input.display().to_string(),
+                split,
+                path: path.display().to_string(),
+                rows: df.height(),
+            });
+        }
+        let dropped = (rows.unwrap_or(0) as usize).saturating_sub(written);
+        if dropped > 0 {
+            eprintln!(
+                "Dropped {} rows of {} whose match has no game_creation in any input",
+                dropped,
+                input.display()
+            );
+        }
+    }
+
+    let timestamp = |millis: i64| {
+        (millis != i64::MAX)
+            .then(|| DateTime::<Utc>::from_timestamp_millis(millis))
+            .flatten()
+            .map(|dt| dt.to_rfc3339())
+    };
+    let splits: Vec<SplitRange> = SPLITS
+        .iter()
+        .map(|split| {
+            let range = ranges.get(split);
+            SplitRange {
+                split,
+                matches: range.map_or(0, |range| range.0),
+                first_game: range.and_then(|range| timestamp(range.1)),
+                last_game: range.and_then(|range| timestamp(range.2)),
+            }
+        })
+        .collect();
+    let manifest = SplitManifest {
+        created_at: Utc::now().to_rfc3339(),
+        fractions: match args.plan {
+            SplitPlan::Fractions(fractions) => Some(fractions),
+            SplitPlan::Cutoffs(_) => None,
+        },
+        validation_from: timestamp(validation_from),
+        test_from: timestamp(test_from),
+        splits,
+        files,
+    };
+    fs::write(
+        args.out_dir.join(SPLIT_MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+
+    for split in &manifest.splits {
+        println!(
+            "{:<10} {:>8} matches  {} -> {}",
+            split.split,
+            split.matches,
+            split.first_game.as_deref().unwrap_or("-"),
+            split.last_game.as_deref().unwrap_or("-")
+        );
+    }
+    println!(
+        "Wrote {} files and {}",
+        manifest.files.len(),
+        args.out_dir.join(SPLIT_MANIFEST_FILE).display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn write_parquet(path: &std::path::Path, mut df: DataFrame) {
+        ParquetWriter::new(File::create(path).unwrap())
+            .finish(&mut df)
+            .unwrap();
+    }
+
+    fn match_ids(path: PathBuf) -> Vec<String> {
+        let df = ParquetReader::new(File::open(path).unwrap())
+            .finish()
+            .unwrap()
+            .sort(["match_id"], false, false)
+            .unwrap();
+        df.column("match_id")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn matches_stay_in_one_split_across_inputs() {
+        let dir = TempDir::new("split");
+        let players = dir.path().join("players.parquet");
+        let lobbies = dir.path().join("lobbies.parquet");
+        write_parquet(
+            &players,
+            df!(
+                "match_id" => ["M1", "M2", "M3", "M4", "M4"],
+                "game_creation" => [1i64, 2, 3, 4, 4],
+            )
+            .unwrap(),
+        );
+        // No game_creation: takes the times of the player file.
+        write_parquet(
+            &lobbies,
+            df!("match_id" => ["M1", "M2", "M3", "M4"]).unwrap(),
+        );
+        let out_dir = dir.path().join("splits");
+
+        split_dataset(&SplitDatasetArgs {
+            inputs: vec![players, lobbies],
+            out_dir: out_dir.clone(),
+            plan: SplitPlan::parse_fractions("0.5,0.25,0.25").unwrap(),
+            parquet: ParquetOptions::default(),
+        })
+        .unwrap();
+        let split = |split: &str, file: &str| match_ids(out_dir.join(split).join(file));
+        let players_by_split = [
+            split("train", "players.parquet"),
+            split("validation", "players.parquet"),
+            split("test", "players.parquet"),
+        ];
+        let lobbies_by_split = [
+            split("train", "lobbies.parquet"),
+            split("validation", "lobbies.parquet"),
+            split("test", "lobbies.parquet"),
+        ];
+        let manifest = out_dir.join(SPLIT_MANIFEST_FILE).exists();
+
+        assert_eq!(
+            players_by_split,
+            [vec!["M1", "M2"], vec!["M3"], vec!["M4", "M4"]]
+        );
+        assert_eq!(lobbies_by_split, [vec!["M1", "M2"], vec!["M3"], vec!["M4"]]);
+        assert!(manifest);
+    }
+}
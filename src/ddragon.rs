@@ -0,0 +1,178 @@
+use crate::kraken::write_json_atomic;
+use crate::kraken_manifest::patch_from_version;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const DDRAGON_URL: &str = "https://ddragon.leagueoflegends.com";
+pub const CHAMPIONS_CACHE_FILE: &str = "champions.json";
+
+/// Static champion metadata from Data Dragon, keyed by champion ID.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChampionTable {
+    /// Data Dragon version the table was built from.
+    pub version: String,
+    pub champions: BTreeMap<i32, ChampionInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChampionInfo {
+    pub name: String,
+    /// First Data Dragon tag (`Fighter`, `Mage`, ...).
+    pub class: Option<String>,
+    pub secondary_class: Option<String>,
+    /// `partype`: `Mana`, `Energy`, `None`, ...
+    pub resource: Option<String>,
+    /// Major.minor patch of the first Data Dragon version listing the champion; `None` when it
+    /// predates the oldest version available.
+    pub release_patch: Option<String>,
+}
+
+impl ChampionTable {
+    pub fn get(&self, champion_id: i32) -> Option<&ChampionInfo> {
+        self.champions.get(&champion_id)
+    }
+}
+
+#[derive(Deserialize)]
+struct ChampionFile {
+    data: HashMap<String, ChampionEntry>,
+}
+
+#[derive(Deserialize)]
+struct ChampionEntry {
+    key: String,
+    name: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    partype: Option<String>,
+}
+
+/// Loads the champion table cached in `cache_dir`, building it from Data Dragon on first use.
+/// Delete the cache file to pick up new champions.
+pub fn load_champions(cache_dir: &Path) -> Result<ChampionTable, Box<dyn Error>> {
+    let cache_path = cache_dir.join(CHAMPIONS_CACHE_FILE);
+    if cache_path.exists() {
+        let raw = fs::read(&cache_path)?;
+        return serde_json::from_slice(&raw)
+            .map_err(|err| format!("Invalid {}: {}", cache_path.display(), err).into());
+    }
+
+    let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let table = fetch_champions(&client)
+        .map_err(|err| format!("Failed to build the Data Dragon champion table: {}", err))?;
+    fs::create_dir_all(cache_dir)?;
+    write_json_atomic(&cache_path, &table, true)?;
+    eprintln!(
+        "Cached {} champions from Data Dragon {} in {}",
+        table.champions.len(),
+        table.version,
+        cache_path.display()
+    );
+    Ok(table)
+}
+
+fn fetch_champions(client: &Client) -> Result<ChampionTable, Box<dyn Error>> {
+    let versions: Vec<String> = client
+        .get(format!("{}/api/versions.json", DDRAGON_URL))
+        .send()?
+        .error_for_status()?
+        .json()?;
+    // Oldest first, skipping the `lolpatch_*` entries.
+    let mut versions: Vec<String> = versions
+        .into_iter()
+        .filter(|version| version.split('.').all(|part| part.parse::<u32>().is_ok()))
+        .rev()
+        .collect();
+    let latest = versions
+        .last()
+        .cloned()
+        .ok_or("Data Dragon listed no versions")?;
+
+    let current = fetch_champion_file(client, &latest)?;
+    let mut rosters: HashMap<String, HashSet<i32>> = HashMap::new();
+    rosters.insert(latest.clone(), roster(&current));
+
+    let mut champions = BTreeMap::new();
+    for entry in current.data.into_values() {
+        let Ok(champion_id) = entry.key.parse::<i32>() else {
+            continue;
+        };
+        let first = first_version_with(client, champion_id, &mut versions, &mut rosters)?;
+        let mut tags = entry.tags.into_iter();
+        champions.insert(
+            champion_id,
+            ChampionInfo {
+                name: entry.name,
+                class: tags.next(),
+                secondary_class: tags.next(),
+                resource: entry.partype.filter(|resource| !resource.is_empty()),
+                release_patch: first.map(|version| patch_from_version(&version)),
+            },
+        );
+    }
+
+    Ok(ChampionTable {
+        version: latest,
+        champions,
+    })
+}
+
+fn fetch_champion_file(client: &Client, version: &str) -> Result<ChampionFile, Box<dyn Error>> {
+    Ok(client
+        .get(format!(
+            "{}/cdn/{}/data/en_US/champion.json",
+            DDRAGON_URL, version
+        ))
+        .send()?
+        .error_for_status()?
+        .json()?)
+}
+
+fn roster(file: &ChampionFile) -> HashSet<i32> {
+    file.data
+        .values()
+        .filter_map(|entry| entry.key.parse().ok())
+        .collect()
+}
+
+/// Oldest version listing `champion_id`, found by bisecting `versions` (champions are never
+/// removed). Versions whose file cannot be fetched are dropped from `versions`; `None` when the
+/// champion is already in the oldest one.
+fn first_version_with(
+    client: &Client,
+    champion_id: i32,
+    versions: &mut Vec<String>,
+    rosters: &mut HashMap<String, HashSet<i32>>,
+) -> Result<Option<String>, Box<dyn Error>> {
+    'search: loop {
+        // The newest version is known to list the champion.
+        let (mut low, mut high) = (0, versions.len() - 1);
+        while low < high {
+            let mid = (low + high) / 2;
+            let version = versions[mid].clone();
+            if !rosters.contains_key(&version) {
+                match fetch_champion_file(client, &version) {
+                    Ok(file) => {
+                        rosters.insert(version.clone(), roster(&file));
+                    }
+                    Err(err) => {
+                        eprintln!("Skipping Data Dragon {}: {}", version, err);
+                        versions.remove(mid);
+                        continue 'search;
+                    }
+                }
+            }
+            if rosters[&version].contains(&champion_id) {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        return Ok((low > 0).then(|| versions[low].clone()));
+    }
+}
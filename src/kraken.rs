@@ -1,96 +1,452 @@
-use crate::riot_api::RiotClient;
+use crate::kraken_dedup::{DedupMode, SeenSet};
+use crate::kraken_frontier::{KrakenFrontier, RankFilter, tier_score};
+use crate::kraken_manifest::{
+    KrakenManifest, KrakenRanks, ManifestRow, RankRow, parse_queues, patch_from_version,
+    queue_label, read_manifest,
+};
+use crate::kraken_notify;
+use crate::kraken_retry::{FAILED_FILE, FailedMatch, append_failed, remove_failed};
+use crate::kraken_tui::{DashboardSnapshot, KrakenDashboard};
+use crate::riot_api::{RankedEntry, RiotClient, max_sustained_reqs_per_2min};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::error::Error;
+use std::fmt;
 use std::fs;
-use std::io::BufRead;
-use std::path::PathBuf;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
 use std::time::{Duration, Instant};
 
+// Players whose match list fails this many times are blacklisted for the rest of the run.
+const MAX_PLAYER_ERRORS: usize = 3;
+
+// Error messages kept for the `--tui` error panel.
+const MAX_RECENT_ERRORS: usize = 20;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum KrakenMode {
     Explore,
     Focus,
     SeedOnly,
+    /// Explore, but detect recurring premade partners and crawl them next.
+    Duo,
+}
+
+// Frontier priority of detected duo partners; above any tier-based priority.
+const DUO_PRIORITY: u32 = 100;
+
+/// Whose position `--role-focus` checks before a match is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RoleFocusScope {
+    /// Any of the ten participants (the original behaviour, which keeps nearly every match).
+    Any,
+    /// The player whose match history is being crawled.
+    CrawledPlayer,
 }
 
 #[derive(Debug, Clone)]
 pub struct KrakenAbsorbArgs {
     pub seed_puuid: Option<String>,
     pub seed_file: Option<PathBuf>,
+    pub exclude_puuid_file: Option<PathBuf>,
     pub duration_mins: u64,
     pub out_dir: PathBuf,
     pub max_req_per_2min: usize,
     pub max_matches_per_player: usize,
     pub max_matches_total: Option<usize>,
+    pub target_players: Option<usize>,
+    pub min_matches_per_player: usize,
+    pub min_recent_games: Option<usize>,
+    pub activity_window_days: u64,
+    /// Oldest games to collect; defaults to the last 90 days.
+    pub since: Option<TimeBound>,
+    pub until: Option<TimeBound>,
+    pub duo_min_games: usize,
+    pub continue_from: Option<PathBuf>,
+    pub continue_frontier: bool,
     pub idle_exit_after_mins: Option<u64>,
     pub mode: String,
     pub role_focus: Option<String>,
+    pub role_focus_scope: String,
+    pub champion_focus: Option<String>,
     pub allow_ranks: Option<String>,
     pub log_interval_secs: u64,
+    pub workers: usize,
+    pub regions: Option<Vec<String>>,
+    pub max_depth: Option<u32>,
+    pub max_new_players_per_match: Option<usize>,
+    pub prioritize_low_coverage_threshold: usize,
+    pub sample_rate: f64,
+    pub layout: String,
+    pub dedup: String,
+    pub dedup_fp_rate: f64,
+    pub resume: bool,
+    pub daemon_every: Option<Duration>,
+    pub repoll_seeds_every: Option<Duration>,
+    pub notify_url: Option<String>,
+    pub notify_format: String,
+    pub tui: bool,
+    /// Comma-separated queue IDs or labels (`soloq,flex`) to keep.
+    pub queues: String,
+    /// Append the queue label to match file names (`EUW1_123_flex.json`).
+    pub queue_suffix: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct KrakenEatArgs {
     pub seed_puuid: String,
     pub out_dir: PathBuf,
-    pub duration_mins: Option<u64>,
+    pub duration_mins: u64,
+    pub max_req_per_2min: usize,
+    pub max_matches_per_player: usize,
+    pub max_matches_total: Option<usize>,
+    pub workers: usize,
+}
+
+/// Defaults for `kraken-eat`; individual values can still be overridden on the command line.
+#[derive(Debug, Clone)]
+pub struct KrakenEatPreset {
+    pub duration_mins: u64,
+    pub max_req_per_2min: usize,
+    pub max_matches_per_player: usize,
+    pub max_matches_total: Option<usize>,
+    pub workers: usize,
+}
+
+impl KrakenEatPreset {
+    /// `light` fits a development key, `standard` and `aggressive` assume a production key.
+    pub fn by_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "light" => Ok(Self {
+                duration_mins: 10,
+                max_req_per_2min: 60,
+                max_matches_per_player: 20,
+                max_matches_total: Some(1000),
+                workers: 1,
+            }),
+            "standard" => Ok(Self {
+                duration_mins: 30,
+                max_req_per_2min: 500,
+                max_matches_per_player: 30,
+                max_matches_total: Some(10_000),
+                workers: 2,
+            }),
+            "aggressive" => Ok(Self {
+                duration_mins: 120,
+                max_req_per_2min: max_sustained_reqs_per_2min(),
+                max_matches_per_player: 50,
+                max_matches_total: None,
+                workers: 4,
+            }),
+            other => Err(format!(
+                "Unknown preset '{}' (use light, standard or aggressive)",
+                other
+            )),
+        }
+    }
 }
 
 pub fn kraken_eat_run(args: &KrakenEatArgs, client: &RiotClient) -> Result<(), Box<dyn Error>> {
     let absorb_args = KrakenAbsorbArgs {
         seed_puuid: Some(args.seed_puuid.clone()),
         seed_file: None,
-        duration_mins: args.duration_mins.unwrap_or(10),
+        exclude_puuid_file: None,
+        duration_mins: args.duration_mins,
         out_dir: args.out_dir.clone(),
-        max_req_per_2min: 60,
-        max_matches_per_player: 20,
-        max_matches_total: Some(1000),
+        max_req_per_2min: args.max_req_per_2min,
+        max_matches_per_player: args.max_matches_per_player,
+        max_matches_total: args.max_matches_total,
+        target_players: None,
+        min_matches_per_player: 1,
+        min_recent_games: None,
+        activity_window_days: 14,
+        since: None,
+        until: None,
+        duo_min_games: 3,
+        continue_from: None,
+        continue_frontier: false,
         idle_exit_after_mins: Some(10),
         mode: "explore".to_string(),
         role_focus: None,
+        role_focus_scope: "crawled-player".to_string(),
+        champion_focus: None,
         allow_ranks: None,
         log_interval_secs: 45,
+        workers: args.workers,
+        regions: None,
+        max_depth: None,
+        max_new_players_per_match: None,
+        prioritize_low_coverage_threshold: 10,
+        sample_rate: 1.0,
+        layout: "flat".to_string(),
+        dedup: "exact".to_string(),
+        dedup_fp_rate: 0.001,
+        resume: false,
+        daemon_every: None,
+        repoll_seeds_every: None,
+        notify_url: None,
+        notify_format: "json".to_string(),
+        tui: false,
+        queues: "soloq".to_string(),
+        queue_suffix: false,
     };
 
-    kraken_absorb_run(&absorb_args, client)
+    kraken_absorb_run(&absorb_args, std::slice::from_ref(client))
+}
+
+/// Immutable crawl configuration plus the shared, mutex-guarded crawl state.
+/// Worker threads only hold the state lock between network calls.
+struct KrakenCrawl<'a> {
+    args: &'a KrakenAbsorbArgs,
+    regions: Vec<KrakenRegion<'a>>,
+    followed: HashSet<String>,
+    mode: KrakenMode,
+    role_focus: Option<HashSet<String>>,
+    role_focus_scope: RoleFocusScope,
+    champion_focus: Option<HashSet<String>>,
+    allowed_ranks: Option<RankFilter>,
+    max_new_per_match: usize,
+    layout: MatchLayout,
+    queues: BTreeSet<i64>,
+    start: Instant,
+    max_duration: Duration,
+    idle_limit: Option<Duration>,
+    /// Match time window in Unix seconds, resolved from `--since`/`--until` at session start.
+    window_start: i64,
+    window_end: Option<i64>,
+    state: Mutex<KrakenState>,
+}
+
+/// One crawled platform with its own client and output directory.
+struct KrakenRegion<'a> {
+    client: &'a RiotClient,
+    out_dir: PathBuf,
+    manifest: KrakenManifest,
+    /// `ranks.csv` of the ranks looked up for --allow-ranks.
+    ranks: Option<KrakenRanks>,
+}
+
+struct KrakenState {
+    /// One frontier per region, indexed like `KrakenCrawl::regions`.
+    queues: Vec<KrakenFrontier>,
+    seen_puuids: SeenSet,
+    rank_cache: HashMap<String, Option<RankedEntry>>,
+    matches_per_player: HashMap<String, usize>,
+    seen_match_ids: SeenSet,
+    downloaded_matches: usize,
+    written_matches: usize,
+    /// Matches written this session per queue ID and source player.
+    written_per_queue: BTreeMap<i64, HashMap<String, usize>>,
+    /// Detected premade partners (both directions) in duo mode.
+    duos: HashMap<String, HashSet<String>>,
+    /// Duo partners already pushed to the front of the frontier.
+    duo_boosted: HashSet<String>,
+    /// Players with at least `--min-matches-per-player` downloaded matches.
+    players_at_target: usize,
+    last_written_at: Instant,
+    last_log: Instant,
+    active_workers: Vec<usize>,
+    stop_reason: Option<KrakenStopReason>,
+    /// Seeds that passed the filters, re-enqueued by `--repoll-seeds-every`.
+    repoll_seeds: Vec<(usize, String)>,
+    last_repoll: Vec<Instant>,
+    /// Unix time (seconds) of each followed seed's last match-list fetch.
+    seed_polled_at: HashMap<String, i64>,
+    /// Retryable match downloads that failed this session, tagged with their region.
+    failed_matches: Vec<(usize, FailedMatch)>,
+    excluded_puuids: HashSet<String>,
+    player_errors: HashMap<String, usize>,
+    skipped: SkipCounts,
+    /// Latest worker errors, shown by the dashboard instead of being printed under `--tui`.
+    recent_errors: VecDeque<String>,
+}
+
+/// Why matches were not written, reported next to the fetched/written counts. `duplicate`
+/// matches were never downloaded; every other reason applies to a fetched match.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct SkipCounts {
+    pub duplicate: usize,
+    pub fetch_error: usize,
+    pub outside_window: usize,
+    pub wrong_queue: usize,
+    pub role_filter: usize,
+    pub champion_filter: usize,
+    pub sampled_out: usize,
+}
+
+impl fmt::Display for SkipCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "duplicate={} fetch_error={} outside_window={} wrong_queue={} role_filter={} champion_filter={} sampled_out={}",
+            self.duplicate,
+            self.fetch_error,
+            self.outside_window,
+            self.wrong_queue,
+            self.role_filter,
+            self.champion_filter,
+            self.sampled_out
+        )
+    }
+}
+
+impl KrakenState {
+    fn queue_len(&self) -> usize {
+        self.queues.iter().map(KrakenFrontier::len).sum()
+    }
+
+    fn total_active_workers(&self) -> usize {
+        self.active_workers.iter().sum()
+    }
+}
+
+impl KrakenCrawl<'_> {
+    fn lock(&self) -> MutexGuard<'_, KrakenState> {
+        self.state
+            .lock()
+            .expect("Kraken state mutex poisoned by a crashed worker")
+    }
 }
 
+/// Crawls with one client per region; `clients` must be non-empty and the first client
+/// receives seeds that carry no `region:` prefix.
+///
+/// With `daemon_every` set, runs bounded sessions forever, sleeping in between; every session
+/// resumes from the checkpoint so the same dataset keeps growing.
 pub fn kraken_absorb_run(
     args: &KrakenAbsorbArgs,
-    client: &RiotClient,
+    clients: &[RiotClient],
 ) -> Result<(), Box<dyn Error>> {
-    let mut seeds: Vec<String> = Vec::new();
+    let mut session = 1usize;
 
-    if let Some(seed) = &args.seed_puuid {
-        if !seed.trim().is_empty() {
-            seeds.push(seed.trim().to_string());
-        }
-    }
+    loop {
+        let resume = args.resume || (args.daemon_every.is_some() && session > 1);
+        let result = kraken_absorb_crawl(args, clients, resume);
 
-    if let Some(path) = &args.seed_file {
-        let file = fs::File::open(path)?;
-        let reader = std::io::BufReader::new(file);
-        for line in reader.lines() {
-            if let Ok(value) = line {
-                let trimmed = value.trim();
-                if !trimmed.is_empty() {
-                    seeds.push(trimmed.to_string());
+        if let Some(url) = &args.notify_url {
+            let sent = match &result {
+                Ok(summary) => kraken_notify::notify_run_summary(url, &args.notify_format, summary),
+                Err(err) => {
+                    kraken_notify::notify_run_aborted(url, &args.notify_format, &err.to_string())
                 }
+            };
+            if let Err(err) = sent {
+                eprintln!("Failed to send kraken notification to {}: {}", url, err);
             }
         }
+
+        let summary = result?;
+        if let Some(err) = summary.error {
+            return Err(err.into());
+        }
+
+        let Some(every) = args.daemon_every else {
+            return Ok(());
+        };
+        if summary.reason == KrakenStopReason::Interrupted {
+            return Ok(());
+        }
+
+        eprintln!(
+            "[kraken-absorb] session {} finished ({:?}); next session in {}s",
+            session,
+            summary.reason,
+            every.as_secs()
+        );
+        thread::sleep(every);
+        session += 1;
+    }
+}
+
+fn kraken_absorb_crawl(
+    args: &KrakenAbsorbArgs,
+    clients: &[RiotClient],
+    resume: bool,
+) -> Result<KrakenRunSummary, Box<dyn Error>> {
+    if clients.is_empty() {
+        return Err("kraken-absorb needs at least one region client".into());
+    }
+
+    let mut seeds: Vec<String> = Vec::new();
+
+    if let Some(seed) = &args.seed_puuid
+        && !seed.trim().is_empty()
+    {
+        seeds.push(seed.trim().to_string());
+    }
+
+    if let Some(path) = &args.seed_file {
+        seeds.extend(read_puuid_file(path)?);
     }
 
-    if seeds.is_empty() {
+    let excluded_puuids: HashSet<String> = match &args.exclude_puuid_file {
+        Some(path) => read_puuid_file(path)?.into_iter().collect(),
+        None => HashSet::new(),
+    };
+
+    if seeds.is_empty() && !(args.continue_frontier && args.continue_from.is_some()) {
         return Err("You must provide at least one seed via --seed-puuid or --seed-file".into());
     }
 
     fs::create_dir_all(&args.out_dir)?;
 
+    // With explicit --regions every platform writes into its own subdirectory so the
+    // datasets stay separable; a single implicit region keeps the flat layout.
+    let mut regions = Vec::new();
+    for client in clients {
+        let out_dir = if args.regions.is_some() {
+            args.out_dir.join(client.platform())
+        } else {
+            args.out_dir.clone()
+        };
+        fs::create_dir_all(&out_dir)?;
+        let manifest = KrakenManifest::open(&out_dir)?;
+        let ranks = match args.allow_ranks {
+            Some(_) => Some(KrakenRanks::open(&out_dir)?),
+            None => None,
+        };
+        regions.push(KrakenRegion {
+            client,
+            out_dir,
+            manifest,
+            ranks,
+        });
+    }
+
+    let seed_targets = seeds
+        .iter()
+        .map(|seed| kraken_seed_region(&regions, seed))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Daemon sessions and --repoll-seeds-every re-poll the seeds for new games, so they are
+    // exempt from the per-player cap that would otherwise freeze them after the first poll.
+    let followed: HashSet<String> =
+        if args.daemon_every.is_some() || args.repoll_seeds_every.is_some() {
+            seed_targets
+                .iter()
+                .map(|(_, puuid)| puuid.clone())
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+    let dedup = DedupMode::parse(&args.dedup, args.dedup_fp_rate)?;
+    let layout = MatchLayout::parse(&args.layout)?;
+
+    if !(args.sample_rate > 0.0 && args.sample_rate <= 1.0) {
+        return Err(format!("--sample-rate must be in (0, 1], got {}", args.sample_rate).into());
+    }
+
     let mode = match args.mode.to_lowercase().as_str() {
         "explore" => KrakenMode::Explore,
         "focus" => KrakenMode::Focus,
         "seed-only" => KrakenMode::SeedOnly,
+        "duo" => KrakenMode::Duo,
         _ => KrakenMode::Explore,
     };
 
@@ -101,365 +457,1929 @@ pub fn kraken_absorb_run(
             .collect()
     });
 
-    let allowed_ranks: Option<HashSet<String>> = args.allow_ranks.as_ref().map(|raw| {
-        raw.split(',')
-            .map(|r| r.trim().to_uppercase())
-            .filter(|r| !r.is_empty())
-            .collect()
-    });
-
-    let mut queue: VecDeque<String> = VecDeque::new();
-    let mut seen_puuids: HashSet<String> = HashSet::new();
-    let mut rank_cache: HashMap<String, Option<String>> = HashMap::new();
-    let mut matches_per_player: HashMap<String, usize> = HashMap::new();
-
-    for seed in seeds {
-        let current_count = *matches_per_player.get(&seed).unwrap_or(&0);
-        if kraken_maybe_enqueue_player(
-            &seed,
-            &mut seen_puuids,
-            &mut queue,
-            &allowed_ranks,
-            &mut rank_cache,
-            client,
-            &mode,
-            None,
-            current_count,
-        )? {
-            continue;
+    let role_focus_scope = match args.role_focus_scope.to_lowercase().as_str() {
+        "any" => RoleFocusScope::Any,
+        "crawled-player" => RoleFocusScope::CrawledPlayer,
+        other => {
+            return Err(format!(
+                "Invalid --role-focus-scope '{}' (use any or crawled-player)",
+                other
+            )
+            .into());
         }
-    }
+    };
 
-    if queue.is_empty() {
-        return Err("No seeds enqueued after applying filters".into());
-    }
+    let champion_focus = args.champion_focus.as_deref().map(parse_champion_focus);
 
-    let mut seen_match_ids: HashSet<String> = HashSet::new();
-    let mut downloaded_matches: usize = 0;
-    let mut written_matches: usize = 0;
-    let start = Instant::now();
-    let mut last_written_at = Instant::now();
-    let max_duration = Duration::from_secs(args.duration_mins * 60);
-    let idle_limit = args
-        .idle_exit_after_mins
-        .map(|mins| Duration::from_secs(mins * 60));
-    let mut last_log = Instant::now();
-
-    // AJUSTE: aumentar max_new_focus según el modo
-    let max_new_focus = match mode {
-        KrakenMode::Explore => 10,  // Agregar todos los jugadores
-        KrakenMode::Focus => 5,     // Balance entre diversidad y profundidad
+    let allowed_ranks = args
+        .allow_ranks
+        .as_deref()
+        .map(RankFilter::parse)
+        .transpose()?;
+
+    // Participants enqueued per match: a match has 10 players, so explore takes all of them
+    // and focus trades breadth for depth; --max-new-players-per-match overrides both.
+    let max_new_per_match = match mode {
         KrakenMode::SeedOnly => 0,
+        KrakenMode::Explore | KrakenMode::Duo => args.max_new_players_per_match.unwrap_or(10),
+        KrakenMode::Focus => args.max_new_players_per_match.unwrap_or(5),
     };
 
-    while !queue.is_empty() && start.elapsed() < max_duration {
-        if let Some(max_total) = args.max_matches_total {
-            if written_matches >= max_total {
-                break;
-            }
-        }
+    let now = Utc::now().timestamp();
+    let window_start = args
+        .since
+        .unwrap_or(TimeBound::Ago(Duration::from_secs(
+            DEFAULT_MATCH_WINDOW_DAYS * 24 * 60 * 60,
+        )))
+        .resolve(now);
+    let window_end = args.until.map(|until| until.resolve(now));
+    if let Some(window_end) = window_end
+        && window_end <= window_start
+    {
+        return Err("--until must be later than --since".into());
+    }
 
-        if let Some(limit) = idle_limit {
-            if written_matches > 0 && last_written_at.elapsed() >= limit {
-                break;
+    let queues = parse_queues(&args.queues)?;
+
+    let crawl = KrakenCrawl {
+        args,
+        followed,
+        mode,
+        role_focus,
+        role_focus_scope,
+        champion_focus,
+        allowed_ranks,
+        max_new_per_match,
+        layout,
+        queues,
+        start: Instant::now(),
+        max_duration: Duration::from_secs(args.duration_mins * 60),
+        idle_limit: args
+            .idle_exit_after_mins
+            .map(|mins| Duration::from_secs(mins * 60)),
+        window_start,
+        window_end,
+        state: Mutex::new(KrakenState {
+            queues: regions.iter().map(|_| KrakenFrontier::new()).collect(),
+            seen_puuids: SeenSet::new(dedup),
+            rank_cache: HashMap::new(),
+            matches_per_player: HashMap::new(),
+            seen_match_ids: SeenSet::new(dedup),
+            downloaded_matches: 0,
+            written_matches: 0,
+            written_per_queue: BTreeMap::new(),
+            duos: HashMap::new(),
+            duo_boosted: HashSet::new(),
+            players_at_target: 0,
+            last_written_at: Instant::now(),
+            last_log: Instant::now(),
+            active_workers: vec![0; regions.len()],
+            stop_reason: None,
+            repoll_seeds: Vec::new(),
+            last_repoll: vec![Instant::now(); regions.len()],
+            seed_polled_at: HashMap::new(),
+            failed_matches: Vec::new(),
+            excluded_puuids,
+            player_errors: HashMap::new(),
+            skipped: SkipCounts::default(),
+            recent_errors: VecDeque::new(),
+        }),
+        regions,
+    };
+
+    if resume && let Some(checkpoint) = load_checkpoint(&args.out_dir)? {
+        eprintln!(
+            "[kraken-absorb] resuming from checkpoint saved at {} ({} matches, {} players)",
+            checkpoint.saved_at,
+            checkpoint.seen_match_ids.len(),
+            checkpoint.matches_per_player.len()
+        );
+        let mut state = crawl.lock();
+        state.seen_match_ids = checkpoint.seen_match_ids.into_mode(dedup);
+        state.matches_per_player = checkpoint.matches_per_player;
+        state.seed_polled_at = checkpoint.seed_polled_at;
+    }
+
+    let mut manifest_players: Vec<(usize, String)> = Vec::new();
+    if let Some(path) = &args.continue_from {
+        let rows = read_manifest(path)?;
+        eprintln!(
+            "[kraken-absorb] continuing from {} ({} matches)",
+            path.display(),
+            rows.len()
+        );
+        // Each player is re-enqueued in the region of their matches, read off the platform
+        // prefix of the match ID (`EUW1_...`).
+        let mut counts: HashMap<String, (Option<usize>, usize)> = HashMap::new();
+        let mut state = crawl.lock();
+        for row in &rows {
+            state.seen_match_ids.insert(&row.match_id);
+            let entry = counts.entry(row.source_puuid.clone()).or_insert((None, 0));
+            entry.0 = entry
+                .0
+                .or_else(|| kraken_match_region(&crawl.regions, &row.match_id));
+            entry.1 += 1;
+        }
+        let mut outside_regions = 0usize;
+        for (puuid, (region, count)) in counts {
+            let current = state.matches_per_player.entry(puuid.clone()).or_insert(0);
+            *current = (*current).max(count);
+            if *current >= args.max_matches_per_player {
+                continue;
+            }
+            match region {
+                Some(region) => manifest_players.push((region, puuid)),
+                None => outside_regions += 1,
             }
         }
-
-        if last_log.elapsed() >= Duration::from_secs(args.log_interval_secs) {
-            // MEJORADO: logging con métricas de cobertura
-            let avg_matches_per_player = if !matches_per_player.is_empty() {
-                matches_per_player.values().sum::<usize>() as f64 
-                    / matches_per_player.len() as f64
-            } else {
-                0.0
-            };
-            
-            let profiles_with_10plus = matches_per_player
-                .values()
-                .filter(|&&count| count >= 10)
-                .count();
-            
+        if args.continue_frontier && outside_regions > 0 {
             eprintln!(
-                "[kraken-absorb] elapsed={}s fetched={} written={} queue={} seen_players={} profiles_10+={} avg_matches/player={:.1} max_req_per_2min={}",
-                start.elapsed().as_secs(),
-                downloaded_matches,
-                written_matches,
-                queue.len(),
-                seen_puuids.len(),
-                profiles_with_10plus,
-                avg_matches_per_player,
-                args.max_req_per_2min
+                "[kraken-absorb] {} manifest players belong to regions not crawled; not re-enqueued",
+                outside_regions
             );
-            last_log = Instant::now();
         }
+    }
 
-        let puuid = match queue.pop_front() {
-            Some(p) => p,
-            None => break,
-        };
+    {
+        let mut state = crawl.lock();
+        state.players_at_target = state
+            .matches_per_player
+            .values()
+            .filter(|&&count| count >= args.min_matches_per_player)
+            .count();
+    }
 
-        let mut downloaded_for_puuid = *matches_per_player.get(&puuid).unwrap_or(&0);
-        if downloaded_for_puuid >= args.max_matches_per_player {
-            continue;
+    for (region, puuid) in seed_targets {
+        if kraken_maybe_enqueue_player(&crawl, region, &puuid, 0, None, None) {
+            crawl.lock().repoll_seeds.push((region, puuid));
         }
+    }
 
-        let match_ids = match client.get_match_ids_by_puuid(&puuid, 100) {
-            Ok(ids) => ids,
-            Err(err) => {
-                eprintln!("Failed to fetch match IDs for {}: {}", puuid, err);
-                continue;
+    // Manifest players below the per-player cap rebuild the frontier of the earlier run.
+    if args.continue_frontier {
+        for (region, puuid) in &manifest_players {
+            kraken_maybe_enqueue_player(&crawl, *region, puuid, 1, None, None);
+        }
+    }
+
+    if crawl.lock().queues.iter().all(KrakenFrontier::is_empty) {
+        return Err("No seeds enqueued after applying filters".into());
+    }
+
+    let workers = args.workers.max(1);
+    let workers_done = AtomicBool::new(false);
+    let results: Vec<Result<(), String>> = thread::scope(|scope| {
+        let crawl = &crawl;
+        let workers_done = &workers_done;
+        if args.tui {
+            // Throttling shows up as the dashboard's 429 count instead of stderr lines.
+            for region in &crawl.regions {
+                region.client.set_quiet(true);
             }
-        };
+            scope.spawn(move || kraken_dashboard(crawl, workers_done));
+        }
 
-        for match_id in match_ids {
-            if let Some(max_total) = args.max_matches_total {
-                if written_matches >= max_total {
-                    break;
-                }
+        let handles: Vec<_> = (0..crawl.regions.len())
+            .flat_map(|region| (0..workers).map(move |_| region))
+            .map(|region| scope.spawn(move || kraken_worker(crawl, region)))
+            .collect();
+
+        let results = handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err("kraken worker panicked".to_string()))
+            })
+            .collect();
+        workers_done.store(true, Ordering::Relaxed);
+        results
+    });
+
+    // The dashboard has closed: stderr is usable again, including for the errors it showed.
+    if args.tui {
+        for region in &crawl.regions {
+            region.client.set_quiet(false);
+        }
+        for message in crawl.lock().recent_errors.drain(..) {
+            eprintln!("{}", message);
+        }
+    }
+
+    if results.iter().all(Result::is_ok) {
+        kraken_retry_failed_matches(&crawl);
+    }
+
+    let state = crawl.lock();
+    let status = kraken_status(&crawl, &state, true);
+
+    if let Err(err) = write_status_file(&args.out_dir, &status) {
+        eprintln!("Failed to write status.json: {}", err);
+    }
+
+    if let Err(err) = save_checkpoint(&args.out_dir, &kraken_checkpoint(&state)) {
+        eprintln!("Failed to write checkpoint.json: {}", err);
+    }
+
+    // NUEVO: Estadísticas finales de cobertura
+    let coverage = KrakenCoverage::from_state(&state);
+    print_coverage_stats(&coverage);
+    if let Err(err) = write_json_atomic(&args.out_dir.join("coverage.json"), &coverage, true) {
+        eprintln!("Failed to write coverage.json: {}", err);
+    }
+
+    let error = results.into_iter().find_map(Result::err);
+    let reason = if error.is_some() {
+        KrakenStopReason::Error
+    } else {
+        state
+            .stop_reason
+            .clone()
+            .unwrap_or(KrakenStopReason::FrontierExhausted)
+    };
+
+    Ok(KrakenRunSummary {
+        reason,
+        error,
+        status,
+        coverage,
+    })
+}
+
+/// Splits an optional `platform:` prefix off a seed; unprefixed seeds go to the first region.
+fn kraken_seed_region(
+    regions: &[KrakenRegion],
+    seed: &str,
+) -> Result<(usize, String), Box<dyn Error>> {
+    let Some((platform, puuid)) = seed.split_once(':') else {
+        return Ok((0, seed.to_string()));
+    };
+
+    regions
+        .iter()
+        .position(|region| {
+            region
+                .client
+                .platform()
+                .eq_ignore_ascii_case(platform.trim())
+        })
+        .map(|idx| (idx, puuid.trim().to_string()))
+        .ok_or_else(|| {
+            format!(
+                "Seed {} targets region '{}' which is not crawled",
+                seed, platform
+            )
+            .into()
+        })
+}
+
+/// Region whose platform prefixes `match_id` (`EUW1_123` belongs to `euw1`), if it is crawled.
+fn kraken_match_region(regions: &[KrakenRegion], match_id: &str) -> Option<usize> {
+    let (platform, _) = match_id.split_once('_')?;
+    regions
+        .iter()
+        .position(|region| region.client.platform().eq_ignore_ascii_case(platform))
+}
+
+fn kraken_worker(crawl: &KrakenCrawl, region: usize) -> Result<(), String> {
+    while let Some((puuid, depth)) = kraken_next_player(crawl, region) {
+        let result = kraken_crawl_player(crawl, region, &puuid, depth);
+
+        let mut state = crawl.lock();
+        state.active_workers[region] -= 1;
+        if result.is_err() {
+            state.stop_reason = Some(KrakenStopReason::Error);
+            return result;
+        }
+    }
+
+    Ok(())
+}
+
+/// Pops the next player from the region's frontier, or returns `None` once a stop
+/// condition is hit or the frontier is empty with no other worker still expanding it.
+fn kraken_next_player(crawl: &KrakenCrawl, region: usize) -> Option<(String, u32)> {
+    loop {
+        kraken_maybe_log_progress(crawl);
+
+        {
+            let mut state = crawl.lock();
+
+            if state.stop_reason.is_some() {
+                return None;
             }
 
-            if downloaded_for_puuid >= args.max_matches_per_player {
-                break;
+            if crawl.start.elapsed() >= crawl.max_duration {
+                state.stop_reason = Some(KrakenStopReason::Duration);
+                return None;
             }
 
-            if !seen_match_ids.insert(match_id.clone()) {
-                continue;
+            if let Some(max_total) = crawl.args.max_matches_total
+                && state.written_matches >= max_total
+            {
+                state.stop_reason = Some(KrakenStopReason::MaxMatchesTotal);
+                return None;
             }
 
-            downloaded_matches += 1;
-            let match_json: Value = match client.get_match_json(&match_id) {
-                Ok(json) => json,
-                Err(err) => {
-                    eprintln!("Failed to fetch match {}: {}", match_id, err);
-                    continue;
-                }
-            };
+            if let Some(target) = crawl.args.target_players
+                && state.players_at_target >= target
+            {
+                state.stop_reason = Some(KrakenStopReason::TargetPlayers);
+                return None;
+            }
 
-            // NUEVO: Filtro temporal - solo partidas de últimos 90 días
-            if !is_recent_match(&match_json, 90) {
-                continue;
+            if let Some(limit) = crawl.idle_limit
+                && state.written_matches > 0
+                && state.last_written_at.elapsed() >= limit
+            {
+                state.stop_reason = Some(KrakenStopReason::IdleExit);
+                return None;
             }
 
-            // NUEVO: Solo partidas ranked (queue_id 420)
-            if !is_ranked_match(&match_json) {
-                continue;
+            if let Some(entry) = state.queues[region].pop() {
+                state.active_workers[region] += 1;
+                return Some(entry);
             }
 
-            let write_allowed = kraken_match_passes_roles(&match_json, role_focus.as_ref());
+            // Only this region is exhausted; other regions keep crawling.
+            if state.active_workers[region] == 0 {
+                let every = crawl.args.repoll_seeds_every?;
 
-            let mut new_added_this_match = 0usize;
-            if let Some(participants) = match_json
-                .get("metadata")
-                .and_then(|metadata| metadata.get("participants"))
-                .and_then(|list| list.as_array())
-            {
-                for participant in participants {
-                    if let Some(participant_puuid) = participant.as_str() {
-                        if mode == KrakenMode::SeedOnly {
-                            if !seen_puuids.contains(participant_puuid) {
-                                seen_puuids.insert(participant_puuid.to_string());
-                            }
-                            continue;
-                        }
-
-                        if mode == KrakenMode::Focus && new_added_this_match >= max_new_focus {
-                            seen_puuids.insert(participant_puuid.to_string());
-                            continue;
-                        }
-
-                        // NUEVO: Priorizar jugadores con pocas partidas
-                        let current_count = *matches_per_player
-                            .get(participant_puuid)
-                            .unwrap_or(&0);
-                        
-                        let enqueued = kraken_maybe_enqueue_player(
-                            participant_puuid,
-                            &mut seen_puuids,
-                            &mut queue,
-                            &allowed_ranks,
-                            &mut rank_cache,
-                            client,
-                            &mode,
-                            Some(max_new_focus.saturating_sub(new_added_this_match)),
-                            current_count,
-                        )?;
-                        
-                        if enqueued {
-                            new_added_this_match += 1;
-                        }
+                // Wait for the next poll of this region's seeds instead of exiting.
+                if state.last_repoll[region].elapsed() >= every {
+                    let seeds: Vec<String> = state
+                        .repoll_seeds
+                        .iter()
+                        .filter(|(seed_region, _)| *seed_region == region)
+                        .map(|(_, puuid)| puuid.clone())
+                        .collect();
+                    for puuid in seeds {
+                        state.queues[region].push(puuid, 0, 0);
                     }
-                }
-            }
-
-            if write_allowed {
-                if let Err(err) = save_match(&args.out_dir, &match_id, &match_json) {
-                    eprintln!("Failed to save match {}: {}", match_id, err);
+                    state.last_repoll[region] = Instant::now();
                     continue;
                 }
-                written_matches += 1;
-                last_written_at = Instant::now();
             }
-
-            downloaded_for_puuid += 1;
         }
 
-        matches_per_player.insert(puuid.clone(), downloaded_for_puuid);
+        // Another worker may still enqueue participants from the match it is processing.
+        thread::sleep(Duration::from_millis(100));
     }
+}
 
-    // NUEVO: Estadísticas finales de cobertura
-    print_coverage_stats(&matches_per_player, written_matches);
+/// Redraws the `--tui` dashboard every second until the workers finish. Quitting from the
+/// dashboard stops the crawl the same way a stop condition would.
+fn kraken_dashboard(crawl: &KrakenCrawl, workers_done: &AtomicBool) {
+    let mut dashboard = match KrakenDashboard::start() {
+        Ok(dashboard) => dashboard,
+        Err(err) => {
+            eprintln!("Failed to start the dashboard: {}", err);
+            return;
+        }
+    };
 
-    Ok(())
-}
+    // (time, requests sent, matches written) samples covering the last two minutes.
+    let mut samples: VecDeque<(Instant, usize, usize)> = VecDeque::new();
+    let mut write_rate_history: VecDeque<u64> = VecDeque::new();
+    let mut last_rate_sample = Instant::now();
+    let mut written_at_last_sample = 0usize;
 
-// NUEVO: Verificar si la partida es reciente
-fn is_recent_match(match_json: &Value, max_age_days: i64) -> bool {
-    if let Some(game_creation) = match_json
-        .get("info")
-        .and_then(|info| info.get("gameCreation"))
-        .and_then(|gc| gc.as_i64())
-    {
-        let now_millis = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as i64;
-        
-        let cutoff = now_millis - (max_age_days * 24 * 60 * 60 * 1000);
-        return game_creation >= cutoff;
-    }
-    true  // Si no hay timestamp, incluir por seguridad
-}
+    while !workers_done.load(Ordering::Relaxed) {
+        let now = Instant::now();
+        let requests: usize = crawl.regions.iter().map(|r| r.client.requests_sent()).sum();
+        let throttled: usize = crawl
+            .regions
+            .iter()
+            .map(|r| r.client.throttled_count())
+            .sum();
 
-// NUEVO: Verificar si es partida ranked
-fn is_ranked_match(match_json: &Value) -> bool {
-    if let Some(queue_id) = match_json
-        .get("info")
-        .and_then(|info| info.get("queueId"))
-        .and_then(|qid| qid.as_i64())
-    {
-        return queue_id == 420;  // Solo Ranked Solo/Duo
-    }
-    false
-}
+        let (status, coverage_histogram, recent_errors) = {
+            let state = crawl.lock();
+            let mut buckets = [0u64; 4];
+            for &count in state.matches_per_player.values() {
+                let bucket = match count {
+                    0..=4 => 0,
+                    5..=9 => 1,
+                    10..=19 => 2,
+                    _ => 3,
+                };
+                buckets[bucket] += 1;
+            }
+            let histogram = ["1-4", "5-9", "10-19", "20+"]
+                .iter()
+                .zip(buckets)
+                .map(|(label, players)| (label.to_string(), players))
+                .collect::<Vec<_>>();
+            (
+                kraken_status(crawl, &state, false),
+                histogram,
+                state.recent_errors.iter().cloned().collect(),
+            )
+        };
 
-fn kraken_match_passes_roles(match_json: &Value, role_focus: Option<&HashSet<String>>) -> bool {
-    let Some(role_focus) = role_focus else {
-        return true;
-    };
+        samples.push_back((now, requests, status.written));
+        while let Some(&(at, _, _)) = samples.front() {
+            if now.duration_since(at) > Duration::from_secs(120) {
+                samples.pop_front();
+            } else {
+                break;
+            }
+        }
+        let requests_2min = requests - samples.front().map_or(requests, |&(_, r, _)| r);
 
-    if let Some(participants) = match_json
-        .get("info")
-        .and_then(|info| info.get("participants"))
-        .and_then(|list| list.as_array())
-    {
-        for participant in participants {
-            if let Some(role) = participant
-                .get("teamPosition")
-                .and_then(|r| r.as_str())
-                .or_else(|| {
-                    participant
-                        .get("individualPosition")
-                        .and_then(|r| r.as_str())
-                })
-            {
-                if role_focus.contains(&role.to_uppercase()) {
-                    return true;
-                }
+        if now.duration_since(last_rate_sample) >= Duration::from_secs(10) {
+            let written_since = status.written.saturating_sub(written_at_last_sample);
+            write_rate_history.push_back(written_since as u64 * 6);
+            if write_rate_history.len() > 120 {
+                write_rate_history.pop_front();
             }
+            written_at_last_sample = status.written;
+            last_rate_sample = now;
+        }
+
+        let snapshot = DashboardSnapshot {
+            status,
+            requests_2min,
+            request_limit: crawl.args.max_req_per_2min * crawl.regions.len(),
+            throttled,
+            write_rate_history: write_rate_history.iter().copied().collect(),
+            coverage_histogram,
+            recent_errors,
+        };
+        if let Err(err) = dashboard.draw(&snapshot) {
+            drop(dashboard);
+            eprintln!("Dashboard stopped: {}", err);
+            return;
+        }
+
+        if dashboard
+            .quit_requested(Duration::from_secs(1))
+            .unwrap_or(false)
+        {
+            crawl
+                .lock()
+                .stop_reason
+                .get_or_insert(KrakenStopReason::Interrupted);
         }
     }
+}
+
+/// Parses daemon intervals such as `90s`, `30m`, `6h` or `1d` (bare numbers are minutes).
+pub fn parse_every(raw: &str) -> Result<Duration, String> {
+    let raw = raw.trim();
+    let split = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+    let (digits, unit) = raw.split_at(split);
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("Invalid interval '{}'", raw))?;
+
+    let secs = match unit {
+        "s" => value,
+        "" | "m" => value * 60,
+        "h" => value * 60 * 60,
+        "d" => value * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "Invalid interval unit in '{}' (use s, m, h or d)",
+                raw
+            ));
+        }
+    };
 
-    false
+    Ok(Duration::from_secs(secs))
 }
 
-// MODIFICADO: Agregar priorización por count de partidas
-fn kraken_maybe_enqueue_player(
-    puuid: &str,
-    seen_puuids: &mut HashSet<String>,
-    queue: &mut VecDeque<String>,
-    allowed_ranks: &Option<HashSet<String>>,
-    rank_cache: &mut HashMap<String, Option<String>>,
-    client: &RiotClient,
-    mode: &KrakenMode,
-    remaining_focus_slots: Option<usize>,
-    current_match_count: usize,
-) -> Result<bool, Box<dyn Error>> {
-    if seen_puuids.contains(puuid) {
-        return Ok(false);
-    }
+// Match window used when `--since` is not given.
+const DEFAULT_MATCH_WINDOW_DAYS: u64 = 90;
 
-    if let Some(allowed) = allowed_ranks {
-        let tier = if let Some(cached) = rank_cache.get(puuid) {
-            cached.clone()
-        } else {
-            let tier = client
-                .get_ranked_tier_by_puuid(puuid)?
-                .map(|t| t.to_uppercase());
-            rank_cache.insert(puuid.to_string(), tier.clone());
-            tier
-        };
+/// Bound of the match time window: a calendar date or an interval before the session start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBound {
+    /// Unix seconds.
+    At(i64),
+    Ago(Duration),
+}
 
-        if let Some(tier_value) = tier {
-            if !allowed.contains(&tier_value) {
-                seen_puuids.insert(puuid.to_string());
-                return Ok(false);
-            }
+impl TimeBound {
+    /// Accepts `YYYY-MM-DD` (UTC midnight) or an interval such as `30d` or `12h`.
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        if let Ok(date) = NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d") {
+            let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+            return Ok(TimeBound::At(midnight.and_utc().timestamp()));
         }
+
+        parse_every(raw)
+            .map(TimeBound::Ago)
+            .map_err(|_| format!("Invalid time '{}' (use YYYY-MM-DD or e.g. 30d)", raw))
     }
 
-    if let Some(limit) = remaining_focus_slots {
-        if *mode == KrakenMode::Focus && limit == 0 {
-            seen_puuids.insert(puuid.to_string());
-            return Ok(false);
+    pub fn resolve(self, now: i64) -> i64 {
+        match self {
+            TimeBound::At(secs) => secs,
+            TimeBound::Ago(ago) => now - ago.as_secs() as i64,
         }
     }
+}
 
-    seen_puuids.insert(puuid.to_string());
-    
-    // NUEVO: Priorizar jugadores con pocas partidas (< 10)
-    // Los agregamos al frente para procesarlos antes
-    if current_match_count < 10 {
-        queue.push_front(puuid.to_string());
-    } else {
-        queue.push_back(puuid.to_string());
-    }
-    
-    Ok(true)
+/// Why a crawl stopped; reported in the final notification payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KrakenStopReason {
+    Duration,
+    MaxMatchesTotal,
+    TargetPlayers,
+    IdleExit,
+    FrontierExhausted,
+    /// Stopped from the `--tui` dashboard.
+    Interrupted,
+    Error,
 }
 
-// NUEVO: Imprimir estadísticas de cobertura
-fn print_coverage_stats(
-    matches_per_player: &HashMap<String, usize>,
-    total_matches: usize,
-) {
-    eprintln!("\n=== Coverage Statistics ===");
-    
-    let total_players = matches_per_player.len();
-    let profiles_5plus = matches_per_player.values().filter(|&&c| c >= 5).count();
-    let profiles_10plus = matches_per_player.values().filter(|&&c| c >= 10).count();
-    let profiles_20plus = matches_per_player.values().filter(|&&c| c >= 20).count();
-    
-    eprintln!("Total unique players: {}", total_players);
-    eprintln!("Profiles with 5+ matches: {} ({:.1}%)", 
-              profiles_5plus, profiles_5plus as f64 / total_players as f64 * 100.0);
-    eprintln!("Profiles with 10+ matches: {} ({:.1}%)", 
-              profiles_10plus, profiles_10plus as f64 / total_players as f64 * 100.0);
-    eprintln!("Profiles with 20+ matches: {} ({:.1}%)", 
-              profiles_20plus, profiles_20plus as f64 / total_players as f64 * 100.0);
-    
-    if total_players > 0 {
-        let sum: usize = matches_per_player.values().sum();
-        let avg = sum as f64 / total_players as f64;
-        eprintln!("Average matches per player: {:.1}", avg);
-    }
-    
-    eprintln!("Total matches written: {}", total_matches);
-    eprintln!("===========================\n");
+#[derive(Debug, Serialize)]
+pub struct KrakenRunSummary {
+    pub reason: KrakenStopReason,
+    pub error: Option<String>,
+    pub status: KrakenStatus,
+    pub coverage: KrakenCoverage,
 }
 
-fn save_match(out_dir: &PathBuf, match_id: &str, match_json: &Value) -> Result<(), Box<dyn Error>> {
-    let serialized = serde_json::to_vec_pretty(match_json)?;
-    let file_path = out_dir.join(format!("{}.json", match_id));
-    fs::write(file_path, serialized)?;
-    Ok(())
+/// Point-in-time view of crawl progress, shared by the stderr log line and `status.json`.
+#[derive(Debug, Serialize)]
+pub struct KrakenStatus {
+    pub updated_at: String,
+    pub finished: bool,
+    pub elapsed_secs: u64,
+    pub fetched: usize,
+    pub written: usize,
+    pub queue: usize,
+    pub seen_players: usize,
+    pub profiles_10plus: usize,
+    pub avg_matches_per_player: f64,
+    pub active_workers: usize,
+    pub max_req_per_2min: usize,
+    pub fetched_per_min: f64,
+    pub written_per_min: f64,
+    pub max_matches_total: Option<usize>,
+    pub eta_secs: Option<u64>,
+    /// ETA against `--max-matches-total` if requests ran at the rate-limit ceiling, using
+    /// the observed matches written per request.
+    pub eta_at_ceiling_secs: Option<u64>,
+    pub remaining_secs: u64,
+    /// Matches written by the end of `--duration-mins` at the current write rate.
+    pub projected_written: usize,
+    pub skipped: SkipCounts,
+}
+
+fn kraken_status(crawl: &KrakenCrawl, state: &KrakenState, finished: bool) -> KrakenStatus {
+    let elapsed = crawl.start.elapsed();
+    let elapsed_mins = elapsed.as_secs_f64() / 60.0;
+
+    // MEJORADO: logging con métricas de cobertura
+    let avg_matches_per_player = if !state.matches_per_player.is_empty() {
+        state.matches_per_player.values().sum::<usize>() as f64
+            / state.matches_per_player.len() as f64
+    } else {
+        0.0
+    };
+
+    let profiles_10plus = state
+        .matches_per_player
+        .values()
+        .filter(|&&count| count >= 10)
+        .count();
+
+    let (fetched_per_min, written_per_min) = if elapsed_mins > 0.0 {
+        (
+            state.downloaded_matches as f64 / elapsed_mins,
+            state.written_matches as f64 / elapsed_mins,
+        )
+    } else {
+        (0.0, 0.0)
+    };
+
+    let eta_secs = crawl.args.max_matches_total.and_then(|max_total| {
+        let remaining = max_total.saturating_sub(state.written_matches);
+        if remaining == 0 {
+            Some(0)
+        } else if written_per_min > 0.0 {
+            Some((remaining as f64 / written_per_min * 60.0) as u64)
+        } else {
+            None
+        }
+    });
+
+    let remaining_secs = crawl.max_duration.saturating_sub(elapsed).as_secs();
+    let mut projected_written =
+        state.written_matches + (written_per_min * remaining_secs as f64 / 60.0) as usize;
+    if let Some(max_total) = crawl.args.max_matches_total {
+        projected_written = projected_written.min(max_total);
+    }
+
+    let requests: usize = crawl.regions.iter().map(|r| r.client.requests_sent()).sum();
+    let eta_at_ceiling_secs = crawl.args.max_matches_total.and_then(|max_total| {
+        if requests == 0 || state.written_matches == 0 {
+            return None;
+        }
+        let written_per_request = state.written_matches as f64 / requests as f64;
+        let max_req_per_2min = crawl
+            .args
+            .max_req_per_2min
+            .min(max_sustained_reqs_per_2min());
+        let ceiling_per_min =
+            (max_req_per_2min * crawl.regions.len()) as f64 / 2.0 * written_per_request;
+        let remaining = max_total.saturating_sub(state.written_matches);
+        Some((remaining as f64 / ceiling_per_min * 60.0) as u64)
+    });
+
+    KrakenStatus {
+        updated_at: Utc::now().to_rfc3339(),
+        finished,
+        elapsed_secs: elapsed.as_secs(),
+        fetched: state.downloaded_matches,
+        written: state.written_matches,
+        queue: state.queue_len(),
+        seen_players: state.seen_puuids.len(),
+        profiles_10plus,
+        avg_matches_per_player,
+        active_workers: state.total_active_workers(),
+        max_req_per_2min: crawl.args.max_req_per_2min,
+        fetched_per_min,
+        written_per_min,
+        max_matches_total: crawl.args.max_matches_total,
+        eta_secs,
+        eta_at_ceiling_secs,
+        remaining_secs,
+        projected_written,
+        skipped: state.skipped.clone(),
+    }
+}
+
+/// Logs progress and saves the checkpoint once per `--log-interval-secs`. The status and a
+/// copy of the checkpoint are taken under the state lock; both are serialized and written
+/// after releasing it, so workers never wait on the disk.
+fn kraken_maybe_log_progress(crawl: &KrakenCrawl) {
+    let (status, checkpoint) = {
+        let mut state = crawl.lock();
+        if state.last_log.elapsed() < Duration::from_secs(crawl.args.log_interval_secs) {
+            return;
+        }
+        state.last_log = Instant::now();
+
+        (
+            kraken_status(crawl, &state, false),
+            kraken_checkpoint(&state).snapshot(),
+        )
+    };
+
+    kraken_log_progress(crawl, &status);
+    if let Err(err) = save_checkpoint(&crawl.args.out_dir, &checkpoint) {
+        kraken_report_error(crawl, format!("Failed to write checkpoint.json: {}", err));
+    }
+}
+
+fn kraken_log_progress(crawl: &KrakenCrawl, status: &KrakenStatus) {
+    if !crawl.args.tui {
+        eprintln!(
+            "[kraken-absorb] elapsed={}s fetched={} written={} queue={} seen_players={} profiles_10+={} avg_matches/player={:.1} workers={} max_req_per_2min={}",
+            status.elapsed_secs,
+            status.fetched,
+            status.written,
+            status.queue,
+            status.seen_players,
+            status.profiles_10plus,
+            status.avg_matches_per_player,
+            status.active_workers,
+            status.max_req_per_2min
+        );
+        eprintln!("[kraken-absorb] skipped: {}", status.skipped);
+        eprintln!("[kraken-absorb] projection: {}", kraken_projection(status));
+    }
+
+    if let Err(err) = write_status_file(&crawl.args.out_dir, status) {
+        kraken_report_error(crawl, format!("Failed to write status.json: {}", err));
+    }
+}
+
+/// One-line budget projection: when `--max-matches-total` is reached (at the current rate
+/// and at the rate-limit ceiling) and whether that happens before `--duration-mins` runs out.
+fn kraken_projection(status: &KrakenStatus) -> String {
+    let mins = |secs: u64| format!("{}m", secs.div_ceil(60));
+    let deadline = format!(
+        "{} projected at the duration limit (in {})",
+        status.projected_written,
+        mins(status.remaining_secs)
+    );
+
+    let Some(max_total) = status.max_matches_total else {
+        return deadline;
+    };
+    let Some(eta) = status.eta_secs else {
+        return format!("no matches written yet; {}", deadline);
+    };
+
+    let ceiling = status
+        .eta_at_ceiling_secs
+        .map(|secs| format!(" ({} at the rate-limit ceiling)", mins(secs)))
+        .unwrap_or_default();
+    if eta <= status.remaining_secs {
+        format!("{} matches in ~{}{}", max_total, mins(eta), ceiling)
+    } else {
+        format!(
+            "{} matches in ~{}{}, after the duration limit; {}",
+            max_total,
+            mins(eta),
+            ceiling,
+            deadline
+        )
+    }
+}
+
+fn write_status_file(out_dir: &Path, status: &KrakenStatus) -> Result<(), Box<dyn Error>> {
+    write_json_atomic(&out_dir.join("status.json"), status, true)
+}
+
+/// Replaces `path` via a temp file + rename so readers never see a partial document.
+pub fn write_json_atomic<T: Serialize>(
+    path: &Path,
+    value: &T,
+    pretty: bool,
+) -> Result<(), Box<dyn Error>> {
+    let serialized = if pretty {
+        serde_json::to_vec_pretty(value)?
+    } else {
+        serde_json::to_vec(value)?
+    };
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    fs::write(&tmp_path, serialized)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Dedup and coverage state persisted between sessions so a resumed crawl continues the same
+/// dataset. Player-level discovery (`seen_puuids`, the frontier) is rebuilt from the seeds;
+/// followed seeds keep their poll watermarks so a restarted daemon stays incremental.
+#[derive(Debug, Serialize, Deserialize)]
+struct KrakenCheckpoint<Ids, Counts, Polls> {
+    saved_at: String,
+    seen_match_ids: Ids,
+    matches_per_player: Counts,
+    /// Absent from checkpoints written before seeds were polled incrementally.
+    #[serde(default)]
+    seed_polled_at: Polls,
+}
+
+type LoadedCheckpoint = KrakenCheckpoint<SeenSet, HashMap<String, usize>, HashMap<String, i64>>;
+
+type BorrowedCheckpoint<'a> =
+    KrakenCheckpoint<&'a SeenSet, &'a HashMap<String, usize>, &'a HashMap<String, i64>>;
+
+fn kraken_checkpoint(state: &KrakenState) -> BorrowedCheckpoint<'_> {
+    KrakenCheckpoint {
+        saved_at: Utc::now().to_rfc3339(),
+        seen_match_ids: &state.seen_match_ids,
+        matches_per_player: &state.matches_per_player,
+        seed_polled_at: &state.seed_polled_at,
+    }
+}
+
+impl BorrowedCheckpoint<'_> {
+    /// Owned copy that outlives the state lock; cloning is far cheaper than serializing.
+    fn snapshot(&self) -> LoadedCheckpoint {
+        KrakenCheckpoint {
+            saved_at: self.saved_at.clone(),
+            seen_match_ids: self.seen_match_ids.clone(),
+            matches_per_player: self.matches_per_player.clone(),
+            seed_polled_at: self.seed_polled_at.clone(),
+        }
+    }
+}
+
+fn save_checkpoint<Ids: Serialize, Counts: Serialize, Polls: Serialize>(
+    out_dir: &Path,
+    checkpoint: &KrakenCheckpoint<Ids, Counts, Polls>,
+) -> Result<(), Box<dyn Error>> {
+    write_json_atomic(&out_dir.join("checkpoint.json"), checkpoint, false)
+}
+
+fn load_checkpoint(out_dir: &Path) -> Result<Option<LoadedCheckpoint>, Box<dyn Error>> {
+    let path = out_dir.join("checkpoint.json");
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&contents)?))
+}
+
+fn kraken_crawl_player(
+    crawl: &KrakenCrawl,
+    region: usize,
+    puuid: &str,
+    depth: u32,
+) -> Result<(), String> {
+    let args = crawl.args;
+    let client = crawl.regions[region].client;
+    let mut downloaded_for_puuid = *crawl.lock().matches_per_player.get(puuid).unwrap_or(&0);
+    let player_cap = if crawl.followed.contains(puuid) {
+        downloaded_for_puuid + args.max_matches_per_player
+    } else {
+        args.max_matches_per_player
+    };
+    if downloaded_for_puuid >= player_cap {
+        return Ok(());
+    }
+
+    // Seeds are always crawled; discovered players must show enough recent activity first.
+    if depth > 0
+        && let Some(min_games) = args.min_recent_games
+        && !kraken_player_is_active(crawl, region, puuid, min_games)
+    {
+        return Ok(());
+    }
+
+    // Followed seeds only ask for games played since their previous poll. The watermark only
+    // moves once every game since then has been handled, so a failed fetch or an early stop
+    // is picked up again by the next poll. With `--dedup probabilistic` a failed match stays
+    // marked as seen and is only recovered through failed.jsonl and `kraken-retry`.
+    let followed = crawl.followed.contains(puuid);
+    let last_polled = if followed {
+        crawl.lock().seed_polled_at.get(puuid).copied()
+    } else {
+        None
+    };
+    let polled_at = Utc::now().timestamp();
+    let start_time =
+        last_polled.map_or(crawl.window_start, |polled| polled.max(crawl.window_start));
+
+    let mut teammates: HashMap<String, usize> = HashMap::new();
+    let mut pending_writes: Vec<(String, Value)> = Vec::new();
+    let mut handled_all = true;
+
+    let match_ids =
+        match kraken_fetch_match_ids(client, puuid, followed, start_time, crawl.window_end) {
+            Ok(ids) => ids,
+            Err(err) => {
+                kraken_report_error(
+                    crawl,
+                    format!("Failed to fetch match IDs for {}: {}", puuid, err),
+                );
+                kraken_record_player_error(crawl, region, puuid, depth);
+                return Ok(());
+            }
+        };
+
+    for match_id in match_ids {
+        if downloaded_for_puuid >= player_cap {
+            handled_all = false;
+            break;
+        }
+
+        {
+            let mut state = crawl.lock();
+            if state.stop_reason.is_some() {
+                handled_all = false;
+                break;
+            }
+
+            if let Some(max_total) = args.max_matches_total
+                && state.written_matches >= max_total
+            {
+                handled_all = false;
+                break;
+            }
+
+            if !state.seen_match_ids.insert(&match_id) {
+                state.skipped.duplicate += 1;
+                continue;
+            }
+
+            state.downloaded_matches += 1;
+        }
+
+        let match_json: Value = match client.get_match_json(&match_id) {
+            Ok(json) => json,
+            Err(err) => {
+                kraken_report_error(
+                    crawl,
+                    format!("Failed to fetch match {}: {}", match_id, err),
+                );
+                kraken_record_failed_match(crawl, region, &match_id, puuid, err.as_ref());
+                crawl.lock().seen_match_ids.remove(&match_id);
+                handled_all = false;
+                continue;
+            }
+        };
+
+        if !kraken_match_eligible(crawl, &match_json) {
+            continue;
+        }
+
+        kraken_enqueue_participants(crawl, region, &match_json, depth + 1);
+
+        if crawl.mode == KrakenMode::Duo {
+            count_teammates(&match_json, puuid, &mut teammates);
+            if kraken_match_wanted(crawl, &match_id, &match_json, puuid) {
+                pending_writes.push((match_id, match_json));
+            }
+        } else if kraken_match_wanted(crawl, &match_id, &match_json, puuid)
+            && !kraken_write_match(crawl, region, &match_id, &match_json, puuid)
+        {
+            handled_all = false;
+            break;
+        }
+
+        downloaded_for_puuid += 1;
+    }
+
+    // Duo mode writes after the player's history is scanned, so their partners are already
+    // known when the matches are tagged in the manifest.
+    if crawl.mode == KrakenMode::Duo {
+        kraken_register_duos(crawl, region, puuid, depth, &teammates);
+        for (match_id, match_json) in &pending_writes {
+            if !kraken_write_match(crawl, region, match_id, match_json, puuid) {
+                handled_all = false;
+                break;
+            }
+        }
+    }
+
+    let mut state = crawl.lock();
+    let previous = state
+        .matches_per_player
+        .insert(puuid.to_string(), downloaded_for_puuid)
+        .unwrap_or(0);
+    let min_matches = args.min_matches_per_player;
+    if previous < min_matches && downloaded_for_puuid >= min_matches {
+        state.players_at_target += 1;
+    }
+    if followed && handled_all {
+        state.seed_polled_at.insert(puuid.to_string(), polled_at);
+    }
+
+    Ok(())
+}
+
+/// Match IDs to crawl for `puuid`, newest first: one page of 100, or for followed seeds every
+/// page since `start_time`, so that their poll watermark can move past all of them.
+fn kraken_fetch_match_ids(
+    client: &RiotClient,
+    puuid: &str,
+    followed: bool,
+    start_time: i64,
+    end_time: Option<i64>,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    const PAGE: usize = 100;
+    let mut ids = Vec::new();
+    loop {
+        let page = client.get_match_ids_page(puuid, ids.len(), PAGE, Some(start_time), end_time)?;
+        let full = page.len() == PAGE;
+        ids.extend(page);
+        if !followed || !full {
+            return Ok(ids);
+        }
+    }
+}
+
+/// Cheap activity probe: asks for at most `min_games` IDs inside the activity window. Lookup
+/// failures count as active so a flaky request never drops a player.
+fn kraken_player_is_active(
+    crawl: &KrakenCrawl,
+    region: usize,
+    puuid: &str,
+    min_games: usize,
+) -> bool {
+    let window_secs = crawl.args.activity_window_days as i64 * 24 * 60 * 60;
+    let start_time = Utc::now().timestamp() - window_secs;
+
+    match crawl.regions[region].client.get_match_ids_by_puuid_since(
+        puuid,
+        min_games,
+        Some(start_time),
+    ) {
+        Ok(ids) => ids.len() >= min_games,
+        Err(err) => {
+            kraken_report_error(
+                crawl,
+                format!("Failed to check activity of {}: {}", puuid, err),
+            );
+            true
+        }
+    }
+}
+
+/// Counts the non-bot teammates of `puuid` in one match.
+fn count_teammates(match_json: &Value, puuid: &str, teammates: &mut HashMap<String, usize>) {
+    let Some(participants) = match_json
+        .get("info")
+        .and_then(|info| info.get("participants"))
+        .and_then(|list| list.as_array())
+    else {
+        return;
+    };
+
+    let team_of = |p: &Value| p.get("teamId").and_then(|t| t.as_i64());
+    let Some(team_id) = participants
+        .iter()
+        .find(|p| p.get("puuid").and_then(|v| v.as_str()) == Some(puuid))
+        .and_then(team_of)
+    else {
+        return;
+    };
+
+    for participant in participants {
+        if team_of(participant) != Some(team_id) {
+            continue;
+        }
+        if let Some(mate) = participant.get("puuid").and_then(|v| v.as_str())
+            && mate != puuid
+            && mate != "BOT"
+        {
+            *teammates.entry(mate.to_string()).or_insert(0) += 1;
+        }
+    }
+}
+
+/// Records teammates seen in at least `--duo-min-games` of the player's matches as duo
+/// partners and moves the ones not crawled yet to the front of the frontier. Partners already
+/// queued are promoted in place; new ones go through the usual rank and depth filters.
+fn kraken_register_duos(
+    crawl: &KrakenCrawl,
+    region: usize,
+    puuid: &str,
+    depth: u32,
+    teammates: &HashMap<String, usize>,
+) {
+    let mut undiscovered = Vec::new();
+    {
+        let mut state = crawl.lock();
+        for (mate, &games) in teammates {
+            if games < crawl.args.duo_min_games {
+                continue;
+            }
+
+            state
+                .duos
+                .entry(puuid.to_string())
+                .or_default()
+                .insert(mate.clone());
+            state
+                .duos
+                .entry(mate.clone())
+                .or_default()
+                .insert(puuid.to_string());
+
+            if state.matches_per_player.contains_key(mate)
+                || state.excluded_puuids.contains(mate)
+                || !state.duo_boosted.insert(mate.clone())
+            {
+                continue;
+            }
+            if state.seen_puuids.contains(mate) {
+                state.queues[region].raise(mate, DUO_PRIORITY);
+            } else {
+                undiscovered.push(mate.clone());
+            }
+        }
+    }
+
+    // Same rule as for participants: players at --max-depth do not expand.
+    if let Some(max_depth) = crawl.args.max_depth
+        && depth + 1 > max_depth
+    {
+        return;
+    }
+    for mate in undiscovered {
+        kraken_maybe_enqueue_player(crawl, region, &mate, depth + 1, None, Some(DUO_PRIORITY));
+    }
+}
+
+/// `a+b` for every detected duo playing on the same team in this match, `;`-separated.
+fn kraken_duo_tag(state: &KrakenState, match_json: &Value) -> String {
+    if state.duos.is_empty() {
+        return String::new();
+    }
+
+    let Some(participants) = match_json
+        .get("info")
+        .and_then(|info| info.get("participants"))
+        .and_then(|list| list.as_array())
+    else {
+        return String::new();
+    };
+
+    let players: Vec<(&str, i64)> = participants
+        .iter()
+        .filter_map(|p| {
+            Some((
+                p.get("puuid")?.as_str()?,
+                p.get("teamId").and_then(|t| t.as_i64()).unwrap_or_default(),
+            ))
+        })
+        .collect();
+
+    let mut pairs = Vec::new();
+    for (i, (a, team_a)) in players.iter().enumerate() {
+        for (b, team_b) in &players[i + 1..] {
+            if team_a == team_b
+                && state
+                    .duos
+                    .get(*a)
+                    .is_some_and(|partners| partners.contains(*b))
+            {
+                let (first, second) = if a < b { (a, b) } else { (b, a) };
+                pairs.push(format!("{}+{}", first, second));
+            }
+        }
+    }
+    pairs.join(";")
+}
+
+fn kraken_match_wanted(
+    crawl: &KrakenCrawl,
+    match_id: &str,
+    match_json: &Value,
+    puuid: &str,
+) -> bool {
+    if !kraken_match_passes_roles(crawl, match_json, puuid) {
+        crawl.lock().skipped.role_filter += 1;
+        return false;
+    }
+    if !kraken_match_passes_champions(match_json, crawl.champion_focus.as_ref()) {
+        crawl.lock().skipped.champion_filter += 1;
+        return false;
+    }
+    if !kraken_match_sampled(match_id, crawl.args.sample_rate) {
+        crawl.lock().skipped.sampled_out += 1;
+        return false;
+    }
+    true
+}
+
+/// Time window and queue checks applied before participants are enqueued.
+fn kraken_match_eligible(crawl: &KrakenCrawl, match_json: &Value) -> bool {
+    // NUEVO: Filtro temporal - solo partidas dentro de --since/--until
+    if !kraken_match_in_window(crawl, match_json) {
+        crawl.lock().skipped.outside_window += 1;
+        return false;
+    }
+
+    // NUEVO: Solo las colas de --queues (por defecto Ranked Solo/Duo)
+    if !kraken_match_in_queues(crawl, match_json) {
+        crawl.lock().skipped.wrong_queue += 1;
+        return false;
+    }
+
+    true
+}
+
+/// Keeps roughly `sample_rate` of the matches. The decision is a 64-bit FNV-1a hash of the
+/// match ID, a fixed algorithm (unlike std's `DefaultHasher`), so it is stable across
+/// workers, regions, resumed sessions and toolchain upgrades.
+fn kraken_match_sampled(match_id: &str, sample_rate: f64) -> bool {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    if sample_rate >= 1.0 {
+        return true;
+    }
+
+    let hash = match_id.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    (hash as f64 / u64::MAX as f64) < sample_rate
+}
+
+/// Saves an accepted match and indexes it in the manifest. Returns `false` once
+/// --max-matches-total has been reached and nothing more may be written.
+fn kraken_write_match(
+    crawl: &KrakenCrawl,
+    region: usize,
+    match_id: &str,
+    match_json: &Value,
+    source_puuid: &str,
+) -> bool {
+    // Reserve the write slot under the lock so concurrent workers never
+    // overshoot --max-matches-total.
+    {
+        let mut state = crawl.lock();
+        if let Some(max_total) = crawl.args.max_matches_total
+            && state.written_matches >= max_total
+        {
+            return false;
+        }
+        state.written_matches += 1;
+    }
+
+    let target = &crawl.regions[region];
+    let mut relative = crawl.layout.relative_path(match_id, match_json);
+    if crawl.args.queue_suffix {
+        let queue = match_json
+            .get("info")
+            .and_then(|info| info.get("queueId"))
+            .and_then(|qid| qid.as_i64())
+            .unwrap_or_default();
+        relative.set_file_name(queue_suffixed_file_name(match_id, queue));
+    }
+    let (file_name, bytes) = match save_match_at(&target.out_dir, &relative, match_json) {
+        Ok(saved) => saved,
+        Err(err) => {
+            kraken_report_error(crawl, format!("Failed to save match {}: {}", match_id, err));
+            crawl.lock().written_matches -= 1;
+            return true;
+        }
+    };
+    let mut row = ManifestRow::new(match_id, match_json, source_puuid, &file_name, bytes);
+    {
+        let mut state = crawl.lock();
+        row.duo = kraken_duo_tag(&state, match_json);
+        state.last_written_at = Instant::now();
+        *state
+            .written_per_queue
+            .entry(row.queue)
+            .or_default()
+            .entry(source_puuid.to_string())
+            .or_insert(0) += 1;
+    }
+
+    if let Err(err) = target.manifest.append(&row) {
+        kraken_report_error(
+            crawl,
+            format!("Failed to append {} to manifest.csv: {}", match_id, err),
+        );
+    }
+    true
+}
+
+/// Worker-side error report: printed to stderr, or kept for the dashboard under `--tui`.
+/// Must be called without holding the state lock.
+fn kraken_report_error(crawl: &KrakenCrawl, message: String) {
+    if !crawl.args.tui {
+        eprintln!("{}", message);
+        return;
+    }
+
+    let mut state = crawl.lock();
+    if state.recent_errors.len() == MAX_RECENT_ERRORS {
+        state.recent_errors.pop_front();
+    }
+    state.recent_errors.push_back(message);
+}
+
+/// Logs a failed match download to the region's `failed.jsonl` and keeps it for the
+/// end-of-run retry pass.
+fn kraken_record_failed_match(
+    crawl: &KrakenCrawl,
+    region: usize,
+    match_id: &str,
+    source_puuid: &str,
+    err: &(dyn Error + 'static),
+) {
+    let target = &crawl.regions[region];
+    let failed = FailedMatch::new(match_id, target.client.platform(), source_puuid, err);
+    if let Err(err) = append_failed(&target.out_dir, &failed) {
+        kraken_report_error(
+            crawl,
+            format!("Failed to append {} to failed.jsonl: {}", match_id, err),
+        );
+    }
+    let mut state = crawl.lock();
+    state.skipped.fetch_error += 1;
+    if failed.is_retryable() {
+        state.failed_matches.push((region, failed));
+    }
+}
+
+/// Gives every match that failed during this session one more attempt after the crawl,
+/// dropping the recovered ones from `failed.jsonl`.
+fn kraken_retry_failed_matches(crawl: &KrakenCrawl) {
+    let failed = std::mem::take(&mut crawl.lock().failed_matches);
+    if failed.is_empty() {
+        return;
+    }
+
+    eprintln!(
+        "[kraken-absorb] retrying {} failed match downloads",
+        failed.len()
+    );
+    let mut recovered: Vec<HashSet<String>> = vec![HashSet::new(); crawl.regions.len()];
+    for (region, entry) in failed {
+        let match_json = match crawl.regions[region].client.get_match_json(&entry.match_id) {
+            Ok(json) => json,
+            Err(err) => {
+                eprintln!("Retry of {} failed again: {}", entry.match_id, err);
+                continue;
+            }
+        };
+        recovered[region].insert(entry.match_id.clone());
+        {
+            let mut state = crawl.lock();
+            state.skipped.fetch_error = state.skipped.fetch_error.saturating_sub(1);
+        }
+
+        if kraken_match_eligible(crawl, &match_json)
+            && kraken_match_wanted(crawl, &entry.match_id, &match_json, &entry.source_puuid)
+            && !kraken_write_match(
+                crawl,
+                region,
+                &entry.match_id,
+                &match_json,
+                &entry.source_puuid,
+            )
+        {
+            break;
+        }
+    }
+
+    for (region, ids) in recovered.iter().enumerate() {
+        if ids.is_empty() {
+            continue;
+        }
+        let path = crawl.regions[region].out_dir.join(FAILED_FILE);
+        if let Err(err) = remove_failed(&path, ids) {
+            eprintln!("Failed to update {}: {}", path.display(), err);
+        }
+    }
+}
+
+/// Retries a failing player later, or blacklists them for the rest of the run once they
+/// have failed `MAX_PLAYER_ERRORS` times.
+fn kraken_record_player_error(crawl: &KrakenCrawl, region: usize, puuid: &str, depth: u32) {
+    if !kraken_count_player_error(crawl, puuid) {
+        crawl.lock().queues[region].push(puuid.to_string(), 0, depth);
+    }
+}
+
+/// Counts a failed request for `puuid` and blacklists them once they reach
+/// `MAX_PLAYER_ERRORS`; returns whether they were blacklisted.
+fn kraken_count_player_error(crawl: &KrakenCrawl, puuid: &str) -> bool {
+    let mut state = crawl.lock();
+    let errors = state.player_errors.entry(puuid.to_string()).or_insert(0);
+    *errors += 1;
+
+    if *errors < MAX_PLAYER_ERRORS {
+        return false;
+    }
+
+    state.excluded_puuids.insert(puuid.to_string());
+    drop(state);
+
+    kraken_report_error(
+        crawl,
+        format!(
+            "Blacklisting {} after {} failed requests",
+            puuid, MAX_PLAYER_ERRORS
+        ),
+    );
+    if let Err(err) = append_blacklist(&crawl.args.out_dir, puuid) {
+        kraken_report_error(
+            crawl,
+            format!("Failed to append {} to blacklist.txt: {}", puuid, err),
+        );
+    }
+    true
+}
+
+fn kraken_enqueue_participants(crawl: &KrakenCrawl, region: usize, match_json: &Value, depth: u32) {
+    // Players at --max-depth are still crawled, but the people they played with are not.
+    // `depth` is the hop count of the path the player was first discovered on, not
+    // necessarily the shortest: the frontier is ordered by priority, not by depth.
+    if let Some(max_depth) = crawl.args.max_depth
+        && depth > max_depth
+    {
+        return;
+    }
+
+    let Some(participants) = match_json
+        .get("metadata")
+        .and_then(|metadata| metadata.get("participants"))
+        .and_then(|list| list.as_array())
+    else {
+        return;
+    };
+
+    let mut new_added_this_match = 0usize;
+    for participant_puuid in participants.iter().filter_map(|p| p.as_str()) {
+        if crawl.mode == KrakenMode::SeedOnly || new_added_this_match >= crawl.max_new_per_match {
+            crawl.lock().seen_puuids.insert(participant_puuid);
+            continue;
+        }
+
+        let enqueued = kraken_maybe_enqueue_player(
+            crawl,
+            region,
+            participant_puuid,
+            depth,
+            Some(crawl.max_new_per_match.saturating_sub(new_added_this_match)),
+            None,
+        );
+
+        if enqueued {
+            new_added_this_match += 1;
+        }
+    }
+}
+
+// NUEVO: Verificar si la partida cae dentro de la ventana temporal
+fn kraken_match_in_window(crawl: &KrakenCrawl, match_json: &Value) -> bool {
+    if let Some(game_creation) = match_json
+        .get("info")
+        .and_then(|info| info.get("gameCreation"))
+        .and_then(|gc| gc.as_i64())
+    {
+        // `--until` is exclusive, as in extract-parquet and the stats commands.
+        let game_secs = game_creation / 1000;
+        return game_secs >= crawl.window_start
+            && crawl
+                .window_end
+                .is_none_or(|end| game_creation < end * 1000);
+    }
+    true // Si no hay timestamp, incluir por seguridad
+}
+
+/// Whether the match was played in one of `queues` (parsed from `--queues`).
+pub fn match_in_queues(match_json: &Value, queues: &BTreeSet<i64>) -> bool {
+    match_json
+        .get("info")
+        .and_then(|info| info.get("queueId"))
+        .and_then(|qid| qid.as_i64())
+        .is_some_and(|queue_id| queues.contains(&queue_id))
+}
+
+fn kraken_match_in_queues(crawl: &KrakenCrawl, match_json: &Value) -> bool {
+    match_in_queues(match_json, &crawl.queues)
+}
+
+fn kraken_match_passes_roles(crawl: &KrakenCrawl, match_json: &Value, puuid: &str) -> bool {
+    let Some(role_focus) = &crawl.role_focus else {
+        return true;
+    };
+
+    let Some(participants) = match_json
+        .get("info")
+        .and_then(|info| info.get("participants"))
+        .and_then(|list| list.as_array())
+    else {
+        return false;
+    };
+
+    participants
+        .iter()
+        .filter(|participant| {
+            crawl.role_focus_scope == RoleFocusScope::Any
+                || participant.get("puuid").and_then(|p| p.as_str()) == Some(puuid)
+        })
+        .any(|participant| {
+            participant
+                .get("teamPosition")
+                .and_then(|r| r.as_str())
+                .filter(|r| !r.is_empty())
+                .or_else(|| {
+                    participant
+                        .get("individualPosition")
+                        .and_then(|r| r.as_str())
+                })
+                .is_some_and(|role| role_focus.contains(&role.to_uppercase()))
+        })
+}
+
+/// Parses a comma-separated `--champion-focus` list into normalized names or IDs.
+pub fn parse_champion_focus(raw: &str) -> HashSet<String> {
+    raw.split(',')
+        .map(normalize_champion)
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+/// Champion names are compared case-insensitively and without spaces or punctuation, so
+/// `Kai'Sa`, `kaisa` and the match-v5 `Kaisa` all match; numeric entries are champion IDs.
+fn normalize_champion(raw: &str) -> String {
+    raw.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+pub fn kraken_match_passes_champions(
+    match_json: &Value,
+    champion_focus: Option<&HashSet<String>>,
+) -> bool {
+    let Some(champion_focus) = champion_focus else {
+        return true;
+    };
+
+    let Some(participants) = match_json
+        .get("info")
+        .and_then(|info| info.get("participants"))
+        .and_then(|list| list.as_array())
+    else {
+        return false;
+    };
+
+    participants.iter().any(|participant| {
+        let by_name = participant
+            .get("championName")
+            .and_then(|c| c.as_str())
+            .is_some_and(|name| champion_focus.contains(&normalize_champion(name)));
+        let by_id = participant
+            .get("championId")
+            .and_then(|c| c.as_i64())
+            .is_some_and(|id| champion_focus.contains(&id.to_string()));
+        by_name || by_id
+    })
+}
+
+// MODIFICADO: Agregar priorización por count de partidas
+/// Claims `puuid` in `seen_puuids` and queues them if they pass the exclusion and rank
+/// filters. `priority` overrides the tier/coverage priority (duo partners jump the queue).
+/// A failed rank lookup skips the player and counts towards their blacklisting.
+fn kraken_maybe_enqueue_player(
+    crawl: &KrakenCrawl,
+    region: usize,
+    puuid: &str,
+    depth: u32,
+    remaining_slots: Option<usize>,
+    priority: Option<u32>,
+) -> bool {
+    let (cached_rank, current_match_count) = {
+        let state = crawl.lock();
+        if state.seen_puuids.contains(puuid) || state.excluded_puuids.contains(puuid) {
+            return false;
+        }
+        (
+            state.rank_cache.get(puuid).cloned(),
+            *state.matches_per_player.get(puuid).unwrap_or(&0),
+        )
+    };
+
+    let mut tier_priority = 0;
+    if let Some(allowed) = &crawl.allowed_ranks {
+        // The rank lookup is a network call, so it runs without holding the state lock.
+        let rank = match cached_rank {
+            Some(cached) => cached,
+            None => {
+                let rank = match crawl.regions[region]
+                    .client
+                    .get_ranked_entry_by_puuid(puuid)
+                {
+                    Ok(rank) => rank,
+                    Err(err) => {
+                        kraken_report_error(
+                            crawl,
+                            format!("Failed to fetch rank for {}: {}", puuid, err),
+                        );
+                        // Left unseen so a later match can retry the lookup, until the
+                        // player is blacklisted.
+                        kraken_count_player_error(crawl, puuid);
+                        return false;
+                    }
+                };
+                crawl
+                    .lock()
+                    .rank_cache
+                    .insert(puuid.to_string(), rank.clone());
+                if let Some(ranks) = &crawl.regions[region].ranks {
+                    let row = RankRow {
+                        puuid: puuid.to_string(),
+                        tier: rank.as_ref().map(|e| e.tier.clone()).unwrap_or_default(),
+                        division: rank
+                            .as_ref()
+                            .map(|e| e.division.clone())
+                            .unwrap_or_default(),
+                    };
+                    // The sidecar only documents the crawl; a failed write must not stop it.
+                    if let Err(err) = ranks.append(&row) {
+                        kraken_report_error(
+                            crawl,
+                            format!("Failed to append {} to ranks.csv: {}", puuid, err),
+                        );
+                    }
+                }
+                rank
+            }
+        };
+
+        // Unranked players have no entry and are kept.
+        if let Some(entry) = &rank
+            && !allowed.allows(&entry.tier, &entry.division)
+        {
+            crawl.lock().seen_puuids.insert(puuid);
+            return false;
+        }
+
+        tier_priority = rank
+            .as_ref()
+            .map(|entry| tier_score(&entry.tier))
+            .unwrap_or(0);
+    }
+
+    let mut state = crawl.lock();
+
+    if let Some(limit) = remaining_slots
+        && limit == 0
+    {
+        state.seen_puuids.insert(puuid);
+        return false;
+    }
+
+    // Another worker may have claimed this player while the rank lookup was in flight.
+    if !state.seen_puuids.insert(puuid) {
+        return false;
+    }
+
+    // Higher tiers are crawled first; within a tier, players with few matches (below
+    // --prioritize-low-coverage-threshold) jump ahead so their profiles fill up before the run ends.
+    let coverage_bonus =
+        u32::from(current_match_count < crawl.args.prioritize_low_coverage_threshold);
+    let priority = priority.unwrap_or(tier_priority * 2 + coverage_bonus);
+    state.queues[region].push(puuid.to_string(), priority, depth);
+
+    true
+}
+
+#[derive(Debug, Serialize)]
+pub struct KrakenCoverage {
+    pub total_players: usize,
+    pub profiles_5plus: usize,
+    pub profiles_10plus: usize,
+    pub profiles_20plus: usize,
+    pub avg_matches_per_player: Option<f64>,
+    pub total_matches_written: usize,
+    /// Crawled players and their downloaded matches, keyed by ranked tier (`UNKNOWN` when
+    /// the tier was never looked up, i.e. without `--allow-ranks`).
+    pub by_tier: BTreeMap<String, TierCoverage>,
+    /// Matches written this session and the players they were crawled from, keyed by queue
+    /// label (`soloq`, `flex`, ...).
+    pub by_queue: BTreeMap<String, QueueCoverage>,
+    pub skipped: SkipCounts,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TierCoverage {
+    pub players: usize,
+    pub matches: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct QueueCoverage {
+    pub queue_id: i64,
+    pub matches: usize,
+    pub players: usize,
+    pub profiles_10plus: usize,
+}
+
+impl KrakenCoverage {
+    fn from_state(state: &KrakenState) -> Self {
+        let matches_per_player = &state.matches_per_player;
+        let total_players = matches_per_player.len();
+        let avg_matches_per_player = if total_players > 0 {
+            let sum: usize = matches_per_player.values().sum();
+            Some(sum as f64 / total_players as f64)
+        } else {
+            None
+        };
+
+        let mut by_tier: BTreeMap<String, TierCoverage> = BTreeMap::new();
+        for (puuid, &count) in matches_per_player {
+            let tier = state
+                .rank_cache
+                .get(puuid)
+                .and_then(|rank| rank.as_ref())
+                .map(|rank| rank.tier.clone())
+                .unwrap_or_else(|| "UNKNOWN".to_string());
+            let entry = by_tier.entry(tier).or_default();
+            entry.players += 1;
+            entry.matches += count;
+        }
+
+        Self {
+            total_players,
+            profiles_5plus: matches_per_player.values().filter(|&&c| c >= 5).count(),
+            profiles_10plus: matches_per_player.values().filter(|&&c| c >= 10).count(),
+            profiles_20plus: matches_per_player.values().filter(|&&c| c >= 20).count(),
+            avg_matches_per_player,
+            total_matches_written: state.written_matches,
+            by_tier,
+            by_queue: state
+                .written_per_queue
+                .iter()
+                .map(|(&queue_id, players)| {
+                    let coverage = QueueCoverage {
+                        queue_id,
+                        matches: players.values().sum(),
+                        players: players.len(),
+                        profiles_10plus: players.values().filter(|&&c| c >= 10).count(),
+                    };
+                    (queue_label(queue_id), coverage)
+                })
+                .collect(),
+            skipped: state.skipped.clone(),
+        }
+    }
+}
+
+// NUEVO: Imprimir estadísticas de cobertura
+fn print_coverage_stats(coverage: &KrakenCoverage) {
+    eprintln!("\n=== Coverage Statistics ===");
+
+    let total_players = coverage.total_players;
+
+    eprintln!("Total unique players: {}", total_players);
+    eprintln!(
+        "Profiles with 5+ matches: {} ({:.1}%)",
+        coverage.profiles_5plus,
+        coverage.profiles_5plus as f64 / total_players as f64 * 100.0
+    );
+    eprintln!(
+        "Profiles with 10+ matches: {} ({:.1}%)",
+        coverage.profiles_10plus,
+        coverage.profiles_10plus as f64 / total_players as f64 * 100.0
+    );
+    eprintln!(
+        "Profiles with 20+ matches: {} ({:.1}%)",
+        coverage.profiles_20plus,
+        coverage.profiles_20plus as f64 / total_players as f64 * 100.0
+    );
+
+    if let Some(avg) = coverage.avg_matches_per_player {
+        eprintln!("Average matches per player: {:.1}", avg);
+    }
+
+    eprintln!("Total matches written: {}", coverage.total_matches_written);
+
+    for (tier, tier_coverage) in &coverage.by_tier {
+        eprintln!(
+            "  {}: {} players, {} matches",
+            tier, tier_coverage.players, tier_coverage.matches
+        );
+    }
+    for (label, queue) in &coverage.by_queue {
+        eprintln!(
+            "  {} (queue {}): {} matches written from {} players, {} with 10+",
+            label, queue.queue_id, queue.matches, queue.players, queue.profiles_10plus
+        );
+    }
+    eprintln!("Skipped matches: {}", coverage.skipped);
+    eprintln!("===========================\n");
+}
+
+pub fn read_puuid_file(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let file = fs::File::open(path)?;
+    let reader = std::io::BufReader::new(file);
+    Ok(reader
+        .lines()
+        .map_while(Result::ok)
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+fn append_blacklist(out_dir: &Path, puuid: &str) -> Result<(), Box<dyn Error>> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(out_dir.join("blacklist.txt"))?;
+    writeln!(file, "{}", puuid)?;
+    Ok(())
+}
+
+/// Directory layout for written match files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchLayout {
+    /// `out_dir/{id}.json`
+    Flat,
+    /// `out_dir/region=EUW1/patch=14.21/date=2024-10-03/{id}.json`, Hive-style.
+    Partitioned,
+    /// `out_dir/soloq/{id}.json`, `out_dir/flex/{id}.json`, ... so one queue can be
+    /// extracted on its own.
+    Queue,
+}
+
+impl MatchLayout {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        match raw.to_lowercase().as_str() {
+            "flat" => Ok(MatchLayout::Flat),
+            "partitioned" => Ok(MatchLayout::Partitioned),
+            "queue" => Ok(MatchLayout::Queue),
+            other => Err(format!(
+                "Invalid layout '{}' (use flat, partitioned or queue)",
+                other
+            )),
+        }
+    }
+
+    /// Path of a match file relative to the output directory.
+    pub fn relative_path(self, match_id: &str, match_json: &Value) -> PathBuf {
+        let file_name = format!("{}.json", match_id);
+        let info = match_json.get("info");
+        match self {
+            MatchLayout::Flat => return PathBuf::from(file_name),
+            MatchLayout::Queue => {
+                let queue = info
+                    .and_then(|i| i.get("queueId"))
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or_default();
+                return PathBuf::from(queue_label(queue)).join(file_name);
+            }
+            MatchLayout::Partitioned => {}
+        }
+
+        let region = info
+            .and_then(|i| i.get("platformId"))
+            .and_then(|v| v.as_str())
+            .map(str::to_uppercase)
+            .or_else(|| match_id.split_once('_').map(|(p, _)| p.to_uppercase()))
+            .unwrap_or_else(|| "UNKNOWN".to_string());
+        let patch = info
+            .and_then(|i| i.get("gameVersion"))
+            .and_then(|v| v.as_str())
+            .map(patch_from_version)
+            .filter(|p| !p.is_empty())
+            .unwrap_or_else(|| "unknown".to_string());
+        let date = info
+            .and_then(|i| i.get("gameCreation"))
+            .and_then(|v| v.as_i64())
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        PathBuf::from(format!("region={}", region))
+            .join(format!("patch={}", patch))
+            .join(format!("date={}", date))
+            .join(file_name)
+    }
+}
+
+/// File name `--queue-suffix` gives a match (`EUW1_123_flex.json`).
+pub fn queue_suffixed_file_name(match_id: &str, queue: i64) -> String {
+    format!("{}_{}.json", match_id, queue_label(queue))
+}
+
+/// Writes the match and returns its path (relative to `out_dir`) and size in bytes.
+pub fn save_match(
+    out_dir: &Path,
+    layout: MatchLayout,
+    match_id: &str,
+    match_json: &Value,
+) -> Result<(String, u64), Box<dyn Error>> {
+    save_match_at(
+        out_dir,
+        &layout.relative_path(match_id, match_json),
+        match_json,
+    )
+}
+
+/// Writes the match to `relative` inside `out_dir` and returns that path and the size in bytes.
+fn save_match_at(
+    out_dir: &Path,
+    relative: &Path,
+    match_json: &Value,
+) -> Result<(String, u64), Box<dyn Error>> {
+    let serialized = serde_json::to_vec_pretty(match_json)?;
+    let file_path = out_dir.join(relative);
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(file_path, &serialized)?;
+    Ok((
+        relative.to_string_lossy().into_owned(),
+        serialized.len() as u64,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn checkpoint_round_trips_poll_watermarks_and_loads_older_files() {
+        let dir = TempDir::new("kraken-checkpoint");
+        let mut seen = SeenSet::new(DedupMode::Exact);
+        seen.insert("EUW1_1");
+        let counts = HashMap::from([("p1".to_string(), 3)]);
+        let polls = HashMap::from([("p1".to_string(), 1_727_900_000)]);
+        let checkpoint = KrakenCheckpoint {
+            saved_at: "2024-10-02T00:00:00Z".to_string(),
+            seen_match_ids: &seen,
+            matches_per_player: &counts,
+            seed_polled_at: &polls,
+        };
+
+        save_checkpoint(dir.path(), &checkpoint).unwrap();
+        let loaded = load_checkpoint(dir.path()).unwrap().unwrap();
+        // Written before seeds were polled incrementally.
+        fs::write(
+            dir.path().join("checkpoint.json"),
+            r#"{"saved_at":"2024-10-01T00:00:00Z","seen_match_ids":["EUW1_1"],"matches_per_player":{}}"#,
+        )
+        .unwrap();
+        let older = load_checkpoint(dir.path()).unwrap().unwrap();
+
+        assert!(loaded.seen_match_ids.contains("EUW1_1"));
+        assert_eq!(loaded.matches_per_player, counts);
+        assert_eq!(loaded.seed_polled_at, polls);
+        assert!(older.seen_match_ids.contains("EUW1_1"));
+        assert!(older.seed_polled_at.is_empty());
+    }
 }
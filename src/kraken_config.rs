@@ -0,0 +1,52 @@
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+
+/// Turns a crawl profile into command-line flags. Keys are flag names without the leading
+/// dashes (`max-req-per-2min = 100`); booleans become bare flags and arrays are joined with
+/// commas. The flags are inserted before the user's own, so anything given on the command
+/// line overrides the profile.
+pub fn config_flags(path: &Path, known_flags: &[String]) -> Result<Vec<OsString>, Box<dyn Error>> {
+    let raw = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    let table: toml::Table =
+        toml::from_str(&raw).map_err(|err| format!("Invalid {}: {}", path.display(), err))?;
+
+    let mut flags = Vec::new();
+    for (key, value) in &table {
+        if key == "config" || !known_flags.iter().any(|flag| flag == key) {
+            return Err(format!("Unknown key '{}' in {}", key, path.display()).into());
+        }
+
+        let flag = format!("--{}", key);
+        match value {
+            toml::Value::Boolean(true) => flags.push(flag.into()),
+            toml::Value::Boolean(false) => {}
+            toml::Value::Array(items) => {
+                let joined = items
+                    .iter()
+                    .map(|item| scalar_to_string(key, item))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(",");
+                flags.push(flag.into());
+                flags.push(joined.into());
+            }
+            other => {
+                flags.push(flag.into());
+                flags.push(scalar_to_string(key, other)?.into());
+            }
+        }
+    }
+
+    Ok(flags)
+}
+
+fn scalar_to_string(key: &str, value: &toml::Value) -> Result<String, String> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        _ => Err(format!("Unsupported value for '{}': {}", key, value)),
+    }
+}
@@ -0,0 +1,109 @@
+use growable_bloom_filter::GrowableBloom;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// Initial sizing of the Bloom filter; it grows in stages as more keys are inserted.
+const BLOOM_INITIAL_CAPACITY: usize = 1_000_000;
+
+/// Dedup strategy selected with `--dedup`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DedupMode {
+    Exact,
+    /// Bloom filter with the given false-positive rate.
+    Probabilistic(f64),
+}
+
+impl DedupMode {
+    pub fn parse(raw: &str, fp_rate: f64) -> Result<Self, String> {
+        match raw.to_lowercase().as_str() {
+            "exact" => Ok(DedupMode::Exact),
+            "probabilistic" => {
+                if !(fp_rate > 0.0 && fp_rate < 1.0) {
+                    return Err(format!(
+                        "--dedup-fp-rate must be between 0 and 1, got {}",
+                        fp_rate
+                    ));
+                }
+                Ok(DedupMode::Probabilistic(fp_rate))
+            }
+            other => Err(format!(
+                "Invalid --dedup '{}' (use exact or probabilistic)",
+                other
+            )),
+        }
+    }
+}
+
+/// Set of already-seen match IDs or PUUIDs. The probabilistic variant keeps memory roughly
+/// constant per key at the cost of occasionally reporting an unseen key as seen, which
+/// means a match or player is skipped, never downloaded twice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SeenSet {
+    Exact(HashSet<String>),
+    Probabilistic(GrowableBloom),
+}
+
+impl SeenSet {
+    pub fn new(mode: DedupMode) -> Self {
+        match mode {
+            DedupMode::Exact => SeenSet::Exact(HashSet::new()),
+            DedupMode::Probabilistic(fp_rate) => {
+                SeenSet::Probabilistic(GrowableBloom::new(fp_rate, BLOOM_INITIAL_CAPACITY))
+            }
+        }
+    }
+
+    /// Converts a set restored from a checkpoint to the requested mode. Exact sets can be
+    /// folded into a Bloom filter; a Bloom filter cannot be expanded back, so it is kept.
+    pub fn into_mode(self, mode: DedupMode) -> Self {
+        match (self, mode) {
+            (SeenSet::Exact(keys), DedupMode::Probabilistic(_)) => {
+                let mut converted = SeenSet::new(mode);
+                for key in &keys {
+                    converted.insert(key);
+                }
+                converted
+            }
+            (SeenSet::Probabilistic(filter), DedupMode::Exact) => {
+                eprintln!(
+                    "[kraken-absorb] checkpoint uses probabilistic dedup; keeping it despite --dedup exact"
+                );
+                SeenSet::Probabilistic(filter)
+            }
+            (set, _) => set,
+        }
+    }
+
+    /// Returns `true` if `key` was not seen before.
+    pub fn insert(&mut self, key: &str) -> bool {
+        match self {
+            SeenSet::Exact(keys) => keys.insert(key.to_string()),
+            SeenSet::Probabilistic(filter) => filter.insert(key),
+        }
+    }
+
+    /// Forgets `key` so it can be inserted again. A Bloom filter cannot forget keys, so the
+    /// probabilistic variant keeps it and returns `false`.
+    pub fn remove(&mut self, key: &str) -> bool {
+        match self {
+            SeenSet::Exact(keys) => keys.remove(key),
+            SeenSet::Probabilistic(_) => false,
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        match self {
+            SeenSet::Exact(keys) => keys.contains(key),
+            SeenSet::Probabilistic(filter) => filter.contains(key),
+        }
+    }
+
+    /// Number of keys inserted (an estimate for the probabilistic variant).
+    pub fn len(&self) -> usize {
+        match self {
+            SeenSet::Exact(keys) => keys.len(),
+            SeenSet::Probabilistic(filter) => filter.len(),
+        }
+    }
+}
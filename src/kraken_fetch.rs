@@ -0,0 +1,166 @@
+use crate::kraken::{
+    MatchLayout, kraken_match_passes_champions, match_in_queues, parse_champion_focus,
+    read_puuid_file, save_match,
+};
+use crate::kraken_manifest::{
+    KrakenManifest, MANIFEST_FILE, ManifestRow, parse_queues, read_manifest,
+};
+use crate::kraken_retry::{FailedMatch, append_failed};
+use crate::riot_api::{RiotClient, regional_route};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct FetchMatchesArgs {
+    /// Plain list of match IDs (one per line) or a `manifest.csv` from another crawl.
+    pub match_id_file: PathBuf,
+    pub out_dir: PathBuf,
+    pub layout: MatchLayout,
+    pub max_req_per_2min: usize,
+    /// Comma-separated queue IDs or labels (`soloq,flex`) to keep, as in kraken-absorb.
+    pub queues: String,
+    /// Keep matches of every queue; `queues` is ignored.
+    pub all_queues: bool,
+    pub champion_focus: Option<String>,
+}
+
+/// Match ID plus the PUUID it was originally crawled from (empty for plain ID lists).
+struct FetchTarget {
+    match_id: String,
+    source_puuid: String,
+}
+
+fn read_fetch_targets(path: &Path) -> Result<Vec<FetchTarget>, Box<dyn Error>> {
+    let is_csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        return Ok(read_manifest(path)?
+            .into_iter()
+            .map(|row| FetchTarget {
+                match_id: row.match_id,
+                source_puuid: row.source_puuid,
+            })
+            .collect());
+    }
+
+    Ok(read_puuid_file(path)?
+        .into_iter()
+        .map(|match_id| FetchTarget {
+            match_id,
+            source_puuid: String::new(),
+        })
+        .collect())
+}
+
+/// Platform encoded in a match ID (`EUW1_7123456789` -> `euw1`).
+fn platform_of(match_id: &str) -> Option<String> {
+    let (prefix, _) = match_id.split_once('_')?;
+    let platform = prefix.to_lowercase();
+    regional_route(&platform).map(|_| platform)
+}
+
+/// Downloads exactly the matches listed in `match_id_file` into `out_dir`, with the same
+/// per-platform rate limiting, queue and champion filters, layout, manifest and
+/// `failed.jsonl` handling as kraken-absorb. Matches already in the output manifest are skipped,
+/// so an interrupted fetch can simply be re-run.
+pub fn fetch_matches_run(args: &FetchMatchesArgs) -> Result<(), Box<dyn Error>> {
+    let queues = (!args.all_queues)
+        .then(|| parse_queues(&args.queues))
+        .transpose()?;
+    let targets = read_fetch_targets(&args.match_id_file)?;
+    if targets.is_empty() {
+        println!("No match IDs in {}", args.match_id_file.display());
+        return Ok(());
+    }
+
+    fs::create_dir_all(&args.out_dir)?;
+    let manifest_path = args.out_dir.join(MANIFEST_FILE);
+    let mut done: HashSet<String> = if manifest_path.exists() {
+        read_manifest(&manifest_path)?
+            .into_iter()
+            .map(|row| row.match_id)
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    let manifest = KrakenManifest::open(&args.out_dir)?;
+    let champion_focus = args.champion_focus.as_deref().map(parse_champion_focus);
+
+    let mut clients: Vec<RiotClient> = Vec::new();
+    let total = targets.len();
+    let mut written = 0usize;
+    let mut already_present = 0usize;
+    let mut filtered = 0usize;
+    let mut failed = 0usize;
+
+    for (idx, target) in targets.iter().enumerate() {
+        if !done.insert(target.match_id.clone()) {
+            already_present += 1;
+            continue;
+        }
+
+        let Some(platform) = platform_of(&target.match_id) else {
+            eprintln!(
+                "[fetch-matches] skipping {}: no known platform prefix",
+                target.match_id
+            );
+            failed += 1;
+            continue;
+        };
+
+        let client = match clients.iter().position(|c| c.platform() == platform) {
+            Some(idx) => &clients[idx],
+            None => {
+                clients.push(RiotClient::for_platform(&platform, args.max_req_per_2min)?);
+                clients.last().expect("client was just pushed")
+            }
+        };
+
+        eprintln!("[fetch-matches] {}/{}: {}", idx + 1, total, target.match_id);
+        let match_json = match client.get_match_json(&target.match_id) {
+            Ok(json) => json,
+            Err(err) => {
+                eprintln!("Failed to fetch {}: {}", target.match_id, err);
+                let entry = FailedMatch::new(
+                    &target.match_id,
+                    &platform,
+                    &target.source_puuid,
+                    err.as_ref(),
+                );
+                append_failed(&args.out_dir, &entry)?;
+                failed += 1;
+                continue;
+            }
+        };
+
+        if queues
+            .as_ref()
+            .is_some_and(|queues| !match_in_queues(&match_json, queues))
+            || !kraken_match_passes_champions(&match_json, champion_focus.as_ref())
+        {
+            filtered += 1;
+            continue;
+        }
+
+        let (file_name, bytes) =
+            save_match(&args.out_dir, args.layout, &target.match_id, &match_json)?;
+        let row = ManifestRow::new(
+            &target.match_id,
+            &match_json,
+            &target.source_puuid,
+            &file_name,
+            bytes,
+        );
+        manifest.append(&row)?;
+        written += 1;
+    }
+
+    println!(
+        "Fetched {} matches ({} already present, {} filtered out, {} failed)",
+        written, already_present, filtered, failed
+    );
+    Ok(())
+}
@@ -0,0 +1,201 @@
+use crate::kraken::{
+    MatchLayout, TimeBound, match_in_queues, read_puuid_file, save_match, write_json_atomic,
+};
+use crate::kraken_manifest::{
+    KrakenManifest, MANIFEST_FILE, ManifestRow, parse_queues, read_manifest,
+};
+use crate::kraken_retry::{FailedMatch, append_failed};
+use crate::riot_api::RiotClient;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const FOLLOW_STATE_FILE: &str = "follow_state.json";
+
+// Match-v5 returns at most 100 IDs per request.
+const PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct KrakenFollowArgs {
+    pub puuid_file: PathBuf,
+    /// Holds `follow_state.json`, and the matches unless `out_dir` differs.
+    pub state_dir: PathBuf,
+    pub out_dir: PathBuf,
+    pub platform: String,
+    pub layout: MatchLayout,
+    pub max_req_per_2min: usize,
+    /// How far back the first invocation looks for players without a saved state.
+    pub since: TimeBound,
+    /// Comma-separated queue IDs or labels (`soloq,flex`) to keep, as in kraken-absorb.
+    pub queues: String,
+    /// Keep every queue; `queues` is ignored.
+    pub all_queues: bool,
+}
+
+/// Per-player progress kept between invocations.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FollowState {
+    pub players: BTreeMap<String, FollowedPlayer>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FollowedPlayer {
+    /// Start (Unix seconds) of the newest game seen for this player.
+    pub last_game_start: Option<i64>,
+    pub last_polled_at: Option<String>,
+    pub matches: usize,
+}
+
+impl FollowedPlayer {
+    fn advance(&mut self, game_start: i64) {
+        self.last_game_start = Some(
+            self.last_game_start
+                .map_or(game_start, |last| last.max(game_start)),
+        );
+    }
+}
+
+fn load_follow_state(path: &Path) -> Result<FollowState, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(FollowState::default());
+    }
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// Downloads, for every player in `puuid_file`, only the games that started after the newest
+/// game seen on the previous invocation. Run it from cron to keep a fixed cohort up to date.
+pub fn kraken_follow_run(args: &KrakenFollowArgs) -> Result<(), Box<dyn Error>> {
+    let queues = (!args.all_queues)
+        .then(|| parse_queues(&args.queues))
+        .transpose()?;
+    let puuids = read_puuid_file(&args.puuid_file)?;
+    if puuids.is_empty() {
+        return Err(format!("No PUUIDs in {}", args.puuid_file.display()).into());
+    }
+
+    fs::create_dir_all(&args.state_dir)?;
+    fs::create_dir_all(&args.out_dir)?;
+    let state_path = args.state_dir.join(FOLLOW_STATE_FILE);
+    let mut state = load_follow_state(&state_path)?;
+
+    // Cohort members share games, so a match is only downloaded for the first of them.
+    let manifest_path = args.out_dir.join(MANIFEST_FILE);
+    // Match ID -> game start (Unix seconds) of everything already on disk.
+    let mut saved: HashMap<String, i64> = if manifest_path.exists() {
+        read_manifest(&manifest_path)?
+            .into_iter()
+            .map(|row| (row.match_id, row.game_creation / 1000))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+    let manifest = KrakenManifest::open(&args.out_dir)?;
+    let client = RiotClient::for_platform(&args.platform, args.max_req_per_2min)?;
+    let default_start = args.since.resolve(Utc::now().timestamp());
+
+    let mut written = 0usize;
+    let mut failed = 0usize;
+    for puuid in &puuids {
+        let player = state.players.entry(puuid.clone()).or_default();
+        let start_time = player
+            .last_game_start
+            .map_or(default_start, |last| last + 1);
+
+        let mut match_ids = Vec::new();
+        let mut page_error = None;
+        loop {
+            match client.get_match_ids_page(
+                puuid,
+                match_ids.len(),
+                PAGE_SIZE,
+                Some(start_time),
+                None,
+            ) {
+                Ok(page) => {
+                    let full = page.len() == PAGE_SIZE;
+                    match_ids.extend(page);
+                    if !full {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    page_error = Some(err);
+                    break;
+                }
+            }
+        }
+        // Without the complete list the newest game is unknown; try this player next time.
+        if let Some(err) = page_error {
+            eprintln!(
+                "[kraken-follow] failed to list matches of {}: {}",
+                puuid, err
+            );
+            continue;
+        }
+
+        let mut player_written = 0usize;
+        for match_id in &match_ids {
+            if let Some(&game_start) = saved.get(match_id) {
+                player.advance(game_start);
+                continue;
+            }
+
+            let match_json = match client.get_match_json(match_id) {
+                Ok(json) => json,
+                Err(err) => {
+                    eprintln!("Failed to fetch match {}: {}", match_id, err);
+                    let entry = FailedMatch::new(match_id, &args.platform, puuid, err.as_ref());
+                    append_failed(&args.out_dir, &entry)?;
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            let game_start = match_json
+                .get("info")
+                .and_then(|info| info.get("gameCreation"))
+                .and_then(|gc| gc.as_i64())
+                .map(|millis| millis / 1000);
+            if let Some(game_start) = game_start {
+                player.advance(game_start);
+            }
+
+            if queues
+                .as_ref()
+                .is_some_and(|queues| !match_in_queues(&match_json, queues))
+            {
+                continue;
+            }
+
+            let (file_name, bytes) = save_match(&args.out_dir, args.layout, match_id, &match_json)?;
+            let row = ManifestRow::new(match_id, &match_json, puuid, &file_name, bytes);
+            manifest.append(&row)?;
+            saved.insert(match_id.clone(), row.game_creation / 1000);
+            player_written += 1;
+        }
+
+        player.matches += player_written;
+        player.last_polled_at = Some(Utc::now().to_rfc3339());
+        written += player_written;
+        eprintln!(
+            "[kraken-follow] {}: {} new matches ({} listed)",
+            puuid,
+            player_written,
+            match_ids.len()
+        );
+
+        // Saved after every player so an interrupted run keeps its progress.
+        write_json_atomic(&state_path, &state, true)?;
+    }
+
+    println!(
+        "Followed {} players: {} new matches written, {} failed",
+        puuids.len(),
+        written,
+        failed
+    );
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// Ranked tiers from lowest to highest, as returned by league-v4.
+pub const TIERS: [&str; 10] = [
+    "IRON",
+    "BRONZE",
+    "SILVER",
+    "GOLD",
+    "PLATINUM",
+    "EMERALD",
+    "DIAMOND",
+    "MASTER",
+    "GRANDMASTER",
+    "CHALLENGER",
+];
+
+/// 1 for IRON up to 10 for CHALLENGER; 0 for unranked or unknown tiers.
+pub fn tier_score(tier: &str) -> u32 {
+    TIERS
+        .iter()
+        .position(|t| t.eq_ignore_ascii_case(tier))
+        .map(|idx| idx as u32 + 1)
+        .unwrap_or(0)
+}
+
+const DIVISIONS: [&str; 4] = ["IV", "III", "II", "I"];
+
+fn parse_division(raw: &str) -> Option<u32> {
+    let raw = raw.trim().to_uppercase();
+    let idx = match raw.as_str() {
+        "4" => 0,
+        "3" => 1,
+        "2" => 2,
+        "1" => 3,
+        _ => DIVISIONS.iter().position(|d| *d == raw)?,
+    };
+    Some(idx as u32)
+}
+
+/// Position on the ladder counting divisions: IRON IV is 0, CHALLENGER I is 39. Apex tiers
+/// only report division I. Returns `None` for unknown tiers.
+pub fn rank_ordinal(tier: &str, division: &str) -> Option<u32> {
+    let tier_idx = tier_score(tier).checked_sub(1)?;
+    let division_idx = parse_division(division).unwrap_or(3);
+    Some(tier_idx * 4 + division_idx)
+}
+
+/// Inverse of [`rank_ordinal`]: `GOLD II`, or the bare tier for apex tiers.
+pub fn rank_label(ordinal: u32) -> String {
+    let tier_idx = (ordinal / 4).min(TIERS.len() as u32 - 1);
+    let tier = TIERS[tier_idx as usize];
+    if tier_idx + 1 >= tier_score("MASTER") {
+        tier.to_string()
+    } else {
+        format!("{} {}", tier, DIVISIONS[(ordinal % 4) as usize])
+    }
+}
+
+/// Parsed `--allow-ranks`: comma-separated tiers (`DIAMOND`), single divisions
+/// (`EMERALD II`) and inclusive ranges (`EMERALD..CHALLENGER`, `PLATINUM II..DIAMOND IV`).
+#[derive(Debug, Clone)]
+pub struct RankFilter {
+    ranges: Vec<(u32, u32)>,
+}
+
+impl RankFilter {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+        for item in raw.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let range = match item.split_once("..") {
+                Some((low, high)) => {
+                    let (low, _) = parse_rank_bounds(low)?;
+                    let (_, high) = parse_rank_bounds(high)?;
+                    if low > high {
+                        return Err(format!("Empty rank range '{}'", item));
+                    }
+                    (low, high)
+                }
+                None => parse_rank_bounds(item)?,
+            };
+            ranges.push(range);
+        }
+
+        if ranges.is_empty() {
+            return Err("--allow-ranks needs at least one tier".to_string());
+        }
+        Ok(Self { ranges })
+    }
+
+    pub fn allows(&self, tier: &str, division: &str) -> bool {
+        rank_ordinal(tier, division).is_some_and(|rank| {
+            self.ranges
+                .iter()
+                .any(|(lo, hi)| (*lo..=*hi).contains(&rank))
+        })
+    }
+}
+
+/// Lowest and highest ordinal matched by `TIER` (all four divisions) or `TIER DIV`.
+fn parse_rank_bounds(raw: &str) -> Result<(u32, u32), String> {
+    let mut parts = raw
+        .split(|c: char| c.is_whitespace() || c == '_')
+        .filter(|p| !p.is_empty());
+    let tier = parts.next().unwrap_or_default();
+    let tier_idx = tier_score(tier)
+        .checked_sub(1)
+        .ok_or_else(|| format!("Unknown tier '{}'", raw.trim()))?;
+
+    match parts.next() {
+        Some(division) => {
+            let division_idx = parse_division(division)
+                .ok_or_else(|| format!("Unknown division in '{}'", raw.trim()))?;
+            let ordinal = tier_idx * 4 + division_idx;
+            Ok((ordinal, ordinal))
+        }
+        None => Ok((tier_idx * 4, tier_idx * 4 + 3)),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct FrontierEntry {
+    priority: u32,
+    seq: Reverse<u64>,
+    puuid: String,
+    depth: u32,
+}
+
+impl Ord for FrontierEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| self.seq.cmp(&other.seq))
+    }
+}
+
+impl PartialOrd for FrontierEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Crawl frontier ordered by priority, FIFO among players with equal priority. Each entry
+/// carries its BFS depth (seeds are 0, players found in their matches 1, and so on).
+#[derive(Debug, Default)]
+pub struct KrakenFrontier {
+    heap: BinaryHeap<FrontierEntry>,
+    next_seq: u64,
+}
+
+impl KrakenFrontier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, puuid: String, priority: u32, depth: u32) {
+        self.heap.push(FrontierEntry {
+            priority,
+            seq: Reverse(self.next_seq),
+            puuid,
+            depth,
+        });
+        self.next_seq += 1;
+    }
+
+    /// Lifts a queued player to at least `priority`, keeping their place among equals and
+    /// their depth. Returns `false` when the player is not in the frontier (already crawled,
+    /// in flight or never queued). Rebuilds the heap, so it is meant for rare promotions.
+    pub fn raise(&mut self, puuid: &str, priority: u32) -> bool {
+        let mut entries = std::mem::take(&mut self.heap).into_vec();
+        let found = entries
+            .iter_mut()
+            .find(|entry| entry.puuid == puuid)
+            .map(|entry| entry.priority = entry.priority.max(priority))
+            .is_some();
+        self.heap = BinaryHeap::from(entries);
+        found
+    }
+
+    /// Returns the next player together with their crawl depth.
+    pub fn pop(&mut self) -> Option<(String, u32)> {
+        self.heap.pop().map(|entry| (entry.puuid, entry.depth))
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontier_pops_by_priority_then_insertion_order() {
+        let mut frontier = KrakenFrontier::new();
+        frontier.push("low".to_string(), 1, 2);
+        frontier.push("high-first".to_string(), 5, 1);
+        frontier.push("high-second".to_string(), 5, 1);
+        frontier.push("seed".to_string(), 0, 0);
+
+        let order: Vec<(String, u32)> = std::iter::from_fn(|| frontier.pop()).collect();
+
+        assert_eq!(
+            order,
+            vec![
+                ("high-first".to_string(), 1),
+                ("high-second".to_string(), 1),
+                ("low".to_string(), 2),
+                ("seed".to_string(), 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use csv::{Writer, WriterBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Mutex;
+
+pub const MANIFEST_FILE: &str = "manifest.csv";
+pub const RANKS_FILE: &str = "ranks.csv";
+
+/// One line of `manifest.csv`; `path` is relative to the directory holding the manifest.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestRow {
+    pub match_id: String,
+    pub queue: i64,
+    pub patch: String,
+    pub game_creation: i64,
+    pub source_puuid: String,
+    pub path: String,
+    pub bytes: u64,
+    /// Duo pairs detected in the match (`a+b`, `;`-separated); empty outside duo mode.
+    #[serde(default)]
+    pub duo: String,
+    /// Label of `queue` (`soloq`, `flex`, ...).
+    #[serde(default)]
+    pub queue_name: String,
+}
+
+impl ManifestRow {
+    pub fn new(
+        match_id: &str,
+        match_json: &Value,
+        source_puuid: &str,
+        path: &str,
+        bytes: u64,
+    ) -> Self {
+        let info = match_json.get("info");
+        let int_field = |key: &str| {
+            info.and_then(|i| i.get(key))
+                .and_then(|v| v.as_i64())
+                .unwrap_or_default()
+        };
+        let game_version = info
+            .and_then(|i| i.get("gameVersion"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+
+        let queue = int_field("queueId");
+        Self {
+            match_id: match_id.to_string(),
+            queue,
+            patch: patch_from_version(game_version),
+            game_creation: int_field("gameCreation"),
+            source_puuid: source_puuid.to_string(),
+            path: path.to_string(),
+            bytes,
+            duo: String::new(),
+            queue_name: queue_label(queue),
+        }
+    }
+}
+
+pub const QUEUE_LABELS: &[(i64, &str)] = &[
+    (420, "soloq"),
+    (440, "flex"),
+    (450, "aram"),
+    (400, "draft"),
+    (430, "blind"),
+    (490, "quickplay"),
+    (700, "clash"),
+    (1700, "arena"),
+    (1710, "arena"),
+    (0, "custom"),
+];
+
+/// Short directory-friendly name of a queue ID (`420` -> `soloq`); unknown queues keep their ID.
+pub fn queue_label(queue: i64) -> String {
+    QUEUE_LABELS
+        .iter()
+        .find(|(id, _)| *id == queue)
+        .map(|(_, label)| label.to_string())
+        .unwrap_or_else(|| format!("queue-{}", queue))
+}
+
+/// Parses a comma-separated list of queue IDs or labels (`soloq,flex`, `420,440`).
+pub fn parse_queues(raw: &str) -> Result<BTreeSet<i64>, String> {
+    let mut queues = BTreeSet::new();
+    for part in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        if let Ok(id) = part.parse::<i64>() {
+            queues.insert(id);
+            continue;
+        }
+        // A label can cover several queue IDs (`arena` is 1700 and 1710).
+        let labelled: Vec<i64> = QUEUE_LABELS
+            .iter()
+            .filter(|(_, label)| label.eq_ignore_ascii_case(part))
+            .map(|(id, _)| *id)
+            .collect();
+        if labelled.is_empty() {
+            return Err(format!("Unknown queue '{}'", part));
+        }
+        queues.extend(labelled);
+    }
+    if queues.is_empty() {
+        return Err("--queues must list at least one queue".to_string());
+    }
+    Ok(queues)
+}
+
+/// Reduces a full game version such as `14.3.558.1234` to its patch (`14.3`).
+pub fn patch_from_version(game_version: &str) -> String {
+    game_version
+        .split('.')
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Reads every row of a `manifest.csv`, e.g. to continue a crawl that predates checkpoints.
+pub fn read_manifest(path: &Path) -> Result<Vec<ManifestRow>, Box<dyn Error>> {
+    // Flexible: manifests started before the `duo` or `queue_name` columns existed gained
+    // rows with more fields than their header.
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let mut rows = Vec::new();
+    for row in reader.deserialize() {
+        rows.push(row?);
+    }
+    Ok(rows)
+}
+
+/// Append-only index of the matches written to one output directory. Each row is flushed
+/// immediately so the manifest stays in step with the files on disk if the crawl is killed.
+pub struct KrakenManifest {
+    writer: Mutex<Writer<File>>,
+}
+
+impl KrakenManifest {
+    pub fn open(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            writer: Mutex::new(open_csv_log(&dir.join(MANIFEST_FILE))?),
+        })
+    }
+
+    pub fn append(&self, row: &ManifestRow) -> Result<(), Box<dyn Error>> {
+        let mut writer = self.writer.lock().expect("manifest mutex poisoned");
+        writer.serialize(row)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Opens `path` for appending, writing the header only when the file is new or empty.
+fn open_csv_log(path: &Path) -> Result<Writer<File>, Box<dyn Error>> {
+    let needs_header = fs::metadata(path).map(|m| m.len() == 0).unwrap_or(true);
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    Ok(WriterBuilder::new()
+        .has_headers(needs_header)
+        .from_writer(file))
+}
+
+/// One line of `ranks.csv`: the solo queue rank of a player when kraken looked it up. `tier`
+/// and `division` are empty for unranked players.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RankRow {
+    pub puuid: String,
+    pub tier: String,
+    pub division: String,
+}
+
+pub fn read_ranks(path: &Path) -> Result<Vec<RankRow>, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut rows = Vec::new();
+    for row in reader.deserialize() {
+        rows.push(row?);
+    }
+    Ok(rows)
+}
+
+/// Append-only record of the ranks looked up for one output directory, flushed row by row like
+/// [`KrakenManifest`].
+pub struct KrakenRanks {
+    writer: Mutex<Writer<File>>,
+}
+
+impl KrakenRanks {
+    pub fn open(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            writer: Mutex::new(open_csv_log(&dir.join(RANKS_FILE))?),
+        })
+    }
+
+    pub fn append(&self, row: &RankRow) -> Result<(), Box<dyn Error>> {
+        let mut writer = self.writer.lock().expect("ranks mutex poisoned");
+        writer.serialize(row)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
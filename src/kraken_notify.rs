@@ -0,0 +1,91 @@
+use crate::kraken::{KrakenRunSummary, KrakenStopReason};
+use reqwest::blocking::Client;
+use serde_json::{Value, json};
+use std::error::Error;
+use std::time::Duration;
+
+// Discord rejects webhook messages whose content exceeds 2000 characters.
+const DISCORD_MAX_CONTENT: usize = 2000;
+
+/// Posts the end-of-run summary (stop reason, final status and coverage) to `url`.
+pub fn notify_run_summary(
+    url: &str,
+    format: &str,
+    summary: &KrakenRunSummary,
+) -> Result<(), Box<dyn Error>> {
+    let event = match summary.reason {
+        KrakenStopReason::Error => "failed",
+        KrakenStopReason::IdleExit => "idle-exit",
+        _ => "finished",
+    };
+
+    let payload = match format {
+        "discord" => {
+            let coverage = &summary.coverage;
+            let mut content = format!(
+                "**kraken-absorb {}** (reason: {})\nelapsed={}s fetched={} written={} queue={}\nplayers={} profiles 5+={} 10+={} 20+={}",
+                event,
+                serde_json::to_value(&summary.reason)?
+                    .as_str()
+                    .unwrap_or_default(),
+                summary.status.elapsed_secs,
+                summary.status.fetched,
+                summary.status.written,
+                summary.status.queue,
+                coverage.total_players,
+                coverage.profiles_5plus,
+                coverage.profiles_10plus,
+                coverage.profiles_20plus,
+            );
+            if let Some(err) = &summary.error {
+                content.push_str(&format!("\nerror: {}", err));
+            }
+            discord_payload(content)
+        }
+        _ => {
+            let mut payload = serde_json::to_value(summary)?;
+            payload["event"] = json!(event);
+            payload
+        }
+    };
+
+    post_payload(url, &payload)
+}
+
+/// Posts a failure notice for runs that aborted before any crawl statistics existed.
+pub fn notify_run_aborted(url: &str, format: &str, error: &str) -> Result<(), Box<dyn Error>> {
+    let payload = match format {
+        "discord" => discord_payload(format!("**kraken-absorb failed**\nerror: {}", error)),
+        _ => json!({
+            "event": "failed",
+            "reason": KrakenStopReason::Error,
+            "error": error,
+        }),
+    };
+
+    post_payload(url, &payload)
+}
+
+fn discord_payload(mut content: String) -> Value {
+    if content.chars().count() > DISCORD_MAX_CONTENT {
+        content = content.chars().take(DISCORD_MAX_CONTENT - 3).collect();
+        content.push_str("...");
+    }
+
+    json!({ "content": content })
+}
+
+fn post_payload(url: &str, payload: &Value) -> Result<(), Box<dyn Error>> {
+    let response = Client::builder()
+        .timeout(Duration::from_secs(15))
+        .build()?
+        .post(url)
+        .json(payload)
+        .send()?;
+
+    if !response.status().is_success() {
+        return Err(format!("Webhook responded with status {}", response.status()).into());
+    }
+
+    Ok(())
+}
@@ -1,25 +1,59 @@
+use std::collections::{HashMap, VecDeque};
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, Result};
+use anyhow::{Result, anyhow};
 use polars::prelude::*;
 
-pub fn kraken_prepare_ml_dispatch(
-    variant: &str,
-    player_parquet: Option<PathBuf>,
-    team_parquet: Option<PathBuf>,
-    out_dir: &Path,
-    history_size: usize,
-    min_matches: usize,
-) -> Result<()> {
+use crate::parquet_extract::{ParquetOptions, with_lane_opponent};
+
+pub struct PrepareMlArgs {
+    pub variant: String,
+    pub player_parquet: Option<PathBuf>,
+    pub team_parquet: Option<PathBuf>,
+    pub out_dir: PathBuf,
+    pub history_size: usize,
+    pub min_matches: usize,
+    pub matchups: MatchupOptions,
+    pub parquet: ParquetOptions,
+}
+
+/// How champion matchup win rates are turned into priors.
+#[derive(Debug, Clone, Copy)]
+pub struct MatchupOptions {
+    /// Matchups seen fewer times get a null win rate.
+    pub min_games: usize,
+    /// Pseudo-games at 50% added to every matchup, pulling rare ones toward even.
+    pub smoothing: f64,
+}
+
+impl MatchupOptions {
+    fn win_rate(&self, games: usize, wins: usize) -> Option<f64> {
+        (games > 0 && games >= self.min_games)
+            .then(|| (wins as f64 + self.smoothing * 0.5) / (games as f64 + self.smoothing))
+    }
+}
+
+pub fn kraken_prepare_ml_dispatch(args: PrepareMlArgs) -> Result<()> {
+    let PrepareMlArgs {
+        variant,
+        player_parquet,
+        team_parquet,
+        out_dir,
+        history_size,
+        min_matches,
+        matchups,
+        parquet,
+    } = args;
+    let out_dir = out_dir.as_path();
     fs::create_dir_all(out_dir)?;
 
-    match variant {
+    match variant.as_str() {
         "team-outcome" => {
             let Some(team_path) = team_parquet else {
                 return Err(anyhow!("--team-parquet is required for team-outcome"));
             };
-            kraken_build_ml_team_outcome(&team_path, out_dir)
+            kraken_build_ml_team_outcome(&team_path, out_dir, parquet)
         }
         "player-profile-only" => {
             let Some(player_path) = player_parquet else {
@@ -27,7 +61,7 @@ pub fn kraken_prepare_ml_dispatch(
                     "--player-parquet is required for player-profile-only"
                 ));
             };
-            kraken_build_player_profile(&player_path, out_dir, history_size, min_matches)
+            kraken_build_player_profile(&player_path, out_dir, history_size, min_matches, parquet)
         }
         "lobby-outcome" => {
             let Some(player_path) = player_parquet else {
@@ -36,13 +70,23 @@ pub fn kraken_prepare_ml_dispatch(
             let Some(team_path) = team_parquet else {
                 return Err(anyhow!("--team-parquet is required for lobby-outcome"));
             };
-            let profile_path = out_dir.join("player_profile.parquet");
-            let profile_opt = if profile_path.exists() {
-                Some(profile_path)
-            } else {
-                None
+            kraken_build_ml_lobby_outcome(
+                &player_path,
+                &team_path,
+                out_dir,
+                history_size,
+                min_matches,
+                matchups,
+                parquet,
+            )
+        }
+        "champion-matchups" => {
+            let Some(player_path) = player_parquet else {
+                return Err(anyhow!(
+                    "--player-parquet is required for champion-matchups"
+                ));
             };
-            kraken_build_ml_lobby_outcome(&player_path, &team_path, profile_opt.as_deref(), out_dir)
+            kraken_build_champion_matchups(&player_path, out_dir, matchups, parquet)
         }
         _ => Err(anyhow!("Unknown variant: {}", variant)),
     }
@@ -53,6 +97,7 @@ pub fn kraken_build_player_profile(
     out_dir: &Path,
     history_size: usize,
     min_matches: usize,
+    parquet: ParquetOptions,
 ) -> Result<()> {
     println!(
         "Building player profiles with history_size={}, min_matches={}",
@@ -63,28 +108,23 @@ pub fn kraken_build_player_profile(
     let lf = LazyFrame::scan_parquet(player_parquet, Default::default())?;
 
     // Filter for ranked SoloQ and valid roles
-    let filtered = lf
-        .filter(col("queue_id").eq(lit(420i32)))
-        .filter(
-            col("role")
-                .eq(lit("TOP"))
-                .or(col("role").eq(lit("JUNGLE")))
-                .or(col("role").eq(lit("MIDDLE")))
-                .or(col("role").eq(lit("BOTTOM")))
-                .or(col("role").eq(lit("UTILITY"))),
-        );
+    let filtered = lf.filter(col("queue_id").eq(lit(420i32))).filter(
+        col("role")
+            .eq(lit("TOP"))
+            .or(col("role").eq(lit("JUNGLE")))
+            .or(col("role").eq(lit("MIDDLE")))
+            .or(col("role").eq(lit("BOTTOM")))
+            .or(col("role").eq(lit("UTILITY"))),
+    );
 
     // Compute cs_per_min if not present (from total_cs and game_duration)
-    let with_derived = filtered.with_columns([
-    when(col("game_duration").gt(lit(0)))
+    let with_derived = filtered.with_columns([when(col("game_duration").gt(lit(0)))
         .then(
             (col("total_cs").cast(DataType::Float64) * lit(60.0))
                 / col("game_duration").cast(DataType::Float64),
         )
         .otherwise(lit(NULL))
-        .alias("cs_per_min"),
-    ]);
-
+        .alias("cs_per_min")]);
 
     // Add ranking within each (puuid, role) group by game_creation descending
     let with_rank = with_derived.with_column(
@@ -93,7 +133,6 @@ pub fn kraken_build_player_profile(
                 RankOptions {
                     method: RankMethod::Dense,
                     descending: true,
-                    ..Default::default()
                 },
                 None,
             )
@@ -110,27 +149,39 @@ pub fn kraken_build_player_profile(
         .agg([
             // Count of games used
             len().alias("games_used"),
-            
             // Win rate
-            col("win").cast(DataType::Float64).mean().alias("recent_winrate"),
-            
+            col("win")
+                .cast(DataType::Float64)
+                .mean()
+                .alias("recent_winrate"),
             // KDA stats
-            col("kills").cast(DataType::Float64).mean().alias("recent_avg_kills"),
-            col("deaths").cast(DataType::Float64).mean().alias("recent_avg_deaths"),
-            col("assists").cast(DataType::Float64).mean().alias("recent_avg_assists"),
-            
+            col("kills")
+                .cast(DataType::Float64)
+                .mean()
+                .alias("recent_avg_kills"),
+            col("deaths")
+                .cast(DataType::Float64)
+                .mean()
+                .alias("recent_avg_deaths"),
+            col("assists")
+                .cast(DataType::Float64)
+                .mean()
+                .alias("recent_avg_assists"),
             // Per-minute stats (use existing columns or computed)
             col("gold_per_min").mean().alias("recent_avg_gold_per_min"),
-            col("damage_per_min").mean().alias("recent_avg_damage_per_min"),
-            col("vision_score_per_min").mean().alias("recent_avg_vision_score_per_min"),
-            
-            // CS per minute (prefer existing column, fallback to computed)
-            col("cs_per_min")
+            col("damage_per_min")
                 .mean()
-                .alias("recent_avg_cs_per_min"),
-            
+                .alias("recent_avg_damage_per_min"),
+            col("vision_score_per_min")
+                .mean()
+                .alias("recent_avg_vision_score_per_min"),
+            // CS per minute (prefer existing column, fallback to computed)
+            col("cs_per_min").mean().alias("recent_avg_cs_per_min"),
             // Game duration average (in seconds)
-            col("game_duration").cast(DataType::Float64).mean().alias("recent_avg_game_duration"),
+            col("game_duration")
+                .cast(DataType::Float64)
+                .mean()
+                .alias("recent_avg_game_duration"),
         ])
         .filter(col("games_used").gt_eq(lit(min_matches as u32)));
 
@@ -138,18 +189,18 @@ pub fn kraken_build_player_profile(
     let mut df = profiles.collect()?;
     let out_path = out_dir.join("player_profile.parquet");
     let mut file = std::fs::File::create(&out_path)?;
-    ParquetWriter::new(&mut file).finish(&mut df)?;
+    parquet.writer(&mut file).finish(&mut df)?;
 
-    println!(
-        "✓ Built {} player profiles → {:?}",
-        df.height(),
-        out_path
-    );
+    println!("✓ Built {} player profiles → {:?}", df.height(), out_path);
 
     Ok(())
 }
 
-pub fn kraken_build_ml_team_outcome(team_parquet: &Path, out_dir: &Path) -> Result<()> {
+pub fn kraken_build_ml_team_outcome(
+    team_parquet: &Path,
+    out_dir: &Path,
+    parquet: ParquetOptions,
+) -> Result<()> {
     let lf = LazyFrame::scan_parquet(team_parquet, Default::default())?
         .filter(col("queue_id").eq(lit(420i32)))
         .select([
@@ -186,24 +237,33 @@ pub fn kraken_build_ml_team_outcome(team_parquet: &Path, out_dir: &Path) -> Resu
     let mut df = lf.collect()?;
     let out_path = out_dir.join("ml_team_outcome.parquet");
     let mut file = std::fs::File::create(out_path)?;
-    ParquetWriter::new(&mut file).finish(&mut df)?;
+    parquet.writer(&mut file).finish(&mut df)?;
     Ok(())
 }
 
+/// One row per team per SoloQ match: the draft of both sides by role, for every player their
+/// form going into the match (`{ally|enemy}_{role}_recent_*`) and for every lane the win rate of
+/// the ally champion against the enemy one (`{role}_matchup_*`). Form is computed as of the
+/// match from the player's previous `history_size` games in that role, and matchups from the
+/// SoloQ games started before it, so no feature sees the match itself or anything played after
+/// it.
 pub fn kraken_build_ml_lobby_outcome(
     player_parquet: &Path,
     team_parquet: &Path,
-    player_profile_parquet: Option<&Path>,
     out_dir: &Path,
+    history_size: usize,
+    min_matches: usize,
+    matchups: MatchupOptions,
+    parquet: ParquetOptions,
 ) -> Result<()> {
-    let players = LazyFrame::scan_parquet(player_parquet, Default::default())?
+    let scanned = LazyFrame::scan_parquet(player_parquet, Default::default())?;
+    let players = scanned
+        .clone()
         .filter(col("queue_id").eq(lit(420i32)))
         .with_column(col("team_id").cast(DataType::Int32));
 
-    let roles = ["TOP", "JUNGLE", "MIDDLE", "BOTTOM", "UTILITY"];
-
     let mut aggs: Vec<Expr> = Vec::new();
-    for role in roles.iter() {
+    for role in LOBBY_ROLES.iter() {
         let lower = role.to_lowercase();
         let champ_alias = format!("ally_{}_champion_id", lower);
         let puuid_alias = format!("ally_{}_puuid", lower);
@@ -230,13 +290,13 @@ pub fn kraken_build_ml_lobby_outcome(
             .alias("enemy_team_id")]);
 
     let mut enemy_select: Vec<Expr> = vec![col("match_id"), col("team_id").alias("enemy_team_id")];
-    for role in roles.iter() {
+    for role in LOBBY_ROLES.iter() {
         let lower = role.to_lowercase();
         let ally_champ = format!("ally_{}_champion_id", lower);
         let ally_puuid = format!("ally_{}_puuid", lower);
         let enemy_champ = format!("enemy_{}_champion_id", lower);
         let enemy_puuid = format!("enemy_{}_puuid", lower);
-        
+
         enemy_select.push(col(&ally_champ).alias(&enemy_champ));
         enemy_select.push(col(&ally_puuid).alias(&enemy_puuid));
     }
@@ -257,6 +317,7 @@ pub fn kraken_build_ml_lobby_outcome(
         .select([
             col("match_id"),
             col("queue_id"),
+            col("game_creation"),
             col("team_id").cast(DataType::Int32).alias("team_id"),
             col("team_side"),
             col("team_win"),
@@ -269,92 +330,529 @@ pub fn kraken_build_ml_lobby_outcome(
         JoinArgs::new(JoinType::Inner),
     );
 
-    if let Some(profile_path) = player_profile_parquet {
-        let profile = LazyFrame::scan_parquet(profile_path, Default::default())?;
-        for role in roles.iter() {
-            let lower = role.to_lowercase();
-            let role_profile = profile.clone().filter(col("role").eq(lit(*role))).select([
-                col("puuid"),
-                col("games_used").alias("recent_games"),
-                col("recent_winrate"),
-                col("recent_avg_gold_per_min"),
-                col("recent_avg_damage_per_min"),
-                col("recent_avg_vision_score_per_min"),
-            ]);
-
-            let ally_puuid_col = format!("ally_{}_puuid", lower);
-            let ally_cols: [String; 5] = [
-                format!("ally_{}_recent_games", lower),
-                format!("ally_{}_recent_winrate", lower),
-                format!("ally_{}_recent_gold_per_min", lower),
-                format!("ally_{}_recent_damage_per_min", lower),
-                format!("ally_{}_recent_vision_per_min", lower),
-            ];
-
-            lobby = lobby
-                .join(
-                    role_profile.clone(),
-                    [col(&ally_puuid_col)],
-                    [col("puuid")],
-                    JoinArgs::new(JoinType::Left),
-                )
-                .rename(
-                    &[
-                        "recent_games",
-                        "recent_winrate",
-                        "recent_avg_gold_per_min",
-                        "recent_avg_damage_per_min",
-                        "recent_avg_vision_score_per_min",
-                    ],
-                    &[
-                        &ally_cols[0],
-                        &ally_cols[1],
-                        &ally_cols[2],
-                        &ally_cols[3],
-                        &ally_cols[4],
-                    ],
-                )
-                .drop(["puuid"]);
-
-            let enemy_puuid_col = format!("enemy_{}_puuid", lower);
-            let enemy_cols: [String; 5] = [
-                format!("enemy_{}_recent_games", lower),
-                format!("enemy_{}_recent_winrate", lower),
-                format!("enemy_{}_recent_gold_per_min", lower),
-                format!("enemy_{}_recent_damage_per_min", lower),
-                format!("enemy_{}_recent_vision_per_min", lower),
-            ];
-
-            lobby = lobby
-                .join(
-                    role_profile,
-                    [col(&enemy_puuid_col)],
-                    [col("puuid")],
-                    JoinArgs::new(JoinType::Left),
-                )
-                .rename(
-                    &[
-                        "recent_games",
-                        "recent_winrate",
-                        "recent_avg_gold_per_min",
-                        "recent_avg_damage_per_min",
-                        "recent_avg_vision_score_per_min",
-                    ],
-                    &[
-                        &enemy_cols[0],
-                        &enemy_cols[1],
-                        &enemy_cols[2],
-                        &enemy_cols[3],
-                        &enemy_cols[4],
-                    ],
-                )
-                .drop(["puuid"]);
+    let form = as_of_player_form(scanned.clone(), history_size, min_matches)?.lazy();
+    for side in ["ally", "enemy"] {
+        for role in LOBBY_ROLES.iter() {
+            let slot = format!("{}_{}", side, role.to_lowercase());
+            let puuid_col = format!("{}_puuid", slot);
+            let mut select = vec![col("match_id"), col("puuid").alias(&puuid_col)];
+            select.extend(
+                FORM_COLUMNS
+                    .iter()
+                    .map(|(column, suffix)| col(column).alias(&format!("{}_{}", slot, suffix))),
+            );
+            lobby = lobby.join(
+                form.clone().select(select),
+                [col("match_id"), col(&puuid_col)],
+                [col("match_id"), col(&puuid_col)],
+                JoinArgs::new(JoinType::Left),
+            );
         }
     }
 
+    let (priors, _) = champion_matchups(scanned, matchups)?;
+    let lobby = lobby.join(
+        lane_matchups(priors.lazy()),
+        [col("match_id"), col("team_id")],
+        [col("match_id"), col("team_id")],
+        JoinArgs::new(JoinType::Left),
+    );
+
     let mut df = lobby.collect()?;
     let out_path = out_dir.join("ml_lobby_outcome.parquet");
     let mut file = std::fs::File::create(out_path)?;
-    ParquetWriter::new(&mut file).finish(&mut df)?;
+    parquet.writer(&mut file).finish(&mut df)?;
     Ok(())
 }
+
+/// Roles of a lobby slot, in the order their columns are written.
+const LOBBY_ROLES: [&str; 5] = ["TOP", "JUNGLE", "MIDDLE", "BOTTOM", "UTILITY"];
+
+/// One row per team per match with `{role}_matchup_games` and `{role}_matchup_winrate` for each
+/// lane, taken from the first prior of that role like the lobby takes the first player of it, so
+/// a team with two players in one role cannot duplicate its lobby row.
+fn lane_matchups(priors: LazyFrame) -> LazyFrame {
+    let mut aggs: Vec<Expr> = Vec::new();
+    for role in LOBBY_ROLES.iter() {
+        let lower = role.to_lowercase();
+        for column in ["matchup_games", "matchup_winrate"] {
+            aggs.push(
+                col(column)
+                    .filter(col("role").eq(lit(*role)))
+                    .first()
+                    .alias(&format!("{}_{}", lower, column)),
+            );
+        }
+    }
+    priors.group_by([col("match_id"), col("team_id")]).agg(aggs)
+}
+
+/// Columns of [`as_of_player_form`] and the suffix they get in each lobby slot.
+const FORM_COLUMNS: [(&str, &str); 5] = [
+    ("recent_games", "recent_games"),
+    ("recent_winrate", "recent_winrate"),
+    ("recent_gold_per_min", "recent_gold_per_min"),
+    ("recent_damage_per_min", "recent_damage_per_min"),
+    ("recent_vision_score_per_min", "recent_vision_per_min"),
+];
+
+/// Win, gold, damage and vision of one game, as averaged into a player's form.
+type FormGame = [Option<f64>; 4];
+
+/// The form of every player going into each SoloQ game they played in a standard role, keyed by
+/// `match_id` and `puuid`: how many of their previous games in that role were used (at most
+/// `history_size`, all strictly earlier by `game_creation`) and their averages. The averages
+/// stay null until `min_matches` earlier games exist.
+fn as_of_player_form(
+    players: LazyFrame,
+    history_size: usize,
+    min_matches: usize,
+) -> Result<DataFrame> {
+    let games = players
+        .filter(col("queue_id").eq(lit(420i32)))
+        .filter(
+            col("role")
+                .eq(lit("TOP"))
+                .or(col("role").eq(lit("JUNGLE")))
+                .or(col("role").eq(lit("MIDDLE")))
+                .or(col("role").eq(lit("BOTTOM")))
+                .or(col("role").eq(lit("UTILITY"))),
+        )
+        .select([
+            col("puuid"),
+            col("role"),
+            col("match_id"),
+            col("game_creation").cast(DataType::Int64),
+            col("win").cast(DataType::Float64),
+            col("gold_per_min").cast(DataType::Float64),
+            col("damage_per_min").cast(DataType::Float64),
+            col("vision_score_per_min").cast(DataType::Float64),
+        ])
+        .sort_by_exprs(
+            [col("puuid"), col("role"), col("game_creation")],
+            [false, false, false],
+            false,
+            false,
+        )
+        .collect()?;
+
+    let puuids = games.column("puuid")?.str()?;
+    let roles = games.column("role")?.str()?;
+    let match_ids = games.column("match_id")?.str()?;
+    let creations = games.column("game_creation")?.i64()?;
+    let stats = [
+        games.column("win")?.f64()?,
+        games.column("gold_per_min")?.f64()?,
+        games.column("damage_per_min")?.f64()?,
+        games.column("vision_score_per_min")?.f64()?,
+    ];
+
+    let height = games.height();
+    let mut out_match_ids: Vec<Option<&str>> = Vec::with_capacity(height);
+    let mut out_puuids: Vec<Option<&str>> = Vec::with_capacity(height);
+    let mut recent_games: Vec<u32> = Vec::with_capacity(height);
+    let mut averages: [Vec<Option<f64>>; 4] = Default::default();
+
+    // The player's last `history_size` games, oldest first, plus games at the current timestamp
+    // that only join the history once a later game is reached.
+    let mut history: VecDeque<FormGame> = VecDeque::with_capacity(history_size + 1);
+    let mut pending: Vec<(Option<i64>, FormGame)> = Vec::new();
+    for idx in 0..height {
+        let same_player = idx > 0
+            && puuids.get(idx) == puuids.get(idx - 1)
+            && roles.get(idx) == roles.get(idx - 1);
+        if !same_player {
+            history.clear();
+            pending.clear();
+        }
+        let creation = creations.get(idx);
+        if pending.first().is_some_and(|(at, _)| *at != creation) {
+            for (_, game) in pending.drain(..) {
+                history.push_back(game);
+                if history.len() > history_size {
+                    history.pop_front();
+                }
+            }
+        }
+
+        out_match_ids.push(match_ids.get(idx));
+        out_puuids.push(puuids.get(idx));
+        recent_games.push(history.len() as u32);
+        for (stat, column) in averages.iter_mut().enumerate() {
+            let values: Vec<f64> = history.iter().filter_map(|game| game[stat]).collect();
+            column.push(
+                (history.len() >= min_matches.max(1) && !values.is_empty())
+                    .then(|| values.iter().sum::<f64>() / values.len() as f64),
+            );
+        }
+
+        pending.push((creation, stats.map(|column| column.get(idx))));
+    }
+
+    let [winrate, gold, damage, vision] = averages;
+    Ok(DataFrame::new(vec![
+        Series::new("match_id", out_match_ids),
+        Series::new("puuid", out_puuids),
+        Series::new("recent_games", recent_games),
+        Series::new("recent_winrate", winrate),
+        Series::new("recent_gold_per_min", gold),
+        Series::new("recent_damage_per_min", damage),
+        Series::new("recent_vision_score_per_min", vision),
+    ])?)
+}
+
+/// Writes `champion_matchups.parquet`: per role, the record of every champion against every
+/// lane opponent over the whole player Parquet, with the smoothed win rate used as a prior.
+/// Matchups seen fewer than `min_games` times are left out.
+pub fn kraken_build_champion_matchups(
+    player_parquet: &Path,
+    out_dir: &Path,
+    matchups: MatchupOptions,
+    parquet: ParquetOptions,
+) -> Result<()> {
+    let players = LazyFrame::scan_parquet(player_parquet, Default::default())?;
+    let (_, totals) = champion_matchups(players, matchups)?;
+    let mut rows: Vec<(&MatchupKey, &(usize, usize))> = totals
+        .iter()
+        .filter(|(_, (games, _))| *games >= matchups.min_games)
+        .collect();
+    rows.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut df = DataFrame::new(vec![
+        Series::new(
+            "role",
+            rows.iter()
+                .map(|((role, _, _), _)| role.as_str())
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "champion_id",
+            rows.iter()
+                .map(|((_, champion, _), _)| *champion)
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "opp_champion_id",
+            rows.iter()
+                .map(|((_, _, opponent), _)| *opponent)
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "games",
+            rows.iter()
+                .map(|(_, (games, _))| *games as u32)
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "win_rate",
+            rows.iter()
+                .map(|(_, (games, wins))| *wins as f64 / *games as f64)
+                .collect::<Vec<_>>(),
+        ),
+        Series::new(
+            "smoothed_win_rate",
+            rows.iter()
+                .map(|(_, (games, wins))| matchups.win_rate(*games, *wins))
+                .collect::<Vec<_>>(),
+        ),
+    ])?;
+
+    let out_path = out_dir.join("champion_matchups.parquet");
+    let mut file = std::fs::File::create(&out_path)?;
+    parquet.writer(&mut file).finish(&mut df)?;
+    println!("✓ Wrote {} champion matchups → {:?}", df.height(), out_path);
+    Ok(())
+}
+
+/// Role, champion and lane opponent's champion.
+type MatchupKey = (String, Option<i32>, Option<i32>);
+/// Games and wins per matchup.
+type MatchupTotals = HashMap<MatchupKey, (usize, usize)>;
+
+/// Champion matchups from the SoloQ games of a player-level Parquet. Returns the prior of every
+/// player's lane as of their match (`match_id`, `team_id`, `role`, `matchup_games` and the
+/// smoothed `matchup_winrate` of the player's champion against the opponent's over earlier
+/// games only), and the games and wins of each matchup over the whole file.
+fn champion_matchups(
+    players: LazyFrame,
+    options: MatchupOptions,
+) -> Result<(DataFrame, MatchupTotals)> {
+    let players = players
+        .filter(col("queue_id").eq(lit(420i32)))
+        .with_column(col("team_id").cast(DataType::Int32));
+    let lanes = with_lane_opponent(players, &["champion_id"])
+        .select([
+            col("match_id"),
+            col("game_creation").cast(DataType::Int64),
+            col("team_id"),
+            col("role"),
+            col("champion_id").cast(DataType::Int32),
+            col("opp_champion_id").cast(DataType::Int32),
+            col("win"),
+        ])
+        .sort_by_exprs(
+            [col("game_creation"), col("match_id")],
+            [false, false],
+            false,
+            false,
+        )
+        .collect()?;
+
+    let match_ids = lanes.column("match_id")?.str()?;
+    let creations = lanes.column("game_creation")?.i64()?;
+    let team_ids = lanes.column("team_id")?.i32()?;
+    let roles = lanes.column("role")?.str()?;
+    let champions = lanes.column("champion_id")?.i32()?;
+    let opponents = lanes.column("opp_champion_id")?.i32()?;
+    let wins = lanes.column("win")?.bool()?;
+
+    let height = lanes.height();
+    let mut out_match_ids: Vec<Option<&str>> = Vec::with_capacity(height);
+    let mut out_team_ids: Vec<Option<i32>> = Vec::with_capacity(height);
+    let mut out_roles: Vec<Option<&str>> = Vec::with_capacity(height);
+    let mut matchup_games: Vec<Option<u32>> = Vec::with_capacity(height);
+    let mut matchup_winrate: Vec<Option<f64>> = Vec::with_capacity(height);
+
+    let mut totals = MatchupTotals::new();
+    // Games at the current timestamp only count once a later game is reached.
+    let mut pending: Vec<(Option<i64>, MatchupKey, bool)> = Vec::new();
+    for idx in 0..height {
+        let creation = creations.get(idx);
+        if pending.first().is_some_and(|(at, _, _)| *at != creation) {
+            for (_, key, win) in pending.drain(..) {
+                let entry = totals.entry(key).or_insert((0, 0));
+                entry.0 += 1;
+                entry.1 += win as usize;
+            }
+        }
+
+        let (champion, opponent) = (champions.get(idx), opponents.get(idx));
+        out_match_ids.push(match_ids.get(idx));
+        out_team_ids.push(team_ids.get(idx));
+        out_roles.push(roles.get(idx));
+        if champion.is_none() || opponent.is_none() {
+            matchup_games.push(None);
+            matchup_winrate.push(None);
+            continue;
+        }
+        let key = (
+            roles.get(idx).unwrap_or_default().to_string(),
+            champion,
+            opponent,
+        );
+        let (games, won) = totals.get(&key).copied().unwrap_or((0, 0));
+        matchup_games.push(Some(games as u32));
+        matchup_winrate.push(options.win_rate(games, won));
+        if let Some(win) = wins.get(idx) {
+            pending.push((creation, key, win));
+        }
+    }
+    for (_, key, win) in pending {
+        let entry = totals.entry(key).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 += win as usize;
+    }
+
+    let priors = DataFrame::new(vec![
+        Series::new("match_id", out_match_ids),
+        Series::new("team_id", out_team_ids),
+        Series::new("role", out_roles),
+        Series::new("matchup_games", matchup_games),
+        Series::new("matchup_winrate", matchup_winrate),
+    ])?;
+    Ok((priors, totals))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Player rows with the columns the as-of builders read; `game_creation` is in milliseconds.
+    fn players(rows: &[(&str, &str, &str, i64, i32, bool, f64)]) -> LazyFrame {
+        df! {
+            "puuid" => rows.iter().map(|row| row.0).collect::<Vec<_>>(),
+            "role" => rows.iter().map(|row| row.1).collect::<Vec<_>>(),
+            "match_id" => rows.iter().map(|row| row.2).collect::<Vec<_>>(),
+            "game_creation" => rows.iter().map(|row| row.3).collect::<Vec<_>>(),
+            "queue_id" => rows.iter().map(|row| row.4).collect::<Vec<_>>(),
+            "win" => rows.iter().map(|row| row.5).collect::<Vec<_>>(),
+            "gold_per_min" => rows.iter().map(|row| row.6).collect::<Vec<_>>(),
+            "damage_per_min" => rows.iter().map(|row| row.6 * 2.0).collect::<Vec<_>>(),
+            "vision_score_per_min" => rows.iter().map(|_| 1.0).collect::<Vec<_>>(),
+        }
+        .expect("valid test frame")
+        .lazy()
+    }
+
+    /// `(recent_games, recent_winrate, recent_gold_per_min)` of `puuid` going into `match_id`.
+    fn form_of(form: &DataFrame, match_id: &str, puuid: &str) -> (u32, Option<f64>, Option<f64>) {
+        let row = form
+            .clone()
+            .lazy()
+            .filter(
+                col("match_id")
+                    .eq(lit(match_id))
+                    .and(col("puuid").eq(lit(puuid))),
+            )
+            .collect()
+            .unwrap();
+        assert_eq!(
+            row.height(),
+            1,
+            "one form row for {} in {}",
+            puuid,
+            match_id
+        );
+        (
+            row.column("recent_games")
+                .unwrap()
+                .u32()
+                .unwrap()
+                .get(0)
+                .unwrap(),
+            row.column("recent_winrate").unwrap().f64().unwrap().get(0),
+            row.column("recent_gold_per_min")
+                .unwrap()
+                .f64()
+                .unwrap()
+                .get(0),
+        )
+    }
+
+    #[test]
+    fn player_form_only_uses_strictly_earlier_games() {
+        let form = as_of_player_form(
+            players(&[
+                ("a", "MIDDLE", "M1", 1_000, 420, true, 100.0),
+                // M2 and M3 start together, so neither sees the other.
+                ("a", "MIDDLE", "M2", 2_000, 420, false, 200.0),
+                ("a", "MIDDLE", "M3", 2_000, 420, true, 300.0),
+                ("a", "MIDDLE", "M4", 3_000, 420, true, 400.0),
+                ("a", "MIDDLE", "M5", 4_000, 420, false, 500.0),
+                // Other queues never count, and each role has its own history.
+                ("a", "MIDDLE", "F1", 3_500, 440, false, 900.0),
+                ("a", "TOP", "M6", 5_000, 420, true, 600.0),
+            ]),
+            2,
+            2,
+        )
+        .unwrap();
+
+        assert_eq!(form.height(), 6);
+        assert_eq!(form_of(&form, "M1", "a"), (0, None, None));
+        // One earlier game is below min_matches.
+        assert_eq!(form_of(&form, "M2", "a"), (1, None, None));
+        assert_eq!(form_of(&form, "M3", "a"), (1, None, None));
+        // history_size keeps the last two of M1..M3.
+        assert_eq!(form_of(&form, "M4", "a"), (2, Some(0.5), Some(250.0)));
+        assert_eq!(form_of(&form, "M5", "a"), (2, Some(1.0), Some(350.0)));
+        assert_eq!(form_of(&form, "M6", "a"), (0, None, None));
+    }
+
+    /// Both mid laners of one game: team 100 plays champion 1 against champion 2.
+    fn mid_lane(match_id: &str, game_creation: i64, blue_wins: bool) -> [LazyFrame; 2] {
+        [(100, 1, blue_wins), (200, 2, !blue_wins)].map(|(team_id, champion_id, win)| {
+            df! {
+                "match_id" => [match_id],
+                "game_creation" => [game_creation],
+                "queue_id" => [420i32],
+                "team_id" => [team_id],
+                "role" => ["MIDDLE"],
+                "champion_id" => [champion_id],
+                "win" => [win],
+            }
+            .expect("valid test frame")
+            .lazy()
+        })
+    }
+
+    /// `(matchup_games, matchup_winrate)` of `team_id`'s mid laner going into `match_id`.
+    fn prior_of(priors: &DataFrame, match_id: &str, team_id: i32) -> (Option<u32>, Option<f64>) {
+        let row = priors
+            .clone()
+            .lazy()
+            .filter(
+                col("match_id")
+                    .eq(lit(match_id))
+                    .and(col("team_id").eq(lit(team_id))),
+            )
+            .collect()
+            .unwrap();
+        assert_eq!(
+            row.height(),
+            1,
+            "one prior for team {} in {}",
+            team_id,
+            match_id
+        );
+        (
+            row.column("matchup_games").unwrap().u32().unwrap().get(0),
+            row.column("matchup_winrate").unwrap().f64().unwrap().get(0),
+        )
+    }
+
+    #[test]
+    fn matchup_priors_only_use_strictly_earlier_games() {
+        let games: Vec<LazyFrame> = [
+            mid_lane("M1", 1_000, true),
+            // M2 and M3 start together, so neither sees the other.
+            mid_lane("M2", 2_000, false),
+            mid_lane("M3", 2_000, true),
+            mid_lane("M4", 3_000, true),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let players = concat(games, UnionArgs::default()).unwrap();
+        let options = MatchupOptions {
+            min_games: 2,
+            smoothing: 0.0,
+        };
+
+        let (priors, totals) = champion_matchups(players.clone(), options).unwrap();
+        assert_eq!(prior_of(&priors, "M1", 100), (Some(0), None));
+        // One earlier game is below min_games.
+        assert_eq!(prior_of(&priors, "M2", 100), (Some(1), None));
+        assert_eq!(prior_of(&priors, "M3", 100), (Some(1), None));
+        assert_eq!(prior_of(&priors, "M4", 100), (Some(3), Some(2.0 / 3.0)));
+        assert_eq!(prior_of(&priors, "M4", 200), (Some(3), Some(1.0 / 3.0)));
+
+        let totals_of =
+            |champion, opponent| totals[&("MIDDLE".to_string(), Some(champion), Some(opponent))];
+        assert_eq!(totals_of(1, 2), (4, 3));
+        assert_eq!(totals_of(2, 1), (4, 1));
+
+        // Smoothing adds pseudo-games at 50%: (2 + 1) / (3 + 2).
+        let smoothed = MatchupOptions {
+            min_games: 2,
+            smoothing: 2.0,
+        };
+        let (priors, _) = champion_matchups(players, smoothed).unwrap();
+        assert_eq!(prior_of(&priors, "M4", 100), (Some(3), Some(0.6)));
+    }
+
+    #[test]
+    fn lane_matchups_keep_one_row_per_team() {
+        // Team 100 of M1 has two MIDDLE players, e.g. from a misassigned role.
+        let priors = df! {
+            "match_id" => ["M1", "M1", "M1", "M1"],
+            "team_id" => [100i32, 100, 100, 200],
+            "role" => ["MIDDLE", "MIDDLE", "TOP", "MIDDLE"],
+            "matchup_games" => [Some(3u32), Some(5), Some(1), Some(3)],
+            "matchup_winrate" => [Some(0.5), Some(0.2), None, Some(0.5)],
+        }
+        .unwrap();
+
+        let lanes = lane_matchups(priors.lazy())
+            .sort("team_id", Default::default())
+            .collect()
+            .unwrap();
+        assert_eq!(lanes.height(), 2);
+        let middle_games = lanes.column("middle_matchup_games").unwrap().u32().unwrap();
+        assert_eq!(middle_games.get(0), Some(3));
+        assert_eq!(middle_games.get(1), Some(3));
+        let top_games = lanes.column("top_matchup_games").unwrap().u32().unwrap();
+        assert_eq!(top_games.get(0), Some(1));
+        assert_eq!(top_games.get(1), None);
+    }
+}
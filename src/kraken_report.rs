@@ -0,0 +1,404 @@
+use anyhow::{Result, bail};
+use chrono::Utc;
+use serde_json::{Map, Value};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// One summarized input of the report: a heading, the file or directory it came from and the
+/// JSON document returned by the `kraken_summary` functions.
+pub struct ReportSection {
+    pub title: &'static str,
+    pub source: String,
+    pub summary: Value,
+    pub layout: &'static SectionLayout,
+}
+
+/// Display order of a summary document: its scalar fields, then each list of rows with its
+/// columns. Fields, tables and columns left out follow the listed ones alphabetically.
+pub struct SectionLayout {
+    pub fields: &'static [&'static str],
+    pub tables: &'static [(&'static str, &'static [&'static str])],
+}
+
+/// Rank fields and table shared by the raw and player summaries when ranks are known.
+const RANK_FIELDS: [&str; 3] = ["ranked_players", "unranked_players", "players_without_rank"];
+const RANK_TABLE: (&str, &[&str]) = ("rank_distribution", &["rank", "players", "matches"]);
+
+pub const RAW_MATCHES_LAYOUT: SectionLayout = SectionLayout {
+    fields: &[
+        "matches_scanned",
+        "soloq_matches",
+        "other_matches",
+        "first_game",
+        "last_game",
+        "participants",
+        RANK_FIELDS[0],
+        RANK_FIELDS[1],
+        RANK_FIELDS[2],
+    ],
+    tables: &[
+        ("queues", &["queue_id", "matches"]),
+        ("patches", &["patch", "matches"]),
+        ("top_champions", &["champion_name", "count"]),
+        ("matches_over_time", &["period", "matches"]),
+        RANK_TABLE,
+    ],
+};
+
+pub const PLAYER_LAYOUT: SectionLayout = SectionLayout {
+    fields: &[
+        "rows",
+        "matches",
+        "players",
+        "first_game",
+        "last_game",
+        RANK_FIELDS[0],
+        RANK_FIELDS[1],
+        RANK_FIELDS[2],
+    ],
+    tables: &[
+        ("patches", &["patch", "matches"]),
+        ("matches_over_time", &["period", "matches"]),
+        ("queue_distribution", &["queue_id", "games"]),
+        ("soloq_side_win_rate", &["team_id", "win_rate"]),
+        ("role_distribution", &["role", "games"]),
+        (
+            "per_role",
+            &[
+                "role",
+                "avg_kills",
+                "avg_gpm",
+                "avg_dpm",
+                "avg_vspm",
+                "win_rate",
+            ],
+        ),
+        ("top_champions", &["champion_name", "games", "win_rate"]),
+        (
+            "top_players",
+            &[
+                "player",
+                "games",
+                "share_of_matches",
+                "win_rate",
+                "main_role",
+            ],
+        ),
+        RANK_TABLE,
+    ],
+};
+
+pub const TEAM_LAYOUT: SectionLayout = SectionLayout {
+    fields: &["rows", "matches"],
+    tables: &[
+        ("soloq_team_win_rate", &["team_id", "win_rate"]),
+        ("objectives", &["objective", "per_team", "per_game"]),
+        (
+            "first_objectives",
+            &[
+                "objective",
+                "taken_rate",
+                "win_rate_with",
+                "win_rate_without",
+                "lift",
+            ],
+        ),
+        (
+            "duration_by_patch",
+            &["patch", "matches", "avg_duration_min"],
+        ),
+    ],
+};
+
+pub const LOBBY_LAYOUT: SectionLayout = SectionLayout {
+    fields: &[
+        "rows",
+        "matches",
+        "wins",
+        "losses",
+        "unlabeled",
+        "win_rate",
+        "inconsistent_matches",
+    ],
+    tables: &[
+        ("side_win_rate", &["team_id", "rows", "win_rate"]),
+        (
+            "slot_null_rates",
+            &[
+                "slot",
+                "champion_id",
+                "puuid",
+                "recent_games",
+                "recent_winrate",
+                "recent_gold_per_min",
+                "recent_damage_per_min",
+                "recent_vision_per_min",
+            ],
+        ),
+        (
+            "label_association",
+            &["column", "mean_won", "mean_lost", "cohens_d", "suspect"],
+        ),
+    ],
+};
+
+pub const CHAMPIONS_LAYOUT: SectionLayout = SectionLayout {
+    fields: &["matches", "role", "patch"],
+    tables: &[(
+        "champions",
+        &[
+            "champion_id",
+            "champion_name",
+            "games",
+            "pick_rate",
+            "win_rate",
+            "bans",
+            "ban_rate",
+        ],
+    )],
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+/// Renders `sections` into one report at `out`, as Markdown (`.md`) or HTML (`.html`). Scalar
+/// summary fields become an overview table and every list of rows a table of its own.
+pub fn write_report(out: &Path, sections: &[ReportSection]) -> Result<()> {
+    let format = match out
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        Some("md" | "markdown") => ReportFormat::Markdown,
+        Some("html" | "htm") => ReportFormat::Html,
+        _ => bail!(
+            "Cannot tell the report format of {}; use a .md or .html file",
+            out.display()
+        ),
+    };
+
+    let generated = Utc::now().format("%Y-%m-%d %H:%M UTC").to_string();
+    let mut doc = String::new();
+    match format {
+        ReportFormat::Markdown => {
+            let _ = writeln!(doc, "# Dataset report\n\nGenerated {}.", generated);
+        }
+        ReportFormat::Html => {
+            let _ = writeln!(
+                doc,
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+                 <title>Dataset report</title>\n<style>{}</style>\n</head>\n<body>\n\
+                 <h1>Dataset report</h1>\n<p>Generated {}.</p>",
+                STYLE, generated
+            );
+        }
+    }
+
+    for section in sections {
+        let Value::Object(fields) = &section.summary else {
+            continue;
+        };
+        match format {
+            ReportFormat::Markdown => {
+                let _ = writeln!(
+                    doc,
+                    "\n## {}\n\nSource: `{}`",
+                    section.title, section.source
+                );
+            }
+            ReportFormat::Html => {
+                let _ = writeln!(
+                    doc,
+                    "<h2>{}</h2>\n<p>Source: <code>{}</code></p>",
+                    escape(section.title),
+                    escape(&section.source)
+                );
+            }
+        }
+
+        let layout = section.layout;
+        let overview: Vec<Vec<String>> = in_order(fields.keys(), layout.fields)
+            .into_iter()
+            .filter(|key| !fields[*key].is_array())
+            .map(|key| vec![label(key), cell(&fields[key])])
+            .collect();
+        render_table(
+            &mut doc,
+            format,
+            None,
+            &["Metric".to_string(), "Value".to_string()],
+            &overview,
+        );
+
+        let table_names: Vec<&str> = layout.tables.iter().map(|(name, _)| *name).collect();
+        for key in in_order(fields.keys(), &table_names) {
+            let Value::Array(rows) = &fields[key] else {
+                continue;
+            };
+            let rows: Vec<&Map<String, Value>> = rows.iter().filter_map(Value::as_object).collect();
+            let order = layout
+                .tables
+                .iter()
+                .find(|(name, _)| name == key)
+                .map_or(&[][..], |(_, columns)| columns);
+            let columns: Vec<String> = rows
+                .first()
+                .map(|row| in_order(row.keys(), order).into_iter().cloned().collect())
+                .unwrap_or_default();
+            let cells: Vec<Vec<String>> = rows
+                .iter()
+                .map(|row| {
+                    columns
+                        .iter()
+                        .map(|column| row.get(column).map(cell).unwrap_or_default())
+                        .collect()
+                })
+                .collect();
+            render_table(&mut doc, format, Some(&label(key)), &columns, &cells);
+        }
+    }
+
+    if format == ReportFormat::Html {
+        doc.push_str("</body>\n</html>\n");
+    }
+    if let Some(parent) = out.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(out, doc)?;
+    Ok(())
+}
+
+/// `keys` with those in `order` first, in that order, and the rest after them as they came.
+fn in_order<'a>(keys: impl Iterator<Item = &'a String>, order: &[&str]) -> Vec<&'a String> {
+    let mut keys: Vec<&String> = keys.collect();
+    keys.sort_by_key(|key| {
+        order
+            .iter()
+            .position(|name| name == key)
+            .unwrap_or(order.len())
+    });
+    keys
+}
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:60em;margin:2em auto;padding:0 1em}\
+table{border-collapse:collapse;margin:0.5em 0 1.5em}\
+th,td{border:1px solid #ccc;padding:0.25em 0.75em;text-align:left}\
+th{background:#f3f3f3}";
+
+fn render_table(
+    doc: &mut String,
+    format: ReportFormat,
+    title: Option<&str>,
+    columns: &[String],
+    rows: &[Vec<String>],
+) {
+    match format {
+        ReportFormat::Markdown => {
+            if let Some(title) = title {
+                let _ = writeln!(doc, "\n### {}", title);
+            }
+            if rows.is_empty() {
+                doc.push_str("\nNo rows.\n");
+                return;
+            }
+            let _ = writeln!(doc, "\n| {} |", columns.join(" | "));
+            let _ = writeln!(doc, "|{}", " --- |".repeat(columns.len()));
+            for row in rows {
+                let cells: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|")).collect();
+                let _ = writeln!(doc, "| {} |", cells.join(" | "));
+            }
+        }
+        ReportFormat::Html => {
+            if let Some(title) = title {
+                let _ = writeln!(doc, "<h3>{}</h3>", escape(title));
+            }
+            if rows.is_empty() {
+                doc.push_str("<p>No rows.</p>\n");
+                return;
+            }
+            doc.push_str("<table>\n<tr>");
+            for column in columns {
+                let _ = write!(doc, "<th>{}</th>", escape(column));
+            }
+            doc.push_str("</tr>\n");
+            for row in rows {
+                doc.push_str("<tr>");
+                for cell in row {
+                    let _ = write!(doc, "<td>{}</td>", escape(cell));
+                }
+                doc.push_str("</tr>\n");
+            }
+            doc.push_str("</table>\n");
+        }
+    }
+}
+
+/// `queue_distribution` -> `Queue distribution`.
+fn label(key: &str) -> String {
+    let text = key.replace('_', " ");
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Whole numbers as is, fractions to three decimals.
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Number(number) if number.is_f64() => {
+            format!("{:.3}", number.as_f64().unwrap_or_default())
+        }
+        other => other.to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use serde_json::json;
+
+    #[test]
+    fn markdown_report_follows_the_section_layout() {
+        let dir = TempDir::new("report");
+        let out = dir.path().join("report.md");
+        let section = ReportSection {
+            title: "Raw matches",
+            source: "data/raw".to_string(),
+            summary: json!({
+                "matches_scanned": 3,
+                "first_game": "2024-10-01",
+                "participants": 30,
+                "queues": [{ "matches": 2, "queue_id": 420 }, { "matches": 1, "queue_id": 440 }],
+            }),
+            layout: &RAW_MATCHES_LAYOUT,
+        };
+
+        write_report(&out, &[section]).unwrap();
+        let doc = fs::read_to_string(&out).unwrap();
+
+        let overview = "| Metric | Value |\n| --- | --- |\n| Matches scanned | 3 |\n\
+                        | First game | 2024-10-01 |\n| Participants | 30 |\n";
+        assert!(doc.contains(overview), "{}", doc);
+        let queues =
+            "### Queues\n\n| queue_id | matches |\n| --- | --- |\n| 420 | 2 |\n| 440 | 1 |\n";
+        assert!(doc.contains(queues), "{}", doc);
+    }
+}
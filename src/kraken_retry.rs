@@ -0,0 +1,247 @@
+use crate::kraken::{MatchLayout, queue_suffixed_file_name, save_match};
+use crate::kraken_manifest::{KrakenManifest, ManifestRow, QUEUE_LABELS};
+use crate::riot_api::{RiotClient, StatusError};
+use chrono::Utc;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+pub const FAILED_FILE: &str = "failed.jsonl";
+
+/// A match whose download failed, as stored (one JSON object per line) in `failed.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedMatch {
+    pub match_id: String,
+    pub platform: String,
+    pub source_puuid: String,
+    /// Coarse error class: timeout, connect, network, decode, rate-limited, not-found,
+    /// server-error or http-error.
+    pub kind: String,
+    pub error: String,
+    pub failed_at: String,
+}
+
+impl FailedMatch {
+    pub fn new(
+        match_id: &str,
+        platform: &str,
+        source_puuid: &str,
+        err: &(dyn Error + 'static),
+    ) -> Self {
+        Self {
+            match_id: match_id.to_string(),
+            platform: platform.to_string(),
+            source_puuid: source_puuid.to_string(),
+            kind: error_kind(err).to_string(),
+            error: err.to_string(),
+            failed_at: Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Missing matches (404) will never succeed; every other failure is worth another try.
+    pub fn is_retryable(&self) -> bool {
+        self.kind != "not-found"
+    }
+}
+
+fn error_kind(err: &(dyn Error + 'static)) -> &'static str {
+    if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        return if err.is_timeout() {
+            "timeout"
+        } else if err.is_connect() {
+            "connect"
+        } else if err.is_decode() {
+            "decode"
+        } else {
+            "network"
+        };
+    }
+
+    match err.downcast_ref::<StatusError>().map(|err| err.status) {
+        Some(StatusCode::TOO_MANY_REQUESTS) => "rate-limited",
+        Some(StatusCode::NOT_FOUND) => "not-found",
+        Some(status) if status.is_server_error() => "server-error",
+        _ => "http-error",
+    }
+}
+
+pub fn append_failed(dir: &Path, failed: &FailedMatch) -> Result<(), Box<dyn Error>> {
+    let line = serde_json::to_string(failed)?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(FAILED_FILE))?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+pub fn read_failed(path: &Path) -> Result<Vec<FailedMatch>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let reader = std::io::BufReader::new(fs::File::open(path)?);
+    let mut failed = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => failed.push(entry),
+            Err(err) => eprintln!("Skipping malformed line in {}: {}", path.display(), err),
+        }
+    }
+    Ok(failed)
+}
+
+/// Rewrites `path` without the given match IDs (deleting it once nothing is left).
+pub fn remove_failed(path: &Path, recovered: &HashSet<String>) -> Result<(), Box<dyn Error>> {
+    let remaining: Vec<FailedMatch> = read_failed(path)?
+        .into_iter()
+        .filter(|entry| !recovered.contains(&entry.match_id))
+        .collect();
+
+    if remaining.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+
+    let mut contents = String::new();
+    for entry in &remaining {
+        contents.push_str(&serde_json::to_string(entry)?);
+        contents.push('\n');
+    }
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct KrakenRetryArgs {
+    pub failed_file: PathBuf,
+    pub out_dir: PathBuf,
+    pub layout: MatchLayout,
+    pub max_req_per_2min: usize,
+}
+
+/// Names a flat-layout crawl may have saved `match_id` under: plain, or with the
+/// `--queue-suffix` of any labelled queue.
+fn flat_file_names(match_id: &str) -> impl Iterator<Item = String> + '_ {
+    std::iter::once(format!("{}.json", match_id)).chain(
+        QUEUE_LABELS
+            .iter()
+            .map(move |(queue, _)| queue_suffixed_file_name(match_id, *queue)),
+    )
+}
+
+/// Re-attempts every retryable entry of a `failed.jsonl`, writing recovered matches (and their
+/// manifest rows) to `out_dir` and dropping them from the failed file.
+pub fn kraken_retry_run(args: &KrakenRetryArgs) -> Result<(), Box<dyn Error>> {
+    let failed = read_failed(&args.failed_file)?;
+    if failed.is_empty() {
+        println!("No failed matches in {}", args.failed_file.display());
+        return Ok(());
+    }
+
+    fs::create_dir_all(&args.out_dir)?;
+    let manifest = KrakenManifest::open(&args.out_dir)?;
+
+    let mut clients: Vec<RiotClient> = Vec::new();
+    let mut recovered: HashSet<String> = HashSet::new();
+    let mut still_failing = 0usize;
+    let mut skipped = 0usize;
+
+    for entry in &failed {
+        if !entry.is_retryable() {
+            skipped += 1;
+            continue;
+        }
+
+        // Unique match IDs only; duplicates of a recovered match are dropped with it.
+        if recovered.contains(&entry.match_id) {
+            continue;
+        }
+
+        // Without the match JSON the partitioned path is unknown, so only flat layouts can
+        // skip matches that were recovered by another run.
+        let already_saved = args.layout == MatchLayout::Flat
+            && flat_file_names(&entry.match_id).any(|name| args.out_dir.join(name).exists());
+        if !already_saved {
+            let client = match clients.iter().position(|c| c.platform() == entry.platform) {
+                Some(idx) => &clients[idx],
+                None => {
+                    clients.push(RiotClient::for_platform(
+                        &entry.platform,
+                        args.max_req_per_2min,
+                    )?);
+                    clients.last().expect("client was just pushed")
+                }
+            };
+
+            let match_json = match client.get_match_json(&entry.match_id) {
+                Ok(json) => json,
+                Err(err) => {
+                    eprintln!("Retry of {} failed again: {}", entry.match_id, err);
+                    still_failing += 1;
+                    continue;
+                }
+            };
+
+            let (file_name, bytes) =
+                save_match(&args.out_dir, args.layout, &entry.match_id, &match_json)?;
+            let row = ManifestRow::new(
+                &entry.match_id,
+                &match_json,
+                &entry.source_puuid,
+                &file_name,
+                bytes,
+            );
+            manifest.append(&row)?;
+        }
+
+        recovered.insert(entry.match_id.clone());
+    }
+
+    remove_failed(&args.failed_file, &recovered)?;
+
+    println!(
+        "Recovered {} matches, {} still failing, {} not retryable",
+        recovered.len(),
+        still_failing,
+        skipped
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_kind_follows_the_response_status() {
+        let kind = |status| {
+            let err = StatusError {
+                url: "https://europe.api.riotgames.com/lol/match/v5/matches/EUW1_1".to_string(),
+                status,
+            };
+            error_kind(&err)
+        };
+        assert_eq!(kind(StatusCode::TOO_MANY_REQUESTS), "rate-limited");
+        assert_eq!(kind(StatusCode::NOT_FOUND), "not-found");
+        assert_eq!(kind(StatusCode::SERVICE_UNAVAILABLE), "server-error");
+        assert_eq!(kind(StatusCode::FORBIDDEN), "http-error");
+        // Only the status decides, not text that looks like one.
+        let message: Box<dyn Error> = "Request failed with status 404 Not Found".into();
+        assert_eq!(error_kind(message.as_ref()), "http-error");
+    }
+}
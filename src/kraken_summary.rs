@@ -1,10 +1,41 @@
-use anyhow::Result;
-use chrono::{DateTime, Utc};
+use crate::kraken_frontier::{TIERS, rank_label, rank_ordinal};
+use crate::kraken_manifest::{RANKS_FILE, patch_from_version, queue_label, read_ranks};
+use crate::match_data::{MatchDocument, MatchInfo};
+use crate::parquet_extract::{
+    collect_match_files, file_match_id, json_value, open_match_file, read_match_documents,
+};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
 use polars::prelude::*;
-use serde_json::Value;
-use std::collections::HashMap;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Instant;
+
+/// How kraken-summary reports: printed tables, or one JSON document per input for scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryFormat {
+    Text,
+    Json,
+}
+
+impl SummaryFormat {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        match raw.to_ascii_lowercase().as_str() {
+            "text" => Ok(SummaryFormat::Text),
+            "json" => Ok(SummaryFormat::Json),
+            other => Err(format!(
+                "Unknown format '{}' (expected text or json)",
+                other
+            )),
+        }
+    }
+}
 
 fn format_ts_millis(ts: i64) -> String {
     DateTime::<Utc>::from_timestamp_millis(ts)
@@ -12,132 +43,602 @@ fn format_ts_millis(ts: i64) -> String {
         .unwrap_or_else(|| ts.to_string())
 }
 
-pub fn kraken_summary_raw(matches_dir: &Path, max_files: Option<usize>) -> Result<()> {
-    println!("== Kraken Summary (raw JSON) ==");
+/// Bucket width of the matches-over-time histogram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimePeriod {
+    Day,
+    /// Weeks starting on Monday, labelled by that day.
+    Week,
+}
 
-    let mut to_visit = vec![matches_dir.to_path_buf()];
-    let mut processed = 0usize;
-    let mut queue_counts: HashMap<i64, usize> = HashMap::new();
-    let mut champion_counts: HashMap<String, usize> = HashMap::new();
-    let mut min_game_creation: Option<i64> = None;
-    let mut max_game_creation: Option<i64> = None;
-    let mut participants_total: usize = 0;
+impl TimePeriod {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        match raw.to_ascii_lowercase().as_str() {
+            "day" => Ok(TimePeriod::Day),
+            "week" => Ok(TimePeriod::Week),
+            other => Err(format!("Unknown period '{}' (expected day or week)", other)),
+        }
+    }
 
-    while let Some(path) = to_visit.pop() {
-        if let Some(limit) = max_files {
-            if processed >= limit {
-                break;
-            }
+    fn bucket(&self, game_creation: i64) -> Option<NaiveDate> {
+        let day = DateTime::<Utc>::from_timestamp_millis(game_creation)?.date_naive();
+        Some(match self {
+            TimePeriod::Day => day,
+            TimePeriod::Week => day - Duration::days(day.weekday().num_days_from_monday() as i64),
+        })
+    }
+
+    fn step(&self) -> Duration {
+        match self {
+            TimePeriod::Day => Duration::days(1),
+            TimePeriod::Week => Duration::weeks(1),
         }
+    }
+}
 
-        if path.is_dir() {
-            if let Ok(entries) = fs::read_dir(&path) {
-                for entry in entries.flatten() {
-                    let p = entry.path();
-                    if p.is_dir() {
-                        to_visit.push(p);
-                    } else if p.extension().and_then(|e| e.to_str()) == Some("json") {
-                        let contents = match fs::read_to_string(&p) {
-                            Ok(data) => data,
-                            Err(_) => continue,
-                        };
+/// Matches per period from the first to the last game, empty periods included so gaps show.
+fn matches_over_time(game_creations: &[i64], period: TimePeriod) -> Vec<(NaiveDate, usize)> {
+    let mut counts: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for bucket in game_creations.iter().filter_map(|gc| period.bucket(*gc)) {
+        *counts.entry(bucket).or_insert(0) += 1;
+    }
+    let (Some(first), Some(last)) = (
+        counts.keys().next().copied(),
+        counts.keys().next_back().copied(),
+    ) else {
+        return Vec::new();
+    };
+    let mut rows = Vec::new();
+    let mut bucket = first;
+    while bucket <= last {
+        rows.push((bucket, counts.get(&bucket).copied().unwrap_or(0)));
+        bucket += period.step();
+    }
+    rows
+}
 
-                        let parsed: Value = match serde_json::from_str(&contents) {
-                            Ok(v) => v,
-                            Err(_) => continue,
-                        };
+/// Widest bar of the printed histogram.
+const HISTOGRAM_WIDTH: usize = 40;
 
-                        let Some(info) = parsed.get("info") else {
-                            continue;
-                        };
+fn print_matches_over_time(rows: &[(NaiveDate, usize)]) {
+    let max = rows.iter().map(|(_, matches)| *matches).max().unwrap_or(0);
+    println!("Matches over time:");
+    for (bucket, matches) in rows {
+        let bar = (matches * HISTOGRAM_WIDTH).div_ceil(max.max(1));
+        println!("  {}  {:>7} {}", bucket, matches, "#".repeat(bar));
+    }
+}
 
-                        let queue_id = info
-                            .get("queueId")
-                            .and_then(|v| v.as_i64())
-                            .unwrap_or_default();
-                        *queue_counts.entry(queue_id).or_insert(0) += 1;
-
-                        if let Some(gc) = info.get("gameCreation").and_then(|v| v.as_i64()) {
-                            min_game_creation = Some(match min_game_creation {
-                                Some(current) => current.min(gc),
-                                None => gc,
-                            });
-                            max_game_creation = Some(match max_game_creation {
-                                Some(current) => current.max(gc),
-                                None => gc,
-                            });
-                        }
+fn matches_over_time_json(rows: &[(NaiveDate, usize)]) -> Vec<Value> {
+    rows.iter()
+        .map(|(bucket, matches)| json!({"period": bucket.to_string(), "matches": matches}))
+        .collect()
+}
 
-                        if let Some(participants) =
-                            info.get("participants").and_then(|p| p.as_array())
-                        {
-                            participants_total += participants.len();
-                            for participant in participants {
-                                if let Some(champ) =
-                                    participant.get("championName").and_then(|c| c.as_str())
-                                {
-                                    *champion_counts.entry(champ.to_string()).or_insert(0) += 1;
-                                }
-                            }
-                        }
+/// `(patch, matches)` pairs, oldest patch first.
+fn patch_rows(counts: BTreeMap<String, usize>) -> Vec<(String, usize)> {
+    let mut rows: Vec<(String, usize)> = counts.into_iter().collect();
+    rows.sort_by_key(|(patch, _)| {
+        patch
+            .split('.')
+            .map(|part| part.parse::<u32>().unwrap_or(0))
+            .collect::<Vec<_>>()
+    });
+    rows
+}
 
-                        processed += 1;
+fn print_patches(patches: &[(String, usize)]) {
+    let listed: Vec<String> = patches
+        .iter()
+        .map(|(patch, matches)| format!("{}={}", patch, matches))
+        .collect();
+    println!("Patches: {}", listed.join(" "));
+}
 
-                        if let Some(limit) = max_files {
-                            if processed >= limit {
-                                break;
-                            }
-                        }
+fn patches_json(patches: &[(String, usize)]) -> Vec<Value> {
+    patches
+        .iter()
+        .map(|(patch, matches)| json!({"patch": patch, "matches": matches}))
+        .collect()
+}
+
+/// Ladder position of each player in the rank files; `None` for players recorded as unranked.
+fn load_ranks(paths: &[PathBuf]) -> Result<HashMap<String, Option<u32>>> {
+    let mut ranks = HashMap::new();
+    for path in paths {
+        let rows = read_ranks(path)
+            .map_err(|err| anyhow!("Failed to read {}: {}", path.display(), err))?;
+        // Later lookups of the same player win.
+        for row in rows {
+            ranks.insert(row.puuid, rank_ordinal(&row.tier, &row.division));
+        }
+    }
+    Ok(ranks)
+}
+
+/// Every `ranks.csv` kraken left under `dir`.
+fn find_rank_files(dir: &Path) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut to_visit = vec![dir.to_path_buf()];
+    while let Some(path) = to_visit.pop() {
+        let Ok(entries) = fs::read_dir(&path) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let p = entry.path();
+            if p.is_dir() {
+                to_visit.push(p);
+            } else if p.file_name().is_some_and(|name| name == RANKS_FILE) {
+                found.push(p);
+            }
+        }
+    }
+    found
+}
+
+/// Tier/division distribution of the players and matches of a dataset, from the ranks known for
+/// their PUUIDs. A match is placed at the mean rank of its ranked participants.
+struct RankSummary {
+    ranked_players: usize,
+    unranked_players: usize,
+    players_without_rank: usize,
+    /// Players and matches per ladder ordinal.
+    distribution: BTreeMap<u32, (usize, usize)>,
+}
+
+impl RankSummary {
+    fn new<'a>(
+        ranks: &HashMap<String, Option<u32>>,
+        matches: impl IntoIterator<Item = Vec<&'a str>>,
+    ) -> Self {
+        let mut summary = RankSummary {
+            ranked_players: 0,
+            unranked_players: 0,
+            players_without_rank: 0,
+            distribution: BTreeMap::new(),
+        };
+        let mut players = HashSet::new();
+        for puuids in matches {
+            let mut ordinals = Vec::new();
+            for puuid in puuids {
+                let rank = ranks.get(puuid);
+                if let Some(Some(ordinal)) = rank {
+                    ordinals.push(*ordinal);
+                }
+                if !players.insert(puuid) {
+                    continue;
+                }
+                match rank {
+                    Some(Some(ordinal)) => {
+                        summary.ranked_players += 1;
+                        summary.distribution.entry(*ordinal).or_default().0 += 1;
                     }
+                    Some(None) => summary.unranked_players += 1,
+                    None => summary.players_without_rank += 1,
                 }
             }
+            if !ordinals.is_empty() {
+                let mean = ordinals.iter().sum::<u32>() as f64 / ordinals.len() as f64;
+                summary
+                    .distribution
+                    .entry(mean.round() as u32)
+                    .or_default()
+                    .1 += 1;
+            }
         }
+        summary
     }
 
-    println!("Matches scanned: {}", processed);
+    fn print(&self) {
+        println!(
+            "Ranks: {} ranked, {} unranked, {} players without a known rank",
+            self.ranked_players, self.unranked_players, self.players_without_rank
+        );
+        println!("Rank distribution (players, matches by mean rank):");
+        for (ordinal, (players, matches)) in &self.distribution {
+            println!(
+                "  {:<14} {:>7} {:>7}",
+                rank_label(*ordinal),
+                players,
+                matches
+            );
+        }
+    }
+
+    fn add_to(&self, summary: &mut Value) {
+        summary["ranked_players"] = json!(self.ranked_players);
+        summary["unranked_players"] = json!(self.unranked_players);
+        summary["players_without_rank"] = json!(self.players_without_rank);
+        summary["rank_distribution"] = self
+            .distribution
+            .iter()
+            .map(|(ordinal, (players, matches))| {
+                json!({"rank": rank_label(*ordinal), "players": players, "matches": matches})
+            })
+            .collect();
+    }
+}
+
+/// The rows of `df` as JSON objects.
+fn frame_rows(df: &DataFrame) -> Result<Value> {
+    let mut rows = Vec::with_capacity(df.height());
+    for row in 0..df.height() {
+        let mut object = Map::new();
+        for column in df.get_columns() {
+            object.insert(column.name().to_string(), json_value(column.get(row)?));
+        }
+        rows.push(Value::Object(object));
+    }
+    Ok(Value::Array(rows))
+}
+
+/// Scans the raw match files under `matches_dir` (`.json` files and `.jsonl` shards, plain or
+/// compressed) on `workers` threads, all cores when unset, showing progress on stderr. Prints
+/// the summary as text and returns it as a JSON document, which is all that happens with
+/// [`SummaryFormat::Json`]. Ranks come from `ranks`, or else from the `ranks.csv` files kraken
+/// wrote under `matches_dir`. With `period`, matches are also counted per day or week.
+pub fn kraken_summary_raw(
+    matches_dir: &Path,
+    max_matches: Option<usize>,
+    ranks: Option<&Path>,
+    period: Option<TimePeriod>,
+    workers: Option<usize>,
+    format: SummaryFormat,
+) -> Result<Value> {
+    let text = format == SummaryFormat::Text;
+    if text {
+        println!("== Kraken Summary (raw JSON) ==");
+    }
+
+    let rank_files = match ranks {
+        Some(path) => vec![path.to_path_buf()],
+        None => find_rank_files(matches_dir),
+    };
+    let ranks = load_ranks(&rank_files)?;
+
+    let files = collect_match_files(&[matches_dir.to_path_buf()]);
+    let workers = workers
+        .or_else(|| thread::available_parallelism().ok().map(usize::from))
+        .unwrap_or(1)
+        .max(1);
+    let RawTotals {
+        matches: processed,
+        queue_counts,
+        patch_counts,
+        champion_counts,
+        first_game: min_game_creation,
+        last_game: max_game_creation,
+        game_creations,
+        participants: participants_total,
+        match_puuids,
+    } = scan_raw_matches(
+        &files,
+        max_matches,
+        workers,
+        period.is_some(),
+        !ranks.is_empty(),
+    )?;
+
     let soloq = queue_counts.get(&420).cloned().unwrap_or_default();
     let other: usize = queue_counts
         .iter()
         .filter(|(k, _)| **k != 420)
         .map(|(_, v)| *v)
         .sum();
-    println!(
-        "Queue distribution: SoloQ={} Other={} ({} queues tracked)",
-        soloq,
-        other,
-        queue_counts.len()
-    );
+    let patches = patch_rows(patch_counts);
+    let mut champs: Vec<_> = champion_counts.into_iter().collect();
+    champs.sort_by_key(|c| std::cmp::Reverse(c.1));
+    champs.truncate(10);
+    let over_time = period.map(|period| matches_over_time(&game_creations, period));
+    let rank_summary = (!ranks.is_empty()).then(|| {
+        RankSummary::new(
+            &ranks,
+            match_puuids
+                .iter()
+                .map(|puuids| puuids.iter().map(String::as_str).collect()),
+        )
+    });
 
-    if let (Some(min_gc), Some(max_gc)) = (min_game_creation, max_game_creation) {
+    if text {
+        println!("Matches scanned: {}", processed);
         println!(
-            "Time range: {} -> {}",
-            format_ts_millis(min_gc),
-            format_ts_millis(max_gc)
+            "Queue distribution: SoloQ={} Other={} ({} queues tracked)",
+            soloq,
+            other,
+            queue_counts.len()
         );
+        if !patches.is_empty() {
+            print_patches(&patches);
+        }
+        if let (Some(min_gc), Some(max_gc)) = (min_game_creation, max_game_creation) {
+            println!(
+                "Time range: {} -> {}",
+                format_ts_millis(min_gc),
+                format_ts_millis(max_gc)
+            );
+        }
+        if let Some(over_time) = &over_time {
+            print_matches_over_time(over_time);
+        }
+        println!("Participants counted: {}", participants_total);
+        if !champs.is_empty() {
+            println!("Top champions:");
+            for (champ, count) in &champs {
+                println!("  {:<20} {}", champ, count);
+            }
+        }
+        if let Some(rank_summary) = &rank_summary {
+            rank_summary.print();
+        }
     }
 
-    println!("Participants counted: {}", participants_total);
+    let mut summary = json!({
+        "matches_scanned": processed,
+        "queues": queue_counts
+            .iter()
+            .map(|(queue_id, matches)| json!({"queue_id": queue_id, "matches": matches}))
+            .collect::<Vec<_>>(),
+        "soloq_matches": soloq,
+        "other_matches": other,
+        "patches": patches_json(&patches),
+        "first_game": min_game_creation.map(format_ts_millis),
+        "last_game": max_game_creation.map(format_ts_millis),
+        "participants": participants_total,
+        "top_champions": champs
+            .iter()
+            .map(|(champion_name, count)| json!({"champion_name": champion_name, "count": count}))
+            .collect::<Vec<_>>(),
+    });
+    if let Some(over_time) = &over_time {
+        summary["matches_over_time"] = json!(matches_over_time_json(over_time));
+    }
+    if let Some(rank_summary) = &rank_summary {
+        rank_summary.add_to(&mut summary);
+    }
+    Ok(summary)
+}
 
-    if !champion_counts.is_empty() {
-        let mut champs: Vec<_> = champion_counts.into_iter().collect();
-        champs.sort_by(|a, b| b.1.cmp(&a.1));
-        let top = champs.into_iter().take(10);
-        println!("Top champions:");
-        for (champ, count) in top {
-            println!("  {:<20} {}", champ, count);
+/// What the raw summary counts, kept per worker while scanning and merged at the end.
+#[derive(Default)]
+struct RawTotals {
+    matches: usize,
+    queue_counts: BTreeMap<i64, usize>,
+    patch_counts: BTreeMap<String, usize>,
+    champion_counts: BTreeMap<String, usize>,
+    first_game: Option<i64>,
+    last_game: Option<i64>,
+    /// Only filled for `--by-period`.
+    game_creations: Vec<i64>,
+    participants: usize,
+    /// Participants per match, only kept when there are ranks to look them up in.
+    match_puuids: Vec<Vec<String>>,
+}
+
+impl RawTotals {
+    fn add(&mut self, info: &MatchInfo, keep_creations: bool, keep_puuids: bool) {
+        self.matches += 1;
+        *self.queue_counts.entry(info.queue_id).or_insert(0) += 1;
+        if !info.game_version.is_empty() {
+            *self
+                .patch_counts
+                .entry(patch_from_version(&info.game_version))
+                .or_insert(0) += 1;
+        }
+        if info.game_creation > 0 {
+            if keep_creations {
+                self.game_creations.push(info.game_creation);
+            }
+            self.first_game = Some(
+                self.first_game
+                    .map_or(info.game_creation, |first| first.min(info.game_creation)),
+            );
+            self.last_game = Some(
+                self.last_game
+                    .map_or(info.game_creation, |last| last.max(info.game_creation)),
+            );
+        }
+        if let Some(participants) = &info.participants {
+            self.participants += participants.len();
+            if keep_puuids {
+                self.match_puuids.push(
+                    participants
+                        .iter()
+                        .filter(|participant| !participant.puuid.is_empty())
+                        .map(|participant| participant.puuid.clone())
+                        .collect(),
+                );
+            }
+            for participant in participants {
+                if !participant.champion_name.is_empty() {
+                    *self
+                        .champion_counts
+                        .entry(participant.champion_name.clone())
+                        .or_insert(0) += 1;
+                }
+            }
         }
     }
 
-    Ok(())
+    fn merge(&mut self, other: RawTotals) {
+        self.matches += other.matches;
+        for (queue, count) in other.queue_counts {
+            *self.queue_counts.entry(queue).or_insert(0) += count;
+        }
+        for (patch, count) in other.patch_counts {
+            *self.patch_counts.entry(patch).or_insert(0) += count;
+        }
+        for (champion, count) in other.champion_counts {
+            *self.champion_counts.entry(champion).or_insert(0) += count;
+        }
+        self.first_game = match (self.first_game, other.first_game) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.last_game = self.last_game.max(other.last_game);
+        self.game_creations.extend(other.game_creations);
+        self.participants += other.participants;
+        self.match_puuids.extend(other.match_puuids);
+    }
 }
 
-pub fn kraken_summary_player(
-    parquet_path: &Path,
-    max_rows: Option<usize>,
-    by_role: bool,
-    by_champion_top_k: Option<usize>,
-) -> Result<()> {
-    println!("== Kraken Summary (player parquet) ==");
+/// How often the raw scan redraws its progress line.
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Reads `files` on `workers` threads, each taking the next unread file and counting into its
+/// own [`RawTotals`]. With `max_matches`, only the first matches in path order are counted, the
+/// same ones whichever worker finishes first. Progress (files, files per second and matches) is
+/// redrawn on stderr when it is a terminal, and a closing line is always printed there.
+fn scan_raw_matches(
+    files: &[PathBuf],
+    max_matches: Option<usize>,
+    workers: usize,
+    keep_creations: bool,
+    keep_puuids: bool,
+) -> Result<RawTotals> {
+    // A readable file holds at least one match, so files past the first `max_matches` are never
+    // needed; unreadable ones among them leave the count short.
+    let files = match max_matches {
+        Some(limit) => &files[..limit.min(files.len())],
+        None => files,
+    };
+    let next_file = AtomicUsize::new(0);
+    let files_done = AtomicUsize::new(0);
+    let matches_read = AtomicUsize::new(0);
+    let scan_done = AtomicBool::new(false);
+    let started = Instant::now();
+    let interactive = std::io::stderr().is_terminal();
+    let read_file = |totals: &mut RawTotals, path: &Path, limit: Option<usize>| {
+        let documents = read_match_documents(path).filter(|document| document.info.is_some());
+        for document in documents.take(limit.unwrap_or(usize::MAX)) {
+            if let Some(info) = &document.info {
+                matches_read.fetch_add(1, Ordering::Relaxed);
+                totals.add(info, keep_creations, keep_puuids);
+            }
+        }
+    };
+    let progress_line = || {
+        let done = files_done.load(Ordering::Relaxed);
+        let elapsed = started.elapsed().as_secs_f64();
+        format!(
+            "[kraken-summary] {}/{} files, {:.0} files/s, {} matches",
+            done,
+            files.len(),
+            done as f64 / elapsed.max(0.001),
+            matches_read
+                .load(Ordering::Relaxed)
+                .min(max_matches.unwrap_or(usize::MAX))
+        )
+    };
+
+    let totals = thread::scope(|scope| -> Result<RawTotals> {
+        if interactive {
+            scope.spawn(|| {
+                while !scan_done.load(Ordering::Relaxed) {
+                    eprint!("\r{}", progress_line());
+                    thread::sleep(PROGRESS_INTERVAL);
+                }
+            });
+        }
+
+        let handles: Vec<_> = (0..workers.min(files.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut totals = RawTotals::default();
+                    // With a cap, each file is kept apart to be merged in path order.
+                    let mut per_file = Vec::new();
+                    loop {
+                        let idx = next_file.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = files.get(idx) else {
+                            break;
+                        };
+                        if max_matches.is_some() {
+                            let mut file_totals = RawTotals::default();
+                            read_file(&mut file_totals, path, max_matches);
+                            per_file.push((idx, file_totals));
+                        } else {
+                            read_file(&mut totals, path, None);
+                        }
+                        files_done.fetch_add(1, Ordering::Relaxed);
+                    }
+                    (totals, per_file)
+                })
+            })
+            .collect();
+
+        let mut totals = RawTotals::default();
+        let mut per_file = Vec::new();
+        let mut panicked = false;
+        for handle in handles {
+            match handle.join() {
+                Ok((partial, files)) => {
+                    totals.merge(partial);
+                    per_file.extend(files);
+                }
+                Err(_) => panicked = true,
+            }
+        }
+        if panicked {
+            scan_done.store(true, Ordering::Relaxed);
+            return Err(anyhow!("A raw summary worker panicked"));
+        }
+        if let Some(limit) = max_matches {
+            per_file.sort_by_key(|(idx, _)| *idx);
+            for (idx, file_totals) in per_file {
+                let remaining = limit - totals.matches;
+                if remaining == 0 {
+                    break;
+                }
+                if file_totals.matches > remaining {
+                    // Only a `.jsonl` shard holds several matches; count the ones that fit.
+                    let mut part = RawTotals::default();
+                    read_file(&mut part, &files[idx], Some(remaining));
+                    totals.merge(part);
+                } else {
+                    totals.merge(file_totals);
+                }
+            }
+        }
+        scan_done.store(true, Ordering::Relaxed);
+        Ok(totals)
+    })?;
+
+    if interactive {
+        eprint!("\r");
+    }
+    eprintln!(
+        "{} in {:.1}s",
+        progress_line(),
+        started.elapsed().as_secs_f64()
+    );
+    Ok(totals)
+}
+
+pub struct PlayerSummaryArgs<'a> {
+    pub parquet_path: &'a Path,
+    pub max_rows: Option<usize>,
+    pub by_role: bool,
+    pub by_champion_top_k: Option<usize>,
+    pub top_players: Option<usize>,
+    /// Needed for the rank distribution.
+    pub ranks: Option<&'a Path>,
+    pub period: Option<TimePeriod>,
+}
+
+/// Summarizes a player-level Parquet; printed and returned like [`kraken_summary_raw`].
+pub fn kraken_summary_player(args: &PlayerSummaryArgs, format: SummaryFormat) -> Result<Value> {
+    let PlayerSummaryArgs {
+        parquet_path,
+        max_rows,
+        by_role,
+        by_champion_top_k,
+        top_players,
+        ranks,
+        period,
+    } = *args;
+    let text = format == SummaryFormat::Text;
+    if text {
+        println!("== Kraken Summary (player parquet) ==");
+    }
 
     let mut lf = LazyFrame::scan_parquet(
         parquet_path.to_string_lossy().as_ref(),
@@ -153,16 +654,82 @@ pub fn kraken_summary_player(
             len().alias("rows"),
             col("match_id").n_unique().alias("matches"),
             col("puuid").n_unique().alias("players"),
+            col("game_creation")
+                .min()
+                .cast(DataType::Int64)
+                .alias("first_game"),
+            col("game_creation")
+                .max()
+                .cast(DataType::Int64)
+                .alias("last_game"),
         ])
         .collect()?;
 
     let rows = basic.column("rows")?.u32()?.get(0).unwrap_or(0);
     let matches = basic.column("matches")?.u32()?.get(0).unwrap_or(0);
     let players = basic.column("players")?.u32()?.get(0).unwrap_or(0);
+    let first_game = basic.column("first_game")?.i64()?.get(0);
+    let last_game = basic.column("last_game")?.i64()?.get(0);
 
-    println!("Rows: {}", rows);
-    println!("Matches: {}", matches);
-    println!("Players: {}", players);
+    let versions = lf
+        .clone()
+        .group_by([col("game_version")])
+        .agg([col("match_id").n_unique().alias("matches")])
+        .collect()?;
+    let mut patch_counts: BTreeMap<String, usize> = BTreeMap::new();
+    for (version, count) in versions
+        .column("game_version")?
+        .str()?
+        .into_iter()
+        .zip(versions.column("matches")?.u32()?)
+    {
+        *patch_counts
+            .entry(version.map(patch_from_version).unwrap_or_default())
+            .or_insert(0) += count.unwrap_or(0) as usize;
+    }
+    let patches = patch_rows(patch_counts);
+
+    if text {
+        println!("Rows: {}", rows);
+        println!("Matches: {}", matches);
+        println!("Players: {}", players);
+        if let (Some(first), Some(last)) = (first_game, last_game) {
+            println!(
+                "Time range: {} -> {}",
+                format_ts_millis(first),
+                format_ts_millis(last)
+            );
+        }
+        print_patches(&patches);
+    }
+    let mut summary = json!({
+        "rows": rows,
+        "matches": matches,
+        "players": players,
+        "first_game": first_game.map(format_ts_millis),
+        "last_game": last_game.map(format_ts_millis),
+        "patches": patches_json(&patches),
+    });
+
+    if let Some(period) = period {
+        let games = lf
+            .clone()
+            .group_by([col("match_id")])
+            .agg([col("game_creation").first().cast(DataType::Int64)])
+            .collect()?;
+        let game_creations: Vec<i64> = games
+            .column("game_creation")?
+            .i64()?
+            .into_iter()
+            .flatten()
+            .collect();
+        let over_time = matches_over_time(&game_creations, period);
+        if text {
+            println!();
+            print_matches_over_time(&over_time);
+        }
+        summary["matches_over_time"] = json!(matches_over_time_json(&over_time));
+    }
 
     let queue_dist = lf
         .clone()
@@ -177,7 +744,10 @@ pub fn kraken_summary_player(
             },
         )
         .collect()?;
-    println!("\nQueue distribution:\n{}", queue_dist);
+    if text {
+        println!("\nQueue distribution:\n{}", queue_dist);
+    }
+    summary["queue_distribution"] = frame_rows(&queue_dist)?;
 
     let side_win = lf
         .clone()
@@ -186,7 +756,10 @@ pub fn kraken_summary_player(
         .agg([col("win").cast(DataType::Float64).mean().alias("win_rate")])
         .sort("team_id", SortOptions::default())
         .collect()?;
-    println!("\nSoloQ side winrate:\n{}", side_win);
+    if text {
+        println!("\nSoloQ side winrate:\n{}", side_win);
+    }
+    summary["soloq_side_win_rate"] = frame_rows(&side_win)?;
 
     let role_dist = lf
         .clone()
@@ -201,7 +774,10 @@ pub fn kraken_summary_player(
             },
         )
         .collect()?;
-    println!("\nRole distribution:\n{}", role_dist);
+    if text {
+        println!("\nRole distribution:\n{}", role_dist);
+    }
+    summary["role_distribution"] = frame_rows(&role_dist)?;
 
     if by_role {
         let role_stats = lf
@@ -228,7 +804,10 @@ pub fn kraken_summary_player(
             ])
             .sort("role", SortOptions::default())
             .collect()?;
-        println!("\nPer-role stats:\n{}", role_stats);
+        if text {
+            println!("\nPer-role stats:\n{}", role_stats);
+        }
+        summary["per_role"] = frame_rows(&role_stats)?;
     }
 
     if let Some(k) = by_champion_top_k {
@@ -249,14 +828,163 @@ pub fn kraken_summary_player(
             )
             .limit(k.try_into().unwrap_or(u32::MAX))
             .collect()?;
-        println!("\nTop champions:\n{}", champ_stats);
+        if text {
+            println!("\nTop champions:\n{}", champ_stats);
+        }
+        summary["top_champions"] = frame_rows(&champ_stats)?;
+    }
+
+    if let Some(k) = top_players {
+        let top = top_players_table(&lf, matches as usize, k)?;
+        if text {
+            println!("\nTop players (by games):");
+            for player in &top {
+                println!(
+                    "  {:<32} {:>6} games {:>5.1}% of matches {:>5.1}% win  {}",
+                    player.player,
+                    player.games,
+                    player.share_of_matches * 100.0,
+                    player.win_rate * 100.0,
+                    player.main_role
+                );
+            }
+        }
+        summary["top_players"] = json!(top);
+    }
+
+    if let Some(ranks) = ranks {
+        let ranks = load_ranks(&[ranks.to_path_buf()])?;
+        let players = lf
+            .clone()
+            .select([col("match_id"), col("puuid")])
+            .collect()?;
+        let mut match_puuids: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (match_id, puuid) in players
+            .column("match_id")?
+            .str()?
+            .into_iter()
+            .zip(players.column("puuid")?.str()?)
+        {
+            if let (Some(match_id), Some(puuid)) = (match_id, puuid) {
+                match_puuids.entry(match_id).or_default().push(puuid);
+            }
+        }
+        let rank_summary = RankSummary::new(&ranks, match_puuids.into_values());
+        if text {
+            println!();
+            rank_summary.print();
+        }
+        rank_summary.add_to(&mut summary);
     }
 
-    Ok(())
+    Ok(summary)
 }
 
-pub fn kraken_summary_team(parquet_path: &Path, max_rows: Option<usize>) -> Result<()> {
-    println!("== Kraken Summary (team parquet) ==");
+/// One row of the player summary's top players table.
+#[derive(Serialize)]
+struct TopPlayer {
+    /// `gameName#tagLine` when the rows carry a Riot ID, the PUUID otherwise.
+    player: String,
+    games: usize,
+    /// Games over the matches in the dataset; a seed player far above the rest means the crawl
+    /// stayed in their bubble.
+    share_of_matches: f64,
+    win_rate: f64,
+    main_role: String,
+}
+
+/// The `k` players with the most rows in a player-level frame.
+fn top_players_table(lf: &LazyFrame, matches: usize, k: usize) -> Result<Vec<TopPlayer>> {
+    let schema = lf.schema()?;
+    let has_riot_id = schema.contains("riot_id_game_name") && schema.contains("riot_id_tagline");
+    let mut aggs = vec![
+        len().alias("games"),
+        col("win").cast(DataType::UInt32).sum().alias("wins"),
+    ];
+    if has_riot_id {
+        aggs.push(col("riot_id_game_name").drop_nulls().last());
+        aggs.push(col("riot_id_tagline").drop_nulls().last());
+    }
+    let per_role = lf
+        .clone()
+        .group_by([col("puuid"), col("role")])
+        .agg(aggs)
+        .collect()?;
+
+    struct Totals {
+        riot_id: Option<String>,
+        games: usize,
+        wins: usize,
+        roles: BTreeMap<String, usize>,
+    }
+    let mut totals: HashMap<&str, Totals> = HashMap::new();
+    let names = if has_riot_id {
+        Some((
+            per_role.column("riot_id_game_name")?.str()?,
+            per_role.column("riot_id_tagline")?.str()?,
+        ))
+    } else {
+        None
+    };
+    let puuids = per_role.column("puuid")?.str()?;
+    let roles = per_role.column("role")?.str()?;
+    let games = per_role.column("games")?.u32()?;
+    let wins = per_role.column("wins")?.u32()?;
+    for idx in 0..per_role.height() {
+        let Some(puuid) = puuids.get(idx) else {
+            continue;
+        };
+        let entry = totals.entry(puuid).or_insert_with(|| Totals {
+            riot_id: None,
+            games: 0,
+            wins: 0,
+            roles: BTreeMap::new(),
+        });
+        let role_games = games.get(idx).unwrap_or(0) as usize;
+        entry.games += role_games;
+        entry.wins += wins.get(idx).unwrap_or(0) as usize;
+        *entry
+            .roles
+            .entry(roles.get(idx).unwrap_or("UNKNOWN").to_string())
+            .or_insert(0) += role_games;
+        if let Some((game_names, taglines)) = &names
+            && let (Some(name), Some(tag)) = (game_names.get(idx), taglines.get(idx))
+        {
+            entry.riot_id = Some(format!("{}#{}", name, tag));
+        }
+    }
+
+    let mut ranked: Vec<(&str, Totals)> = totals.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.games.cmp(&a.1.games).then(a.0.cmp(b.0)));
+    Ok(ranked
+        .into_iter()
+        .take(k)
+        .map(|(puuid, totals)| TopPlayer {
+            player: totals.riot_id.unwrap_or_else(|| puuid.to_string()),
+            games: totals.games,
+            share_of_matches: totals.games as f64 / matches.max(1) as f64,
+            win_rate: totals.wins as f64 / totals.games.max(1) as f64,
+            // Most games first, alphabetical on ties.
+            main_role: totals
+                .roles
+                .iter()
+                .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+                .map(|(role, _)| role.clone())
+                .unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Summarizes a team-level Parquet; printed and returned like [`kraken_summary_raw`].
+pub fn kraken_summary_team(
+    parquet_path: &Path,
+    max_rows: Option<usize>,
+    format: SummaryFormat,
+) -> Result<Value> {
+    let text = format == SummaryFormat::Text;
+    if text {
+        println!("== Kraken Summary (team parquet) ==");
+    }
 
     let mut lf = LazyFrame::scan_parquet(
         parquet_path.to_string_lossy().as_ref(),
@@ -277,17 +1005,1242 @@ pub fn kraken_summary_team(parquet_path: &Path, max_rows: Option<usize>) -> Resu
     let rows = basic.column("rows")?.u32()?.get(0).unwrap_or(0);
     let matches = basic.column("matches")?.u32()?.get(0).unwrap_or(0);
 
-    println!("Rows: {}", rows);
-    println!("Matches: {}", matches);
+    if text {
+        println!("Rows: {}", rows);
+        println!("Matches: {}", matches);
+    }
 
     let side_win = lf
         .clone()
         .filter(col("queue_id").eq(lit(420)))
         .group_by([col("team_id")])
-        .agg([col("team_win").cast(DataType::Float64).mean().alias("win_rate")])
+        .agg([col("team_win")
+            .cast(DataType::Float64)
+            .mean()
+            .alias("win_rate")])
+        .sort("team_id", SortOptions::default())
+        .collect()?;
+    if text {
+        println!("\nSoloQ team winrate:\n{}", side_win);
+    }
+
+    let objective_means = lf
+        .clone()
+        .select(
+            TEAM_OBJECTIVES
+                .iter()
+                .map(|(column, name)| col(column).cast(DataType::Float64).mean().alias(name))
+                .collect::<Vec<_>>(),
+        )
+        .collect()?;
+    let mut objectives = Vec::new();
+    for (_, name) in TEAM_OBJECTIVES {
+        let per_team = objective_means.column(name)?.f64()?.get(0);
+        objectives.push(json!({
+            "objective": name,
+            "per_team": per_team,
+            "per_game": per_team.map(|mean| mean * 2.0),
+        }));
+    }
+
+    // Win rate of the teams that took each first objective against those that did not.
+    let first_rates = lf
+        .clone()
+        .select(
+            FIRST_OBJECTIVES
+                .iter()
+                .flat_map(|column| {
+                    let win = col("team_win").cast(DataType::Float64);
+                    [
+                        win.clone()
+                            .filter(col(column).eq(lit(true)))
+                            .mean()
+                            .alias(&format!("{}_with", column)),
+                        win.filter(col(column).eq(lit(false)))
+                            .mean()
+                            .alias(&format!("{}_without", column)),
+                        col(column)
+                            .cast(DataType::Float64)
+                            .mean()
+                            .alias(&format!("{}_rate", column)),
+                    ]
+                })
+                .collect::<Vec<_>>(),
+        )
+        .collect()?;
+    let mut first_objectives = Vec::new();
+    for column in FIRST_OBJECTIVES {
+        let rate = |suffix: &str| -> Result<Option<f64>> {
+            Ok(first_rates
+                .column(&format!("{}_{}", column, suffix))?
+                .f64()?
+                .get(0))
+        };
+        let (with, without) = (rate("with")?, rate("without")?);
+        first_objectives.push(json!({
+            "objective": column,
+            "taken_rate": rate("rate")?,
+            "win_rate_with": with,
+            "win_rate_without": without,
+            "lift": with.zip(without).map(|(with, without)| with - without),
+        }));
+    }
+
+    let durations = lf
+        .clone()
+        .group_by([col("game_version")])
+        .agg([
+            col("game_duration")
+                .cast(DataType::Float64)
+                .sum()
+                .alias("seconds"),
+            col("match_id").n_unique().alias("matches"),
+            len().alias("teams"),
+        ])
+        .collect()?;
+    // Per patch: summed seconds and team rows, which share their match's duration.
+    let mut by_patch: BTreeMap<String, (f64, usize, usize)> = BTreeMap::new();
+    for ((version, seconds), (matches, teams)) in durations
+        .column("game_version")?
+        .str()?
+        .into_iter()
+        .zip(durations.column("seconds")?.f64()?)
+        .zip(
+            durations
+                .column("matches")?
+                .u32()?
+                .into_iter()
+                .zip(durations.column("teams")?.u32()?),
+        )
+    {
+        let entry = by_patch
+            .entry(version.map(patch_from_version).unwrap_or_default())
+            .or_default();
+        entry.0 += seconds.unwrap_or(0.0);
+        entry.1 += matches.unwrap_or(0) as usize;
+        entry.2 += teams.unwrap_or(0) as usize;
+    }
+    let mut duration_rows: Vec<(String, usize, f64)> = patch_rows(
+        by_patch
+            .iter()
+            .map(|(patch, (_, matches, _))| (patch.clone(), *matches))
+            .collect(),
+    )
+    .into_iter()
+    .map(|(patch, matches)| {
+        let (seconds, _, teams) = by_patch[&patch];
+        (patch, matches, seconds / teams.max(1) as f64 / 60.0)
+    })
+    .collect();
+    duration_rows.retain(|(_, matches, _)| *matches > 0);
+
+    if text {
+        println!("\nObjectives (mean per team / per game):");
+        for row in &objectives {
+            let mean = |key: &str| {
+                row[key]
+                    .as_f64()
+                    .map(|value| format!("{:.2}", value))
+                    .unwrap_or_else(|| "n/a".to_string())
+            };
+            println!(
+                "  {:<12} {:>6} {:>6}",
+                row["objective"].as_str().unwrap_or_default(),
+                mean("per_team"),
+                mean("per_game")
+            );
+        }
+        println!("\nFirst objectives (taken by, win rate with / without, lift):");
+        for row in &first_objectives {
+            let percent = |key: &str| {
+                row[key]
+                    .as_f64()
+                    .map(|value| format!("{:.1}%", value * 100.0))
+                    .unwrap_or_else(|| "n/a".to_string())
+            };
+            println!(
+                "  {:<16} {:>7} {:>7} {:>7} {:>8}",
+                row["objective"].as_str().unwrap_or_default(),
+                percent("taken_rate"),
+                percent("win_rate_with"),
+                percent("win_rate_without"),
+                row["lift"]
+                    .as_f64()
+                    .map(|lift| format!("{:+.1} pts", lift * 100.0))
+                    .unwrap_or_else(|| "n/a".to_string())
+            );
+        }
+        println!("\nAverage game duration by patch:");
+        for (patch, matches, minutes) in &duration_rows {
+            println!("  {:<8} {:>7} matches {:>6.1} min", patch, matches, minutes);
+        }
+    }
+
+    Ok(json!({
+        "rows": rows,
+        "matches": matches,
+        "soloq_team_win_rate": frame_rows(&side_win)?,
+        "objectives": objectives,
+        "first_objectives": first_objectives,
+        "duration_by_patch": duration_rows
+            .iter()
+            .map(|(patch, matches, minutes)| {
+                json!({"patch": patch, "matches": matches, "avg_duration_min": minutes})
+            })
+            .collect::<Vec<_>>(),
+    }))
+}
+
+/// Team-level objective counts, with their label in the summary.
+const TEAM_OBJECTIVES: [(&str, &str); 6] = [
+    ("team_dragons", "dragons"),
+    ("team_barons", "barons"),
+    ("team_heralds", "heralds"),
+    ("team_towers_destroyed", "towers"),
+    ("team_inhibitors_destroyed", "inhibitors"),
+    ("team_plates", "plates"),
+];
+
+const FIRST_OBJECTIVES: [&str; 6] = [
+    "first_blood",
+    "first_tower",
+    "first_dragon",
+    "first_herald",
+    "first_baron",
+    "first_inhibitor",
+];
+
+const LOBBY_ROLES: [&str; 5] = ["top", "jungle", "middle", "bottom", "utility"];
+
+/// A feature whose means for won and lost rows are this many pooled standard deviations apart
+/// separates the label too well for pre-game information, and is listed as suspect.
+const LEAKAGE_EFFECT_SIZE: f64 = 1.0;
+
+/// Summarizes an `ml_lobby_outcome.parquet` from kraken-prepare-ml before training: the label
+/// balance and whether every match has both sides with one winner, the side win rate, the null
+/// rate of each per-slot column (`ally_top_*` to `enemy_utility_*`), which shows failed champion
+/// and profile joins, and the won/lost effect size of every numeric feature to catch leakage.
+/// Printed and returned like [`kraken_summary_raw`].
+pub fn kraken_summary_lobby(
+    parquet_path: &Path,
+    max_rows: Option<usize>,
+    format: SummaryFormat,
+) -> Result<Value> {
+    let text = format == SummaryFormat::Text;
+    if text {
+        println!("== Kraken Summary (lobby-outcome parquet) ==");
+    }
+
+    let mut lf = LazyFrame::scan_parquet(
+        parquet_path.to_string_lossy().as_ref(),
+        ScanArgsParquet::default(),
+    )?;
+    if let Some(limit) = max_rows {
+        lf = lf.limit(limit.try_into().unwrap_or(u32::MAX));
+    }
+    let schema = lf.schema()?;
+
+    let basic = lf
+        .clone()
+        .select([
+            len().alias("rows"),
+            col("match_id").n_unique().alias("matches"),
+            col("team_win").cast(DataType::UInt32).sum().alias("wins"),
+            col("team_win").null_count().alias("unlabeled"),
+        ])
+        .collect()?;
+    let rows = basic.column("rows")?.u32()?.get(0).unwrap_or(0);
+    let matches = basic.column("matches")?.u32()?.get(0).unwrap_or(0);
+    let wins = basic.column("wins")?.u32()?.get(0).unwrap_or(0);
+    let unlabeled = basic.column("unlabeled")?.u32()?.get(0).unwrap_or(0);
+    let losses = rows.saturating_sub(wins + unlabeled);
+
+    // Each match should appear once per side, with exactly one of them winning.
+    let per_match = lf
+        .clone()
+        .group_by([col("match_id")])
+        .agg([
+            len().alias("sides"),
+            col("team_win")
+                .cast(DataType::UInt32)
+                .sum()
+                .alias("winners"),
+        ])
+        .filter(
+            col("sides")
+                .neq(lit(2u32))
+                .or(col("winners").neq(lit(1u32))),
+        )
+        .select([len().alias("inconsistent")])
+        .collect()?;
+    let inconsistent = per_match.column("inconsistent")?.u32()?.get(0).unwrap_or(0);
+
+    let side_win = lf
+        .clone()
+        .group_by([col("team_id")])
+        .agg([
+            len().alias("rows"),
+            col("team_win")
+                .cast(DataType::Float64)
+                .mean()
+                .alias("win_rate"),
+        ])
         .sort("team_id", SortOptions::default())
         .collect()?;
-    println!("\nSoloQ team winrate:\n{}", side_win);
 
-    Ok(())
+    if text {
+        println!("Rows: {}", rows);
+        println!("Matches: {}", matches);
+        println!(
+            "Label balance: {} won / {} lost ({:.1}% won), {} unlabeled",
+            wins,
+            losses,
+            wins as f64 / (wins + losses).max(1) as f64 * 100.0,
+            unlabeled
+        );
+        if inconsistent > 0 {
+            println!(
+                "Warning: {} matches do not have exactly two sides with one winner",
+                inconsistent
+            );
+        }
+        println!("\nSide winrate:\n{}", side_win);
+    }
+
+    // Null rate per slot and per column suffix, e.g. `ally_top` x `recent_winrate`.
+    let mut slot_columns: Vec<(String, String, String)> = Vec::new();
+    for side in ["ally", "enemy"] {
+        for role in LOBBY_ROLES {
+            let slot = format!("{}_{}", side, role);
+            let prefix = format!("{}_", slot);
+            for name in schema.iter_names() {
+                if let Some(feature) = name.strip_prefix(&prefix) {
+                    slot_columns.push((slot.clone(), feature.to_string(), name.to_string()));
+                }
+            }
+        }
+    }
+    let null_counts = lf
+        .clone()
+        .select(
+            slot_columns
+                .iter()
+                .map(|(_, _, name)| col(name).null_count().alias(name))
+                .collect::<Vec<_>>(),
+        )
+        .collect()?;
+    let mut slot_nulls: Vec<(String, Map<String, Value>)> = Vec::new();
+    for (slot, feature, name) in &slot_columns {
+        if slot_nulls.last().is_none_or(|(last, _)| last != slot) {
+            let mut row = Map::new();
+            row.insert("slot".to_string(), json!(slot));
+            slot_nulls.push((slot.clone(), row));
+        }
+        let nulls = null_counts.column(name)?.u32()?.get(0).unwrap_or(0);
+        if let Some((_, row)) = slot_nulls.last_mut() {
+            row.insert(feature.clone(), json!(nulls as f64 / rows.max(1) as f64));
+        }
+    }
+
+    let features: Vec<String> = schema
+        .iter()
+        .filter(|(name, dtype)| *name != "team_win" && is_feature_column(name, dtype))
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let won = feature_moments(&lf.clone().filter(col("team_win").eq(lit(true))), &features)?;
+    let lost = feature_moments(
+        &lf.clone().filter(col("team_win").eq(lit(false))),
+        &features,
+    )?;
+    let mut associations: Vec<(String, f64, f64, Option<f64>)> = features
+        .iter()
+        .map(|name| {
+            let (won, lost) = (won[name], lost[name]);
+            (name.clone(), won[0], lost[0], cohens_d(lost, won))
+        })
+        .collect();
+    // Without spread inside each label, any difference in means separates them perfectly.
+    let separation = |mean_won: f64, mean_lost: f64, d: Option<f64>| match d {
+        Some(d) => d.abs(),
+        None if mean_won.is_finite() && mean_lost.is_finite() && mean_won != mean_lost => {
+            f64::INFINITY
+        }
+        None => 0.0,
+    };
+    let is_suspect = |mean_won: f64, mean_lost: f64, d: Option<f64>| {
+        separation(mean_won, mean_lost, d) >= LEAKAGE_EFFECT_SIZE
+    };
+    associations.sort_by(|x, y| separation(y.1, y.2, y.3).total_cmp(&separation(x.1, x.2, x.3)));
+    let suspects: Vec<&str> = associations
+        .iter()
+        .filter(|(_, mean_won, mean_lost, d)| is_suspect(*mean_won, *mean_lost, *d))
+        .map(|(name, _, _, _)| name.as_str())
+        .collect();
+
+    if text {
+        println!("\nNull rate per slot:");
+        for (slot, row) in &slot_nulls {
+            let rates: Vec<String> = row
+                .iter()
+                .filter(|(key, _)| *key != "slot")
+                .map(|(key, rate)| format!("{} {:.1}%", key, rate.as_f64().unwrap_or(0.0) * 100.0))
+                .collect();
+            println!("  {:<14} {}", slot, rates.join(", "));
+        }
+        println!("\nFeature means, won vs lost (Cohen's d):");
+        for (name, mean_won, mean_lost, d) in &associations {
+            println!(
+                "  {:<40} {:>10.3} {:>10.3} {:>7}",
+                name,
+                mean_won,
+                mean_lost,
+                d.map(|d| format!("{:+.2}", d))
+                    .unwrap_or_else(|| "n/a".to_string())
+            );
+        }
+        if !suspects.is_empty() {
+            println!(
+                "Warning: {} features separate wins with |d| >= {} or perfectly, check them for leakage: {}",
+                suspects.len(),
+                LEAKAGE_EFFECT_SIZE,
+                suspects.join(", ")
+            );
+        }
+    }
+
+    Ok(json!({
+        "rows": rows,
+        "matches": matches,
+        "wins": wins,
+        "losses": losses,
+        "unlabeled": unlabeled,
+        "win_rate": wins as f64 / (wins + losses).max(1) as f64,
+        "inconsistent_matches": inconsistent,
+        "side_win_rate": frame_rows(&side_win)?,
+        "slot_null_rates": slot_nulls.into_iter().map(|(_, row)| Value::Object(row)).collect::<Vec<_>>(),
+        "label_association": associations
+            .iter()
+            .map(|(name, mean_won, mean_lost, d)| {
+                json!({
+                    "column": name,
+                    "mean_won": mean_won,
+                    "mean_lost": mean_lost,
+                    "cohens_d": d,
+                    "suspect": is_suspect(*mean_won, *mean_lost, *d),
+                })
+            })
+            .collect::<Vec<_>>(),
+    }))
+}
+
+/// A file, or one line of a `.jsonl` shard, that holds no usable match.
+#[derive(Serialize)]
+struct BadDocument {
+    file: String,
+    /// 1-based line of a shard.
+    line: Option<usize>,
+    detail: String,
+}
+
+/// A single-match file whose name disagrees with its `metadata.matchId`.
+#[derive(Serialize)]
+struct MisnamedFile {
+    file: String,
+    match_id: String,
+}
+
+#[derive(Serialize)]
+struct DuplicateMatch {
+    match_id: String,
+    copies: usize,
+    /// `file` or `file:line`, `;`-separated.
+    locations: String,
+}
+
+/// Finds the cruft long or interrupted crawls leave under `matches_dir`: empty files, truncated
+/// or otherwise invalid documents, files named after another match and match IDs stored more
+/// than once. Offenders are listed in the text output and the returned document.
+pub fn kraken_integrity_report(matches_dir: &Path, format: SummaryFormat) -> Result<Value> {
+    let files = collect_match_files(&[matches_dir.to_path_buf()]);
+    let mut documents = 0usize;
+    let mut empty = Vec::new();
+    let mut truncated = Vec::new();
+    let mut invalid = Vec::new();
+    let mut misnamed = Vec::new();
+    let mut locations: HashMap<String, Vec<String>> = HashMap::new();
+
+    for path in &files {
+        let file = path.display().to_string();
+        if fs::metadata(path).is_ok_and(|meta| meta.len() == 0) {
+            empty.push(BadDocument {
+                file,
+                line: None,
+                detail: "empty file".to_string(),
+            });
+            continue;
+        }
+
+        // Single-match files are one document; shards hold one per non-blank line.
+        let mut lines: Vec<(Option<usize>, String)> = Vec::new();
+        let read = open_match_file(path).and_then(|mut reader| {
+            let mut contents = String::new();
+            reader.read_to_string(&mut contents)?;
+            Ok(contents)
+        });
+        match read {
+            Ok(contents) if file_match_id(path).is_some() => lines.push((None, contents)),
+            Ok(contents) => lines.extend(
+                contents
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| !line.trim().is_empty())
+                    .map(|(idx, line)| (Some(idx + 1), line.to_string())),
+            ),
+            // Cut-off compressed streams fail here rather than in the JSON parser.
+            Err(err) => {
+                truncated.push(BadDocument {
+                    file,
+                    line: None,
+                    detail: err.to_string(),
+                });
+                continue;
+            }
+        }
+
+        for (line, json) in lines {
+            let document: MatchDocument = match serde_json::from_str(&json) {
+                Ok(document) => document,
+                Err(err) => {
+                    let bad = BadDocument {
+                        file: file.clone(),
+                        line,
+                        detail: err.to_string(),
+                    };
+                    if err.is_eof() {
+                        truncated.push(bad);
+                    } else {
+                        invalid.push(bad);
+                    }
+                    continue;
+                }
+            };
+            let Some(match_id) = document.match_id() else {
+                invalid.push(BadDocument {
+                    file: file.clone(),
+                    line,
+                    detail: "no metadata.matchId".to_string(),
+                });
+                continue;
+            };
+            documents += 1;
+
+            // kraken's --queue-suffix names files `{match_id}_{queue}.json`.
+            if let Some(name) = file_match_id(path)
+                && name != match_id
+                && !name
+                    .strip_prefix(match_id)
+                    .is_some_and(|rest| rest.starts_with('_'))
+            {
+                misnamed.push(MisnamedFile {
+                    file: file.clone(),
+                    match_id: match_id.to_string(),
+                });
+            }
+            locations
+                .entry(match_id.to_string())
+                .or_default()
+                .push(match line {
+                    Some(line) => format!("{}:{}", file, line),
+                    None => file.clone(),
+                });
+        }
+    }
+
+    let mut duplicates: Vec<DuplicateMatch> = locations
+        .into_iter()
+        .filter(|(_, found)| found.len() > 1)
+        .map(|(match_id, found)| DuplicateMatch {
+            match_id,
+            copies: found.len(),
+            locations: found.join("; "),
+        })
+        .collect();
+    duplicates.sort_by(|a, b| a.match_id.cmp(&b.match_id));
+
+    if format == SummaryFormat::Text {
+        println!("== Kraken Integrity Check ==");
+        println!("Files checked: {}", files.len());
+        println!("Valid documents: {}", documents);
+        for (title, bad) in [
+            ("Empty files", &empty),
+            ("Truncated documents", &truncated),
+            ("Invalid documents", &invalid),
+        ] {
+            println!("{}: {}", title, bad.len());
+            for bad in bad {
+                match bad.line {
+                    Some(line) => println!("  {}:{}: {}", bad.file, line, bad.detail),
+                    None => println!("  {}: {}", bad.file, bad.detail),
+                }
+            }
+        }
+        println!("Files named after another match: {}", misnamed.len());
+        for file in &misnamed {
+            println!("  {} holds {}", file.file, file.match_id);
+        }
+        println!("Duplicate match IDs: {}", duplicates.len());
+        for duplicate in &duplicates {
+            println!(
+                "  {} ({}x): {}",
+                duplicate.match_id, duplicate.copies, duplicate.locations
+            );
+        }
+    }
+
+    Ok(json!({
+        "files_checked": files.len(),
+        "documents": documents,
+        "empty_files": empty,
+        "truncated": truncated,
+        "invalid": invalid,
+        "misnamed_files": misnamed,
+        "duplicates": duplicates,
+    }))
+}
+
+/// Keeps the rows whose `game_version` belongs to `patch` (`14.21`).
+fn filter_patch(lf: LazyFrame, patch: &str) -> Result<LazyFrame> {
+    let versions = lf
+        .clone()
+        .select([col("game_version").unique()])
+        .collect()?;
+    let keep = versions
+        .column("game_version")?
+        .str()?
+        .into_iter()
+        .flatten()
+        .filter(|version| patch_from_version(version) == patch)
+        .fold(lit(false), |keep, version| {
+            keep.or(col("game_version").eq(lit(version.to_string())))
+        });
+    Ok(lf.filter(keep))
+}
+
+/// Per-champion pick rate, win rate and, with a team Parquet, ban rate: the usual sanity check
+/// against public stats sites. Rates are over the matches of `patch` (every match without one);
+/// `role` only restricts the picks counted. Printed and returned like [`kraken_summary_raw`].
+pub fn kraken_champion_table(
+    player_parquet: &Path,
+    team_parquet: Option<&Path>,
+    role: Option<&str>,
+    patch: Option<&str>,
+    top_k: Option<usize>,
+    format: SummaryFormat,
+) -> Result<Value> {
+    let mut players = LazyFrame::scan_parquet(
+        player_parquet.to_string_lossy().as_ref(),
+        ScanArgsParquet::default(),
+    )?;
+    if let Some(patch) = patch {
+        players = filter_patch(players, patch)?;
+    }
+    let matches = players
+        .clone()
+        .select([col("match_id").n_unique().alias("matches")])
+        .collect()?
+        .column("matches")?
+        .u32()?
+        .get(0)
+        .unwrap_or(0);
+
+    if let Some(role) = role {
+        players = players.filter(col("role").eq(lit(role.to_ascii_uppercase())));
+    }
+    let mut table = players
+        .group_by([col("champion_id").cast(DataType::Int64)])
+        .agg([
+            col("champion_name").first(),
+            len().alias("games"),
+            col("win").cast(DataType::Float64).mean().alias("win_rate"),
+        ])
+        .with_column(
+            (col("games").cast(DataType::Float64) / lit(matches.max(1) as f64)).alias("pick_rate"),
+        );
+
+    let mut columns = vec![
+        col("champion_id"),
+        col("champion_name"),
+        col("games"),
+        col("pick_rate"),
+        col("win_rate"),
+    ];
+    if let Some(team_parquet) = team_parquet {
+        let mut teams = LazyFrame::scan_parquet(
+            team_parquet.to_string_lossy().as_ref(),
+            ScanArgsParquet::default(),
+        )?;
+        if let Some(patch) = patch {
+            teams = filter_patch(teams, patch)?;
+        }
+        let team_matches = teams
+            .clone()
+            .select([col("match_id").n_unique().alias("matches")])
+            .collect()?
+            .column("matches")?
+            .u32()?
+            .get(0)
+            .unwrap_or(0);
+        let slots: Vec<LazyFrame> = (1..=5)
+            .map(|slot| {
+                teams.clone().select([
+                    col("match_id"),
+                    col(&format!("ban{}_champion_id", slot))
+                        .cast(DataType::Int64)
+                        .alias("champion_id"),
+                ])
+            })
+            .collect();
+        // Both teams banning a champion is one ban of the match.
+        let bans = concat(slots, UnionArgs::default())?
+            .filter(col("champion_id").is_not_null())
+            .group_by([col("champion_id")])
+            .agg([col("match_id").n_unique().alias("bans")]);
+        table = table.left_join(bans, col("champion_id"), col("champion_id"));
+        columns.push(col("bans").fill_null(lit(0)).alias("bans"));
+        columns.push(
+            (col("bans").fill_null(lit(0)).cast(DataType::Float64)
+                / lit(team_matches.max(1) as f64))
+            .alias("ban_rate"),
+        );
+    }
+
+    let mut table = table.select(columns).sort(
+        "games",
+        SortOptions {
+            descending: true,
+            nulls_last: true,
+            ..Default::default()
+        },
+    );
+    if let Some(k) = top_k {
+        table = table.limit(k.try_into().unwrap_or(u32::MAX));
+    }
+    let table = table.collect()?;
+
+    if format == SummaryFormat::Text {
+        let scope = [
+            role.map(|role| format!("role {}", role.to_ascii_uppercase())),
+            patch.map(|patch| format!("patch {}", patch)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        println!(
+            "\n== Champion pick/ban/win table ({} matches{}) ==\n{}",
+            matches,
+            scope
+                .iter()
+                .map(|part| format!(", {}", part))
+                .collect::<String>(),
+            table
+        );
+    }
+    Ok(json!({
+        "matches": matches,
+        "role": role.map(str::to_ascii_uppercase),
+        "patch": patch,
+        "champions": frame_rows(&table)?,
+    }))
+}
+
+/// Quality of one Parquet column.
+#[derive(Serialize)]
+struct ColumnQuality {
+    column: String,
+    dtype: String,
+    null_rate: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    /// Why the column deserves a look; empty when nothing stands out.
+    flag: String,
+}
+
+#[derive(Serialize)]
+struct QualityCheck {
+    check: String,
+    count: usize,
+}
+
+/// Rows a match has at each level: ten players, two teams.
+fn expected_rows_per_match(df: &DataFrame) -> Option<u32> {
+    if df.get_column_index("puuid").is_some() {
+        Some(10)
+    } else if df.get_column_index("team_id").is_some() {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Per-column null rates and ranges of an extract-parquet output, plus range checks on the
+/// match fields. Columns that are entirely null, or null in some patches but filled in others
+/// (schema drift), are flagged. Printed and returned like [`kraken_summary_raw`].
+pub fn kraken_quality_report(
+    parquet_path: &Path,
+    max_rows: Option<usize>,
+    format: SummaryFormat,
+) -> Result<Value> {
+    let mut lf = LazyFrame::scan_parquet(
+        parquet_path.to_string_lossy().as_ref(),
+        ScanArgsParquet::default(),
+    )?;
+    if let Some(limit) = max_rows {
+        lf = lf.limit(limit.try_into().unwrap_or(u32::MAX));
+    }
+    let df = lf.clone().collect()?;
+    let rows = df.height();
+
+    // Null counts per patch, to tell drift from columns that are sparse everywhere.
+    let mut patch_nulls: BTreeMap<String, (usize, Vec<usize>)> = BTreeMap::new();
+    if df.get_column_index("game_version").is_some() {
+        let mut aggs = vec![len().alias("rows")];
+        aggs.extend(
+            df.get_column_names()
+                .iter()
+                .enumerate()
+                .map(|(idx, name)| col(name).null_count().alias(&format!("nulls_{}", idx))),
+        );
+        let by_version = lf.group_by([col("game_version")]).agg(aggs).collect()?;
+        let versions = by_version.column("game_version")?.str()?;
+        let heights = by_version.column("rows")?.u32()?;
+        for row in 0..by_version.height() {
+            let patch = versions
+                .get(row)
+                .map(patch_from_version)
+                .unwrap_or_default();
+            let (height, nulls) = patch_nulls
+                .entry(patch)
+                .or_insert_with(|| (0, vec![0; df.width()]));
+            *height += heights.get(row).unwrap_or(0) as usize;
+            for (idx, count) in nulls.iter_mut().enumerate() {
+                let column = by_version.column(&format!("nulls_{}", idx))?;
+                *count += column.cast(&DataType::UInt64)?.u64()?.get(row).unwrap_or(0) as usize;
+            }
+        }
+    }
+
+    let mut columns = Vec::new();
+    for (idx, column) in df.get_columns().iter().enumerate() {
+        let nulls = column.null_count();
+        let numeric = column.dtype().is_numeric();
+        let (min, max) = if numeric {
+            let values = column.cast(&DataType::Float64)?;
+            (values.f64()?.min(), values.f64()?.max())
+        } else {
+            (None, None)
+        };
+        let flag = if rows > 0 && nulls == rows {
+            "all null".to_string()
+        } else {
+            let empty: Vec<&str> = patch_nulls
+                .iter()
+                .filter(|(_, (height, nulls))| *height > 0 && nulls[idx] == *height)
+                .map(|(patch, _)| patch.as_str())
+                .collect();
+            let with_nulls: Vec<&str> = patch_nulls
+                .iter()
+                .filter(|(_, (_, nulls))| nulls[idx] > 0)
+                .map(|(patch, _)| patch.as_str())
+                .collect();
+            if !empty.is_empty() {
+                format!("all null in patch {}", empty.join(", "))
+            } else if !with_nulls.is_empty() && with_nulls.len() < patch_nulls.len() {
+                format!("nulls only in patch {}", with_nulls.join(", "))
+            } else {
+                String::new()
+            }
+        };
+        columns.push(ColumnQuality {
+            column: column.name().to_string(),
+            dtype: column.dtype().to_string(),
+            null_rate: if rows > 0 {
+                nulls as f64 / rows as f64
+            } else {
+                0.0
+            },
+            min,
+            max,
+            flag,
+        });
+    }
+
+    let mut checks = Vec::new();
+    for (name, label) in [
+        ("game_duration", "rows with game_duration <= 0"),
+        ("game_creation", "rows with game_creation <= 0"),
+    ] {
+        if let Ok(column) = df.column(name) {
+            let values = column.cast(&DataType::Int64)?;
+            checks.push(QualityCheck {
+                check: label.to_string(),
+                count: values
+                    .i64()?
+                    .into_iter()
+                    .flatten()
+                    .filter(|v| *v <= 0)
+                    .count(),
+            });
+        }
+    }
+    if let (Some(expected), Ok(match_ids)) = (expected_rows_per_match(&df), df.column("match_id")) {
+        let per_match = match_ids.value_counts(false, false)?;
+        let off = per_match
+            .column("count")?
+            .u32()?
+            .into_iter()
+            .flatten()
+            .filter(|count| *count != expected)
+            .count();
+        checks.push(QualityCheck {
+            check: format!("matches without exactly {} rows", expected),
+            count: off,
+        });
+    }
+
+    if format == SummaryFormat::Text {
+        println!(
+            "\n== Data quality ({}, {} rows) ==",
+            parquet_path.display(),
+            rows
+        );
+        println!(
+            "{:<36} {:<8} {:>7} {:>14} {:>14}  Flag",
+            "Column", "Type", "Null%", "Min", "Max"
+        );
+        let number = |value: Option<f64>| match value {
+            Some(v) if v.fract() == 0.0 => format!("{}", v as i64),
+            Some(v) => format!("{:.3}", v),
+            None => String::new(),
+        };
+        for column in &columns {
+            println!(
+                "{:<36} {:<8} {:>6.1}% {:>14} {:>14}  {}",
+                column.column,
+                column.dtype,
+                column.null_rate * 100.0,
+                number(column.min),
+                number(column.max),
+                column.flag
+            );
+        }
+        for check in &checks {
+            let mark = if check.count > 0 { "FAIL" } else { "ok" };
+            println!("{}: {} ({})", check.check, check.count, mark);
+        }
+    }
+
+    let flagged = columns.iter().filter(|c| !c.flag.is_empty()).count();
+    Ok(json!({
+        "rows": rows,
+        "flagged_columns": flagged,
+        "columns": columns,
+        "checks": checks,
+    }))
+}
+
+/// Numeric columns that hold IDs or codes, whose means say nothing.
+const CODE_COLUMN_PREFIXES: [&str; 5] = ["item", "keystone", "primary_", "secondary_", "stat_"];
+
+fn is_feature_column(name: &str, dtype: &DataType) -> bool {
+    (dtype.is_numeric() || *dtype == DataType::Boolean)
+        && !name.ends_with("_id")
+        && name != "game_creation"
+        && !CODE_COLUMN_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+/// What one side of a dataset diff contributes: sizes and category counts.
+struct DatasetProfile {
+    rows: usize,
+    matches: usize,
+    players: usize,
+    /// Matches per patch and per queue.
+    patches: BTreeMap<String, usize>,
+    queues: BTreeMap<String, usize>,
+    /// Rows per champion.
+    champions: BTreeMap<String, usize>,
+    /// Distinct players per tier, when ranks are known.
+    tiers: BTreeMap<String, usize>,
+}
+
+impl DatasetProfile {
+    fn load(lf: &LazyFrame, ranks: &HashMap<String, Option<u32>>) -> Result<Self> {
+        let df = lf
+            .clone()
+            .select([
+                col("match_id"),
+                col("puuid"),
+                col("queue_id").cast(DataType::Int64),
+                col("game_version"),
+                col("champion_name"),
+            ])
+            .collect()?;
+        let mut profile = DatasetProfile {
+            rows: df.height(),
+            matches: 0,
+            players: 0,
+            patches: BTreeMap::new(),
+            queues: BTreeMap::new(),
+            champions: BTreeMap::new(),
+            tiers: BTreeMap::new(),
+        };
+        let mut matches = HashSet::new();
+        let mut players = HashSet::new();
+        let columns = (
+            df.column("match_id")?.str()?,
+            df.column("puuid")?.str()?,
+            df.column("queue_id")?.i64()?,
+            df.column("game_version")?.str()?,
+            df.column("champion_name")?.str()?,
+        );
+        for idx in 0..df.height() {
+            let champion = columns.4.get(idx).unwrap_or_default();
+            *profile.champions.entry(champion.to_string()).or_insert(0) += 1;
+            if let Some(puuid) = columns.1.get(idx)
+                && players.insert(puuid)
+                && let Some(rank) = ranks.get(puuid)
+            {
+                let tier = match rank {
+                    Some(ordinal) => TIERS[(*ordinal / 4) as usize].to_string(),
+                    None => "UNRANKED".to_string(),
+                };
+                *profile.tiers.entry(tier).or_insert(0) += 1;
+            }
+            if let Some(match_id) = columns.0.get(idx)
+                && matches.insert(match_id)
+            {
+                let patch = columns
+                    .3
+                    .get(idx)
+                    .map(patch_from_version)
+                    .unwrap_or_default();
+                *profile.patches.entry(patch).or_insert(0) += 1;
+                let queue = columns.2.get(idx).map(queue_label).unwrap_or_default();
+                *profile.queues.entry(queue).or_insert(0) += 1;
+            }
+        }
+        profile.matches = matches.len();
+        profile.players = players.len();
+        Ok(profile)
+    }
+}
+
+/// Share of each category on both sides, biggest shift first, and the total variation distance
+/// between the two mixes (0 identical, 1 disjoint).
+fn mix_shift(
+    key: &str,
+    base: &BTreeMap<String, usize>,
+    candidate: &BTreeMap<String, usize>,
+) -> (Vec<Value>, f64) {
+    let base_total = base.values().sum::<usize>().max(1) as f64;
+    let candidate_total = candidate.values().sum::<usize>().max(1) as f64;
+    let keys: BTreeSet<&String> = base.keys().chain(candidate.keys()).collect();
+    let mut rows: Vec<(&String, f64, f64)> = keys
+        .into_iter()
+        .map(|name| {
+            (
+                name,
+                base.get(name).copied().unwrap_or(0) as f64 / base_total,
+                candidate.get(name).copied().unwrap_or(0) as f64 / candidate_total,
+            )
+        })
+        .collect();
+    let distance = rows.iter().map(|(_, a, b)| (b - a).abs()).sum::<f64>() / 2.0;
+    rows.sort_by(|x, y| (y.2 - y.1).abs().total_cmp(&(x.2 - x.1).abs()));
+    let rows = rows
+        .into_iter()
+        .map(|(name, a, b)| {
+            json!({key: name, "base_share": a, "candidate_share": b, "change": b - a})
+        })
+        .collect();
+    (rows, distance)
+}
+
+/// Mean, standard deviation and non-null count of every feature column.
+fn feature_moments(lf: &LazyFrame, names: &[String]) -> Result<HashMap<String, [f64; 3]>> {
+    let mut exprs = Vec::with_capacity(names.len() * 3);
+    for name in names {
+        let values = col(name).cast(DataType::Float64);
+        exprs.push(values.clone().mean().alias(&format!("{}__mean", name)));
+        exprs.push(values.clone().std(1).alias(&format!("{}__std", name)));
+        exprs.push(
+            values
+                .count()
+                .cast(DataType::Float64)
+                .alias(&format!("{}__n", name)),
+        );
+    }
+    let df = lf.clone().select(exprs).collect()?;
+    let value = |column: String| -> Result<f64> {
+        Ok(df.column(&column)?.f64()?.get(0).unwrap_or(f64::NAN))
+    };
+    let mut moments = HashMap::new();
+    for name in names {
+        moments.insert(
+            name.clone(),
+            [
+                value(format!("{}__mean", name))?,
+                value(format!("{}__std", name))?,
+                value(format!("{}__n", name))?,
+            ],
+        );
+    }
+    Ok(moments)
+}
+
+/// `b` minus `a` in pooled standard deviations, from [`feature_moments`] values; `None` when the
+/// pooled deviation is zero or undefined.
+fn cohens_d([mean_a, sd_a, n_a]: [f64; 3], [mean_b, sd_b, n_b]: [f64; 3]) -> Option<f64> {
+    let pooled =
+        (((n_a - 1.0) * sd_a * sd_a + (n_b - 1.0) * sd_b * sd_b) / (n_a + n_b - 2.0)).sqrt();
+    (pooled.is_finite() && pooled > 0.0).then(|| (mean_b - mean_a) / pooled)
+}
+
+/// Compares two player-level Parquets, `candidate` against `base`: sizes, patch/queue/rank and
+/// champion mixes with their total variation distance, and the mean of every shared feature
+/// with Cohen's d. Lists are sorted by the size of the shift; text output shows the `top_k`
+/// first. Printed and returned like [`kraken_summary_raw`].
+pub fn kraken_dataset_diff(
+    base: &Path,
+    candidate: &Path,
+    ranks: Option<&Path>,
+    top_k: usize,
+    format: SummaryFormat,
+) -> Result<Value> {
+    let scan = |path: &Path| {
+        LazyFrame::scan_parquet(path.to_string_lossy().as_ref(), ScanArgsParquet::default())
+    };
+    let (base_lf, candidate_lf) = (scan(base)?, scan(candidate)?);
+    let ranks = match ranks {
+        Some(path) => load_ranks(&[path.to_path_buf()])?,
+        None => HashMap::new(),
+    };
+    let base_profile = DatasetProfile::load(&base_lf, &ranks)?;
+    let candidate_profile = DatasetProfile::load(&candidate_lf, &ranks)?;
+
+    let candidate_schema = candidate_lf.schema()?;
+    let features: Vec<String> = base_lf
+        .schema()?
+        .iter()
+        .filter(|(name, dtype)| {
+            is_feature_column(name, dtype)
+                && candidate_schema
+                    .get(name)
+                    .is_some_and(|other| is_feature_column(name, other))
+        })
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let base_moments = feature_moments(&base_lf, &features)?;
+    let candidate_moments = feature_moments(&candidate_lf, &features)?;
+    let mut feature_rows: Vec<(String, f64, f64, Option<f64>)> = features
+        .iter()
+        .map(|name| {
+            let (a, b) = (base_moments[name], candidate_moments[name]);
+            (name.clone(), a[0], b[0], cohens_d(a, b))
+        })
+        .collect();
+    feature_rows.sort_by(|x, y| {
+        y.3.map_or(0.0, f64::abs)
+            .total_cmp(&x.3.map_or(0.0, f64::abs))
+    });
+
+    let (patches, patch_distance) =
+        mix_shift("patch", &base_profile.patches, &candidate_profile.patches);
+    let (queues, queue_distance) =
+        mix_shift("queue", &base_profile.queues, &candidate_profile.queues);
+    let (champions, champion_distance) = mix_shift(
+        "champion_name",
+        &base_profile.champions,
+        &candidate_profile.champions,
+    );
+    let (tiers, tier_distance) = mix_shift("tier", &base_profile.tiers, &candidate_profile.tiers);
+    let sizes = [
+        ("rows", base_profile.rows, candidate_profile.rows),
+        ("matches", base_profile.matches, candidate_profile.matches),
+        ("players", base_profile.players, candidate_profile.players),
+    ];
+
+    if format == SummaryFormat::Text {
+        println!("== Dataset diff ==");
+        println!("Base:      {}", base.display());
+        println!("Candidate: {}", candidate.display());
+        for (metric, a, b) in sizes {
+            println!(
+                "{:<9} {:>10} -> {:>10} ({:+})",
+                metric,
+                a,
+                b,
+                b as i64 - a as i64
+            );
+        }
+        let print_mix = |title: &str, key: &str, rows: &[Value], distance: f64| {
+            if rows.is_empty() {
+                return;
+            }
+            println!("\n{} (total variation distance {:.3}):", title, distance);
+            for row in rows.iter().take(top_k) {
+                println!(
+                    "  {:<20} {:>6.1}% -> {:>6.1}% ({:+.1} pts)",
+                    row[key].as_str().unwrap_or_default(),
+                    row["base_share"].as_f64().unwrap_or_default() * 100.0,
+                    row["candidate_share"].as_f64().unwrap_or_default() * 100.0,
+                    row["change"].as_f64().unwrap_or_default() * 100.0
+                );
+            }
+        };
+        print_mix("Patch mix (matches)", "patch", &patches, patch_distance);
+        print_mix("Queue mix (matches)", "queue", &queues, queue_distance);
+        print_mix("Rank mix (players)", "tier", &tiers, tier_distance);
+        print_mix(
+            "Champion mix (picks)",
+            "champion_name",
+            &champions,
+            champion_distance,
+        );
+        println!("\nFeature means, largest effect first (Cohen's d):");
+        for (name, a, b, d) in feature_rows.iter().take(top_k) {
+            println!(
+                "  {:<36} {:>12.3} -> {:>12.3}  d={}",
+                name,
+                a,
+                b,
+                d.map(|d| format!("{:+.3}", d))
+                    .unwrap_or_else(|| "n/a".to_string())
+            );
+        }
+    }
+
+    Ok(json!({
+        "base": base.display().to_string(),
+        "candidate": candidate.display().to_string(),
+        "sizes": sizes
+            .iter()
+            .map(|(metric, a, b)| json!({"metric": metric, "base": a, "candidate": b}))
+            .collect::<Vec<_>>(),
+        "patch_distance": patch_distance,
+        "queue_distance": queue_distance,
+        "rank_distance": (!ranks.is_empty()).then_some(tier_distance),
+        "champion_distance": champion_distance,
+        "patches": patches,
+        "queues": queues,
+        "ranks": tiers,
+        "champions": champions,
+        "features": feature_rows
+            .iter()
+            .map(|(name, a, b, d)| {
+                json!({"feature": name, "base_mean": a, "candidate_mean": b, "cohens_d": d})
+            })
+            .collect::<Vec<_>>(),
+    }))
 }
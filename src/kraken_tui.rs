@@ -0,0 +1,163 @@
+use crate::kraken::KrakenStatus;
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{BarChart, Block, Borders, Gauge, List, ListItem, Paragraph, Sparkline};
+use std::io;
+use std::time::Duration;
+
+/// Everything the `--tui` dashboard shows, gathered by the crawl once per refresh.
+pub struct DashboardSnapshot {
+    pub status: KrakenStatus,
+    /// Requests sent in the last two minutes, over all regions.
+    pub requests_2min: usize,
+    /// Configured 2-minute limit, over all regions.
+    pub request_limit: usize,
+    pub throttled: usize,
+    /// Matches written per minute, oldest first.
+    pub write_rate_history: Vec<u64>,
+    /// Crawled players per matches-downloaded bucket (`1-4`, `5-9`, ...).
+    pub coverage_histogram: Vec<(String, u64)>,
+    pub recent_errors: Vec<String>,
+}
+
+/// Full-screen dashboard on the alternate screen; the terminal is restored on drop.
+pub struct KrakenDashboard {
+    terminal: DefaultTerminal,
+}
+
+impl KrakenDashboard {
+    pub fn start() -> io::Result<Self> {
+        Ok(Self {
+            terminal: ratatui::try_init()?,
+        })
+    }
+
+    /// Waits up to `timeout` for a key press and returns `true` on `q`, `Esc` or `Ctrl-C`
+    /// (raw mode swallows the interrupt signal, so the dashboard has to handle it).
+    pub fn quit_requested(&self, timeout: Duration) -> io::Result<bool> {
+        if !event::poll(timeout)? {
+            return Ok(false);
+        }
+
+        let Event::Key(key) = event::read()? else {
+            return Ok(false);
+        };
+        Ok(key.kind == KeyEventKind::Press
+            && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                || (key.code == KeyCode::Char('c')
+                    && key.modifiers.contains(KeyModifiers::CONTROL))))
+    }
+
+    pub fn draw(&mut self, snapshot: &DashboardSnapshot) -> io::Result<()> {
+        self.terminal.draw(|frame| {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(4),
+                    Constraint::Length(3),
+                    Constraint::Min(8),
+                    Constraint::Length(8),
+                ])
+                .split(frame.area());
+            let middle = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(rows[2]);
+
+            let status = &snapshot.status;
+            let eta = status
+                .eta_secs
+                .map(|secs| format!("{}m{:02}s", secs / 60, secs % 60))
+                .unwrap_or_else(|| "-".to_string());
+            let summary = Paragraph::new(vec![
+                Line::from(format!(
+                    "elapsed {}s   fetched {}   written {}   frontier {}   players {}   workers {}",
+                    status.elapsed_secs,
+                    status.fetched,
+                    status.written,
+                    status.queue,
+                    status.seen_players,
+                    status.active_workers
+                )),
+                Line::from(format!(
+                    "{:.1} fetched/min   {:.1} written/min   eta {}   skipped: {}",
+                    status.fetched_per_min, status.written_per_min, eta, status.skipped
+                )),
+            ])
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" kraken-absorb (q to stop) "),
+            );
+            frame.render_widget(summary, rows[0]);
+
+            let limit = snapshot.request_limit.max(1);
+            let ratio = (snapshot.requests_2min as f64 / limit as f64).min(1.0);
+            let requests = Gauge::default()
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!(" requests / 2 min ({} x 429) ", snapshot.throttled)),
+                )
+                .gauge_style(Style::default().fg(if snapshot.throttled > 0 {
+                    Color::Yellow
+                } else {
+                    Color::Green
+                }))
+                .ratio(ratio)
+                .label(format!("{} / {}", snapshot.requests_2min, limit));
+            frame.render_widget(requests, rows[1]);
+
+            let write_rate = Sparkline::default()
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(" matches written / min "),
+                )
+                .data(&snapshot.write_rate_history)
+                .style(Style::default().fg(Color::Cyan));
+            frame.render_widget(write_rate, middle[0]);
+
+            let bars: Vec<(&str, u64)> = snapshot
+                .coverage_histogram
+                .iter()
+                .map(|(label, count)| (label.as_str(), *count))
+                .collect();
+            let coverage = BarChart::default()
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(" players by matches downloaded "),
+                )
+                .data(&bars)
+                .bar_width(7)
+                .bar_style(Style::default().fg(Color::Magenta));
+            frame.render_widget(coverage, middle[1]);
+
+            let errors: Vec<ListItem> = snapshot
+                .recent_errors
+                .iter()
+                .rev()
+                .map(|message| ListItem::new(message.as_str()))
+                .collect();
+            let errors = List::new(errors)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(" recent errors "),
+                )
+                .style(Style::default().fg(Color::Red));
+            frame.render_widget(errors, rows[3]);
+        })?;
+        Ok(())
+    }
+}
+
+impl Drop for KrakenDashboard {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
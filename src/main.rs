@@ -1,13 +1,33 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use std::collections::HashSet;
 use std::env;
-use std::path::PathBuf;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 
+mod column_spec;
+mod dataset_split;
+mod ddragon;
 mod kraken;
+mod kraken_config;
+mod kraken_dedup;
+mod kraken_fetch;
+mod kraken_follow;
+mod kraken_frontier;
+mod kraken_manifest;
+mod kraken_notify;
 mod kraken_prepare_ml;
+mod kraken_report;
+mod kraken_retry;
 mod kraken_summary;
+mod kraken_tui;
+mod match_data;
+mod match_timeline;
 mod parquet_extract;
+mod player_profile;
 mod riot_api;
 mod stats;
+#[cfg(test)]
+mod test_support;
 
 // Example usage:
 // cargo run -- --game-name "DeadlyBubble" --tag-line "EUW"
@@ -34,6 +54,7 @@ struct Cli {
 }
 
 #[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// List match IDs for a given PUUID
     Matches {
@@ -63,11 +84,28 @@ enum Commands {
 
     /// Extract basic stats for downloaded matches and save them to CSV
     ExtractStats {
-        /// Player Universal Unique Identifier (can also come from RIOT_PUUID env var)
+        /// Player Universal Unique Identifier; repeat for several players (defaults to the
+        /// RIOT_PUUID env var)
         #[arg(long = "puuid")]
-        puuid: Option<String>,
+        puuid: Vec<String>,
+
+        /// File of PUUIDs (one per line) to extract alongside any --puuid
+        #[arg(long = "puuid-file")]
+        puuid_file: Option<String>,
+
+        /// Only include these comma-separated queues (IDs or labels such as soloq,flex)
+        #[arg(long = "queues")]
+        queues: Option<String>,
 
-        /// Directory containing downloaded match JSON files
+        /// Only include games started after this date (YYYY-MM-DD) or interval ago (e.g. 30d)
+        #[arg(long = "since", value_parser = kraken::TimeBound::parse)]
+        since: Option<kraken::TimeBound>,
+
+        /// Only include games started before this date (YYYY-MM-DD) or interval ago; exclusive
+        #[arg(long = "until", value_parser = kraken::TimeBound::parse)]
+        until: Option<kraken::TimeBound>,
+
+        /// Directory of downloaded match JSON files and shards, searched recursively
         #[arg(long = "matches-dir", default_value = "data/raw/matches")]
         matches_dir: String,
 
@@ -77,10 +115,131 @@ enum Commands {
             default_value = "data/processed/deadlybubble_basic.csv"
         )]
         out_file: String,
+
+        /// Output format: csv, json (one array) or ndjson
+        #[arg(long = "format", default_value = "csv", value_parser = stats::StatsFormat::parse)]
+        format: stats::StatsFormat,
+
+        /// Keep an existing --out-file and only add the games it lacks (csv and ndjson)
+        #[arg(long = "append", default_value_t = false)]
+        append: bool,
+
+        /// Print games, win rate, KDA, CS/min and the top champions when done
+        #[arg(long = "summary", default_value_t = false)]
+        summary: bool,
     },
 
+    /// Per-champion and role summary of a player's downloaded matches
+    ChampionSummary {
+        /// Player Universal Unique Identifier; repeat for several players (defaults to the
+        /// RIOT_PUUID env var)
+        #[arg(long = "puuid")]
+        puuid: Vec<String>,
+
+        /// File of PUUIDs (one per line) to summarize alongside any --puuid
+        #[arg(long = "puuid-file")]
+        puuid_file: Option<String>,
+
+        /// Directory of downloaded match JSON files and shards, searched recursively
+        #[arg(long = "matches-dir", default_value = "data/raw/matches")]
+        matches_dir: String,
+
+        /// Also write the summary to this file
+        #[arg(long = "out-file")]
+        out_file: Option<String>,
+
+        /// Format of --out-file: csv, json (one array) or ndjson
+        #[arg(long = "format", default_value = "csv", value_parser = stats::StatsFormat::parse)]
+        format: stats::StatsFormat,
+    },
+
+    /// Rolling win rates and win/loss streaks over a player's downloaded matches
+    Form {
+        /// Player Universal Unique Identifier; repeat for several players (defaults to the
+        /// RIOT_PUUID env var)
+        #[arg(long = "puuid")]
+        puuid: Vec<String>,
+
+        /// File of PUUIDs (one per line) to report alongside any --puuid
+        #[arg(long = "puuid-file")]
+        puuid_file: Option<String>,
+
+        /// Directory of downloaded match JSON files and shards, searched recursively
+        #[arg(long = "matches-dir", default_value = "data/raw/matches")]
+        matches_dir: String,
+
+        /// Also write one row per game, oldest first, to this file
+        #[arg(long = "out-file")]
+        out_file: Option<String>,
+
+        /// Format of --out-file: csv, json (one array) or ndjson
+        #[arg(long = "format", default_value = "csv", value_parser = stats::StatsFormat::parse)]
+        format: stats::StatsFormat,
+    },
+
+    /// Record and lane stats of two players in the matches they shared
+    HeadToHead {
+        /// Player Universal Unique Identifier (can also come from RIOT_PUUID env var)
+        #[arg(long = "puuid")]
+        puuid: Option<String>,
+
+        /// PUUID of the other player
+        #[arg(
+            long = "other-puuid",
+            conflicts_with = "other_riot_id",
+            required_unless_present = "other_riot_id"
+        )]
+        other_puuid: Option<String>,
+
+        /// Riot ID of the other player (Name#TAG), as recorded in the matches
+        #[arg(long = "other-riot-id", value_parser = stats::Opponent::parse_riot_id)]
+        other_riot_id: Option<stats::Opponent>,
+
+        /// Directory of downloaded match JSON files and shards, searched recursively
+        #[arg(long = "matches-dir", default_value = "data/raw/matches")]
+        matches_dir: String,
+
+        /// Also write one row per shared match to this file
+        #[arg(long = "out-file")]
+        out_file: Option<String>,
+
+        /// Format of --out-file: csv, json (one array) or ndjson
+        #[arg(long = "format", default_value = "csv", value_parser = stats::StatsFormat::parse)]
+        format: stats::StatsFormat,
+    },
+
+    /// Recurring teammates of a player and how the player does with and without them
+    DuoReport {
+        /// Player Universal Unique Identifier (can also come from RIOT_PUUID env var)
+        #[arg(long = "puuid")]
+        puuid: Option<String>,
+
+        /// Directory of downloaded match JSON files and shards, searched recursively
+        #[arg(long = "matches-dir", default_value = "data/raw/matches")]
+        matches_dir: String,
+
+        /// Only list teammates from at least this many games
+        #[arg(long = "min-games", default_value_t = 2)]
+        min_games: usize,
+
+        /// Also write one row per partner to this file
+        #[arg(long = "out-file")]
+        out_file: Option<String>,
+
+        /// Format of --out-file: csv, json (one array) or ndjson
+        #[arg(long = "format", default_value = "csv", value_parser = stats::StatsFormat::parse)]
+        format: stats::StatsFormat,
+    },
+
+    // Flags repeated after a --config profile replace the profile's values.
     /// Long-running kraken harvester for crawling matches
+    #[command(args_override_self = true)]
     KrakenAbsorb {
+        /// TOML crawl profile whose keys are the flag names below; flags on the command line
+        /// override it
+        #[arg(long = "config")]
+        config: Option<String>,
+
         /// Optional single seed PUUID to start crawling from
         #[arg(long = "seed-puuid")]
         seed_puuid: Option<String>,
@@ -89,6 +248,10 @@ enum Commands {
         #[arg(long = "seed-file")]
         seed_file: Option<String>,
 
+        /// Optional file of PUUIDs (one per line) that must never be enqueued
+        #[arg(long = "exclude-puuid-file")]
+        exclude_puuid_file: Option<String>,
+
         /// Duration in minutes for how long the crawler should run
         #[arg(long = "duration-mins")]
         duration_mins: u64,
@@ -109,11 +272,52 @@ enum Commands {
         #[arg(long = "max-matches-total")]
         max_matches_total: Option<usize>,
 
+        /// Stop once this many players have --min-matches-per-player downloaded matches
+        #[arg(long = "target-players")]
+        target_players: Option<usize>,
+
+        /// Matches a player needs to count towards --target-players
+        #[arg(long = "min-matches-per-player", default_value_t = 1)]
+        min_matches_per_player: usize,
+
+        /// Skip discovered players with fewer games than this in the activity window
+        #[arg(long = "min-recent-games")]
+        min_recent_games: Option<usize>,
+
+        /// Length of the --min-recent-games window in days
+        #[arg(long = "activity-window-days", default_value_t = 14)]
+        activity_window_days: u64,
+
+        /// Only collect games started after this date (YYYY-MM-DD) or interval ago (e.g. 30d);
+        /// defaults to the last 90 days
+        #[arg(long = "since", value_parser = kraken::TimeBound::parse)]
+        since: Option<kraken::TimeBound>,
+
+        /// Only collect games started before this date (YYYY-MM-DD) or interval ago; exclusive
+        #[arg(long = "until", value_parser = kraken::TimeBound::parse)]
+        until: Option<kraken::TimeBound>,
+
+        /// Shared games on the same team needed to treat two players as a duo (--mode duo)
+        #[arg(long = "duo-min-games", default_value_t = 3)]
+        duo_min_games: usize,
+
+        /// Restore seen matches and per-player counts from a previous run's manifest.csv
+        #[arg(long = "continue-from")]
+        continue_from: Option<String>,
+
+        /// With --continue-from, re-enqueue manifest players below the per-player cap
+        #[arg(
+            long = "continue-frontier",
+            default_value_t = false,
+            requires = "continue_from"
+        )]
+        continue_frontier: bool,
+
         /// Exit if no matches are written for this many minutes (optional)
         #[arg(long = "idle-exit-after-mins")]
         idle_exit_after_mins: Option<u64>,
 
-        /// Crawl strategy: explore, focus, or seed-only
+        /// Crawl strategy: explore, focus, seed-only, or duo
         #[arg(long = "mode", default_value = "explore")]
         mode: String,
 
@@ -121,13 +325,96 @@ enum Commands {
         #[arg(long = "role-focus")]
         role_focus: Option<String>,
 
-        /// Comma-separated list of allowed tiers for rank filtering
+        /// Whose role --role-focus checks: crawled-player or any participant
+        #[arg(long = "role-focus-scope", default_value = "crawled-player")]
+        role_focus_scope: String,
+
+        /// Comma-separated champion names or IDs; only matches where one was played are written
+        #[arg(long = "champion-focus")]
+        champion_focus: Option<String>,
+
+        /// Allowed ranks: tiers, divisions or ranges (e.g. "EMERALD II..CHALLENGER,GOLD")
         #[arg(long = "allow-ranks")]
         allow_ranks: Option<String>,
 
         /// Progress log interval in seconds
         #[arg(long = "log-interval-secs", default_value_t = 60)]
         log_interval_secs: u64,
+
+        /// Number of parallel crawl workers sharing the frontier and rate limiter
+        #[arg(long = "workers", default_value_t = 1)]
+        workers: usize,
+
+        /// Comma-separated platforms to crawl in one run (e.g. euw1,kr,na1); seeds may be
+        /// prefixed with `platform:` and each platform writes to its own subdirectory
+        #[arg(long = "regions")]
+        regions: Option<String>,
+
+        /// Only expand players up to this many hops from the seeds (seeds are depth 0); a player's
+        /// depth is fixed when first discovered, so it is an upper bound on the shortest path
+        #[arg(long = "max-depth")]
+        max_depth: Option<u32>,
+
+        /// Participants enqueued per downloaded match (default 10 in explore, 5 in focus)
+        #[arg(long = "max-new-players-per-match")]
+        max_new_players_per_match: Option<usize>,
+
+        /// Players with fewer downloaded matches than this are crawled first within a tier
+        #[arg(long = "prioritize-low-coverage-threshold", default_value_t = 10)]
+        prioritize_low_coverage_threshold: usize,
+
+        /// Fraction of eligible matches to write (0-1]; the player graph is still fully traversed
+        #[arg(long = "sample-rate", default_value_t = 1.0)]
+        sample_rate: f64,
+
+        /// Match file layout: flat, partitioned (region=/patch=/date= subdirectories) or queue
+        /// (soloq/, flex/, ... subdirectories)
+        #[arg(long = "layout", default_value = "flat")]
+        layout: String,
+
+        /// Dedup structures for seen matches and players: exact or probabilistic (Bloom filter)
+        #[arg(long = "dedup", default_value = "exact")]
+        dedup: String,
+
+        /// False-positive rate of the probabilistic dedup filter
+        #[arg(long = "dedup-fp-rate", default_value_t = 0.001)]
+        dedup_fp_rate: f64,
+
+        /// Restore seen matches and per-player counts from checkpoint.json in the out dir
+        #[arg(long = "resume", default_value_t = false)]
+        resume: bool,
+
+        /// Keep running bounded sessions forever, sleeping --every between them
+        #[arg(long = "daemon", default_value_t = false, requires = "every")]
+        daemon: bool,
+
+        /// Pause between daemon sessions (e.g. 30m, 6h, 1d)
+        #[arg(long = "every", value_parser = kraken::parse_every)]
+        every: Option<std::time::Duration>,
+
+        /// When the frontier empties, re-fetch the seeds' new games at this interval (e.g. 15m)
+        #[arg(long = "repoll-seeds-every", value_parser = kraken::parse_every)]
+        repoll_seeds_every: Option<std::time::Duration>,
+
+        /// Webhook URL notified when the run finishes, idles out or aborts
+        #[arg(long = "notify-url")]
+        notify_url: Option<String>,
+
+        /// Webhook payload format: json or discord
+        #[arg(long = "notify-format", default_value = "json")]
+        notify_format: String,
+
+        /// Show a live dashboard instead of periodic log lines (q stops the crawl)
+        #[arg(long = "tui", default_value_t = false)]
+        tui: bool,
+
+        /// Queues to harvest: comma-separated IDs or labels (soloq, flex, aram, ...)
+        #[arg(long = "queues", default_value = "soloq")]
+        queues: String,
+
+        /// Append the queue label to match file names (EUW1_123_flex.json)
+        #[arg(long = "queue-suffix", default_value_t = false)]
+        queue_suffix: bool,
     },
 
     /// Quick kraken crawl with opinionated defaults
@@ -140,24 +427,258 @@ enum Commands {
         #[arg(long = "out-dir")]
         out_dir: String,
 
-        /// Optional duration in minutes (default 10)
+        /// Limits preset: light (development key), standard or aggressive (production key)
+        #[arg(long = "preset", default_value = "light", value_parser = kraken::KrakenEatPreset::by_name)]
+        preset: kraken::KrakenEatPreset,
+
+        /// Duration in minutes (overrides the preset)
         #[arg(long = "duration-mins")]
         duration_mins: Option<u64>,
+
+        /// Maximum requests per 2 minutes (overrides the preset)
+        #[arg(long = "max-req-per-2min")]
+        max_req_per_2min: Option<usize>,
+
+        /// Maximum matches to download per player (overrides the preset)
+        #[arg(long = "max-matches-per-player")]
+        max_matches_per_player: Option<usize>,
+
+        /// Stop after writing this many matches in total (overrides the preset)
+        #[arg(long = "max-matches-total")]
+        max_matches_total: Option<usize>,
+
+        /// Number of crawler threads (overrides the preset)
+        #[arg(long = "workers")]
+        workers: Option<usize>,
+    },
+
+    /// Download only the games a fixed set of players played since the previous invocation
+    KrakenFollow {
+        /// File with one PUUID per line
+        #[arg(long = "puuid-file")]
+        puuid_file: String,
+
+        /// Directory holding follow_state.json (and the matches unless --out-dir is given)
+        #[arg(long = "state")]
+        state: String,
+
+        /// Output directory for match JSON files (defaults to --state)
+        #[arg(long = "out-dir")]
+        out_dir: Option<String>,
+
+        /// Platform of the followed players (e.g. euw1, kr)
+        #[arg(long = "platform", default_value = "euw1")]
+        platform: String,
+
+        /// Match file layout: flat, partitioned or queue
+        #[arg(long = "layout", default_value = "flat", value_parser = kraken::MatchLayout::parse)]
+        layout: kraken::MatchLayout,
+
+        /// Maximum requests per 2 minutes
+        #[arg(long = "max-req-per-2min", default_value_t = 100)]
+        max_req_per_2min: usize,
+
+        /// History fetched for players seen for the first time: a date (YYYY-MM-DD) or interval
+        #[arg(long = "since", default_value = "90d", value_parser = kraken::TimeBound::parse)]
+        since: kraken::TimeBound,
+
+        /// Queues to keep: comma-separated IDs or labels (soloq, flex, aram, ...)
+        #[arg(long = "queues", default_value = "soloq")]
+        queues: String,
+
+        /// Keep matches from every queue, ignoring --queues
+        #[arg(long = "all-queues", default_value_t = false)]
+        all_queues: bool,
+    },
+
+    /// Re-attempt match downloads recorded in a kraken failed.jsonl
+    KrakenRetry {
+        /// failed.jsonl written by kraken-absorb
+        #[arg(long = "failed-file")]
+        failed_file: String,
+
+        /// Output directory for recovered matches (defaults to the failed file's directory)
+        #[arg(long = "out-dir")]
+        out_dir: Option<String>,
+
+        /// Match file layout used by the original crawl: flat, partitioned or queue
+        #[arg(long = "layout", default_value = "flat", value_parser = kraken::MatchLayout::parse)]
+        layout: kraken::MatchLayout,
+
+        /// Maximum requests per 2 minutes
+        #[arg(long = "max-req-per-2min", default_value_t = 100)]
+        max_req_per_2min: usize,
     },
 
-    /// Extract player- or team-level features into Parquet for ML workflows
+    /// Download exactly the matches listed in a file (match IDs or a kraken manifest.csv)
+    FetchMatches {
+        /// Text file with one match ID per line, or a manifest.csv from another crawl
+        #[arg(long = "match-id-file")]
+        match_id_file: String,
+
+        /// Output directory for match JSON files
+        #[arg(long = "out-dir")]
+        out_dir: String,
+
+        /// Match file layout: flat, partitioned or queue
+        #[arg(long = "layout", default_value = "flat", value_parser = kraken::MatchLayout::parse)]
+        layout: kraken::MatchLayout,
+
+        /// Maximum requests per 2 minutes (per platform)
+        #[arg(long = "max-req-per-2min", default_value_t = 100)]
+        max_req_per_2min: usize,
+
+        /// Queues to keep: comma-separated IDs or labels (soloq, flex, aram, ...)
+        #[arg(long = "queues", default_value = "soloq")]
+        queues: String,
+
+        /// Keep matches from every queue, ignoring --queues
+        #[arg(long = "all-queues", default_value_t = false)]
+        all_queues: bool,
+
+        /// Only keep matches featuring one of these champions (comma-separated names or IDs)
+        #[arg(long = "champion-focus")]
+        champion_focus: Option<String>,
+    },
+
+    /// Extract player- or team-level features into Parquet (or CSV, IPC, NDJSON) for ML workflows
+    #[command(visible_alias = "extract")]
     ExtractParquet {
-        /// Directory containing downloaded match JSON files
-        #[arg(long = "matches-dir")]
-        matches_dir: String,
+        /// Directory or glob pattern (e.g. 'data/raw/euw*') of downloaded match files; repeat
+        /// to read several
+        #[arg(long = "matches-dir", required = true)]
+        matches_dir: Vec<String>,
 
-        /// Output Parquet file path
-        #[arg(long = "out-parquet")]
-        out_parquet: String,
+        /// Output file path (a directory with --partition-by)
+        #[arg(
+            long = "out-parquet",
+            visible_alias = "out",
+            required_unless_present = "merge_into",
+            conflicts_with = "merge_into"
+        )]
+        out_parquet: Option<String>,
+
+        /// Upsert into this existing Parquet file instead of writing --out-parquet: rows with a
+        /// re-extracted key (match_id and puuid, or team_id at team level) are replaced
+        #[arg(long = "merge-into")]
+        merge_into: Option<String>,
 
-        /// Aggregation level ('player' or 'team')
+        /// Aggregation level ('player', 'player-champion', 'team', 'match', 'matchup', 'arena' or
+        /// 'events')
         #[arg(long = "level")]
         level: String,
+
+        /// Append only matches missing from an existing --out-parquet instead of rebuilding it
+        #[arg(long = "incremental", default_value_t = false)]
+        incremental: bool,
+
+        /// Write a Hive-partitioned directory at --out-parquet split by these comma-separated
+        /// columns (e.g. patch,queue_id)
+        #[arg(long = "partition-by")]
+        partition_by: Option<String>,
+
+        /// Extra challenge columns for --level player: 'all' or a file with one challenge key
+        /// per line (e.g. soloKills)
+        #[arg(long = "challenge-cols")]
+        challenge_cols: Option<String>,
+
+        /// Only extract these comma-separated queues (IDs or labels such as soloq,flex)
+        #[arg(long = "queues")]
+        queues: Option<String>,
+
+        /// Only extract games started after this date (YYYY-MM-DD) or interval ago (e.g. 30d)
+        #[arg(long = "since", value_parser = kraken::TimeBound::parse)]
+        since: Option<kraken::TimeBound>,
+
+        /// Only extract games started before this date (YYYY-MM-DD) or interval ago; exclusive
+        #[arg(long = "until", value_parser = kraken::TimeBound::parse)]
+        until: Option<kraken::TimeBound>,
+
+        /// Only extract these comma-separated patches (e.g. 14.20,14.21)
+        #[arg(long = "patches")]
+        patches: Option<String>,
+
+        /// Add Data Dragon class, resource and release patch columns for every champion
+        #[arg(long = "enrich-champions", default_value_t = false)]
+        enrich_champions: bool,
+
+        /// Where the Data Dragon champion table is cached for --enrich-champions
+        #[arg(long = "ddragon-cache", default_value = "data/ddragon")]
+        ddragon_cache: String,
+
+        /// Skip remakes: games shorter than this many seconds or ended by an early surrender
+        /// (0 keeps them)
+        #[arg(long = "min-duration-secs", default_value_t = 300)]
+        min_duration_secs: i64,
+
+        /// Output format: parquet, csv, ipc (Arrow) or ndjson
+        #[arg(long = "format", default_value = "parquet", value_parser = parquet_extract::OutputFormat::parse)]
+        format: parquet_extract::OutputFormat,
+
+        /// Also store the schema version as a schema_version column (useful for CSV/NDJSON)
+        #[arg(long = "schema-version-column", default_value_t = false)]
+        schema_version_column: bool,
+
+        /// TOML or JSON column spec: columns to keep, renames and extra participant/team fields
+        #[arg(long = "columns")]
+        columns: Option<String>,
+
+        /// Directory of match timelines ({match_id}.json) for the lane differential columns and
+        /// --level events
+        #[arg(long = "timelines-dir")]
+        timelines_dir: Option<String>,
+
+        /// Add jungle, river and lane-distance features from timeline positions
+        #[arg(long = "timeline-positions", default_value_t = false)]
+        timeline_positions: bool,
+
+        /// Parquet compression: zstd, snappy or uncompressed
+        #[arg(long = "compression", default_value = "zstd")]
+        compression: String,
+
+        /// Zstd compression level (1-22)
+        #[arg(long = "compression-level")]
+        compression_level: Option<i32>,
+
+        /// Rows per Parquet row group
+        #[arg(long = "row-group-size")]
+        row_group_size: Option<usize>,
+
+        /// Write Parquet column statistics (min/max, null counts)
+        #[arg(long = "statistics", default_value_t = false)]
+        statistics: bool,
+    },
+
+    /// Upgrade a dataset written by extract-parquet to the current schema version
+    MigrateDataset {
+        /// Parquet file or partitioned directory to upgrade
+        #[arg(long = "input")]
+        input: String,
+
+        /// Where to write the upgraded dataset (defaults to rewriting --input in place)
+        #[arg(long = "output")]
+        output: Option<String>,
+
+        /// Level of files that predate schema versioning ('player', 'team', 'match',
+        /// 'matchup' or 'arena')
+        #[arg(long = "level")]
+        level: Option<String>,
+
+        /// Parquet compression: zstd, snappy or uncompressed
+        #[arg(long = "compression", default_value = "zstd")]
+        compression: String,
+
+        /// Zstd compression level (1-22)
+        #[arg(long = "compression-level")]
+        compression_level: Option<i32>,
+
+        /// Rows per Parquet row group
+        #[arg(long = "row-group-size")]
+        row_group_size: Option<usize>,
+
+        /// Write Parquet column statistics (min/max, null counts)
+        #[arg(long = "statistics", default_value_t = false)]
+        statistics: bool,
     },
 
     /// Summarize harvested datasets from JSON or Parquet inputs
@@ -174,6 +695,10 @@ enum Commands {
         #[arg(long = "team-parquet")]
         team_parquet: Option<String>,
 
+        /// Optional ml_lobby_outcome.parquet from kraken-prepare-ml
+        #[arg(long = "lobby-parquet")]
+        lobby_parquet: Option<String>,
+
         /// Optional cap for heavy operations
         #[arg(long = "max-rows")]
         max_rows: Option<usize>,
@@ -185,11 +710,136 @@ enum Commands {
         /// Show top champions (Parquet only)
         #[arg(long = "by-champion-top-k")]
         by_champion_top_k: Option<usize>,
+
+        /// Show the players with the most games, with win rate and main role (Parquet only)
+        #[arg(long = "top-players")]
+        top_players: Option<usize>,
+
+        /// ranks.csv (puuid,tier,division) for the rank distribution; raw summaries also pick up
+        /// the ranks.csv kraken writes with --allow-ranks
+        #[arg(long = "ranks")]
+        ranks: Option<String>,
+
+        /// Count matches per day or week of gameCreation to show gaps and crawl bursts
+        #[arg(long = "by-period", value_parser = kraken_summary::TimePeriod::parse)]
+        by_period: Option<kraken_summary::TimePeriod>,
+
+        /// Per-champion pick rate and win rate from --player-parquet, plus ban rate with
+        /// --team-parquet
+        #[arg(
+            long = "champion-table",
+            default_value_t = false,
+            requires = "player_parquet"
+        )]
+        champion_table: bool,
+
+        /// Only count picks in this role (TOP, JUNGLE, MIDDLE, BOTTOM, UTILITY) in the champion table
+        #[arg(long = "role", requires = "champion_table")]
+        role: Option<String>,
+
+        /// Restrict the champion table to one major.minor patch (e.g. 14.21)
+        #[arg(long = "patch", requires = "champion_table")]
+        patch: Option<String>,
+
+        /// Null rates, ranges and range checks per column of --player-parquet and --team-parquet
+        #[arg(long = "quality", default_value_t = false)]
+        quality: bool,
+
+        /// Threads parsing --matches-dir files (default: all cores)
+        #[arg(long = "workers")]
+        workers: Option<usize>,
+
+        /// Also list empty, truncated, misnamed and duplicate match files under --matches-dir
+        #[arg(
+            long = "check-integrity",
+            default_value_t = false,
+            requires = "matches_dir"
+        )]
+        check_integrity: bool,
+
+        /// Output format: text tables, or json (one document with a section per input)
+        #[arg(
+            long = "format",
+            default_value = "text",
+            value_parser = kraken_summary::SummaryFormat::parse
+        )]
+        format: kraken_summary::SummaryFormat,
+    },
+
+    /// Render the kraken-summary sections into one Markdown or HTML report
+    KrakenReport {
+        /// Optional directory of raw match JSON files
+        #[arg(long = "matches-dir")]
+        matches_dir: Option<String>,
+
+        /// Optional player-level Parquet
+        #[arg(long = "player-parquet")]
+        player_parquet: Option<String>,
+
+        /// Optional team-level Parquet
+        #[arg(long = "team-parquet")]
+        team_parquet: Option<String>,
+
+        /// Optional ml_lobby_outcome.parquet from kraken-prepare-ml
+        #[arg(long = "lobby-parquet")]
+        lobby_parquet: Option<String>,
+
+        /// Optional cap for heavy operations
+        #[arg(long = "max-rows")]
+        max_rows: Option<usize>,
+
+        /// Champions listed in the player section
+        #[arg(long = "by-champion-top-k", default_value_t = 20)]
+        by_champion_top_k: usize,
+
+        /// Players listed in the player section, by games
+        #[arg(long = "top-players", default_value_t = 10)]
+        top_players: usize,
+
+        /// ranks.csv (puuid,tier,division) for the rank distribution, as in kraken-summary
+        #[arg(long = "ranks")]
+        ranks: Option<String>,
+
+        /// Matches-over-time table per day or week
+        #[arg(long = "by-period", value_parser = kraken_summary::TimePeriod::parse)]
+        by_period: Option<kraken_summary::TimePeriod>,
+
+        /// Report file; .md writes Markdown, .html a standalone page
+        #[arg(long = "out")]
+        out: String,
+    },
+
+    /// Compare two player-level Parquets, e.g. a new crawl batch against a training set
+    KrakenDiff {
+        /// Existing dataset (player-level Parquet)
+        #[arg(long = "base")]
+        base: String,
+
+        /// Dataset compared against --base; changes read candidate minus base
+        #[arg(long = "candidate")]
+        candidate: String,
+
+        /// ranks.csv (puuid,tier,division) to compare the rank mix of the players
+        #[arg(long = "ranks")]
+        ranks: Option<String>,
+
+        /// Rows printed per list (largest shifts first)
+        #[arg(long = "top-k", default_value_t = 20)]
+        top_k: usize,
+
+        /// Output format: text, or one json document
+        #[arg(
+            long = "format",
+            default_value = "text",
+            value_parser = kraken_summary::SummaryFormat::parse
+        )]
+        format: kraken_summary::SummaryFormat,
     },
 
     /// Build ML-ready Parquet datasets from harvested player/team parquets
+    #[command(visible_alias = "prepare-ml")]
     KrakenPrepareMl {
-        /// Variant to build: team-outcome | player-profile-only | lobby-outcome
+        /// Variant to build: team-outcome | player-profile-only | lobby-outcome | champion-matchups
         #[arg(long = "variant")]
         variant: String,
 
@@ -212,11 +862,166 @@ enum Commands {
         /// Minimum matches required for a profile
         #[arg(long = "min-matches", default_value_t = 5)]
         min_matches: usize,
+
+        /// Matchups (role, champion vs lane opponent) seen fewer times get no win rate
+        #[arg(long = "matchup-min-games", default_value_t = 5)]
+        matchup_min_games: usize,
+
+        /// Pseudo-games at 50% added to every matchup win rate
+        #[arg(long = "matchup-smoothing", default_value_t = 10.0)]
+        matchup_smoothing: f64,
+
+        /// Parquet compression: zstd, snappy or uncompressed
+        #[arg(long = "compression", default_value = "zstd")]
+        compression: String,
+
+        /// Zstd compression level (1-22)
+        #[arg(long = "compression-level")]
+        compression_level: Option<i32>,
+
+        /// Rows per Parquet row group
+        #[arg(long = "row-group-size")]
+        row_group_size: Option<usize>,
+
+        /// Write Parquet column statistics (min/max, null counts)
+        #[arg(long = "statistics", default_value_t = false)]
+        statistics: bool,
+    },
+
+    /// Split ML Parquets chronologically into train, validation and test by game_creation
+    SplitDataset {
+        /// Parquet to split (repeatable); every match lands in the same split across inputs
+        #[arg(long = "input", required = true)]
+        inputs: Vec<String>,
+
+        /// Directory for train/, validation/, test/ and split_manifest.json
+        #[arg(long = "out-dir")]
+        out_dir: String,
+
+        /// Train, validation and test shares of the matches, oldest first
+        #[arg(
+            long = "fractions",
+            default_value = "0.7,0.15,0.15",
+            value_parser = dataset_split::SplitPlan::parse_fractions,
+            conflicts_with_all = ["validation_from", "test_from"]
+        )]
+        fractions: dataset_split::SplitPlan,
+
+        /// Fixed boundary instead of --fractions: validation starts this day (YYYY-MM-DD, UTC)
+        #[arg(
+            long = "validation-from",
+            value_parser = dataset_split::parse_date,
+            requires = "test_from"
+        )]
+        validation_from: Option<chrono::NaiveDate>,
+
+        /// Fixed boundary instead of --fractions: test starts this day (YYYY-MM-DD, UTC)
+        #[arg(
+            long = "test-from",
+            value_parser = dataset_split::parse_date,
+            requires = "validation_from"
+        )]
+        test_from: Option<chrono::NaiveDate>,
+
+        /// Parquet compression: zstd, snappy or uncompressed
+        #[arg(long = "compression", default_value = "zstd")]
+        compression: String,
+
+        /// Zstd compression level (1-22)
+        #[arg(long = "compression-level")]
+        compression_level: Option<i32>,
+
+        /// Rows per Parquet row group
+        #[arg(long = "row-group-size")]
+        row_group_size: Option<usize>,
+
+        /// Write Parquet column statistics (min/max, null counts)
+        #[arg(long = "statistics", default_value_t = false)]
+        statistics: bool,
+    },
+
+    /// Per-player, per-role profiles (recent form and lane diffs) from a player-level Parquet
+    PlayerProfile {
+        /// Player-level parquet from extract-parquet
+        #[arg(long = "player-parquet")]
+        player_parquet: String,
+
+        /// Profile Parquet to write
+        #[arg(long = "out-parquet")]
+        out_parquet: String,
+
+        /// Most recent SoloQ games per player and role to average over
+        #[arg(long = "history-size", default_value_t = 10)]
+        history_size: usize,
+
+        /// Minimum games in a role for a profile
+        #[arg(long = "min-matches", default_value_t = 5)]
+        min_matches: usize,
+
+        /// Parquet compression: zstd, snappy or uncompressed
+        #[arg(long = "compression", default_value = "zstd")]
+        compression: String,
+
+        /// Zstd compression level (1-22)
+        #[arg(long = "compression-level")]
+        compression_level: Option<i32>,
+
+        /// Rows per Parquet row group
+        #[arg(long = "row-group-size")]
+        row_group_size: Option<usize>,
+
+        /// Write Parquet column statistics (min/max, null counts)
+        #[arg(long = "statistics", default_value_t = false)]
+        statistics: bool,
     },
 }
 
+/// Expands `kraken-absorb --config crawl.toml` into the profile's flags, placed right after
+/// the subcommand so the flags given on the command line take precedence.
+fn expand_kraken_config(argv: Vec<OsString>) -> Result<Vec<OsString>, String> {
+    let Some(sub_idx) = argv.iter().position(|arg| arg == "kraken-absorb") else {
+        return Ok(argv);
+    };
+
+    let mut config_path = None;
+    for (idx, arg) in argv.iter().enumerate().skip(sub_idx + 1) {
+        let arg = arg.to_string_lossy();
+        if arg == "--config" {
+            config_path = argv.get(idx + 1).map(PathBuf::from);
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            config_path = Some(PathBuf::from(path));
+        }
+    }
+    let Some(config_path) = config_path else {
+        return Ok(argv);
+    };
+
+    let command = Cli::command();
+    let known_flags: Vec<String> = command
+        .find_subcommand("kraken-absorb")
+        .map(|sub| {
+            sub.get_arguments()
+                .filter_map(|arg| arg.get_long().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    let flags =
+        kraken_config::config_flags(&config_path, &known_flags).map_err(|err| err.to_string())?;
+
+    let mut expanded = argv;
+    expanded.splice(sub_idx + 1..sub_idx + 1, flags);
+    Ok(expanded)
+}
+
 fn main() {
-    let args = Cli::parse();
+    let argv = match expand_kraken_config(env::args_os().collect()) {
+        Ok(argv) => argv,
+        Err(err) => {
+            eprintln!("Error loading kraken config: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let args = Cli::parse_from(argv);
 
     match &args.command {
         Some(Commands::Matches { puuid, count }) => {
@@ -256,37 +1061,190 @@ fn main() {
         }
         Some(Commands::ExtractStats {
             puuid,
+            puuid_file,
+            queues,
+            since,
+            until,
             matches_dir,
             out_file,
+            format,
+            append,
+            summary,
         }) => {
-            let puuid_str = resolve_puuid(puuid);
+            let puuids = resolve_puuids(puuid, puuid_file.as_deref());
+            let filter = match stats::GameFilter::new(queues.as_deref(), *since, *until) {
+                Ok(filter) => filter,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+            };
 
             let matches_path = PathBuf::from(matches_dir);
             let out_path = PathBuf::from(out_file);
 
-            if let Err(err) =
-                stats::extract_basic_stats_for_puuid(&puuid_str, &matches_path, &out_path)
-            {
+            if let Err(err) = stats::extract_basic_stats(
+                &puuids,
+                &matches_path,
+                &filter,
+                &out_path,
+                *format,
+                *append,
+                *summary,
+            ) {
                 eprintln!("Error extracting stats: {}", err);
                 std::process::exit(1);
             }
         }
+        Some(Commands::ChampionSummary {
+            puuid,
+            puuid_file,
+            matches_dir,
+            out_file,
+            format,
+        }) => {
+            let puuids = resolve_puuids(puuid, puuid_file.as_deref());
+            let out_path = out_file.as_ref().map(PathBuf::from);
+
+            if let Err(err) = stats::champion_summary(
+                &puuids,
+                &PathBuf::from(matches_dir),
+                out_path.as_deref(),
+                *format,
+            ) {
+                eprintln!("Error summarizing champions: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Form {
+            puuid,
+            puuid_file,
+            matches_dir,
+            out_file,
+            format,
+        }) => {
+            let puuids = resolve_puuids(puuid, puuid_file.as_deref());
+            let out_path = out_file.as_ref().map(PathBuf::from);
+
+            if let Err(err) = stats::form(
+                &puuids,
+                &PathBuf::from(matches_dir),
+                out_path.as_deref(),
+                *format,
+            ) {
+                eprintln!("Error computing form: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::HeadToHead {
+            puuid,
+            other_puuid,
+            other_riot_id,
+            matches_dir,
+            out_file,
+            format,
+        }) => {
+            let puuid_str = resolve_puuid(puuid);
+            let other = match (other_puuid, other_riot_id) {
+                (Some(other_puuid), _) => stats::Opponent::Puuid(other_puuid.clone()),
+                (None, Some(riot_id)) => riot_id.clone(),
+                (None, None) => unreachable!("clap requires --other-puuid or --other-riot-id"),
+            };
+            let out_path = out_file.as_ref().map(PathBuf::from);
+
+            if let Err(err) = stats::head_to_head(
+                &puuid_str,
+                &other,
+                &PathBuf::from(matches_dir),
+                out_path.as_deref(),
+                *format,
+            ) {
+                eprintln!("Error comparing players: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::DuoReport {
+            puuid,
+            matches_dir,
+            min_games,
+            out_file,
+            format,
+        }) => {
+            let puuid_str = resolve_puuid(puuid);
+            let out_path = out_file.as_ref().map(PathBuf::from);
+
+            if let Err(err) = stats::duo_report(
+                &puuid_str,
+                &PathBuf::from(matches_dir),
+                *min_games,
+                out_path.as_deref(),
+                *format,
+            ) {
+                eprintln!("Error building the duo report: {}", err);
+                std::process::exit(1);
+            }
+        }
         Some(Commands::KrakenAbsorb {
+            config: _,
             seed_puuid,
             seed_file,
+            exclude_puuid_file,
             duration_mins,
             out_dir,
             max_req_per_2min,
             max_matches_per_player,
             max_matches_total,
+            target_players,
+            min_matches_per_player,
+            min_recent_games,
+            activity_window_days,
+            since,
+            until,
+            duo_min_games,
+            continue_from,
+            continue_frontier,
             idle_exit_after_mins,
             mode,
             role_focus,
+            role_focus_scope,
+            champion_focus,
             allow_ranks,
             log_interval_secs,
+            workers,
+            regions,
+            max_depth,
+            max_new_players_per_match,
+            prioritize_low_coverage_threshold,
+            sample_rate,
+            layout,
+            dedup,
+            dedup_fp_rate,
+            resume,
+            daemon,
+            every,
+            repoll_seeds_every,
+            notify_url,
+            notify_format,
+            tui,
+            queues,
+            queue_suffix,
         }) => {
-            let client = match riot_api::RiotClient::new_with_max(*max_req_per_2min) {
-                Ok(client) => client,
+            let regions: Option<Vec<String>> = regions.as_ref().map(|raw| {
+                raw.split(',')
+                    .map(|r| r.trim().to_lowercase())
+                    .filter(|r| !r.is_empty())
+                    .collect()
+            });
+
+            let clients = match &regions {
+                Some(platforms) => platforms
+                    .iter()
+                    .map(|platform| riot_api::RiotClient::for_platform(platform, *max_req_per_2min))
+                    .collect::<Result<Vec<_>, _>>(),
+                None => riot_api::RiotClient::new_with_max(*max_req_per_2min).map(|c| vec![c]),
+            };
+            let clients = match clients {
+                Ok(clients) => clients,
                 Err(err) => {
                     eprintln!("Failed to create Riot API client: {}", err);
                     std::process::exit(1);
@@ -296,19 +1254,48 @@ fn main() {
             let args = kraken::KrakenAbsorbArgs {
                 seed_puuid: seed_puuid.clone(),
                 seed_file: seed_file.as_ref().map(PathBuf::from),
+                exclude_puuid_file: exclude_puuid_file.as_ref().map(PathBuf::from),
                 duration_mins: *duration_mins,
                 out_dir: PathBuf::from(out_dir),
                 max_req_per_2min: *max_req_per_2min,
                 max_matches_per_player: *max_matches_per_player,
                 max_matches_total: *max_matches_total,
+                target_players: *target_players,
+                min_matches_per_player: *min_matches_per_player,
+                min_recent_games: *min_recent_games,
+                activity_window_days: *activity_window_days,
+                since: *since,
+                until: *until,
+                duo_min_games: *duo_min_games,
+                continue_from: continue_from.as_ref().map(PathBuf::from),
+                continue_frontier: *continue_frontier,
                 idle_exit_after_mins: *idle_exit_after_mins,
                 mode: mode.clone(),
                 role_focus: role_focus.clone(),
+                role_focus_scope: role_focus_scope.clone(),
+                champion_focus: champion_focus.clone(),
                 allow_ranks: allow_ranks.clone(),
                 log_interval_secs: *log_interval_secs,
+                workers: *workers,
+                regions,
+                max_depth: *max_depth,
+                max_new_players_per_match: *max_new_players_per_match,
+                prioritize_low_coverage_threshold: *prioritize_low_coverage_threshold,
+                sample_rate: *sample_rate,
+                layout: layout.clone(),
+                dedup: dedup.clone(),
+                dedup_fp_rate: *dedup_fp_rate,
+                resume: *resume,
+                daemon_every: if *daemon { *every } else { None },
+                repoll_seeds_every: *repoll_seeds_every,
+                notify_url: notify_url.clone(),
+                notify_format: notify_format.clone(),
+                tui: *tui,
+                queues: queues.clone(),
+                queue_suffix: *queue_suffix,
             };
 
-            if let Err(err) = kraken::kraken_absorb_run(&args, &client) {
+            if let Err(err) = kraken::kraken_absorb_run(&args, &clients) {
                 eprintln!("Error running kraken-absorb crawler: {}", err);
                 std::process::exit(1);
             }
@@ -316,9 +1303,25 @@ fn main() {
         Some(Commands::KrakenEat {
             seed_puuid,
             out_dir,
+            preset,
             duration_mins,
+            max_req_per_2min,
+            max_matches_per_player,
+            max_matches_total,
+            workers,
         }) => {
-            let client = match riot_api::RiotClient::new_with_max(60) {
+            let args = kraken::KrakenEatArgs {
+                seed_puuid: seed_puuid.clone(),
+                out_dir: PathBuf::from(out_dir),
+                duration_mins: duration_mins.unwrap_or(preset.duration_mins),
+                max_req_per_2min: max_req_per_2min.unwrap_or(preset.max_req_per_2min),
+                max_matches_per_player: max_matches_per_player
+                    .unwrap_or(preset.max_matches_per_player),
+                max_matches_total: max_matches_total.or(preset.max_matches_total),
+                workers: workers.unwrap_or(preset.workers),
+            };
+
+            let client = match riot_api::RiotClient::new_with_max(args.max_req_per_2min) {
                 Ok(client) => client,
                 Err(err) => {
                     eprintln!("Failed to create Riot API client: {}", err);
@@ -326,68 +1329,514 @@ fn main() {
                 }
             };
 
-            let args = kraken::KrakenEatArgs {
-                seed_puuid: seed_puuid.clone(),
+            if let Err(err) = kraken::kraken_eat_run(&args, &client) {
+                eprintln!("Error running kraken-eat crawler: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::KrakenFollow {
+            puuid_file,
+            state,
+            out_dir,
+            platform,
+            layout,
+            max_req_per_2min,
+            since,
+            queues,
+            all_queues,
+        }) => {
+            let state_dir = PathBuf::from(state);
+            let args = kraken_follow::KrakenFollowArgs {
+                puuid_file: PathBuf::from(puuid_file),
+                out_dir: out_dir
+                    .as_ref()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| state_dir.clone()),
+                state_dir,
+                platform: platform.clone(),
+                layout: *layout,
+                max_req_per_2min: *max_req_per_2min,
+                since: *since,
+                queues: queues.clone(),
+                all_queues: *all_queues,
+            };
+
+            if let Err(err) = kraken_follow::kraken_follow_run(&args) {
+                eprintln!("Error following players: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::KrakenRetry {
+            failed_file,
+            out_dir,
+            layout,
+            max_req_per_2min,
+        }) => {
+            let failed_file = PathBuf::from(failed_file);
+            let out_dir = match out_dir {
+                Some(dir) => PathBuf::from(dir),
+                None => failed_file
+                    .parent()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from(".")),
+            };
+
+            let args = kraken_retry::KrakenRetryArgs {
+                failed_file,
+                out_dir,
+                layout: *layout,
+                max_req_per_2min: *max_req_per_2min,
+            };
+
+            if let Err(err) = kraken_retry::kraken_retry_run(&args) {
+                eprintln!("Error retrying failed matches: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::FetchMatches {
+            match_id_file,
+            out_dir,
+            layout,
+            max_req_per_2min,
+            queues,
+            all_queues,
+            champion_focus,
+        }) => {
+            let args = kraken_fetch::FetchMatchesArgs {
+                match_id_file: PathBuf::from(match_id_file),
                 out_dir: PathBuf::from(out_dir),
-                duration_mins: *duration_mins,
+                layout: *layout,
+                max_req_per_2min: *max_req_per_2min,
+                queues: queues.clone(),
+                all_queues: *all_queues,
+                champion_focus: champion_focus.clone(),
             };
 
-            if let Err(err) = kraken::kraken_eat_run(&args, &client) {
-                eprintln!("Error running kraken-eat crawler: {}", err);
+            if let Err(err) = kraken_fetch::fetch_matches_run(&args) {
+                eprintln!("Error fetching matches: {}", err);
                 std::process::exit(1);
             }
         }
         Some(Commands::ExtractParquet {
             matches_dir,
             out_parquet,
+            merge_into,
             level,
+            incremental,
+            partition_by,
+            challenge_cols,
+            queues,
+            since,
+            until,
+            patches,
+            enrich_champions,
+            ddragon_cache,
+            min_duration_secs,
+            format,
+            schema_version_column,
+            columns,
+            timelines_dir,
+            timeline_positions,
+            compression,
+            compression_level,
+            row_group_size,
+            statistics,
         }) => {
-            let matches_path = PathBuf::from(matches_dir);
-            let out_path = PathBuf::from(out_parquet);
+            let parquet = parquet_options(
+                compression,
+                *compression_level,
+                *row_group_size,
+                *statistics,
+            );
+            let args = parquet_extract::ExtractParquetArgs {
+                matches_dirs: matches_dir.clone(),
+                out_parquet: PathBuf::from(
+                    merge_into
+                        .as_ref()
+                        .or(out_parquet.as_ref())
+                        .expect("clap requires --out-parquet or --merge-into"),
+                ),
+                level: level.clone(),
+                incremental: *incremental,
+                merge: merge_into.is_some(),
+                partition_by: partition_by.as_ref().map(|raw| {
+                    raw.split(',')
+                        .map(|key| key.trim().to_string())
+                        .filter(|key| !key.is_empty())
+                        .collect()
+                }),
+                challenge_cols: challenge_cols.clone(),
+                queues: queues.clone(),
+                since: *since,
+                until: *until,
+                patches: patches.as_ref().map(|raw| {
+                    raw.split(',')
+                        .map(|patch| patch.trim().to_string())
+                        .filter(|patch| !patch.is_empty())
+                        .collect()
+                }),
+                format: *format,
+                enrich_champions: enrich_champions.then(|| PathBuf::from(ddragon_cache)),
+                min_duration_secs: *min_duration_secs,
+                schema_version_column: *schema_version_column,
+                columns: columns.as_ref().map(PathBuf::from),
+                timelines_dir: timelines_dir.as_ref().map(PathBuf::from),
+                timeline_positions: *timeline_positions,
+                parquet,
+            };
 
-            if let Err(err) =
-                parquet_extract::extract_parquet(&matches_path, &out_path, level.as_str())
-            {
+            if let Err(err) = parquet_extract::extract_parquet(&args) {
                 eprintln!("Error extracting Parquet dataset: {}", err);
                 std::process::exit(1);
             }
         }
+        Some(Commands::MigrateDataset {
+            input,
+            output,
+            level,
+            compression,
+            compression_level,
+            row_group_size,
+            statistics,
+        }) => {
+            let parquet = parquet_options(
+                compression,
+                *compression_level,
+                *row_group_size,
+                *statistics,
+            );
+            let args = parquet_extract::MigrateDatasetArgs {
+                input: PathBuf::from(input),
+                output: output.as_ref().map(PathBuf::from),
+                level: level.clone(),
+                parquet,
+            };
+
+            if let Err(err) = parquet_extract::migrate_dataset(&args) {
+                eprintln!("Error migrating dataset: {}", err);
+                std::process::exit(1);
+            }
+        }
         Some(Commands::KrakenSummary {
             matches_dir,
             player_parquet,
             team_parquet,
+            lobby_parquet,
             max_rows,
             by_role,
             by_champion_top_k,
+            top_players,
+            ranks,
+            by_period,
+            champion_table,
+            role,
+            patch,
+            quality,
+            workers,
+            check_integrity,
+            format,
         }) => {
-            if matches_dir.is_none() && player_parquet.is_none() {
-                eprintln!("You must provide --matches-dir or --player-parquet");
+            if matches_dir.is_none()
+                && player_parquet.is_none()
+                && team_parquet.is_none()
+                && lobby_parquet.is_none()
+            {
+                eprintln!(
+                    "You must provide --matches-dir, --player-parquet, --team-parquet or --lobby-parquet"
+                );
                 std::process::exit(1);
             }
 
+            // Sections that fail are reported on stderr and left out of the JSON document; the
+            // command still prints the others, then exits 1 so partial output is detectable.
+            let mut document = serde_json::Map::new();
+            let mut failed = false;
             if let Some(dir) = matches_dir {
-                if let Err(err) = kraken_summary::kraken_summary_raw(&PathBuf::from(dir), *max_rows)
-                {
-                    eprintln!("Error summarizing raw matches: {}", err);
+                match kraken_summary::kraken_summary_raw(
+                    &PathBuf::from(dir),
+                    *max_rows,
+                    ranks.as_deref().map(Path::new),
+                    *by_period,
+                    *workers,
+                    *format,
+                ) {
+                    Ok(section) => {
+                        document.insert("raw".to_string(), section);
+                    }
+                    Err(err) => {
+                        eprintln!("Error summarizing raw matches: {}", err);
+                        failed = true;
+                    }
+                }
+            }
+
+            if *check_integrity && let Some(dir) = matches_dir {
+                match kraken_summary::kraken_integrity_report(&PathBuf::from(dir), *format) {
+                    Ok(section) => {
+                        document.insert("integrity".to_string(), section);
+                    }
+                    Err(err) => {
+                        eprintln!("Error checking raw matches: {}", err);
+                        failed = true;
+                    }
                 }
             }
 
             if let Some(parquet) = player_parquet {
-                if let Err(err) = kraken_summary::kraken_summary_player(
+                let args = kraken_summary::PlayerSummaryArgs {
+                    parquet_path: Path::new(parquet),
+                    max_rows: *max_rows,
+                    by_role: *by_role,
+                    by_champion_top_k: *by_champion_top_k,
+                    top_players: *top_players,
+                    ranks: ranks.as_deref().map(Path::new),
+                    period: *by_period,
+                };
+                match kraken_summary::kraken_summary_player(&args, *format) {
+                    Ok(section) => {
+                        document.insert("player".to_string(), section);
+                    }
+                    Err(err) => {
+                        eprintln!("Error summarizing player parquet: {}", err);
+                        failed = true;
+                    }
+                }
+            }
+
+            if let Some(parquet) = team_parquet {
+                match kraken_summary::kraken_summary_team(
+                    &PathBuf::from(parquet),
+                    *max_rows,
+                    *format,
+                ) {
+                    Ok(section) => {
+                        document.insert("team".to_string(), section);
+                    }
+                    Err(err) => {
+                        eprintln!("Error summarizing team parquet: {}", err);
+                        failed = true;
+                    }
+                }
+            }
+
+            if let Some(parquet) = lobby_parquet {
+                match kraken_summary::kraken_summary_lobby(
                     &PathBuf::from(parquet),
                     *max_rows,
-                    *by_role,
+                    *format,
+                ) {
+                    Ok(section) => {
+                        document.insert("lobby".to_string(), section);
+                    }
+                    Err(err) => {
+                        eprintln!("Error summarizing lobby parquet: {}", err);
+                        failed = true;
+                    }
+                }
+            }
+
+            if *champion_table && let Some(parquet) = player_parquet {
+                match kraken_summary::kraken_champion_table(
+                    Path::new(parquet),
+                    team_parquet.as_deref().map(Path::new),
+                    role.as_deref(),
+                    patch.as_deref(),
                     *by_champion_top_k,
+                    *format,
                 ) {
-                    eprintln!("Error summarizing player parquet: {}", err);
+                    Ok(section) => {
+                        document.insert("champions".to_string(), section);
+                    }
+                    Err(err) => {
+                        eprintln!("Error building the champion table: {}", err);
+                        failed = true;
+                    }
                 }
             }
 
-            if let Some(parquet) = team_parquet {
-                if let Err(err) =
-                    kraken_summary::kraken_summary_team(&PathBuf::from(parquet), *max_rows)
-                {
-                    eprintln!("Error summarizing team parquet: {}", err);
+            if *quality {
+                for (key, parquet) in [
+                    ("player_quality", player_parquet),
+                    ("team_quality", team_parquet),
+                ] {
+                    let Some(parquet) = parquet else {
+                        continue;
+                    };
+                    match kraken_summary::kraken_quality_report(
+                        Path::new(parquet),
+                        *max_rows,
+                        *format,
+                    ) {
+                        Ok(section) => {
+                            document.insert(key.to_string(), section);
+                        }
+                        Err(err) => {
+                            eprintln!("Error checking {}: {}", parquet, err);
+                            failed = true;
+                        }
+                    }
+                }
+            }
+
+            if *format == kraken_summary::SummaryFormat::Json {
+                match serde_json::to_string_pretty(&document) {
+                    Ok(json) => println!("{}", json),
+                    Err(err) => {
+                        eprintln!("Error writing the summary: {}", err);
+                        failed = true;
+                    }
+                }
+            }
+            if failed {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::KrakenReport {
+            matches_dir,
+            player_parquet,
+            team_parquet,
+            lobby_parquet,
+            max_rows,
+            by_champion_top_k,
+            top_players,
+            ranks,
+            by_period,
+            out,
+        }) => {
+            if matches_dir.is_none()
+                && player_parquet.is_none()
+                && team_parquet.is_none()
+                && lobby_parquet.is_none()
+            {
+                eprintln!(
+                    "You must provide --matches-dir, --player-parquet, --team-parquet or --lobby-parquet"
+                );
+                std::process::exit(1);
+            }
+
+            let json = kraken_summary::SummaryFormat::Json;
+            let mut sections = Vec::new();
+            let summaries = [
+                matches_dir.as_ref().map(|dir| {
+                    (
+                        "Raw matches",
+                        dir,
+                        &kraken_report::RAW_MATCHES_LAYOUT,
+                        kraken_summary::kraken_summary_raw(
+                            &PathBuf::from(dir),
+                            *max_rows,
+                            ranks.as_deref().map(Path::new),
+                            *by_period,
+                            None,
+                            json,
+                        ),
+                    )
+                }),
+                player_parquet.as_ref().map(|parquet| {
+                    (
+                        "Player Parquet",
+                        parquet,
+                        &kraken_report::PLAYER_LAYOUT,
+                        kraken_summary::kraken_summary_player(
+                            &kraken_summary::PlayerSummaryArgs {
+                                parquet_path: Path::new(parquet),
+                                max_rows: *max_rows,
+                                by_role: true,
+                                by_champion_top_k: Some(*by_champion_top_k),
+                                top_players: Some(*top_players),
+                                ranks: ranks.as_deref().map(Path::new),
+                                period: *by_period,
+                            },
+                            json,
+                        ),
+                    )
+                }),
+                team_parquet.as_ref().map(|parquet| {
+                    (
+                        "Team Parquet",
+                        parquet,
+                        &kraken_report::TEAM_LAYOUT,
+                        kraken_summary::kraken_summary_team(
+                            &PathBuf::from(parquet),
+                            *max_rows,
+                            json,
+                        ),
+                    )
+                }),
+                lobby_parquet.as_ref().map(|parquet| {
+                    (
+                        "Lobby outcome",
+                        parquet,
+                        &kraken_report::LOBBY_LAYOUT,
+                        kraken_summary::kraken_summary_lobby(
+                            &PathBuf::from(parquet),
+                            *max_rows,
+                            json,
+                        ),
+                    )
+                }),
+                player_parquet.as_ref().map(|parquet| {
+                    (
+                        "Champions",
+                        parquet,
+                        &kraken_report::CHAMPIONS_LAYOUT,
+                        kraken_summary::kraken_champion_table(
+                            Path::new(parquet),
+                            team_parquet.as_deref().map(Path::new),
+                            None,
+                            None,
+                            Some(*by_champion_top_k),
+                            json,
+                        ),
+                    )
+                }),
+            ];
+            for (title, source, layout, summary) in summaries.into_iter().flatten() {
+                match summary {
+                    Ok(summary) => sections.push(kraken_report::ReportSection {
+                        title,
+                        source: source.clone(),
+                        summary,
+                        layout,
+                    }),
+                    Err(err) => {
+                        eprintln!("Error summarizing {}: {}", source, err);
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            if let Err(err) = kraken_report::write_report(Path::new(out), &sections) {
+                eprintln!("Error writing the report: {}", err);
+                std::process::exit(1);
+            }
+            println!("Wrote report to {}", out);
+        }
+        Some(Commands::KrakenDiff {
+            base,
+            candidate,
+            ranks,
+            top_k,
+            format,
+        }) => {
+            match kraken_summary::kraken_dataset_diff(
+                Path::new(base),
+                Path::new(candidate),
+                ranks.as_deref().map(Path::new),
+                *top_k,
+                *format,
+            ) {
+                Ok(document) => {
+                    if *format == kraken_summary::SummaryFormat::Json {
+                        match serde_json::to_string_pretty(&document) {
+                            Ok(json) => println!("{}", json),
+                            Err(err) => eprintln!("Error writing the diff: {}", err),
+                        }
+                    }
+                }
+                Err(err) => {
+                    eprintln!("Error comparing datasets: {}", err);
+                    std::process::exit(1);
                 }
             }
         }
@@ -398,19 +1847,100 @@ fn main() {
             out_dir,
             history_size,
             min_matches,
+            matchup_min_games,
+            matchup_smoothing,
+            compression,
+            compression_level,
+            row_group_size,
+            statistics,
         }) => {
-            if let Err(err) = kraken_prepare_ml::kraken_prepare_ml_dispatch(
-                variant,
-                player_parquet.as_ref().map(PathBuf::from),
-                team_parquet.as_ref().map(PathBuf::from),
-                &PathBuf::from(out_dir),
-                *history_size,
-                *min_matches,
-            ) {
+            let args = kraken_prepare_ml::PrepareMlArgs {
+                variant: variant.clone(),
+                player_parquet: player_parquet.as_ref().map(PathBuf::from),
+                team_parquet: team_parquet.as_ref().map(PathBuf::from),
+                out_dir: PathBuf::from(out_dir),
+                history_size: *history_size,
+                min_matches: *min_matches,
+                matchups: kraken_prepare_ml::MatchupOptions {
+                    min_games: *matchup_min_games,
+                    smoothing: matchup_smoothing.max(0.0),
+                },
+                parquet: parquet_options(
+                    compression,
+                    *compression_level,
+                    *row_group_size,
+                    *statistics,
+                ),
+            };
+            if let Err(err) = kraken_prepare_ml::kraken_prepare_ml_dispatch(args) {
                 eprintln!("Error running kraken-prepare-ml: {}", err);
                 std::process::exit(1);
             }
         }
+        Some(Commands::SplitDataset {
+            inputs,
+            out_dir,
+            fractions,
+            validation_from,
+            test_from,
+            compression,
+            compression_level,
+            row_group_size,
+            statistics,
+        }) => {
+            let plan = match (validation_from, test_from) {
+                (Some(validation_from), Some(test_from)) => {
+                    dataset_split::SplitPlan::from_dates(*validation_from, *test_from)
+                        .unwrap_or_else(|err| {
+                            eprintln!("{}", err);
+                            std::process::exit(1);
+                        })
+                }
+                _ => *fractions,
+            };
+            let args = dataset_split::SplitDatasetArgs {
+                inputs: inputs.iter().map(PathBuf::from).collect(),
+                out_dir: PathBuf::from(out_dir),
+                plan,
+                parquet: parquet_options(
+                    compression,
+                    *compression_level,
+                    *row_group_size,
+                    *statistics,
+                ),
+            };
+            if let Err(err) = dataset_split::split_dataset(&args) {
+                eprintln!("Error splitting dataset: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::PlayerProfile {
+            player_parquet,
+            out_parquet,
+            history_size,
+            min_matches,
+            compression,
+            compression_level,
+            row_group_size,
+            statistics,
+        }) => {
+            let args = player_profile::PlayerProfileArgs {
+                player_parquet: Path::new(player_parquet),
+                out_parquet: Path::new(out_parquet),
+                history_size: *history_size,
+                min_matches: *min_matches,
+                parquet: parquet_options(
+                    compression,
+                    *compression_level,
+                    *row_group_size,
+                    *statistics,
+                ),
+            };
+            if let Err(err) = player_profile::build_player_profiles(args) {
+                eprintln!("Error building player profiles: {}", err);
+                std::process::exit(1);
+            }
+        }
         None => {
             let game_name = args.game_name.as_deref().unwrap_or("");
             let tag_line = args.tag_line.as_deref().unwrap_or("");
@@ -433,6 +1963,46 @@ fn main() {
     }
 }
 
+/// Parquet writer settings from the shared `--compression`, `--compression-level`,
+/// `--row-group-size` and `--statistics` flags; exits on invalid values.
+fn parquet_options(
+    compression: &str,
+    compression_level: Option<i32>,
+    row_group_size: Option<usize>,
+    statistics: bool,
+) -> parquet_extract::ParquetOptions {
+    parquet_extract::ParquetOptions::new(compression, compression_level, row_group_size, statistics)
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        })
+}
+
+/// PUUIDs from repeated `--puuid` flags and a `--puuid-file`, deduplicated in order; falls back
+/// to RIOT_PUUID when neither names one.
+fn resolve_puuids(puuid_args: &[String], puuid_file: Option<&str>) -> Vec<String> {
+    let mut puuids: Vec<String> = puuid_args
+        .iter()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect();
+    if let Some(path) = puuid_file {
+        match kraken::read_puuid_file(Path::new(path)) {
+            Ok(listed) => puuids.extend(listed),
+            Err(err) => {
+                eprintln!("Failed to read {}: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+    }
+    if puuids.is_empty() {
+        puuids.push(resolve_puuid(&None));
+    }
+    let mut seen = HashSet::new();
+    puuids.retain(|value| seen.insert(value.clone()));
+    puuids
+}
+
 fn resolve_puuid(puuid_arg: &Option<String>) -> String {
     match puuid_arg {
         Some(value) if !value.trim().is_empty() => value.clone(),
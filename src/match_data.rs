@@ -0,0 +1,368 @@
+use serde::Deserialize;
+use serde::de::{Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The parts of a match-v5 document read by the extractors. Parsing straight into these
+/// structs skips every field nobody uses instead of building a full `serde_json::Value` tree.
+/// Missing fields take their default, so partial documents still parse.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MatchDocument {
+    pub metadata: Option<MatchMetadata>,
+    pub info: Option<MatchInfo>,
+    /// The whole JSON tree, kept only when a column spec reads fields not listed here.
+    #[serde(skip)]
+    pub raw: Option<Value>,
+}
+
+impl MatchDocument {
+    pub fn match_id(&self) -> Option<&str> {
+        self.metadata.as_ref()?.match_id.as_deref()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MatchMetadata {
+    pub match_id: Option<String>,
+    pub platform_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MatchInfo {
+    pub game_creation: i64,
+    pub game_duration: i64,
+    pub queue_id: i64,
+    pub game_version: String,
+    pub platform_id: Option<String>,
+    pub participants: Option<Vec<Participant>>,
+    pub teams: Option<Vec<Team>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Participant {
+    /// 1 to 10; keys the participant's timeline frames.
+    pub participant_id: i32,
+    pub team_id: i64,
+    pub puuid: String,
+    pub riot_id_game_name: Option<String>,
+    pub riot_id_tagline: Option<String>,
+    /// Legacy name, left empty by Riot since the move to Riot IDs.
+    pub summoner_name: Option<String>,
+    pub champion_id: i32,
+    pub champion_name: String,
+    pub team_position: String,
+    pub individual_position: String,
+    pub win: bool,
+    pub game_ended_in_surrender: bool,
+    /// Set on every participant of a remake.
+    pub game_ended_in_early_surrender: bool,
+    pub kills: i32,
+    pub deaths: i32,
+    pub assists: i32,
+    pub champ_level: i32,
+    pub gold_earned: i32,
+    pub gold_spent: i32,
+    pub total_minions_killed: i32,
+    pub neutral_minions_killed: i32,
+    pub total_damage_dealt_to_champions: i32,
+    pub physical_damage_dealt_to_champions: i32,
+    pub magic_damage_dealt_to_champions: i32,
+    pub true_damage_dealt_to_champions: i32,
+    pub total_damage_taken: i32,
+    pub physical_damage_taken: i32,
+    pub magic_damage_taken: i32,
+    pub true_damage_taken: i32,
+    pub damage_self_mitigated: i32,
+    pub total_heal: i32,
+    pub total_heals_on_teammates: i32,
+    pub total_damage_shielded_on_teammates: i32,
+    pub damage_dealt_to_objectives: i32,
+    pub damage_dealt_to_turrets: i32,
+    pub turret_takedowns: i32,
+    pub inhibitor_takedowns: i32,
+    pub objectives_stolen: i32,
+    pub objectives_stolen_assists: i32,
+    pub first_blood_kill: bool,
+    pub first_blood_assist: bool,
+    pub first_tower_kill: bool,
+    pub first_tower_assist: bool,
+    pub vision_score: i32,
+    pub wards_placed: i32,
+    pub wards_killed: i32,
+    pub vision_wards_bought_in_game: i32,
+    pub item0: i32,
+    pub item1: i32,
+    pub item2: i32,
+    pub item3: i32,
+    pub item4: i32,
+    pub item5: i32,
+    pub item6: i32,
+    pub perks: Option<Perks>,
+    // Ping counts, absent from matches played before the ping wheel (patch 13.x).
+    pub all_in_pings: Option<i32>,
+    pub assist_me_pings: Option<i32>,
+    pub bait_pings: Option<i32>,
+    pub basic_pings: Option<i32>,
+    pub command_pings: Option<i32>,
+    pub danger_pings: Option<i32>,
+    pub enemy_missing_pings: Option<i32>,
+    pub enemy_vision_pings: Option<i32>,
+    pub get_back_pings: Option<i32>,
+    pub hold_pings: Option<i32>,
+    pub need_vision_pings: Option<i32>,
+    pub on_my_way_pings: Option<i32>,
+    pub push_pings: Option<i32>,
+    pub retreat_pings: Option<i32>,
+    pub vision_cleared_pings: Option<i32>,
+    /// Numeric `challenges` by their Riot key; list-valued challenges are dropped.
+    #[serde(deserialize_with = "numeric_challenges")]
+    pub challenges: Option<HashMap<String, f64>>,
+    // Arena only.
+    pub player_subteam_id: Option<i32>,
+    pub subteam_placement: Option<i32>,
+    pub placement: Option<i32>,
+    pub player_augment1: Option<i32>,
+    pub player_augment2: Option<i32>,
+    pub player_augment3: Option<i32>,
+    pub player_augment4: Option<i32>,
+    pub player_augment5: Option<i32>,
+    pub player_augment6: Option<i32>,
+}
+
+impl Participant {
+    /// `teamPosition`, falling back to `individualPosition` when Riot left it empty.
+    pub fn role(&self) -> &str {
+        if self.team_position.is_empty() {
+            &self.individual_position
+        } else {
+            &self.team_position
+        }
+    }
+
+    pub fn total_cs(&self) -> i32 {
+        self.total_minions_killed + self.neutral_minions_killed
+    }
+
+    pub fn challenge(&self, key: &str) -> Option<f64> {
+        self.challenges.as_ref()?.get(key).copied()
+    }
+
+    /// Ping counts in [`PING_COLUMNS`] order.
+    pub fn pings(&self) -> [Option<i32>; PING_COLUMNS.len()] {
+        [
+            self.all_in_pings,
+            self.assist_me_pings,
+            self.bait_pings,
+            self.basic_pings,
+            self.command_pings,
+            self.danger_pings,
+            self.enemy_missing_pings,
+            self.enemy_vision_pings,
+            self.get_back_pings,
+            self.hold_pings,
+            self.need_vision_pings,
+            self.on_my_way_pings,
+            self.push_pings,
+            self.retreat_pings,
+            self.vision_cleared_pings,
+        ]
+    }
+
+    pub fn augments(&self) -> [Option<i32>; 6] {
+        [
+            self.player_augment1,
+            self.player_augment2,
+            self.player_augment3,
+            self.player_augment4,
+            self.player_augment5,
+            self.player_augment6,
+        ]
+    }
+}
+
+/// Snake-case names of the `*Pings` participant fields returned by [`Participant::pings`].
+pub const PING_COLUMNS: [&str; 15] = [
+    "all_in_pings",
+    "assist_me_pings",
+    "bait_pings",
+    "basic_pings",
+    "command_pings",
+    "danger_pings",
+    "enemy_missing_pings",
+    "enemy_vision_pings",
+    "get_back_pings",
+    "hold_pings",
+    "need_vision_pings",
+    "on_my_way_pings",
+    "push_pings",
+    "retreat_pings",
+    "vision_cleared_pings",
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Perks {
+    pub stat_perks: Option<StatPerks>,
+    pub styles: Vec<PerkStyle>,
+}
+
+impl Perks {
+    /// The style tree with this `description` (`primaryStyle` or `subStyle`).
+    pub fn style(&self, description: &str) -> Option<&PerkStyle> {
+        self.styles
+            .iter()
+            .find(|style| style.description == description)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct StatPerks {
+    pub offense: Option<i32>,
+    pub flex: Option<i32>,
+    pub defense: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PerkStyle {
+    pub description: String,
+    pub style: Option<i32>,
+    pub selections: Vec<PerkSelection>,
+}
+
+impl PerkStyle {
+    pub fn selection(&self, slot: usize) -> Option<i32> {
+        self.selections.get(slot)?.perk
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PerkSelection {
+    pub perk: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Team {
+    pub team_id: Option<i64>,
+    pub win: bool,
+    pub bans: Vec<Ban>,
+    pub objectives: Option<Objectives>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Ban {
+    pub champion_id: Option<i32>,
+    pub pick_turn: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Objectives {
+    pub baron: Option<Objective>,
+    pub champion: Option<Objective>,
+    pub dragon: Option<Objective>,
+    pub inhibitor: Option<Objective>,
+    pub rift_herald: Option<Objective>,
+    pub tower: Option<Objective>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Objective {
+    pub first: Option<bool>,
+    pub kills: i32,
+    pub plates: Option<i32>,
+}
+
+fn numeric_challenges<'de, D>(deserializer: D) -> Result<Option<HashMap<String, f64>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct ChallengesVisitor;
+
+    impl<'de> Visitor<'de> for ChallengesVisitor {
+        type Value = Option<HashMap<String, f64>>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a challenges object")
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut challenges = HashMap::new();
+            while let Some((key, value)) = map.next_entry::<String, MaybeNumber>()? {
+                if let Some(value) = value.0 {
+                    challenges.insert(key, value);
+                }
+            }
+            Ok(Some(challenges))
+        }
+    }
+
+    deserializer.deserialize_any(ChallengesVisitor)
+}
+
+/// A JSON value kept only when it is a number.
+struct MaybeNumber(Option<f64>);
+
+impl<'de> Deserialize<'de> for MaybeNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NumberVisitor;
+
+        impl<'de> Visitor<'de> for NumberVisitor {
+            type Value = MaybeNumber;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any JSON value")
+            }
+
+            fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E> {
+                Ok(MaybeNumber(Some(value)))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E> {
+                Ok(MaybeNumber(Some(value as f64)))
+            }
+
+            fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+                Ok(MaybeNumber(Some(value as f64)))
+            }
+
+            fn visit_bool<E>(self, _: bool) -> Result<Self::Value, E> {
+                Ok(MaybeNumber(None))
+            }
+
+            fn visit_str<E>(self, _: &str) -> Result<Self::Value, E> {
+                Ok(MaybeNumber(None))
+            }
+
+            fn visit_unit<E>(self) -> Result<Self::Value, E> {
+                Ok(MaybeNumber(None))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(MaybeNumber(None))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+                Ok(MaybeNumber(None))
+            }
+        }
+
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
@@ -0,0 +1,200 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// The parts of a match-v5 timeline (`/lol/match/v5/matches/{matchId}/timeline`) read by the
+/// extractors. Like [`crate::match_data::MatchDocument`], unused fields are skipped while
+/// parsing and missing ones take their default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MatchTimeline {
+    pub info: Option<TimelineInfo>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TimelineInfo {
+    pub frames: Vec<Frame>,
+}
+
+/// A snapshot of every participant, taken once per `frameInterval` (a minute).
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Frame {
+    /// Milliseconds since the game started.
+    pub timestamp: i64,
+    /// Keyed by participant ID as a string (`"1"` to `"10"`).
+    pub participant_frames: HashMap<String, ParticipantFrame>,
+    /// Everything that happened since the previous frame, in order.
+    pub events: Vec<Event>,
+}
+
+/// One timeline event. Which fields are set depends on `type`; participant IDs of 0 stand for
+/// minions, turrets and monsters.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Event {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub timestamp: i64,
+    /// Item, skill and level events.
+    pub participant_id: Option<i32>,
+    pub killer_id: Option<i32>,
+    /// Who placed a ward.
+    pub creator_id: Option<i32>,
+    pub victim_id: Option<i32>,
+    pub assisting_participant_ids: Vec<i32>,
+    /// For `BUILDING_KILL`, the team that lost the building.
+    pub team_id: Option<i32>,
+    pub killer_team_id: Option<i32>,
+    pub position: Option<Position>,
+    pub monster_type: Option<String>,
+    pub monster_sub_type: Option<String>,
+    pub building_type: Option<String>,
+    pub lane_type: Option<String>,
+    pub tower_type: Option<String>,
+    pub ward_type: Option<String>,
+    pub item_id: Option<i32>,
+    /// `ITEM_UNDO`: the item an undone purchase bought (`before_id`) or an undone sale sold
+    /// (`after_id`); the other is 0.
+    pub before_id: Option<i32>,
+    pub after_id: Option<i32>,
+    pub skill_slot: Option<i32>,
+    pub kill_type: Option<String>,
+    pub bounty: Option<i32>,
+    pub shutdown_bounty: Option<i32>,
+}
+
+impl Event {
+    /// The participant who acted: bought, levelled, killed or placed the ward.
+    pub fn actor(&self) -> Option<i32> {
+        self.participant_id
+            .or(self.killer_id)
+            .or(self.creator_id)
+            .filter(|id| *id > 0)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ParticipantFrame {
+    pub total_gold: i32,
+    pub xp: i32,
+    pub minions_killed: i32,
+    pub jungle_minions_killed: i32,
+    pub position: Option<Position>,
+}
+
+impl MatchTimeline {
+    pub fn frames(&self) -> &[Frame] {
+        self.info.as_ref().map_or(&[], |info| &info.frames)
+    }
+
+    /// The first frame taken at or after `minute`; `None` when the game ended earlier.
+    pub fn frame_at(&self, minute: i64) -> Option<&Frame> {
+        self.frames()
+            .iter()
+            .find(|frame| frame.timestamp >= minute * 60_000)
+    }
+}
+
+impl Frame {
+    pub fn participant(&self, participant_id: i32) -> Option<&ParticipantFrame> {
+        self.participant_frames.get(&participant_id.to_string())
+    }
+}
+
+impl ParticipantFrame {
+    pub fn cs(&self) -> i32 {
+        self.minions_killed + self.jungle_minions_killed
+    }
+}
+
+/// Map coordinates of a participant when the frame was taken.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Summoner's Rift runs from the blue fountain near (0, 0) to the red one near this corner.
+const MAP_SIZE: f64 = 14_800.0;
+/// Each base is the square of this side in its corner.
+const BASE_SIZE: f64 = 4_500.0;
+/// Points this close to a lane's center line are on the lane.
+const LANE_HALF_WIDTH: f64 = 1_000.0;
+/// Points this close to the river's center line (the anti-diagonal) are in the river.
+const RIVER_HALF_WIDTH: f64 = 900.0;
+
+/// Center lines of the lanes between the bases, as polylines.
+const TOP_LANE: [(f64, f64); 3] = [
+    (1_300.0, 4_500.0),
+    (1_300.0, 13_500.0),
+    (10_300.0, 13_500.0),
+];
+const MID_LANE: [(f64, f64); 2] = [(4_500.0, 4_500.0), (10_300.0, 10_300.0)];
+const BOTTOM_LANE: [(f64, f64); 3] = [
+    (4_500.0, 1_300.0),
+    (13_500.0, 1_300.0),
+    (13_500.0, 10_300.0),
+];
+
+/// Coarse area of the map a position falls in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zone {
+    Base,
+    Lane,
+    River,
+    /// A jungle quadrant, by the side of the river it lies on.
+    Jungle {
+        blue_side: bool,
+    },
+}
+
+impl Position {
+    pub fn zone(&self) -> Zone {
+        let (x, y) = (self.x as f64, self.y as f64);
+        if (x < BASE_SIZE && y < BASE_SIZE)
+            || (x > MAP_SIZE - BASE_SIZE && y > MAP_SIZE - BASE_SIZE)
+        {
+            return Zone::Base;
+        }
+        let lane_distance = [&TOP_LANE[..], &MID_LANE[..], &BOTTOM_LANE[..]]
+            .into_iter()
+            .map(|lane| polyline_distance(lane, x, y))
+            .fold(f64::INFINITY, f64::min);
+        if lane_distance < LANE_HALF_WIDTH {
+            return Zone::Lane;
+        }
+        if (x + y - MAP_SIZE).abs() / std::f64::consts::SQRT_2 < RIVER_HALF_WIDTH {
+            return Zone::River;
+        }
+        Zone::Jungle {
+            blue_side: x + y < MAP_SIZE,
+        }
+    }
+
+    /// Distance to the center line of the lane `role` plays in (supports share the bottom
+    /// lane); `None` for junglers and unknown roles.
+    pub fn lane_distance(&self, role: &str) -> Option<f64> {
+        let lane: &[(f64, f64)] = match role {
+            "TOP" => &TOP_LANE,
+            "MIDDLE" => &MID_LANE,
+            "BOTTOM" | "UTILITY" => &BOTTOM_LANE,
+            _ => return None,
+        };
+        Some(polyline_distance(lane, self.x as f64, self.y as f64))
+    }
+}
+
+fn polyline_distance(points: &[(f64, f64)], x: f64, y: f64) -> f64 {
+    points
+        .windows(2)
+        .map(|segment| {
+            let ((x1, y1), (x2, y2)) = (segment[0], segment[1]);
+            let (dx, dy) = (x2 - x1, y2 - y1);
+            let t = (((x - x1) * dx + (y - y1) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+            (x - (x1 + t * dx)).hypot(y - (y1 + t * dy))
+        })
+        .fold(f64::INFINITY, f64::min)
+}
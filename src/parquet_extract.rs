@@ -1,8 +1,27 @@
+use crate::column_spec::{ColumnSpec, FieldSpec};
+use crate::ddragon::{ChampionInfo, ChampionTable, load_champions};
+use crate::kraken::{TimeBound, write_json_atomic};
+use crate::kraken_manifest::{parse_queues, patch_from_version};
+use crate::match_data::{
+    MatchDocument, MatchInfo, Objective, Objectives, PING_COLUMNS, Participant, PerkStyle, Perks,
+    Team,
+};
+use crate::match_timeline::{Frame, MatchTimeline, Zone};
+use chrono::Utc;
+use flate2::read::MultiGzDecoder;
+use polars::io::csv::BatchedWriter as CsvBatchedWriter;
+use polars::io::ipc::BatchedWriter as IpcBatchedWriter;
+use polars::io::parquet::BatchedWriter;
 use polars::prelude::ParquetWriter;
 use polars::prelude::*;
-use serde_json::Value;
+use polars_parquet::write::KeyValue;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
+use std::fmt;
 use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 
 struct PlayerRow {
@@ -13,10 +32,15 @@ struct PlayerRow {
     game_version: String,
     team_id: i32,
     puuid: String,
+    riot_id_game_name: Option<String>,
+    riot_id_tagline: Option<String>,
+    summoner_name: Option<String>,
     champion_id: i32,
     champion_name: String,
     role: String,
     win: bool,
+    game_ended_in_surrender: bool,
+    game_ended_in_early_surrender: bool,
     kills: i32,
     deaths: i32,
     assists: i32,
@@ -29,12 +53,42 @@ struct PlayerRow {
     damage_to_champions: i32,
     damage_to_objectives: i32,
     damage_to_turrets: i32,
+    physical_damage_to_champions: i32,
+    magic_damage_to_champions: i32,
+    true_damage_to_champions: i32,
+    damage_taken: i32,
+    physical_damage_taken: i32,
+    magic_damage_taken: i32,
+    true_damage_taken: i32,
+    damage_self_mitigated: i32,
+    total_heal: i32,
+    heals_on_teammates: i32,
+    shields_on_teammates: i32,
     turret_takedowns: i32,
     inhibitor_takedowns: i32,
+    objectives_stolen: i32,
+    objectives_stolen_assists: i32,
+    /// `epicMonsterSteals` challenge; `None` without `challenges`.
+    epic_monster_steals: Option<i32>,
+    first_blood_kill: bool,
+    first_blood_assist: bool,
+    first_tower_kill: bool,
+    first_tower_assist: bool,
     vision_score: i32,
     wards_placed: i32,
     wards_killed: i32,
     control_wards_placed: i32,
+    /// Final inventory item IDs; `item6` is the trinket slot and 0 means empty.
+    item0: i32,
+    item1: i32,
+    item2: i32,
+    item3: i32,
+    item4: i32,
+    item5: i32,
+    item6: i32,
+    runes: RunePage,
+    /// Ping counts in `PING_COLUMNS` order; `None` for matches that predate them.
+    pings: [Option<i32>; PING_COLUMNS.len()],
     damage_per_min: Option<f64>,
     gold_per_min: Option<f64>,
     team_damage_percentage: Option<f64>,
@@ -43,202 +97,2084 @@ struct PlayerRow {
     vision_score_per_min: Option<f64>,
     lane_minions_first10: Option<f64>,
     jungle_cs_before10: Option<f64>,
+    /// Some challenge metric above was computed from raw totals because `challenges` lacked it.
+    challenges_derived: bool,
+    /// Differences to the lane opponent at each of `LANE_DIFF_MINUTES`; `None` without a
+    /// timeline, an opponent or a frame that late.
+    lane_diffs: [Option<LaneDiff>; LANE_DIFF_MINUTES.len()],
+    /// Lane minions and jungle monsters killed by each of `LANE_DIFF_MINUTES`, from the timeline.
+    cs_at: [Option<i32>; LANE_DIFF_MINUTES.len()],
+    /// Item purchases and sales in game order; `None` without a timeline.
+    item_build: Option<ItemBuild>,
+    /// Only written with `--timeline-positions`.
+    positions: PositionFeatures,
+    /// Challenges selected with `--challenge-cols`, by their Riot key.
+    extra_challenges: HashMap<String, f64>,
+    /// Values of the column spec's participant fields, in spec order.
+    extra_fields: Vec<Value>,
 }
 
-pub fn extract_parquet(
-    matches_dir: &Path,
-    out_parquet: &Path,
-    level: &str,
+/// Rune and stat shard IDs from `participant.perks`; `None` when the page is missing.
+#[derive(Default)]
+struct RunePage {
+    primary_style: Option<i32>,
+    keystone: Option<i32>,
+    primary_rune1: Option<i32>,
+    primary_rune2: Option<i32>,
+    primary_rune3: Option<i32>,
+    secondary_style: Option<i32>,
+    secondary_rune1: Option<i32>,
+    secondary_rune2: Option<i32>,
+    stat_offense: Option<i32>,
+    stat_flex: Option<i32>,
+    stat_defense: Option<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtractParquetArgs {
+    /// Directories or match files to read, each possibly a glob pattern (`data/raw/euw*`).
+    pub matches_dirs: Vec<String>,
+    pub out_parquet: PathBuf,
+    pub level: String,
+    /// Only parse matches missing from an existing `out_parquet` and append them to it.
+    pub incremental: bool,
+    /// Upsert into an existing `out_parquet`: its rows whose key (`match_id` plus `puuid` or
+    /// `team_id`) was extracted again are replaced, the others kept.
+    pub merge: bool,
+    /// Write a Hive-style directory dataset split by these columns (`patch` is derived from
+    /// `game_version`) instead of a single file.
+    pub partition_by: Option<Vec<String>>,
+    /// `all`, or a file listing `participant.challenges` keys to add as player columns.
+    pub challenge_cols: Option<String>,
+    /// Queue IDs or labels (`soloq,flex`); every queue when unset.
+    pub queues: Option<String>,
+    pub since: Option<TimeBound>,
+    pub until: Option<TimeBound>,
+    /// Major.minor patches such as `14.21`.
+    pub patches: Option<Vec<String>>,
+    pub format: OutputFormat,
+    /// Data Dragon cache directory; when set, champion ID columns gain class, resource and
+    /// release patch columns.
+    pub enrich_champions: Option<PathBuf>,
+    /// Drop remakes: games shorter than this or ended by an early surrender. 0 keeps them.
+    pub min_duration_secs: i64,
+    /// Also store the schema version as a `schema_version` column, for formats without
+    /// file metadata.
+    pub schema_version_column: bool,
+    /// Column spec file selecting, renaming and adding columns; the built-in schema when unset.
+    pub columns: Option<PathBuf>,
+    /// Directory of match-v5 timelines saved as `{match_id}.json` (or `.json.gz`/`.json.zst`),
+    /// read for the laning columns of player rows.
+    pub timelines_dir: Option<PathBuf>,
+    /// Also add the positional feature group computed from the timeline frames.
+    pub timeline_positions: bool,
+    pub parquet: ParquetOptions,
+}
+
+/// Compression, row group and statistics settings of the Parquet files written.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParquetOptions {
+    pub compression: ParquetCompression,
+    /// Rows per row group; otherwise one per chunk written, or per `COPY_BATCH_ROWS` rows of a
+    /// sorted extract-parquet file.
+    pub row_group_size: Option<usize>,
+    /// Write per-column min/max and null counts, which lets readers skip row groups.
+    pub statistics: bool,
+}
+
+impl ParquetOptions {
+    /// `codec` is `zstd`, `snappy` or `uncompressed`; `level` (1-22) only applies to zstd.
+    pub fn new(
+        codec: &str,
+        level: Option<i32>,
+        row_group_size: Option<usize>,
+        statistics: bool,
+    ) -> Result<Self, String> {
+        let compression = match (codec.to_lowercase().as_str(), level) {
+            ("zstd", level) => ParquetCompression::Zstd(
+                level
+                    .map(ZstdLevel::try_new)
+                    .transpose()
+                    .map_err(|err| format!("Invalid zstd compression level: {}", err))?,
+            ),
+            ("snappy", None) => ParquetCompression::Snappy,
+            ("uncompressed" | "none", None) => ParquetCompression::Uncompressed,
+            ("snappy" | "uncompressed" | "none", Some(_)) => {
+                return Err("--compression-level only applies to zstd".to_string());
+            }
+            (other, _) => {
+                return Err(format!(
+                    "Invalid compression '{}' (use zstd, snappy or uncompressed)",
+                    other
+                ));
+            }
+        };
+        if row_group_size == Some(0) {
+            return Err("--row-group-size must be at least 1".to_string());
+        }
+        Ok(Self {
+            compression,
+            row_group_size,
+            statistics,
+        })
+    }
+
+    pub fn writer<W: Write>(&self, out: W) -> ParquetWriter<W> {
+        ParquetWriter::new(out)
+            .with_compression(self.compression)
+            .with_row_group_size(self.row_group_size)
+            .with_statistics(self.statistics)
+    }
+}
+
+/// Player columns added by `--timeline-positions`.
+const POSITION_COLUMNS: [&str; 4] = [
+    "own_jungle_share",
+    "enemy_jungle_share",
+    "river_share_before10",
+    "avg_lane_distance",
+];
+
+/// Version of the extracted column layout, recorded in the metadata of every Parquet file this
+/// module writes. Bump it whenever a level gains, loses or retypes a column, so older datasets
+/// can be recognised and upgraded with `migrate-dataset`.
+pub const SCHEMA_VERSION: u32 = 12;
+
+const SCHEMA_VERSION_KEY: &str = "riot_rust_api.schema_version";
+const LEVEL_KEY: &str = "riot_rust_api.level";
+
+/// File format of the extracted dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Parquet,
+    Csv,
+    /// Arrow IPC (Feather v2).
+    Ipc,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+impl OutputFormat {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        match raw.to_lowercase().as_str() {
+            "parquet" => Ok(OutputFormat::Parquet),
+            "csv" => Ok(OutputFormat::Csv),
+            "ipc" | "arrow" | "feather" => Ok(OutputFormat::Ipc),
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
+            other => Err(format!(
+                "Invalid format '{}' (use parquet, csv, ipc or ndjson)",
+                other
+            )),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Ipc => "arrow",
+            OutputFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Match-level restrictions from the extract-parquet filter flags.
+struct MatchFilter {
+    queues: Option<BTreeSet<i64>>,
+    /// `gameCreation` bounds in Unix milliseconds.
+    since_ms: Option<i64>,
+    until_ms: Option<i64>,
+    patches: Option<Vec<String>>,
+    /// Games shorter than this or ended by an early surrender are remakes; 0 keeps them.
+    min_duration_secs: i64,
+}
+
+impl MatchFilter {
+    fn from_args(args: &ExtractParquetArgs) -> Result<Self, Box<dyn Error>> {
+        let now = Utc::now().timestamp();
+        Ok(Self {
+            queues: args.queues.as_deref().map(parse_queues).transpose()?,
+            since_ms: args.since.map(|bound| bound.resolve(now) * 1000),
+            until_ms: args.until.map(|bound| bound.resolve(now) * 1000),
+            patches: args.patches.clone(),
+            min_duration_secs: args.min_duration_secs,
+        })
+    }
+
+    /// Why the match is left out, with a short detail for the skip report; `None` to keep it.
+    fn rejection(&self, info: &MatchInfo) -> Option<(SkipReason, String)> {
+        if let Some(queues) = &self.queues
+            && !queues.contains(&info.queue_id)
+        {
+            return Some((SkipReason::Filtered, format!("queue {}", info.queue_id)));
+        }
+        if self
+            .since_ms
+            .is_some_and(|since| info.game_creation < since)
+            || self
+                .until_ms
+                .is_some_and(|until| info.game_creation >= until)
+        {
+            return Some((
+                SkipReason::Filtered,
+                format!("created at {} ms", info.game_creation),
+            ));
+        }
+        let patch = patch_from_version(&info.game_version);
+        if self
+            .patches
+            .as_ref()
+            .is_some_and(|patches| !patches.contains(&patch))
+        {
+            return Some((SkipReason::Filtered, format!("patch {}", patch)));
+        }
+
+        if self.min_duration_secs > 0 && is_remake(info, self.min_duration_secs) {
+            return Some((SkipReason::Remake, format!("{}s long", info.game_duration)));
+        }
+        None
+    }
+}
+
+fn is_remake(info: &MatchInfo, min_duration_secs: i64) -> bool {
+    info.game_duration < min_duration_secs
+        || info.participants.as_ref().is_some_and(|participants| {
+            participants
+                .iter()
+                .any(|participant| participant.game_ended_in_early_surrender)
+        })
+}
+
+/// Extra `participant.challenges` keys lifted into player columns.
+enum ChallengeColumns {
+    /// Every numeric challenge found in the extracted matches, in key order.
+    All,
+    Keys(Vec<String>),
+}
+
+impl ChallengeColumns {
+    /// `all`, or a file with one challenge key per line (`#` starts a comment).
+    fn parse(raw: &str) -> Result<Self, Box<dyn Error>> {
+        if raw.eq_ignore_ascii_case("all") {
+            return Ok(Self::All);
+        }
+
+        let contents = fs::read_to_string(raw)
+            .map_err(|err| format!("Failed to read challenge list {}: {}", raw, err))?;
+        let keys: Vec<String> = contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim().to_string())
+            .filter(|key| !key.is_empty())
+            .collect();
+        if keys.is_empty() {
+            return Err(format!("No challenge keys in {}", raw).into());
+        }
+        Ok(Self::Keys(keys))
+    }
+
+    fn includes(&self, key: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Keys(keys) => keys.iter().any(|k| k == key),
+        }
+    }
+}
+
+pub fn extract_parquet(args: &ExtractParquetArgs) -> Result<(), Box<dyn Error>> {
+    extract_in_chunks(args, CHUNK_MATCHES)
+}
+
+/// `extract_parquet` reading `chunk_matches` matches per chunk.
+fn extract_in_chunks(
+    args: &ExtractParquetArgs,
+    chunk_matches: usize,
 ) -> Result<(), Box<dyn Error>> {
-    if let Some(parent) = out_parquet.parent() {
-        if !parent.as_os_str().is_empty() {
-            fs::create_dir_all(parent)?;
+    let out_parquet = args.out_parquet.as_path();
+    if let Some(parent) = out_parquet.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+
+    if args.incremental && args.format != OutputFormat::Parquet {
+        return Err("--incremental only supports --format parquet".into());
+    }
+    if args.merge && (args.incremental || args.partition_by.is_some()) {
+        return Err("--merge-into cannot be combined with --incremental or --partition-by".into());
+    }
+    if args.merge && args.format != OutputFormat::Parquet {
+        return Err("--merge-into only supports --format parquet".into());
+    }
+    if args.level == "player-champion" && (args.incremental || args.merge) {
+        return Err(
+            "--level player-champion cannot be combined with --incremental or --merge-into".into(),
+        );
+    }
+    if args.timelines_dir.is_some()
+        && !matches!(args.level.as_str(), "player" | "player-champion" | "events")
+    {
+        return Err(
+            "--timelines-dir only applies to --level player, player-champion and events".into(),
+        );
+    }
+    if args.level == "events" && args.timelines_dir.is_none() {
+        return Err("--level events needs --timelines-dir".into());
+    }
+    if args.timeline_positions && args.timelines_dir.is_none() {
+        return Err("--timeline-positions needs --timelines-dir".into());
+    }
+    let timelines = args.timelines_dir.as_deref().map(|dir| TimelineOptions {
+        dir,
+        positions: args.timeline_positions,
+    });
+    let append = args.incremental && out_parquet.exists();
+    let known = if !append {
+        HashSet::new()
+    } else if args.partition_by.is_some() {
+        let mut known = HashSet::new();
+        for part in collect_files_with_extension(out_parquet, "parquet") {
+            known.extend(existing_match_ids(&part)?);
+        }
+        known
+    } else {
+        existing_match_ids(out_parquet)?
+    };
+
+    let challenge_cols = args
+        .challenge_cols
+        .as_deref()
+        .map(ChallengeColumns::parse)
+        .transpose()?;
+
+    let spec = match &args.columns {
+        Some(path) => ColumnSpec::load(path)?,
+        None => ColumnSpec::default(),
+    };
+    check_column_spec(&spec, args)?;
+
+    let filter = MatchFilter::from_args(args)?;
+    let champions = args
+        .enrich_champions
+        .as_deref()
+        .map(load_champions)
+        .transpose()?;
+
+    // Match files are named after their match ID, so known matches are skipped unread.
+    let files: Vec<PathBuf> = collect_match_files(&expand_inputs(&args.matches_dirs)?)
+        .into_iter()
+        .filter(|path| file_match_id(path).is_none_or(|id| !known.contains(id)))
+        .collect();
+    let mut seen = SeenMatches {
+        known,
+        extracted: HashSet::new(),
+    };
+    let mut skips = SkipReport::default();
+    let mut documents = files
+        .iter()
+        .flat_map(|path| {
+            match_documents(path, spec.reads_raw_fields()).map(move |doc| (path.as_path(), doc))
+        })
+        .peekable();
+
+    let mut output = DatasetWriter::new(
+        out_parquet,
+        args.partition_by.as_deref(),
+        append,
+        args.merge,
+        args.format,
+        args.parquet,
+        &args.level,
+    )?;
+    // Every row group must share one schema, but with `all` a challenge key may first appear
+    // in a late chunk; those chunks are staged and widened to every key once the input is read.
+    let challenge_keys = match &challenge_cols {
+        Some(ChallengeColumns::Keys(keys)) => keys.clone(),
+        _ => Vec::new(),
+    };
+    let mut staged = match &challenge_cols {
+        Some(ChallengeColumns::All) if args.level == "player" => {
+            Some(StagedChunks::new(out_parquet)?)
+        }
+        _ => None,
+    };
+    let mut write_chunk = |df: DataFrame| -> Result<(), Box<dyn Error>> {
+        let df = match &champions {
+            Some(champions) => with_champion_metadata(df, champions)?,
+            None => df,
+        };
+        let df = spec.apply(sort_rows(df)?)?;
+        let df = if args.schema_version_column {
+            with_schema_version_column(df)?
+        } else {
+            df
+        };
+        output.write(df)
+    };
+    // `--level player-champion` sums every chunk into this and writes once at the end.
+    let mut champion_sums: Option<DataFrame> = None;
+
+    loop {
+        let chunk: Vec<(&Path, MatchDocument)> = documents
+            .by_ref()
+            .take(chunk_matches)
+            .filter_map(|(path, document)| match document {
+                Ok(document) => Some((path, document)),
+                Err(skipped) => {
+                    skips.skipped.push(skipped);
+                    None
+                }
+            })
+            .collect();
+        let chunk = chunk.into_iter();
+        let df = match args.level.as_str() {
+            "player" => {
+                let rows = extract_player_rows(
+                    chunk,
+                    &mut seen,
+                    &filter,
+                    challenge_cols.as_ref(),
+                    &spec.participant,
+                    timelines.as_ref(),
+                    &mut skips,
+                );
+                if let Some(staged) = staged.as_mut() {
+                    staged.stage(rows, &spec.participant, args.timeline_positions)?;
+                    if documents.peek().is_some() {
+                        continue;
+                    }
+                    break;
+                }
+                build_dataframe(
+                    rows,
+                    &challenge_keys,
+                    &spec.participant,
+                    args.timeline_positions,
+                )?
+            }
+            "team" => build_team_dataframe(
+                extract_team_rows(chunk, &mut seen, &filter, &spec.team, &mut skips),
+                &spec.team,
+            )?,
+            "matchup" => build_matchup_dataframe(build_dataframe(
+                extract_player_rows(chunk, &mut seen, &filter, None, &[], None, &mut skips),
+                &[],
+                &[],
+                false,
+            )?)?,
+            "arena" => {
+                build_arena_dataframe(extract_arena_rows(chunk, &mut seen, &filter, &mut skips))?
+            }
+            "player-champion" => {
+                let players = build_dataframe(
+                    extract_player_rows(
+                        chunk,
+                        &mut seen,
+                        &filter,
+                        None,
+                        &[],
+                        timelines.as_ref(),
+                        &mut skips,
+                    ),
+                    &[],
+                    &[],
+                    false,
+                )?;
+                let sums = champion_sums_of(players.lazy(), champion_sums.take())?;
+                if documents.peek().is_some() {
+                    champion_sums = Some(sums);
+                    continue;
+                }
+                build_player_champion_dataframe(sums)?
+            }
+            "events" => build_event_dataframe(extract_event_rows(
+                chunk,
+                &mut seen,
+                &filter,
+                args.timelines_dir.as_deref().unwrap_or(Path::new("")),
+                &mut skips,
+            ))?,
+            "match" => build_match_dataframe(&build_team_dataframe(
+                extract_team_rows(chunk, &mut seen, &filter, &spec.team, &mut skips),
+                &spec.team,
+            )?)?,
+            other => return Err(unsupported_level(other)),
+        };
+        write_chunk(df)?;
+
+        if documents.peek().is_none() {
+            break;
         }
     }
-    match level {
-        "player" => extract_player_parquet(matches_dir, out_parquet),
-        "team" => extract_team_parquet(matches_dir, out_parquet),
-        other => Err(format!(
-            "Unsupported level '{}'. Supported levels: player, team.",
-            other
-        )
-        .into()),
+    if let Some(staged) = staged {
+        staged.drain(&spec.participant, args.timeline_positions, &mut write_chunk)?;
+    }
+
+    let duplicates = skips.count(SkipReason::Duplicate);
+    if duplicates > 0 {
+        eprintln!(
+            "Dropped {} duplicate copies of matches (kept the first one read)",
+            duplicates
+        );
     }
+    let remakes = skips.count(SkipReason::Remake);
+    if remakes > 0 {
+        eprintln!(
+            "Skipped {} remakes (under {}s or ended by an early surrender)",
+            remakes, args.min_duration_secs
+        );
+    }
+    let arena = skips.count(SkipReason::Arena);
+    if arena > 0 {
+        eprintln!(
+            "Skipped {} Arena matches; extract them with --level arena",
+            arena
+        );
+    }
+    let not_arena = skips.count(SkipReason::NotArena);
+    if not_arena > 0 {
+        eprintln!("Skipped {} matches outside the Arena queues", not_arena);
+    }
+    output.finish(files.len())?;
+    skips.write(out_parquet, files.len())
 }
 
-fn extract_player_parquet(matches_dir: &Path, out_parquet: &Path) -> Result<(), Box<dyn Error>> {
-    let mut rows: Vec<PlayerRow> = Vec::new();
+/// Matches parsed per chunk. Each chunk becomes its own row group, so memory use is bounded by
+/// the chunk instead of growing with the match directory.
+const CHUNK_MATCHES: usize = 2_000;
 
-    for path in collect_json_files(matches_dir) {
-        let contents = match fs::read_to_string(&path) {
-            Ok(data) => data,
-            Err(err) => {
-                eprintln!("Skipping unreadable file {}: {}", path.display(), err);
-                continue;
+fn unsupported_level(level: &str) -> Box<dyn Error> {
+    format!(
+        "Unsupported level '{}'. Supported levels: player, player-champion, team, match, matchup, arena, events.",
+        level
+    )
+    .into()
+}
+
+/// The columns `level` is extracted with, without any `--challenge-cols` or `--columns`
+/// additions.
+fn empty_level_frame(level: &str, positions: bool) -> Result<DataFrame, Box<dyn Error>> {
+    Ok(match level {
+        "player" => build_dataframe(Vec::new(), &[], &[], positions)?,
+        "team" => build_team_dataframe(Vec::new(), &[])?,
+        "matchup" => build_matchup_dataframe(build_dataframe(Vec::new(), &[], &[], false)?)?,
+        "arena" => build_arena_dataframe(Vec::new())?,
+        "player-champion" => build_player_champion_dataframe(champion_sums_of(
+            build_dataframe(Vec::new(), &[], &[], false)?.lazy(),
+            None,
+        )?)?,
+        "events" => build_event_dataframe(Vec::new())?,
+        "match" => build_match_dataframe(&build_team_dataframe(Vec::new(), &[])?)?,
+        other => return Err(unsupported_level(other)),
+    })
+}
+
+/// Rejects spec fields the level never reads or whose names clash with built-in columns, and
+/// specs that drop or rename the keys `--incremental` and `--merge-into` match rows on.
+fn check_column_spec(spec: &ColumnSpec, args: &ExtractParquetArgs) -> Result<(), Box<dyn Error>> {
+    let (fields, base_level) = match args.level.as_str() {
+        "player" if spec.team.is_empty() => (spec.participant.as_slice(), "player"),
+        "team" | "match" if spec.participant.is_empty() => (spec.team.as_slice(), "team"),
+        _ if !spec.reads_raw_fields() => (&[][..], args.level.as_str()),
+        _ => {
+            return Err(
+                "Column spec participant fields need --level player, team fields --level team or match"
+                    .into(),
+            );
+        }
+    };
+
+    let builtin = empty_level_frame(base_level, args.timeline_positions)?;
+    for field in fields {
+        let name = field.column_name();
+        if builtin.get_column_index(&name).is_some() {
+            return Err(format!(
+                "Column spec field '{}' clashes with the built-in column '{}'; give it another name",
+                field.path, name
+            )
+            .into());
+        }
+    }
+
+    if args.incremental || args.merge {
+        for key in merge_key_columns(&args.level) {
+            let kept = spec.keep.is_empty() || spec.keep.iter().any(|name| name == key);
+            if !kept || spec.rename.contains_key(*key) {
+                return Err(format!(
+                    "--incremental and --merge-into need the '{}' column; keep it under its name",
+                    key
+                )
+                .into());
             }
+        }
+    }
+    Ok(())
+}
+
+/// Adds `<prefix>champion_class`, `_secondary_class`, `_resource` and `_release_patch` after
+/// every `<prefix>champion_id` column (bans excepted). Unknown champions get nulls.
+fn with_champion_metadata(mut df: DataFrame, champions: &ChampionTable) -> PolarsResult<DataFrame> {
+    let id_columns: Vec<String> = df
+        .get_column_names()
+        .into_iter()
+        .filter(|name| name.ends_with("champion_id") && !is_ban_column(name))
+        .map(str::to_string)
+        .collect();
+
+    for id_column in id_columns {
+        let prefix = id_column.trim_end_matches("id");
+        let ids = df.column(&id_column)?.cast(&DataType::Int32)?;
+        let infos: Vec<Option<&ChampionInfo>> = ids
+            .i32()?
+            .into_iter()
+            .map(|id| champions.get(id?))
+            .collect();
+        let field = |name: &str, value: fn(&ChampionInfo) -> &Option<String>| {
+            let values: Vec<Option<&str>> = infos
+                .iter()
+                .map(|info| value((*info)?).as_deref())
+                .collect();
+            Series::new(&format!("{}{}", prefix, name), values)
         };
+        let columns = [
+            field("class", |info| &info.class),
+            field("secondary_class", |info| &info.secondary_class),
+            field("resource", |info| &info.resource),
+            field("release_patch", |info| &info.release_patch),
+        ];
+        for column in columns {
+            df.with_column(column)?;
+        }
+    }
+    Ok(df)
+}
 
-        let parsed: Value = match serde_json::from_str(&contents) {
-            Ok(value) => value,
-            Err(err) => {
-                eprintln!("Skipping invalid JSON {}: {}", path.display(), err);
-                continue;
+/// Whether `name` is a `banN_champion_id` column, either at the team level or with the match
+/// level's `blue_`/`red_` prefix.
+fn is_ban_column(name: &str) -> bool {
+    let name = name
+        .strip_prefix("blue_")
+        .or_else(|| name.strip_prefix("red_"))
+        .unwrap_or(name);
+    name.strip_prefix("ban")
+        .and_then(|rest| rest.strip_suffix("_champion_id"))
+        .is_some_and(|slot| !slot.is_empty() && slot.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Columns rows are ordered by, most significant first; each level uses those it has.
+const SORT_COLUMNS: [&str; 7] = [
+    "game_creation",
+    "match_id",
+    "event_index",
+    "team_id",
+    "subteam_id",
+    "role",
+    "puuid",
+];
+
+/// Orders a chunk by `SORT_COLUMNS`. Together with the sorted file list this makes repeated
+/// extractions of the same inputs byte-identical; Parquet files are sorted again as a whole
+/// when finished (see `ChunkedFile::sorted`).
+fn sort_rows(df: DataFrame) -> PolarsResult<DataFrame> {
+    let names = df.get_column_names();
+    let by: Vec<&str> = SORT_COLUMNS
+        .into_iter()
+        .filter(|column| names.contains(column))
+        .collect();
+    let descending = vec![false; by.len()];
+    df.sort(by, descending, true)
+}
+
+fn with_schema_version_column(mut df: DataFrame) -> PolarsResult<DataFrame> {
+    let versions = Series::new("schema_version", vec![SCHEMA_VERSION; df.height()]);
+    df.with_column(versions)?;
+    Ok(df)
+}
+
+/// Match IDs that must not produce rows again: those already in the dataset being appended to,
+/// and those extracted earlier in this run. Overlapping crawls can hold the same match twice.
+struct SeenMatches {
+    known: HashSet<String>,
+    extracted: HashSet<String>,
+}
+
+impl SeenMatches {
+    /// Whether `match_id` still needs extracting; records it as extracted if so. Copies of a
+    /// match already extracted in this run go to the skip report.
+    fn first_sight(&mut self, path: &Path, match_id: &str, skips: &mut SkipReport) -> bool {
+        if self.known.contains(match_id) {
+            return false;
+        }
+        if !self.extracted.insert(match_id.to_string()) {
+            skips.record(SkipReason::Duplicate, path, Some(match_id), None);
+            return false;
+        }
+        true
+    }
+}
+
+/// Why an input produced no rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum SkipReason {
+    Unreadable,
+    InvalidJson,
+    MissingSection,
+    /// Rejected by `--queues`, `--since`/`--until` or `--patches`.
+    Filtered,
+    Remake,
+    Arena,
+    /// `--level arena` found a match from another queue.
+    NotArena,
+    Duplicate,
+    /// `--level events` found no timeline for the match.
+    MissingTimeline,
+}
+
+#[derive(Debug, Serialize)]
+struct Skipped {
+    reason: SkipReason,
+    file: PathBuf,
+    /// 1-based line of a `.jsonl` shard.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    match_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+impl Skipped {
+    /// A file or shard line that could not be read or parsed.
+    fn input(reason: SkipReason, path: &Path, line: Option<usize>, err: impl fmt::Display) -> Self {
+        Self {
+            reason,
+            file: path.to_path_buf(),
+            line,
+            match_id: None,
+            detail: Some(err.to_string()),
+        }
+    }
+}
+
+/// Every input skipped during one extraction, written next to the output as
+/// `<output>.skipped.json` so skips can be inspected after the run.
+#[derive(Default)]
+struct SkipReport {
+    skipped: Vec<Skipped>,
+}
+
+impl SkipReport {
+    fn record(
+        &mut self,
+        reason: SkipReason,
+        path: &Path,
+        match_id: Option<&str>,
+        detail: Option<String>,
+    ) {
+        self.skipped.push(Skipped {
+            reason,
+            file: path.to_path_buf(),
+            line: None,
+            match_id: match_id.map(str::to_string),
+            detail,
+        });
+    }
+
+    fn missing_section(&mut self, path: &Path, section: &str) {
+        eprintln!("Missing {} in {}", section, path.display());
+        self.record(
+            SkipReason::MissingSection,
+            path,
+            None,
+            Some(section.to_string()),
+        );
+    }
+
+    fn count(&self, reason: SkipReason) -> usize {
+        self.skipped
+            .iter()
+            .filter(|skipped| skipped.reason == reason)
+            .count()
+    }
+
+    fn write(&self, output: &Path, files_read: usize) -> Result<(), Box<dyn Error>> {
+        let mut counts: BTreeMap<SkipReason, usize> = BTreeMap::new();
+        for skipped in &self.skipped {
+            *counts.entry(skipped.reason).or_default() += 1;
+        }
+        let report = json!({
+            "output": output,
+            "files_read": files_read,
+            "counts": counts,
+            "skipped": self.skipped,
+        });
+
+        let mut path = output.as_os_str().to_owned();
+        path.push(".skipped.json");
+        let path = PathBuf::from(path);
+        write_json_atomic(&path, &report, true)?;
+        if !self.skipped.is_empty() {
+            eprintln!(
+                "Skipped {} inputs; see {}",
+                self.skipped.len(),
+                path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Player chunks extracted with `--challenge-cols all`, each written with the challenge keys
+/// of its own rows into a `.challenges.tmp` sibling of the output until every key is known.
+struct StagedChunks {
+    dir: PathBuf,
+    keys: BTreeSet<String>,
+    chunks: usize,
+}
+
+impl StagedChunks {
+    fn new(out: &Path) -> Result<Self, Box<dyn Error>> {
+        let dir = with_suffix(out, ".challenges.tmp");
+        // Left behind by a run that failed before draining it.
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            keys: BTreeSet::new(),
+            chunks: 0,
+        })
+    }
+
+    fn chunk_path(&self, idx: usize) -> PathBuf {
+        self.dir.join(format!("chunk-{}.parquet", idx))
+    }
+
+    fn stage(
+        &mut self,
+        rows: Vec<PlayerRow>,
+        fields: &[FieldSpec],
+        positions: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let keys: Vec<String> = rows
+            .iter()
+            .flat_map(|row| row.extra_challenges.keys().cloned())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let mut df = build_dataframe(rows, &keys, fields, positions)?;
+        self.keys.extend(keys);
+        ParquetWriter::new(File::create(self.chunk_path(self.chunks))?).finish(&mut df)?;
+        self.chunks += 1;
+        Ok(())
+    }
+
+    /// Hands every staged chunk to `write` in order, with a null column for each key it lacks,
+    /// and removes the staging directory.
+    fn drain(
+        self,
+        fields: &[FieldSpec],
+        positions: bool,
+        write: &mut impl FnMut(DataFrame) -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let keys: Vec<String> = self.keys.iter().cloned().collect();
+        let template = build_dataframe(Vec::new(), &keys, fields, positions)?;
+        for idx in 0..self.chunks {
+            let chunk = ParquetReader::new(File::open(self.chunk_path(idx))?).finish()?;
+            let columns = template
+                .get_columns()
+                .iter()
+                .map(|column| match chunk.column(column.name()) {
+                    Ok(staged) => staged.clone(),
+                    Err(_) => Series::full_null(column.name(), chunk.height(), column.dtype()),
+                })
+                .collect();
+            write(DataFrame::new(columns)?)?;
+        }
+        fs::remove_dir_all(&self.dir)?;
+        Ok(())
+    }
+}
+
+/// Where `extract_parquet` writes its chunks: one file, or a Hive-style directory dataset.
+enum DatasetWriter {
+    File {
+        path: PathBuf,
+        /// The existing rows are copied into the rewritten file ahead of the new ones.
+        append: bool,
+        /// Rows copied over from the existing file.
+        existing_rows: usize,
+        /// Set with `--merge-into`: existing rows are copied after the new ones, minus those
+        /// whose key was written again.
+        merge: Option<MergedKeys>,
+        format: OutputFormat,
+        parquet: ParquetOptions,
+        metadata: Vec<KeyValue>,
+        writer: Option<Box<ChunkedFile>>,
+        /// Empty chunk kept for its schema, in case no rows are extracted at all.
+        empty: Option<DataFrame>,
+    },
+    Partitioned {
+        root: PathBuf,
+        /// Set for a full rewrite: partitions are written into this `.tmp` sibling, which
+        /// replaces `root` in `finish`, so a failed run leaves the old dataset in place.
+        staging: Option<PathBuf>,
+        keys: Vec<String>,
+        append: bool,
+        format: OutputFormat,
+        parquet: ParquetOptions,
+        metadata: Vec<KeyValue>,
+        parts: BTreeMap<PathBuf, ChunkedFile>,
+    },
+}
+
+impl DatasetWriter {
+    fn new(
+        path: &Path,
+        partition_by: Option<&[String]>,
+        append: bool,
+        merge: bool,
+        format: OutputFormat,
+        parquet: ParquetOptions,
+        level: &str,
+    ) -> Result<Self, Box<dyn Error>> {
+        let metadata = schema_metadata(level);
+        let Some(keys) = partition_by else {
+            return Ok(Self::File {
+                path: path.to_path_buf(),
+                append,
+                existing_rows: 0,
+                merge: merge.then(|| MergedKeys {
+                    columns: merge_key_columns(level),
+                    keys: HashSet::new(),
+                }),
+                format,
+                parquet,
+                metadata,
+                writer: None,
+                empty: None,
+            });
+        };
+
+        ensure_partitioned_dataset(path)?;
+        let staging = if append {
+            None
+        } else {
+            let staging = with_suffix(path, ".tmp");
+            ensure_partitioned_dataset(&staging)?;
+            if staging.exists() {
+                fs::remove_dir_all(&staging)?;
             }
+            fs::create_dir_all(&staging)?;
+            Some(staging)
         };
+        Ok(Self::Partitioned {
+            root: path.to_path_buf(),
+            staging,
+            keys: keys.to_vec(),
+            append,
+            format,
+            parquet,
+            metadata,
+            parts: BTreeMap::new(),
+        })
+    }
 
-        let Some(metadata) = parsed.get("metadata") else {
-            eprintln!("Missing metadata in {}", path.display());
+    fn write(&mut self, mut df: DataFrame) -> Result<(), Box<dyn Error>> {
+        match self {
+            Self::File {
+                path,
+                append,
+                existing_rows,
+                merge,
+                format,
+                parquet,
+                metadata,
+                writer,
+                empty,
+            } => {
+                if df.height() == 0 {
+                    if writer.is_none() {
+                        *empty = Some(df);
+                    }
+                    return Ok(());
+                }
+
+                if writer.is_none() {
+                    if *append || (merge.is_some() && path.exists()) {
+                        ensure_appendable(path, &df.schema())?;
+                    }
+                    let mut file =
+                        ChunkedFile::create(path, &df.schema(), *format, *parquet, metadata)?
+                            .sorted();
+                    if *append {
+                        copy_parquet_rows(path, &mut file, Ok)?;
+                        *existing_rows = file.rows;
+                    }
+                    *writer = Some(Box::new(file));
+                }
+                if let Some(merge) = merge {
+                    merge.keys.extend(row_keys(&df, merge.columns)?);
+                }
+                writer
+                    .as_mut()
+                    .expect("writer was just created")
+                    .write(&mut df)
+            }
+            Self::Partitioned {
+                root,
+                staging,
+                keys,
+                append,
+                format,
+                parquet,
+                metadata,
+                parts,
+            } => {
+                let write_root = staging.as_ref().unwrap_or(root);
+                for (dir, rows) in partition_rows(&df, write_root, keys)? {
+                    let part = match parts.entry(dir) {
+                        std::collections::btree_map::Entry::Occupied(entry) => entry.into_mut(),
+                        std::collections::btree_map::Entry::Vacant(entry) => {
+                            let dir = entry.key();
+                            fs::create_dir_all(dir)?;
+                            let mut n = 0;
+                            let path = loop {
+                                let path = dir.join(format!("part-{}.{}", n, format.extension()));
+                                if !path.exists() {
+                                    break path;
+                                }
+                                // The partition's earlier parts must have the new rows' columns.
+                                if *append {
+                                    ensure_appendable(&path, &df.schema())?;
+                                }
+                                n += 1;
+                            };
+                            entry.insert(
+                                ChunkedFile::create(
+                                    &path,
+                                    &df.schema(),
+                                    *format,
+                                    *parquet,
+                                    metadata,
+                                )?
+                                .sorted(),
+                            )
+                        }
+                    };
+                    part.write(&mut df.take(&IdxCa::from_vec("idx", rows))?)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(self, file_count: usize) -> Result<(), Box<dyn Error>> {
+        match self {
+            Self::File {
+                path,
+                append,
+                existing_rows,
+                merge,
+                format,
+                parquet,
+                metadata,
+                writer,
+                empty,
+            } => match writer {
+                None if append || (merge.is_some() && path.exists()) => {
+                    println!("No new matches to extract into {}", path.display());
+                    Ok(())
+                }
+                None => {
+                    let mut df = empty.unwrap_or_default();
+                    let mut file =
+                        ChunkedFile::create(&path, &df.schema(), format, parquet, &metadata)?;
+                    file.write(&mut df)?;
+                    file.finish()?;
+                    Ok(())
+                }
+                Some(mut writer) if merge.is_some() && path.exists() => {
+                    let merge = merge.expect("checked by the guard");
+                    let new_rows = writer.rows;
+                    let existing = ParquetReader::new(File::open(&path)?).num_rows()?;
+                    copy_parquet_rows(&path, &mut writer, |chunk| drop_merged_rows(chunk, &merge))?;
+                    let kept = writer.rows - new_rows;
+                    writer.finish()?;
+                    println!(
+                        "Merged {} rows into {}: {} existing rows replaced, {} kept",
+                        new_rows,
+                        path.display(),
+                        existing - kept,
+                        kept
+                    );
+                    Ok(())
+                }
+                Some(writer) if append => {
+                    let new_rows = writer.finish()? - existing_rows;
+                    println!(
+                        "Appended {} rows from {} new files to {}",
+                        new_rows,
+                        file_count,
+                        path.display()
+                    );
+                    Ok(())
+                }
+                Some(writer) => {
+                    writer.finish()?;
+                    Ok(())
+                }
+            },
+            Self::Partitioned {
+                root,
+                staging,
+                parts,
+                ..
+            } => {
+                if parts.is_empty() {
+                    // Rewriting with no rows must not replace the existing dataset.
+                    if let Some(staging) = staging {
+                        fs::remove_dir_all(&staging)?;
+                    }
+                    println!("No new matches to extract into {}", root.display());
+                    return Ok(());
+                }
+                let partitions = parts.len();
+                let mut rows = 0;
+                for part in parts.into_values() {
+                    rows += part.finish()?;
+                }
+                if let Some(staging) = staging {
+                    if root.exists() {
+                        fs::remove_dir_all(&root)?;
+                    }
+                    fs::rename(&staging, &root)?;
+                }
+                println!(
+                    "Wrote {} rows into {} partitions under {}",
+                    rows,
+                    partitions,
+                    root.display()
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Refuses to add rows with `schema` to the Parquet file `path` when its columns differ, e.g.
+/// because it was written by an older schema version.
+fn ensure_appendable(path: &Path, schema: &Schema) -> Result<(), Box<dyn Error>> {
+    let existing = ParquetReader::new(File::open(path)?)
+        .with_n_rows(Some(0))
+        .finish()?;
+    if existing.schema() != *schema {
+        let (version, _) = stored_schema(path)?;
+        return Err(format!(
+            "Cannot add rows to {} (its columns differ; it has schema version {}, current is {}). Run migrate-dataset or rebuild it from scratch",
+            path.display(),
+            version,
+            SCHEMA_VERSION
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Refuses to write partitions into `path` unless it is missing, empty, or holds only
+/// `key=value/.../part-N.*` trees such as this tool writes, so a mistyped `--out-parquet`
+/// never deletes or litters unrelated data.
+fn ensure_partitioned_dataset(path: &Path) -> Result<(), Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(());
+    }
+    if !path.is_dir() || !is_dataset_tree(path)? {
+        return Err(format!(
+            "{} exists and is not a partitioned dataset (key=value/part-N files); refusing to write --partition-by output there",
+            path.display()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Whether `dir` holds only `key=value` directories ending in `part-N` files.
+fn is_dataset_tree(dir: &Path) -> std::io::Result<bool> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let ok = if entry.file_type()?.is_dir() {
+            name.contains('=') && is_dataset_tree(&entry.path())?
+        } else {
+            name.starts_with("part-")
+        };
+        if !ok {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Groups the rows of `df` by their `root/key=value/...` partition directory.
+fn partition_rows(
+    df: &DataFrame,
+    root: &Path,
+    keys: &[String],
+) -> Result<BTreeMap<PathBuf, Vec<IdxSize>>, Box<dyn Error>> {
+    let mut key_values: Vec<Vec<String>> = Vec::new();
+    for key in keys {
+        let values: Vec<String> = if key == "patch" {
+            df.column("game_version")?
+                .str()?
+                .into_iter()
+                .map(|v| patch_from_version(v.unwrap_or_default()))
+                .collect()
+        } else {
+            let column = df.column(key).map_err(|_| {
+                format!(
+                    "Unknown --partition-by column '{}' (use patch or an output column)",
+                    key
+                )
+            })?;
+            column
+                .cast(&DataType::String)?
+                .str()?
+                .into_iter()
+                .map(|v| v.unwrap_or("__HIVE_DEFAULT_PARTITION__").to_string())
+                .collect()
+        };
+        key_values.push(values);
+    }
+
+    let mut partitions: BTreeMap<PathBuf, Vec<IdxSize>> = BTreeMap::new();
+    for row in 0..df.height() {
+        let mut dir = root.to_path_buf();
+        for (key, values) in keys.iter().zip(&key_values) {
+            let value = values[row].replace(['/', '\\', '='], "_");
+            dir.push(format!("{}={}", key, value));
+        }
+        partitions.entry(dir).or_default().push(row as IdxSize);
+    }
+    Ok(partitions)
+}
+
+/// Keys of the rows a `--merge-into` run has written so far.
+struct MergedKeys {
+    columns: &'static [&'static str],
+    keys: HashSet<String>,
+}
+
+/// Columns identifying a row of `level` when merging.
+fn merge_key_columns(level: &str) -> &'static [&'static str] {
+    match level {
+        "team" => &["match_id", "team_id"],
+        "match" => &["match_id"],
+        "events" => &["match_id", "event_index"],
+        _ => &["match_id", "puuid"],
+    }
+}
+
+/// The values of `columns` joined into one key per row.
+fn row_keys(df: &DataFrame, columns: &[&str]) -> PolarsResult<Vec<String>> {
+    let values = columns
+        .iter()
+        .map(|column| df.column(column)?.cast(&DataType::String))
+        .collect::<PolarsResult<Vec<_>>>()?;
+    let values = values
+        .iter()
+        .map(|column| column.str())
+        .collect::<PolarsResult<Vec<_>>>()?;
+    Ok((0..df.height())
+        .map(|row| {
+            values
+                .iter()
+                .map(|column| column.get(row).unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join("\u{1f}")
+        })
+        .collect())
+}
+
+fn drop_merged_rows(chunk: DataFrame, merge: &MergedKeys) -> PolarsResult<DataFrame> {
+    let keep: BooleanChunked = row_keys(&chunk, merge.columns)?
+        .iter()
+        .map(|key| !merge.keys.contains(key))
+        .collect();
+    chunk.filter(&keep)
+}
+
+/// One output file written a chunk at a time into a `.tmp` sibling that `finish` renames into
+/// place, so an interrupted run never leaves a truncated dataset behind.
+struct ChunkedFile {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    schema: Schema,
+    writer: FormatWriter,
+    parquet: ParquetOptions,
+    /// Key-value metadata written into a Parquet footer.
+    metadata: Vec<KeyValue>,
+    /// Parquet rows held back until they fill a row group of `--row-group-size`.
+    pending: Option<(usize, DataFrame)>,
+    rows: usize,
+    /// Rewrite a Parquet file in `SORT_COLUMNS` order before it is renamed into place.
+    sort_on_finish: bool,
+}
+
+enum FormatWriter {
+    Parquet(Box<BatchedWriter<File>>),
+    Csv(CsvBatchedWriter<File>),
+    Ipc(IpcBatchedWriter<File>),
+    Ndjson(BufWriter<File>),
+}
+
+impl ChunkedFile {
+    fn create(
+        path: &Path,
+        schema: &Schema,
+        format: OutputFormat,
+        parquet: ParquetOptions,
+        metadata: &[KeyValue],
+    ) -> Result<Self, Box<dyn Error>> {
+        let tmp_path = with_suffix(path, ".tmp");
+        let file = File::create(&tmp_path)?;
+        let writer = match format {
+            OutputFormat::Parquet => {
+                FormatWriter::Parquet(Box::new(parquet.writer(file).batched(schema)?))
+            }
+            OutputFormat::Csv => FormatWriter::Csv(CsvWriter::new(file).batched(schema)?),
+            OutputFormat::Ipc => FormatWriter::Ipc(IpcWriter::new(file).batched(schema)?),
+            OutputFormat::Ndjson => FormatWriter::Ndjson(BufWriter::new(file)),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            tmp_path,
+            schema: schema.clone(),
+            writer,
+            parquet,
+            metadata: metadata.to_vec(),
+            pending: parquet
+                .row_group_size
+                .filter(|_| format == OutputFormat::Parquet)
+                .map(|size| (size, DataFrame::from(schema))),
+            rows: 0,
+            sort_on_finish: false,
+        })
+    }
+
+    /// Chunks are only sorted among themselves, so a Parquet file made of several gets one
+    /// out-of-core sort over all its rows when it is finished.
+    fn sorted(mut self) -> Self {
+        self.sort_on_finish = matches!(self.writer, FormatWriter::Parquet(_));
+        self
+    }
+
+    fn write(&mut self, df: &mut DataFrame) -> Result<(), Box<dyn Error>> {
+        if df.schema() != self.schema {
+            return Err(format!(
+                "Extracted columns changed while writing {}",
+                self.path.display()
+            )
+            .into());
+        }
+        df.align_chunks();
+        match &mut self.writer {
+            FormatWriter::Parquet(writer) => match &mut self.pending {
+                // The batched writer turns every batch into one row group, so batches are
+                // regrouped to the requested size here.
+                Some((size, pending)) => {
+                    pending.vstack_mut(df)?;
+                    while pending.height() >= *size {
+                        let mut group = pending.slice(0, *size);
+                        group.as_single_chunk();
+                        writer.write_batch(&group)?;
+                        *pending = pending.slice(*size as i64, usize::MAX);
+                    }
+                }
+                None => writer.write_batch(df)?,
+            },
+            FormatWriter::Csv(writer) => writer.write_batch(df)?,
+            FormatWriter::Ipc(writer) => writer.write_batch(df)?,
+            FormatWriter::Ndjson(writer) => write_ndjson(writer, df)?,
+        }
+        self.rows += df.height();
+        Ok(())
+    }
+
+    /// Returns the number of rows written.
+    fn finish(self) -> Result<usize, Box<dyn Error>> {
+        match self.writer {
+            FormatWriter::Parquet(mut writer) => {
+                if let Some((_, mut rest)) = self.pending
+                    && rest.height() > 0
+                {
+                    rest.as_single_chunk();
+                    writer.write_batch(&rest)?;
+                }
+                let mut file_writer = writer
+                    .get_writer()
+                    .lock()
+                    .map_err(|_| "Parquet writer lock poisoned")?;
+                file_writer.end(Some(self.metadata.clone()))?;
+            }
+            FormatWriter::Csv(mut writer) => writer.finish()?,
+            FormatWriter::Ipc(mut writer) => writer.finish()?,
+            FormatWriter::Ndjson(mut writer) => writer.flush()?,
+        }
+        if self.sort_on_finish {
+            return sort_into_place(
+                &self.tmp_path,
+                &self.path,
+                &self.schema,
+                self.parquet,
+                &self.metadata,
+            );
+        }
+        fs::rename(&self.tmp_path, &self.path)?;
+        Ok(self.rows)
+    }
+}
+
+/// Sorts the finished Parquet file at `tmp_path` with the streaming engine and copies the result
+/// into a fresh file at `path` written with the original writer settings and footer metadata.
+fn sort_into_place(
+    tmp_path: &Path,
+    path: &Path,
+    schema: &Schema,
+    parquet: ParquetOptions,
+    metadata: &[KeyValue],
+) -> Result<usize, Box<dyn Error>> {
+    let unsorted = with_suffix(tmp_path, ".unsorted");
+    let sorted = with_suffix(tmp_path, ".sorted");
+    fs::rename(tmp_path, &unsorted)?;
+    let by: Vec<Expr> = SORT_COLUMNS
+        .into_iter()
+        .filter(|column| schema.contains(column))
+        .map(col)
+        .collect();
+    let descending = vec![false; by.len()];
+    LazyFrame::scan_parquet(&unsorted, ScanArgsParquet::default())?
+        .sort_by_exprs(by, descending, false, false)
+        .sink_parquet(
+            sorted.clone(),
+            ParquetWriteOptions {
+                maintain_order: true,
+                ..Default::default()
+            },
+        )?;
+    fs::remove_file(&unsorted)?;
+
+    let mut file = ChunkedFile::create(path, schema, OutputFormat::Parquet, parquet, metadata)?;
+    copy_parquet_rows(&sorted, &mut file, Ok)?;
+    fs::remove_file(&sorted)?;
+    file.finish()
+}
+
+/// Writes one JSON object per row, keys in column order.
+fn write_ndjson(out: &mut impl Write, df: &DataFrame) -> Result<(), Box<dyn Error>> {
+    let columns = df.get_columns();
+    for row in 0..df.height() {
+        out.write_all(b"{")?;
+        for (idx, column) in columns.iter().enumerate() {
+            let value = json_value(column.get(row)?);
+            if idx > 0 {
+                out.write_all(b",")?;
+            }
+            serde_json::to_writer(&mut *out, column.name())?;
+            out.write_all(b":")?;
+            serde_json::to_writer(&mut *out, &value)?;
+        }
+        out.write_all(b"}\n")?;
+    }
+    Ok(())
+}
+
+/// A DataFrame cell as JSON; types without a JSON counterpart become their display text.
+pub fn json_value(value: AnyValue) -> Value {
+    match value {
+        AnyValue::Null => Value::Null,
+        AnyValue::Boolean(v) => Value::from(v),
+        AnyValue::String(v) => Value::from(v),
+        AnyValue::Int8(v) => Value::from(v),
+        AnyValue::Int16(v) => Value::from(v),
+        AnyValue::Int32(v) => Value::from(v),
+        AnyValue::Int64(v) => Value::from(v),
+        AnyValue::UInt8(v) => Value::from(v),
+        AnyValue::UInt16(v) => Value::from(v),
+        AnyValue::UInt32(v) => Value::from(v),
+        AnyValue::UInt64(v) => Value::from(v),
+        // Non-finite floats have no JSON representation.
+        AnyValue::Float32(v) => {
+            serde_json::Number::from_f64(v as f64).map_or(Value::Null, Value::Number)
+        }
+        AnyValue::Float64(v) => serde_json::Number::from_f64(v).map_or(Value::Null, Value::Number),
+        other => Value::from(other.to_string()),
+    }
+}
+
+/// Streams every row of the Parquet file at `path` through `convert` into `out`, a row group at
+/// a time.
+fn copy_parquet_rows(
+    path: &Path,
+    out: &mut ChunkedFile,
+    convert: impl Fn(DataFrame) -> PolarsResult<DataFrame>,
+) -> Result<(), Box<dyn Error>> {
+    let mut batches = ParquetReader::new(File::open(path)?).batched(COPY_BATCH_ROWS)?;
+    while let Some(chunks) = futures_executor::block_on(batches.next_batches(1))? {
+        for chunk in chunks {
+            out.write(&mut convert(chunk)?)?;
+        }
+    }
+    Ok(())
+}
+
+const COPY_BATCH_ROWS: usize = 50_000;
+
+fn schema_metadata(level: &str) -> Vec<KeyValue> {
+    vec![
+        KeyValue {
+            key: SCHEMA_VERSION_KEY.to_string(),
+            value: Some(SCHEMA_VERSION.to_string()),
+        },
+        KeyValue {
+            key: LEVEL_KEY.to_string(),
+            value: Some(level.to_string()),
+        },
+    ]
+}
+
+/// Schema version and level recorded in a Parquet file. Files written before versioning carry
+/// neither and count as version 0.
+fn stored_schema(path: &Path) -> Result<(u32, Option<String>), Box<dyn Error>> {
+    let mut reader = ParquetReader::new(File::open(path)?);
+    let metadata = reader.get_metadata()?;
+    let lookup = |key: &str| {
+        metadata
+            .key_value_metadata
+            .as_ref()?
+            .iter()
+            .find(|entry| entry.key == key)?
+            .value
+            .clone()
+    };
+    let version = lookup(SCHEMA_VERSION_KEY)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+    Ok((version, lookup(LEVEL_KEY)))
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrateDatasetArgs {
+    /// A Parquet file, or a directory dataset written with `--partition-by`.
+    pub input: PathBuf,
+    /// Where the upgraded dataset goes; `input` is rewritten in place when unset.
+    pub output: Option<PathBuf>,
+    /// Level of files written before the level was recorded in their metadata.
+    pub level: Option<String>,
+    pub parquet: ParquetOptions,
+}
+
+/// Upgrades a dataset extracted by an older version to the current column layout. Columns are
+/// cast to their current types, missing ones are added as nulls and extra ones (challenge
+/// columns) are kept after them.
+pub fn migrate_dataset(args: &MigrateDatasetArgs) -> Result<(), Box<dyn Error>> {
+    let output = args.output.as_deref().unwrap_or(&args.input);
+    let files: Vec<(PathBuf, PathBuf)> = if args.input.is_dir() {
+        collect_files_with_extension(&args.input, "parquet")
+            .into_iter()
+            .map(|path| {
+                let relative = path
+                    .strip_prefix(&args.input)
+                    .expect("collected under the input directory");
+                let target = output.join(relative);
+                (path, target)
+            })
+            .collect()
+    } else {
+        vec![(args.input.clone(), output.to_path_buf())]
+    };
+    if files.is_empty() {
+        return Err(format!("No Parquet files under {}", args.input.display()).into());
+    }
+
+    let mut migrated = 0usize;
+    for (source, target) in &files {
+        let (version, stored_level) = stored_schema(source)?;
+        if version > SCHEMA_VERSION {
+            return Err(format!(
+                "{} has schema version {}, newer than the supported {}",
+                source.display(),
+                version,
+                SCHEMA_VERSION
+            )
+            .into());
+        }
+        if version == SCHEMA_VERSION && source == target {
+            continue;
+        }
+        let level = stored_level.or_else(|| args.level.clone()).ok_or_else(|| {
+            format!(
+                "{} does not record its level; pass --level",
+                source.display()
+            )
+        })?;
+
+        let existing = ParquetReader::new(File::open(source)?)
+            .with_n_rows(Some(0))
+            .finish()?
+            .schema();
+        let positions = POSITION_COLUMNS.iter().any(|name| existing.contains(name));
+        let current = empty_level_frame(&level, positions)?.schema();
+        let schema = migrated_schema(&current, &existing);
+
+        if let Some(parent) = target.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = ChunkedFile::create(
+            target,
+            &schema,
+            OutputFormat::Parquet,
+            args.parquet,
+            &schema_metadata(&level),
+        )?;
+        copy_parquet_rows(source, &mut file, |chunk| conform_to_schema(chunk, &schema))?;
+        let rows = file.finish()?;
+        println!(
+            "Migrated {} ({} rows) from schema version {} to {}",
+            source.display(),
+            rows,
+            version,
+            SCHEMA_VERSION
+        );
+        migrated += 1;
+    }
+
+    if migrated == 0 {
+        println!(
+            "{} is already at schema version {}",
+            args.input.display(),
+            SCHEMA_VERSION
+        );
+    }
+    Ok(())
+}
+
+/// The current columns of a level followed by the columns only `existing` has. Opt-in groups
+/// such as `POSITION_COLUMNS` are part of `current` when `existing` was written with them, so
+/// they keep their place instead of moving to the end.
+fn migrated_schema(current: &Schema, existing: &Schema) -> Schema {
+    let mut schema = current.clone();
+    for (name, dtype) in existing.iter() {
+        if !current.contains(name) {
+            schema.with_column(name.clone(), dtype.clone());
+        }
+    }
+    schema
+}
+
+fn conform_to_schema(chunk: DataFrame, schema: &Schema) -> PolarsResult<DataFrame> {
+    let height = chunk.height();
+    let columns = schema
+        .iter()
+        .map(|(name, dtype)| {
+            if name.as_str() == "schema_version" {
+                return Series::new(name, vec![SCHEMA_VERSION; height]).cast(dtype);
+            }
+            match chunk.column(name) {
+                Ok(column) => column.cast(dtype),
+                Err(_) => Ok(Series::full_null(name, height, dtype)),
+            }
+        })
+        .collect::<PolarsResult<Vec<_>>>()?;
+    DataFrame::new(columns)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Match IDs already present in an extracted dataset.
+fn existing_match_ids(path: &Path) -> Result<HashSet<String>, Box<dyn Error>> {
+    let df = ParquetReader::new(File::open(path)?)
+        .with_columns(Some(vec!["match_id".to_string()]))
+        .finish()?;
+    Ok(df
+        .column("match_id")?
+        .str()?
+        .into_iter()
+        .flatten()
+        .map(str::to_string)
+        .collect())
+}
+
+fn extract_player_rows<'a>(
+    documents: impl Iterator<Item = (&'a Path, MatchDocument)>,
+    seen: &mut SeenMatches,
+    filter: &MatchFilter,
+    challenge_cols: Option<&ChallengeColumns>,
+    fields: &[FieldSpec],
+    timelines: Option<&TimelineOptions>,
+    skips: &mut SkipReport,
+) -> Vec<PlayerRow> {
+    let mut rows: Vec<PlayerRow> = Vec::new();
+
+    for (path, document) in documents {
+        if document.metadata.is_none() {
+            skips.missing_section(path, "metadata");
+            continue;
+        }
+
+        let Some(info) = &document.info else {
+            skips.missing_section(path, "info section");
             continue;
         };
 
-        let Some(info) = parsed.get("info") else {
-            eprintln!("Missing info section in {}", path.display());
+        let Some(participants) = &info.participants else {
+            skips.missing_section(path, "participants array");
             continue;
         };
 
-        let Some(participants) = info.get("participants").and_then(|p| p.as_array()) else {
-            eprintln!("Missing participants array in {}", path.display());
+        let Some(match_id) = document.match_id().or_else(|| file_match_id(path)) else {
+            skips.missing_section(path, "match ID");
             continue;
         };
 
-        let Some(match_id) = metadata
-            .get("matchId")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
-            .or_else(|| {
-                path.file_stem()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.to_string())
-            })
-        else {
-            continue;
-        };
-
-        let game_creation = info
-            .get("gameCreation")
-            .and_then(|v| v.as_i64())
-            .unwrap_or_default();
-        let game_duration = info
-            .get("gameDuration")
-            .and_then(|v| v.as_i64())
-            .unwrap_or_default() as i32;
-        let queue_id = info
-            .get("queueId")
-            .and_then(|v| v.as_i64())
-            .unwrap_or_default() as i32;
-        let game_version = info
-            .get("gameVersion")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
+        if !seen.first_sight(path, match_id, skips) {
+            continue;
+        }
+
+        let game_duration = info.game_duration as i32;
+        let queue_id = info.queue_id as i32;
+
+        if let Some((reason, detail)) = filter.rejection(info) {
+            skips.record(reason, path, Some(match_id), Some(detail));
+            continue;
+        }
+
+        // Arena has no lanes or two-team result; --level arena handles it.
+        if is_arena_queue(queue_id) {
+            skips.record(SkipReason::Arena, path, Some(match_id), None);
+            continue;
+        }
 
+        // Team kills and champion damage, for the share-of-team fallbacks below.
+        let mut team_totals: HashMap<i64, (i64, i64)> = HashMap::new();
         for participant in participants {
-            let team_id = participant
-                .get("teamId")
-                .and_then(|v| v.as_i64())
-                .unwrap_or_default() as i32;
-            let puuid = participant
-                .get("puuid")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-            let champion_id = participant
-                .get("championId")
-                .and_then(|v| v.as_i64())
-                .unwrap_or_default() as i32;
-            let champion_name = participant
-                .get("championName")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-            let role = participant
-                .get("teamPosition")
-                .and_then(|v| v.as_str())
-                .filter(|s| !s.is_empty())
-                .map(|s| s.to_string())
-                .or_else(|| {
-                    participant
-                        .get("individualPosition")
-                        .and_then(|v| v.as_str())
-                        .map(|s| s.to_string())
-                })
+            let totals = team_totals.entry(participant.team_id).or_default();
+            totals.0 += participant.kills as i64;
+            totals.1 += participant.total_damage_dealt_to_champions as i64;
+        }
+
+        let timeline = timelines.and_then(|options| load_timeline(options.dir, match_id));
+        let positions = timelines.is_some_and(|options| options.positions);
+        let lane_frames = LANE_DIFF_MINUTES.map(|minute| {
+            timeline
+                .as_ref()
+                .and_then(|timeline| timeline.frame_at(minute))
+        });
+
+        let raw_participants = document
+            .raw
+            .as_ref()
+            .and_then(|raw| raw["info"]["participants"].as_array());
+
+        for (idx, participant) in participants.iter().enumerate() {
+            let raw_participant = raw_participants.and_then(|raw| raw.get(idx));
+            let extra_fields = fields
+                .iter()
+                .map(|field| field.lookup(raw_participant))
+                .collect();
+            let opponent = lane_opponent(participants, participant);
+            let lane_diffs = lane_frames.map(|frame| lane_diff(frame?, participant, opponent?));
+            let cs_at =
+                lane_frames.map(|frame| Some(frame?.participant(participant.participant_id)?.cs()));
+            let item_build = timeline
+                .as_ref()
+                .map(|timeline| item_build(timeline, participant.participant_id));
+            let position_features = match &timeline {
+                Some(timeline) if positions => position_features(timeline, participant),
+                _ => PositionFeatures::default(),
+            };
+            let extra_challenges = match (challenge_cols, &participant.challenges) {
+                (Some(selected), Some(all)) => all
+                    .iter()
+                    .filter(|(key, _)| selected.includes(key))
+                    .map(|(key, value)| (key.clone(), *value))
+                    .collect(),
+                _ => HashMap::new(),
+            };
+
+            // Older matches and some modes have no `challenges`; derive what the raw totals
+            // allow instead of leaving the metric null.
+            let (team_kills, team_damage) = team_totals
+                .get(&participant.team_id)
+                .copied()
                 .unwrap_or_default();
-            let win = participant
-                .get("win")
-                .and_then(|v| v.as_bool())
-                .unwrap_or(false);
-            let kills = as_i32(participant.get("kills"));
-            let deaths = as_i32(participant.get("deaths"));
-            let assists = as_i32(participant.get("assists"));
-            let champ_level = as_i32(participant.get("champLevel"));
-            let gold_earned = as_i32(participant.get("goldEarned"));
-            let gold_spent = as_i32(participant.get("goldSpent"));
-            let total_minions_killed = as_i32(participant.get("totalMinionsKilled"));
-            let neutral_minions_killed = as_i32(participant.get("neutralMinionsKilled"));
-            let total_cs = total_minions_killed + neutral_minions_killed;
-            let damage_to_champions = as_i32(participant.get("totalDamageDealtToChampions"));
-            let damage_to_objectives = as_i32(participant.get("damageDealtToObjectives"));
-            let damage_to_turrets = as_i32(participant.get("damageDealtToTurrets"));
-            let turret_takedowns = as_i32(participant.get("turretTakedowns"));
-            let inhibitor_takedowns = as_i32(participant.get("inhibitorTakedowns"));
-            let vision_score = as_i32(participant.get("visionScore"));
-            let wards_placed = as_i32(participant.get("wardsPlaced"));
-            let wards_killed = as_i32(participant.get("wardsKilled"));
-            let control_wards_placed = as_i32(participant.get("visionWardsBoughtInGame"));
-
-            let challenges = participant.get("challenges");
+            let takedowns = (participant.kills + participant.assists) as i64;
+            let mut challenges_derived = false;
+            let mut challenge_or = |key: &str, fallback: Option<f64>| {
+                participant.challenge(key).or_else(|| {
+                    challenges_derived |= fallback.is_some();
+                    fallback
+                })
+            };
+            let damage_per_min = challenge_or(
+                "damagePerMinute",
+                per_min(
+                    participant.total_damage_dealt_to_champions as i64,
+                    game_duration,
+                ),
+            );
+            let gold_per_min = challenge_or(
+                "goldPerMinute",
+                per_min(participant.gold_earned as i64, game_duration),
+            );
+            let team_damage_percentage = challenge_or(
+                "teamDamagePercentage",
+                share(
+                    participant.total_damage_dealt_to_champions as i64,
+                    team_damage,
+                ),
+            );
+            let kill_participation =
+                challenge_or("killParticipation", share(takedowns, team_kills));
+            let kda = challenge_or(
+                "kda",
+                Some(takedowns as f64 / participant.deaths.max(1) as f64),
+            );
+            let vision_score_per_min = challenge_or(
+                "visionScorePerMinute",
+                per_min(participant.vision_score as i64, game_duration),
+            );
 
             let row = PlayerRow {
-                match_id: match_id.clone(),
-                game_creation,
+                match_id: match_id.to_string(),
+                game_creation: info.game_creation,
                 game_duration,
                 queue_id,
-                game_version: game_version.clone(),
-                team_id,
-                puuid,
-                champion_id,
-                champion_name,
-                role,
-                win,
-                kills,
-                deaths,
-                assists,
-                champ_level,
-                gold_earned,
-                gold_spent,
-                total_minions_killed,
-                neutral_minions_killed,
-                total_cs,
-                damage_to_champions,
-                damage_to_objectives,
-                damage_to_turrets,
-                turret_takedowns,
-                inhibitor_takedowns,
-                vision_score,
-                wards_placed,
-                wards_killed,
-                control_wards_placed,
-                damage_per_min: as_f64(challenges, "damagePerMinute"),
-                gold_per_min: as_f64(challenges, "goldPerMinute"),
-                team_damage_percentage: as_f64(challenges, "teamDamagePercentage"),
-                kill_participation: as_f64(challenges, "killParticipation"),
-                kda: as_f64(challenges, "kda"),
-                vision_score_per_min: as_f64(challenges, "visionScorePerMinute"),
-                lane_minions_first10: as_f64(challenges, "laneMinionsFirst10Minutes"),
-                jungle_cs_before10: as_f64(challenges, "jungleCsBefore10Minutes"),
+                game_version: info.game_version.clone(),
+                team_id: participant.team_id as i32,
+                puuid: participant.puuid.clone(),
+                riot_id_game_name: non_empty(&participant.riot_id_game_name),
+                riot_id_tagline: non_empty(&participant.riot_id_tagline),
+                summoner_name: non_empty(&participant.summoner_name),
+                champion_id: participant.champion_id,
+                champion_name: participant.champion_name.clone(),
+                role: participant.role().to_string(),
+                win: participant.win,
+                game_ended_in_surrender: participant.game_ended_in_surrender,
+                game_ended_in_early_surrender: participant.game_ended_in_early_surrender,
+                kills: participant.kills,
+                deaths: participant.deaths,
+                assists: participant.assists,
+                champ_level: participant.champ_level,
+                gold_earned: participant.gold_earned,
+                gold_spent: participant.gold_spent,
+                total_minions_killed: participant.total_minions_killed,
+                neutral_minions_killed: participant.neutral_minions_killed,
+                total_cs: participant.total_cs(),
+                damage_to_champions: participant.total_damage_dealt_to_champions,
+                damage_to_objectives: participant.damage_dealt_to_objectives,
+                damage_to_turrets: participant.damage_dealt_to_turrets,
+                physical_damage_to_champions: participant.physical_damage_dealt_to_champions,
+                magic_damage_to_champions: participant.magic_damage_dealt_to_champions,
+                true_damage_to_champions: participant.true_damage_dealt_to_champions,
+                damage_taken: participant.total_damage_taken,
+                physical_damage_taken: participant.physical_damage_taken,
+                magic_damage_taken: participant.magic_damage_taken,
+                true_damage_taken: participant.true_damage_taken,
+                damage_self_mitigated: participant.damage_self_mitigated,
+                total_heal: participant.total_heal,
+                heals_on_teammates: participant.total_heals_on_teammates,
+                shields_on_teammates: participant.total_damage_shielded_on_teammates,
+                turret_takedowns: participant.turret_takedowns,
+                inhibitor_takedowns: participant.inhibitor_takedowns,
+                objectives_stolen: participant.objectives_stolen,
+                objectives_stolen_assists: participant.objectives_stolen_assists,
+                epic_monster_steals: participant
+                    .challenge("epicMonsterSteals")
+                    .map(|steals| steals as i32),
+                first_blood_kill: participant.first_blood_kill,
+                first_blood_assist: participant.first_blood_assist,
+                first_tower_kill: participant.first_tower_kill,
+                first_tower_assist: participant.first_tower_assist,
+                vision_score: participant.vision_score,
+                wards_placed: participant.wards_placed,
+                wards_killed: participant.wards_killed,
+                control_wards_placed: participant.vision_wards_bought_in_game,
+                item0: participant.item0,
+                item1: participant.item1,
+                item2: participant.item2,
+                item3: participant.item3,
+                item4: participant.item4,
+                item5: participant.item5,
+                item6: participant.item6,
+                runes: rune_page(participant.perks.as_ref()),
+                pings: participant.pings(),
+                damage_per_min,
+                gold_per_min,
+                team_damage_percentage,
+                kill_participation,
+                kda,
+                vision_score_per_min,
+                lane_minions_first10: participant.challenge("laneMinionsFirst10Minutes"),
+                jungle_cs_before10: participant.challenge("jungleCsBefore10Minutes"),
+                challenges_derived,
+                lane_diffs,
+                cs_at,
+                item_build,
+                positions: position_features,
+                extra_challenges,
+                extra_fields,
             };
 
-            rows.push(row);
-        }
-    }
+            rows.push(row);
+        }
+    }
+
+    rows
+}
+
+/// Where player rows read timelines from, and which optional timeline features they add.
+struct TimelineOptions<'a> {
+    dir: &'a Path,
+    positions: bool,
+}
+
+/// Where a player spent the game, from the position sampled in every timeline frame.
+#[derive(Clone, Copy, Default)]
+struct PositionFeatures {
+    /// Shares of the frames after the first spent in each half of the jungle.
+    own_jungle_share: Option<f64>,
+    enemy_jungle_share: Option<f64>,
+    /// Share of the frames up to minute 10 spent in the river.
+    river_share_before10: Option<f64>,
+    /// Mean distance to the center of the player's lane over minutes 1 to 14; `None` for
+    /// junglers.
+    avg_lane_distance: Option<f64>,
+}
+
+fn position_features(timeline: &MatchTimeline, player: &Participant) -> PositionFeatures {
+    // (timestamp, position) per frame; the first frame is taken in the fountain.
+    let samples: Vec<_> = timeline
+        .frames()
+        .iter()
+        .filter(|frame| frame.timestamp > 0)
+        .filter_map(|frame| {
+            Some((
+                frame.timestamp,
+                frame.participant(player.participant_id)?.position?,
+            ))
+        })
+        .collect();
+    let share = |until_ms: i64, wanted: Zone| {
+        let zones: Vec<Zone> = samples
+            .iter()
+            .filter(|(timestamp, _)| *timestamp <= until_ms)
+            .map(|(_, position)| position.zone())
+            .collect();
+        (!zones.is_empty()).then(|| {
+            zones.iter().filter(|zone| **zone == wanted).count() as f64 / zones.len() as f64
+        })
+    };
+    let lane_distances: Vec<f64> = samples
+        .iter()
+        .filter(|(timestamp, _)| *timestamp <= 14 * 60_000)
+        .filter_map(|(_, position)| position.lane_distance(player.role()))
+        .collect();
+    let blue = player.team_id == 100;
+
+    PositionFeatures {
+        own_jungle_share: share(i64::MAX, Zone::Jungle { blue_side: blue }),
+        enemy_jungle_share: share(i64::MAX, Zone::Jungle { blue_side: !blue }),
+        river_share_before10: share(10 * 60_000, Zone::River),
+        avg_lane_distance: (!lane_distances.is_empty())
+            .then(|| lane_distances.iter().sum::<f64>() / lane_distances.len() as f64),
+    }
+}
+
+/// Item IDs a player bought and sold, in the order they did, with undone ones removed.
+#[derive(Default)]
+struct ItemBuild {
+    purchased: Vec<i32>,
+    sold: Vec<i32>,
+}
+
+fn item_build(timeline: &MatchTimeline, participant_id: i32) -> ItemBuild {
+    let mut build = ItemBuild::default();
+    let events = timeline.frames().iter().flat_map(|frame| &frame.events);
+    for event in events.filter(|event| event.participant_id == Some(participant_id)) {
+        match (event.kind.as_str(), event.item_id) {
+            ("ITEM_PURCHASED", Some(item)) => build.purchased.push(item),
+            ("ITEM_SOLD", Some(item)) => build.sold.push(item),
+            ("ITEM_UNDO", _) => {
+                let undone = [
+                    (&mut build.purchased, event.before_id),
+                    (&mut build.sold, event.after_id),
+                ];
+                for (items, item) in undone {
+                    if let Some(item) = item.filter(|item| *item > 0)
+                        && let Some(idx) = items.iter().rposition(|bought| *bought == item)
+                    {
+                        items.remove(idx);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    build
+}
+
+fn join_ids(ids: &[i32]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Minutes at which the gold, XP and CS differences to the lane opponent, and the creep score,
+/// are taken.
+const LANE_DIFF_MINUTES: [i64; 2] = [10, 15];
+
+#[derive(Clone, Copy)]
+struct LaneDiff {
+    gold: i32,
+    xp: i32,
+    cs: i32,
+}
 
-    let mut df = build_dataframe(rows)?;
-    let mut file = File::create(out_parquet)?;
-    ParquetWriter::new(&mut file).finish(&mut df)?;
+/// The participant in the same standard position on the other team.
+pub fn lane_opponent<'a>(
+    participants: &'a [Participant],
+    player: &Participant,
+) -> Option<&'a Participant> {
+    let role = player.role();
+    if !["TOP", "JUNGLE", "MIDDLE", "BOTTOM", "UTILITY"].contains(&role) {
+        return None;
+    }
+    participants
+        .iter()
+        .find(|other| other.team_id != player.team_id && other.role() == role)
+}
 
-    Ok(())
+fn lane_diff(frame: &Frame, player: &Participant, opponent: &Participant) -> Option<LaneDiff> {
+    let ours = frame.participant(player.participant_id)?;
+    let theirs = frame.participant(opponent.participant_id)?;
+    Some(LaneDiff {
+        gold: ours.total_gold - theirs.total_gold,
+        xp: ours.xp - theirs.xp,
+        cs: ours.cs() - theirs.cs(),
+    })
 }
 
+const BAN_SLOTS: usize = 5;
+
 #[derive(Default)]
 struct TeamRow {
     match_id: String,
@@ -247,6 +2183,9 @@ struct TeamRow {
     game_version: String,
     game_creation: i64,
     game_duration: i32,
+    /// Riot flags every participant of a surrendered game, so both teams share these.
+    game_ended_in_surrender: bool,
+    game_ended_in_early_surrender: bool,
     team_id: i16,
     team_side: String,
     team_win: i32,
@@ -255,6 +2194,9 @@ struct TeamRow {
     middle_champion_id: Option<i32>,
     bottom_champion_id: Option<i32>,
     utility_champion_id: Option<i32>,
+    /// Banned champions in pick-turn order; `None` for skipped or missing bans.
+    ban_champion_ids: [Option<i32>; BAN_SLOTS],
+    ban_pick_turns: [Option<i32>; BAN_SLOTS],
     team_kills: i32,
     team_deaths: i32,
     team_assists: i32,
@@ -272,206 +2214,790 @@ struct TeamRow {
     team_barons: i32,
     team_heralds: i32,
     team_plates: Option<i32>,
+    team_objectives_stolen: i32,
+    /// From the members' `teamElderDragonKills` challenge; `None` without `challenges`.
+    team_elder_dragons: Option<i32>,
+    /// Derived: at least four elemental dragons (dragons minus elders) grant the soul.
+    team_dragon_soul: Option<bool>,
     first_blood: Option<bool>,
     first_tower: Option<bool>,
     first_inhibitor: Option<bool>,
     first_baron: Option<bool>,
     first_dragon: Option<bool>,
     first_herald: Option<bool>,
+    /// Values of the column spec's team fields, in spec order.
+    extra_fields: Vec<Value>,
+}
+
+fn extract_team_rows<'a>(
+    documents: impl Iterator<Item = (&'a Path, MatchDocument)>,
+    seen: &mut SeenMatches,
+    filter: &MatchFilter,
+    fields: &[FieldSpec],
+    skips: &mut SkipReport,
+) -> Vec<TeamRow> {
+    let mut rows: Vec<TeamRow> = Vec::new();
+
+    for (path, document) in documents {
+        let Some(metadata) = &document.metadata else {
+            skips.missing_section(path, "metadata");
+            continue;
+        };
+
+        let Some(info) = &document.info else {
+            skips.missing_section(path, "info section");
+            continue;
+        };
+
+        let Some(participants) = &info.participants else {
+            skips.missing_section(path, "participants array");
+            continue;
+        };
+
+        let Some(teams) = &info.teams else {
+            skips.missing_section(path, "teams array");
+            continue;
+        };
+
+        let Some(match_id) = document.match_id().or_else(|| file_match_id(path)) else {
+            skips.missing_section(path, "match ID");
+            continue;
+        };
+
+        if !seen.first_sight(path, match_id, skips) {
+            continue;
+        }
+
+        let platform_id = metadata
+            .platform_id
+            .clone()
+            .or_else(|| info.platform_id.clone());
+        let game_duration = info.game_duration as i32;
+        let queue_id = info.queue_id as i32;
+
+        if let Some((reason, detail)) = filter.rejection(info) {
+            skips.record(reason, path, Some(match_id), Some(detail));
+            continue;
+        }
+
+        // Arena has no lanes or two-team result; --level arena handles it.
+        if is_arena_queue(queue_id) {
+            skips.record(SkipReason::Arena, path, Some(match_id), None);
+            continue;
+        }
+
+        let raw_teams = document
+            .raw
+            .as_ref()
+            .and_then(|raw| raw["info"]["teams"].as_array());
+
+        for (idx, team) in teams.iter().enumerate() {
+            let Some(team_id) = team.team_id else {
+                continue;
+            };
+
+            let team_participants: Vec<&Participant> = participants
+                .iter()
+                .filter(|p| p.team_id == team_id)
+                .collect();
+
+            let team_kills: i32 = team_participants.iter().map(|p| p.kills).sum();
+            let team_deaths: i32 = team_participants.iter().map(|p| p.deaths).sum();
+            let team_assists: i32 = team_participants.iter().map(|p| p.assists).sum();
+            let team_gold_earned: i64 =
+                team_participants.iter().map(|p| p.gold_earned as i64).sum();
+            let team_damage_to_champions: i64 = team_participants
+                .iter()
+                .map(|p| p.total_damage_dealt_to_champions as i64)
+                .sum();
+            let team_vision_score: i64 = team_participants
+                .iter()
+                .map(|p| p.vision_score as i64)
+                .sum();
+            let team_cs_total: i32 = team_participants.iter().map(|p| p.total_cs()).sum();
+            let team_objectives_stolen: i32 =
+                team_participants.iter().map(|p| p.objectives_stolen).sum();
+            let team_elder_dragons = team_participants
+                .iter()
+                .filter_map(|p| p.challenge("teamElderDragonKills"))
+                .reduce(f64::max)
+                .map(|elders| elders as i32);
+
+            let (
+                team_towers_destroyed,
+                team_inhibitors_destroyed,
+                team_dragons,
+                team_barons,
+                team_heralds,
+                team_plates,
+                first_blood,
+                first_tower,
+                first_inhibitor,
+                first_baron,
+                first_dragon,
+                first_herald,
+            ) = team_objectives(team);
+            let (ban_champion_ids, ban_pick_turns) = team_bans(team);
+
+            let row = TeamRow {
+                match_id: match_id.to_string(),
+                platform_id: platform_id.clone(),
+                queue_id,
+                game_version: info.game_version.clone(),
+                game_creation: info.game_creation,
+                game_duration,
+                game_ended_in_surrender: team_participants
+                    .iter()
+                    .any(|p| p.game_ended_in_surrender),
+                game_ended_in_early_surrender: team_participants
+                    .iter()
+                    .any(|p| p.game_ended_in_early_surrender),
+                team_id: team_id as i16,
+                team_side: if team_id == 100 { "blue" } else { "red" }.to_string(),
+                // Riot's raw teams[].win is a boolean; store it as a numeric flag for aggregation.
+                team_win: if team.win { 1 } else { 0 },
+                top_champion_id: find_role_champion(&team_participants, "TOP"),
+                jungle_champion_id: find_role_champion(&team_participants, "JUNGLE"),
+                middle_champion_id: find_role_champion(&team_participants, "MIDDLE"),
+                bottom_champion_id: find_role_champion(&team_participants, "BOTTOM"),
+                utility_champion_id: find_role_champion(&team_participants, "UTILITY"),
+                ban_champion_ids,
+                ban_pick_turns,
+                team_kills,
+                team_deaths,
+                team_assists,
+                team_gold_earned,
+                team_damage_to_champions,
+                team_vision_score,
+                team_cs_total,
+                team_gold_per_min: per_min(team_gold_earned, game_duration),
+                team_damage_per_min: per_min(team_damage_to_champions, game_duration),
+                team_vision_score_per_min: per_min(team_vision_score, game_duration),
+                team_cs_per_min: per_min(team_cs_total as i64, game_duration),
+                team_towers_destroyed,
+                team_inhibitors_destroyed,
+                team_dragons,
+                team_barons,
+                team_heralds,
+                team_plates,
+                team_objectives_stolen,
+                team_elder_dragons,
+                team_dragon_soul: team_elder_dragons.map(|elders| team_dragons - elders >= 4),
+                first_blood,
+                first_tower,
+                first_inhibitor,
+                first_baron,
+                first_dragon,
+                first_herald,
+                extra_fields: fields
+                    .iter()
+                    .map(|field| field.lookup(raw_teams.and_then(|raw| raw.get(idx))))
+                    .collect(),
+            };
+
+            rows.push(row);
+        }
+    }
+
+    rows
+}
+
+/// Arena (Cherry) queues: eight duos ranked by placement instead of two teams.
+const ARENA_QUEUES: [i32; 2] = [1700, 1710];
+
+fn is_arena_queue(queue_id: i32) -> bool {
+    ARENA_QUEUES.contains(&queue_id)
+}
+
+struct ArenaRow {
+    match_id: String,
+    game_creation: i64,
+    game_duration: i32,
+    queue_id: i32,
+    game_version: String,
+    puuid: String,
+    champion_id: i32,
+    champion_name: String,
+    subteam_id: Option<i32>,
+    /// 1 (first) to 8 (last); shared by both members of a duo.
+    placement: Option<i32>,
+    win: bool,
+    kills: i32,
+    deaths: i32,
+    assists: i32,
+    gold_earned: i32,
+    damage_to_champions: i32,
+    damage_taken: i32,
+    /// Chosen augment IDs in pick order; `None` for unused slots.
+    augments: [Option<i32>; ARENA_AUGMENT_SLOTS],
+}
+
+const ARENA_AUGMENT_SLOTS: usize = 6;
+
+fn extract_arena_rows<'a>(
+    documents: impl Iterator<Item = (&'a Path, MatchDocument)>,
+    seen: &mut SeenMatches,
+    filter: &MatchFilter,
+    skips: &mut SkipReport,
+) -> Vec<ArenaRow> {
+    let mut rows: Vec<ArenaRow> = Vec::new();
+
+    for (path, document) in documents {
+        let Some(info) = &document.info else {
+            skips.missing_section(path, "info section");
+            continue;
+        };
+
+        let queue_id = info.queue_id as i32;
+        if !is_arena_queue(queue_id) {
+            skips.record(SkipReason::NotArena, path, document.match_id(), None);
+            continue;
+        }
+
+        let Some(participants) = &info.participants else {
+            skips.missing_section(path, "participants array");
+            continue;
+        };
+
+        let Some(match_id) = document.match_id().or_else(|| file_match_id(path)) else {
+            skips.missing_section(path, "match ID");
+            continue;
+        };
+
+        if !seen.first_sight(path, match_id, skips) {
+            continue;
+        }
+
+        if let Some((reason, detail)) = filter.rejection(info) {
+            skips.record(reason, path, Some(match_id), Some(detail));
+            continue;
+        }
+
+        for participant in participants {
+            // Riot reports unused slots and missing placements as 0.
+            let positive = |value: Option<i32>| value.filter(|v| *v > 0);
+
+            rows.push(ArenaRow {
+                match_id: match_id.to_string(),
+                game_creation: info.game_creation,
+                game_duration: info.game_duration as i32,
+                queue_id,
+                game_version: info.game_version.clone(),
+                puuid: participant.puuid.clone(),
+                champion_id: participant.champion_id,
+                champion_name: participant.champion_name.clone(),
+                subteam_id: positive(participant.player_subteam_id),
+                placement: positive(participant.subteam_placement)
+                    .or_else(|| positive(participant.placement)),
+                win: participant.win,
+                kills: participant.kills,
+                deaths: participant.deaths,
+                assists: participant.assists,
+                gold_earned: participant.gold_earned,
+                damage_to_champions: participant.total_damage_dealt_to_champions,
+                damage_taken: participant.total_damage_taken,
+                augments: participant.augments().map(positive),
+            });
+        }
+    }
+
+    rows
+}
+
+fn build_arena_dataframe(rows: Vec<ArenaRow>) -> Result<DataFrame, PolarsError> {
+    let mut match_id: Vec<String> = Vec::new();
+    let mut game_creation: Vec<i64> = Vec::new();
+    let mut game_duration: Vec<i32> = Vec::new();
+    let mut queue_id: Vec<i32> = Vec::new();
+    let mut game_version: Vec<String> = Vec::new();
+    let mut puuid: Vec<String> = Vec::new();
+    let mut champion_id: Vec<i32> = Vec::new();
+    let mut champion_name: Vec<String> = Vec::new();
+    let mut subteam_id: Vec<Option<i32>> = Vec::new();
+    let mut placement: Vec<Option<i32>> = Vec::new();
+    let mut win: Vec<bool> = Vec::new();
+    let mut kills: Vec<i32> = Vec::new();
+    let mut deaths: Vec<i32> = Vec::new();
+    let mut assists: Vec<i32> = Vec::new();
+    let mut gold_earned: Vec<i32> = Vec::new();
+    let mut damage_to_champions: Vec<i32> = Vec::new();
+    let mut damage_taken: Vec<i32> = Vec::new();
+    let mut augments: [Vec<Option<i32>>; ARENA_AUGMENT_SLOTS] = Default::default();
+
+    for row in rows {
+        match_id.push(row.match_id);
+        game_creation.push(row.game_creation);
+        game_duration.push(row.game_duration);
+        queue_id.push(row.queue_id);
+        game_version.push(row.game_version);
+        puuid.push(row.puuid);
+        champion_id.push(row.champion_id);
+        champion_name.push(row.champion_name);
+        subteam_id.push(row.subteam_id);
+        placement.push(row.placement);
+        win.push(row.win);
+        kills.push(row.kills);
+        deaths.push(row.deaths);
+        assists.push(row.assists);
+        gold_earned.push(row.gold_earned);
+        damage_to_champions.push(row.damage_to_champions);
+        damage_taken.push(row.damage_taken);
+        for (slot, augment) in row.augments.into_iter().enumerate() {
+            augments[slot].push(augment);
+        }
+    }
+
+    let mut columns = vec![
+        Series::new("match_id", match_id),
+        Series::new("game_creation", game_creation),
+        Series::new("game_duration", game_duration),
+        Series::new("queue_id", queue_id),
+        Series::new("game_version", game_version),
+        Series::new("puuid", puuid),
+        Series::new("champion_id", champion_id),
+        Series::new("champion_name", champion_name),
+        Series::new("subteam_id", subteam_id),
+        Series::new("placement", placement),
+        Series::new("win", win),
+        Series::new("kills", kills),
+        Series::new("deaths", deaths),
+        Series::new("assists", assists),
+        Series::new("gold_earned", gold_earned),
+        Series::new("damage_to_champions", damage_to_champions),
+        Series::new("damage_taken", damage_taken),
+    ];
+    for (slot, values) in augments.into_iter().enumerate() {
+        columns.push(Series::new(&format!("augment{}", slot + 1), values));
+    }
+
+    DataFrame::new(columns)
+}
+
+/// One timeline event of one match.
+struct EventRow {
+    match_id: String,
+    game_creation: i64,
+    queue_id: i32,
+    game_version: String,
+    /// Position of the event in the timeline, from 0; keeps same-timestamp events in order.
+    event_index: i32,
+    /// Milliseconds since the game started.
+    timestamp: i64,
+    event_type: String,
+    participant_id: Option<i32>,
+    puuid: Option<String>,
+    victim_id: Option<i32>,
+    victim_puuid: Option<String>,
+    /// Comma-separated participant IDs, so the column also fits CSV.
+    assisting_participant_ids: Option<String>,
+    team_id: Option<i32>,
+    killer_team_id: Option<i32>,
+    x: Option<i32>,
+    y: Option<i32>,
+    monster_type: Option<String>,
+    monster_sub_type: Option<String>,
+    building_type: Option<String>,
+    lane_type: Option<String>,
+    tower_type: Option<String>,
+    ward_type: Option<String>,
+    item_id: Option<i32>,
+    skill_slot: Option<i32>,
+    kill_type: Option<String>,
+    bounty: Option<i32>,
+    shutdown_bounty: Option<i32>,
+}
+
+fn extract_event_rows<'a>(
+    documents: impl Iterator<Item = (&'a Path, MatchDocument)>,
+    seen: &mut SeenMatches,
+    filter: &MatchFilter,
+    timelines_dir: &Path,
+    skips: &mut SkipReport,
+) -> Vec<EventRow> {
+    let mut rows: Vec<EventRow> = Vec::new();
+
+    for (path, document) in documents {
+        let Some(info) = &document.info else {
+            skips.missing_section(path, "info section");
+            continue;
+        };
+
+        let Some(match_id) = document.match_id().or_else(|| file_match_id(path)) else {
+            skips.missing_section(path, "match ID");
+            continue;
+        };
+
+        if !seen.first_sight(path, match_id, skips) {
+            continue;
+        }
+
+        if let Some((reason, detail)) = filter.rejection(info) {
+            skips.record(reason, path, Some(match_id), Some(detail));
+            continue;
+        }
+
+        let Some(timeline) = load_timeline(timelines_dir, match_id) else {
+            skips.record(SkipReason::MissingTimeline, path, Some(match_id), None);
+            continue;
+        };
+
+        let puuids: HashMap<i32, &str> = info
+            .participants
+            .iter()
+            .flatten()
+            .map(|participant| (participant.participant_id, participant.puuid.as_str()))
+            .collect();
+        let puuid = |id: Option<i32>| id.and_then(|id| puuids.get(&id)).map(|p| p.to_string());
+
+        let events = timeline.frames().iter().flat_map(|frame| &frame.events);
+        for (event_index, event) in events.enumerate() {
+            let actor = event.actor();
+            let victim = event.victim_id.filter(|id| *id > 0);
+            rows.push(EventRow {
+                match_id: match_id.to_string(),
+                game_creation: info.game_creation,
+                queue_id: info.queue_id as i32,
+                game_version: info.game_version.clone(),
+                event_index: event_index as i32,
+                timestamp: event.timestamp,
+                event_type: event.kind.clone(),
+                participant_id: actor,
+                puuid: puuid(actor),
+                victim_id: victim,
+                victim_puuid: puuid(victim),
+                assisting_participant_ids: (!event.assisting_participant_ids.is_empty())
+                    .then(|| join_ids(&event.assisting_participant_ids)),
+                team_id: event.team_id,
+                killer_team_id: event.killer_team_id,
+                x: event.position.map(|position| position.x),
+                y: event.position.map(|position| position.y),
+                monster_type: event.monster_type.clone(),
+                monster_sub_type: event.monster_sub_type.clone(),
+                building_type: event.building_type.clone(),
+                lane_type: event.lane_type.clone(),
+                tower_type: event.tower_type.clone(),
+                ward_type: event.ward_type.clone(),
+                item_id: event.item_id,
+                skill_slot: event.skill_slot,
+                kill_type: event.kill_type.clone(),
+                bounty: event.bounty,
+                shutdown_bounty: event.shutdown_bounty,
+            });
+        }
+    }
+
+    rows
+}
+
+fn build_event_dataframe(rows: Vec<EventRow>) -> Result<DataFrame, PolarsError> {
+    let mut match_id: Vec<String> = Vec::new();
+    let mut game_creation: Vec<i64> = Vec::new();
+    let mut queue_id: Vec<i32> = Vec::new();
+    let mut game_version: Vec<String> = Vec::new();
+    let mut event_index: Vec<i32> = Vec::new();
+    let mut timestamp: Vec<i64> = Vec::new();
+    let mut event_type: Vec<String> = Vec::new();
+    let mut participant_id: Vec<Option<i32>> = Vec::new();
+    let mut puuid: Vec<Option<String>> = Vec::new();
+    let mut victim_id: Vec<Option<i32>> = Vec::new();
+    let mut victim_puuid: Vec<Option<String>> = Vec::new();
+    let mut assisting_participant_ids: Vec<Option<String>> = Vec::new();
+    let mut team_id: Vec<Option<i32>> = Vec::new();
+    let mut killer_team_id: Vec<Option<i32>> = Vec::new();
+    let mut x: Vec<Option<i32>> = Vec::new();
+    let mut y: Vec<Option<i32>> = Vec::new();
+    let mut monster_type: Vec<Option<String>> = Vec::new();
+    let mut monster_sub_type: Vec<Option<String>> = Vec::new();
+    let mut building_type: Vec<Option<String>> = Vec::new();
+    let mut lane_type: Vec<Option<String>> = Vec::new();
+    let mut tower_type: Vec<Option<String>> = Vec::new();
+    let mut ward_type: Vec<Option<String>> = Vec::new();
+    let mut item_id: Vec<Option<i32>> = Vec::new();
+    let mut skill_slot: Vec<Option<i32>> = Vec::new();
+    let mut kill_type: Vec<Option<String>> = Vec::new();
+    let mut bounty: Vec<Option<i32>> = Vec::new();
+    let mut shutdown_bounty: Vec<Option<i32>> = Vec::new();
+
+    for row in rows {
+        match_id.push(row.match_id);
+        game_creation.push(row.game_creation);
+        queue_id.push(row.queue_id);
+        game_version.push(row.game_version);
+        event_index.push(row.event_index);
+        timestamp.push(row.timestamp);
+        event_type.push(row.event_type);
+        participant_id.push(row.participant_id);
+        puuid.push(row.puuid);
+        victim_id.push(row.victim_id);
+        victim_puuid.push(row.victim_puuid);
+        assisting_participant_ids.push(row.assisting_participant_ids);
+        team_id.push(row.team_id);
+        killer_team_id.push(row.killer_team_id);
+        x.push(row.x);
+        y.push(row.y);
+        monster_type.push(row.monster_type);
+        monster_sub_type.push(row.monster_sub_type);
+        building_type.push(row.building_type);
+        lane_type.push(row.lane_type);
+        tower_type.push(row.tower_type);
+        ward_type.push(row.ward_type);
+        item_id.push(row.item_id);
+        skill_slot.push(row.skill_slot);
+        kill_type.push(row.kill_type);
+        bounty.push(row.bounty);
+        shutdown_bounty.push(row.shutdown_bounty);
+    }
+
+    DataFrame::new(vec![
+        Series::new("match_id", match_id),
+        Series::new("game_creation", game_creation),
+        Series::new("queue_id", queue_id),
+        Series::new("game_version", game_version),
+        Series::new("event_index", event_index),
+        Series::new("timestamp", timestamp),
+        Series::new("event_type", event_type),
+        Series::new("participant_id", participant_id),
+        Series::new("puuid", puuid),
+        Series::new("victim_id", victim_id),
+        Series::new("victim_puuid", victim_puuid),
+        Series::new("assisting_participant_ids", assisting_participant_ids),
+        Series::new("team_id", team_id),
+        Series::new("killer_team_id", killer_team_id),
+        Series::new("x", x),
+        Series::new("y", y),
+        Series::new("monster_type", monster_type),
+        Series::new("monster_sub_type", monster_sub_type),
+        Series::new("building_type", building_type),
+        Series::new("lane_type", lane_type),
+        Series::new("tower_type", tower_type),
+        Series::new("ward_type", ward_type),
+        Series::new("item_id", item_id),
+        Series::new("skill_slot", skill_slot),
+        Series::new("kill_type", kill_type),
+        Series::new("bounty", bounty),
+        Series::new("shutdown_bounty", shutdown_bounty),
+    ])
 }
 
-fn extract_team_parquet(matches_dir: &Path, out_parquet: &Path) -> Result<(), Box<dyn Error>> {
-    let mut rows: Vec<TeamRow> = Vec::new();
+/// Single-match files (`{match_id}.json`) and multi-match shards (`.jsonl`, one match per line),
+/// each optionally compressed.
+const MATCH_FILE_SUFFIXES: [&str; 6] = [
+    ".json",
+    ".json.gz",
+    ".json.zst",
+    ".jsonl",
+    ".jsonl.gz",
+    ".jsonl.zst",
+];
 
-    for path in collect_json_files(matches_dir) {
-        let contents = match fs::read_to_string(&path) {
-            Ok(data) => data,
-            Err(err) => {
-                eprintln!("Skipping unreadable file {}: {}", path.display(), err);
-                continue;
-            }
-        };
+/// Resolves `--matches-dir` values into paths, expanding glob patterns. A pattern that
+/// matches nothing is an error, since it is almost always a typo.
+fn expand_inputs(patterns: &[String]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut roots = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            roots.push(PathBuf::from(pattern));
+            continue;
+        }
+        let matched = glob::glob(pattern)
+            .map_err(|err| format!("Invalid pattern '{}': {}", pattern, err))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if matched.is_empty() {
+            return Err(format!("No paths match '{}'", pattern).into());
+        }
+        roots.extend(matched);
+    }
+    Ok(roots)
+}
 
-        let parsed: Value = match serde_json::from_str(&contents) {
-            Ok(value) => value,
-            Err(err) => {
-                eprintln!("Skipping invalid JSON {}: {}", path.display(), err);
-                continue;
-            }
-        };
+/// Match files under `roots` (directories, searched recursively, or match files), sorted and
+/// without duplicates.
+pub fn collect_match_files(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut stack = roots.to_vec();
 
-        let Some(metadata) = parsed.get("metadata") else {
-            eprintln!("Missing metadata in {}", path.display());
+    while let Some(path) = stack.pop() {
+        if path.is_file() {
+            if match_file_suffix(&path).is_some() {
+                files.push(path);
+            }
             continue;
-        };
-
-        let Some(info) = parsed.get("info") else {
-            eprintln!("Missing info section in {}", path.display());
+        }
+        let Ok(entries) = fs::read_dir(&path) else {
             continue;
         };
 
-        let Some(participants) = info.get("participants").and_then(|p| p.as_array()) else {
-            eprintln!("Missing participants array in {}", path.display());
-            continue;
-        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if match_file_suffix(&path).is_some() {
+                files.push(path);
+            }
+        }
+    }
 
-        let Some(teams) = info.get("teams").and_then(|t| t.as_array()) else {
-            eprintln!("Missing teams array in {}", path.display());
-            continue;
-        };
+    // Directory listings come back in arbitrary order; sort so every run reads the same way.
+    // Overlapping inputs (`data/raw` and `data/raw/euw1`) list the same files twice.
+    files.sort();
+    files.dedup();
+    files
+}
 
-        let Some(match_id) = metadata
-            .get("matchId")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
-            .or_else(|| {
-                path.file_stem()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.to_string())
-            })
-        else {
-            continue;
-        };
+fn match_file_suffix(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+    MATCH_FILE_SUFFIXES
+        .iter()
+        .filter(|suffix| name.ends_with(*suffix))
+        .max_by_key(|suffix| suffix.len())
+        .copied()
+}
 
-        let platform_id = metadata
-            .get("platformId")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
-            .or_else(|| {
-                info.get("platformId")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-            });
+/// Match ID a single-match file is named after; `None` for shards.
+pub fn file_match_id(path: &Path) -> Option<&str> {
+    let suffix = match_file_suffix(path)?;
+    if suffix.starts_with(".jsonl") {
+        return None;
+    }
+    let name = path.file_name()?.to_str()?;
+    name.get(..name.len() - suffix.len())
+}
 
-        let game_creation = info
-            .get("gameCreation")
-            .and_then(|v| v.as_i64())
-            .unwrap_or_default();
-        let game_duration = info
-            .get("gameDuration")
-            .and_then(|v| v.as_i64())
-            .unwrap_or_default() as i32;
-        let queue_id = info
-            .get("queueId")
-            .and_then(|v| v.as_i64())
-            .unwrap_or_default() as i32;
-        let game_version = info
-            .get("gameVersion")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .to_string();
-
-        for team in teams {
-            let Some(team_id) = team.get("teamId").and_then(|v| v.as_i64()) else {
-                continue;
-            };
+/// The valid match documents stored in `path`, for readers that do not keep a skip report.
+pub fn read_match_documents(path: &Path) -> impl Iterator<Item = MatchDocument> + '_ {
+    match_documents(path, false).filter_map(Result::ok)
+}
 
-            let team_participants: Vec<&Value> = participants
-                .iter()
-                .filter(|p| p.get("teamId").and_then(|v| v.as_i64()) == Some(team_id))
-                .collect();
+/// Match documents stored in `path`, decompressing `.gz`/`.zst` and splitting `.jsonl` shards
+/// line by line. Unreadable files and invalid documents are reported and yielded as skips.
+fn match_documents(
+    path: &Path,
+    keep_raw: bool,
+) -> Box<dyn Iterator<Item = Result<MatchDocument, Skipped>> + '_> {
+    let reader = match open_match_file(path) {
+        Ok(reader) => reader,
+        Err(err) => {
+            eprintln!("Skipping unreadable file {}: {}", path.display(), err);
+            return Box::new(std::iter::once(Err(Skipped::input(
+                SkipReason::Unreadable,
+                path,
+                None,
+                err,
+            ))));
+        }
+    };
 
-            let team_kills: i32 = team_participants
-                .iter()
-                .map(|p| as_i32(p.get("kills")))
-                .sum();
-            let team_deaths: i32 = team_participants
-                .iter()
-                .map(|p| as_i32(p.get("deaths")))
-                .sum();
-            let team_assists: i32 = team_participants
-                .iter()
-                .map(|p| as_i32(p.get("assists")))
-                .sum();
-            let team_gold_earned: i64 = team_participants
-                .iter()
-                .map(|p| as_i32(p.get("goldEarned")) as i64)
-                .sum();
-            let team_damage_to_champions: i64 = team_participants
-                .iter()
-                .map(|p| as_i32(p.get("totalDamageDealtToChampions")) as i64)
-                .sum();
-            let team_vision_score: i64 = team_participants
-                .iter()
-                .map(|p| as_i32(p.get("visionScore")) as i64)
-                .sum();
-            let team_cs_total: i32 = team_participants
-                .iter()
-                .map(|p| {
-                    as_i32(p.get("totalMinionsKilled")) + as_i32(p.get("neutralMinionsKilled"))
+    if match_file_suffix(path).is_some_and(|suffix| suffix.starts_with(".jsonl")) {
+        let lines = BufReader::new(reader).lines().enumerate();
+        let mut stopped = false;
+        return Box::new(
+            lines
+                .map_while(move |(idx, line)| {
+                    if stopped {
+                        return None;
+                    }
+                    match line {
+                        Ok(line) => Some(Ok((idx, line))),
+                        Err(err) => {
+                            eprintln!(
+                                "Stopped reading {} at line {}: {}",
+                                path.display(),
+                                idx + 1,
+                                err
+                            );
+                            stopped = true;
+                            Some(Err(Skipped::input(
+                                SkipReason::Unreadable,
+                                path,
+                                Some(idx + 1),
+                                err,
+                            )))
+                        }
+                    }
                 })
-                .sum();
-
-            // Riot's raw teams[].win is a boolean; store it as a numeric flag for aggregation.
-            let team_win = team.get("win").and_then(|v| v.as_bool()).unwrap_or(false);
+                .filter(|line| {
+                    line.as_ref()
+                        .map_or(true, |(_, line)| !line.trim().is_empty())
+                })
+                .map(move |line| {
+                    let (idx, line) = line?;
+                    parse_document(&line, keep_raw).map_err(|err| {
+                        eprintln!(
+                            "Skipping invalid JSON on line {} of {}: {}",
+                            idx + 1,
+                            path.display(),
+                            err
+                        );
+                        Skipped::input(SkipReason::InvalidJson, path, Some(idx + 1), err)
+                    })
+                }),
+        );
+    }
 
-            let (
-                team_towers_destroyed,
-                team_inhibitors_destroyed,
-                team_dragons,
-                team_barons,
-                team_heralds,
-                team_plates,
-                first_blood,
-                first_tower,
-                first_inhibitor,
-                first_baron,
-                first_dragon,
-                first_herald,
-            ) = team_objectives(team);
+    let mut contents = String::new();
+    let mut reader = reader;
+    if let Err(err) = reader.read_to_string(&mut contents) {
+        eprintln!("Skipping unreadable file {}: {}", path.display(), err);
+        return Box::new(std::iter::once(Err(Skipped::input(
+            SkipReason::Unreadable,
+            path,
+            None,
+            err,
+        ))));
+    }
+    Box::new(std::iter::once(
+        parse_document(&contents, keep_raw).map_err(|err| {
+            eprintln!("Skipping invalid JSON {}: {}", path.display(), err);
+            Skipped::input(SkipReason::InvalidJson, path, None, err)
+        }),
+    ))
+}
 
-            let row = TeamRow {
-                match_id: match_id.clone(),
-                platform_id: platform_id.clone(),
-                queue_id,
-                game_version: game_version.clone(),
-                game_creation,
-                game_duration,
-                team_id: team_id as i16,
-                team_side: if team_id == 100 { "blue" } else { "red" }.to_string(),
-                team_win: if team_win { 1 } else { 0 },
-                top_champion_id: find_role_champion(&team_participants, "TOP"),
-                jungle_champion_id: find_role_champion(&team_participants, "JUNGLE"),
-                middle_champion_id: find_role_champion(&team_participants, "MIDDLE"),
-                bottom_champion_id: find_role_champion(&team_participants, "BOTTOM"),
-                utility_champion_id: find_role_champion(&team_participants, "UTILITY"),
-                team_kills,
-                team_deaths,
-                team_assists,
-                team_gold_earned,
-                team_damage_to_champions,
-                team_vision_score,
-                team_cs_total,
-                team_gold_per_min: per_min(team_gold_earned, game_duration),
-                team_damage_per_min: per_min(team_damage_to_champions, game_duration),
-                team_vision_score_per_min: per_min(team_vision_score, game_duration),
-                team_cs_per_min: per_min(team_cs_total as i64, game_duration),
-                team_towers_destroyed,
-                team_inhibitors_destroyed,
-                team_dragons,
-                team_barons,
-                team_heralds,
-                team_plates,
-                first_blood,
-                first_tower,
-                first_inhibitor,
-                first_baron,
-                first_dragon,
-                first_herald,
-            };
+/// The timeline saved for `match_id` in `dir`; `None` when there is none. Unreadable
+/// timelines are reported and treated as missing.
+fn load_timeline(dir: &Path, match_id: &str) -> Option<MatchTimeline> {
+    let path = [".json", ".json.gz", ".json.zst"]
+        .iter()
+        .map(|suffix| dir.join(format!("{}{}", match_id, suffix)))
+        .find(|path| path.is_file())?;
 
-            rows.push(row);
+    let mut contents = String::new();
+    let parsed = open_match_file(&path)
+        .and_then(|mut reader| reader.read_to_string(&mut contents))
+        .map_err(|err| err.to_string())
+        .and_then(|_| serde_json::from_str(&contents).map_err(|err| err.to_string()));
+    match parsed {
+        Ok(timeline) => Some(timeline),
+        Err(err) => {
+            eprintln!("Ignoring timeline {}: {}", path.display(), err);
+            None
         }
     }
+}
 
-    let mut df = build_team_dataframe(rows)?;
-    let mut file = File::create(out_parquet)?;
-    ParquetWriter::new(&mut file).finish(&mut df)?;
+/// Parses one match document, also keeping its JSON tree when `keep_raw` is set so column spec
+/// fields can be read from it.
+fn parse_document(json: &str, keep_raw: bool) -> serde_json::Result<MatchDocument> {
+    if !keep_raw {
+        return serde_json::from_str(json);
+    }
+    let raw: Value = serde_json::from_str(json)?;
+    let mut document = MatchDocument::deserialize(&raw)?;
+    document.raw = Some(raw);
+    Ok(document)
+}
 
-    Ok(())
+/// Reader over the decompressed contents of a match file or shard.
+pub fn open_match_file(path: &Path) -> std::io::Result<Box<dyn Read>> {
+    let file = File::open(path)?;
+    Ok(match match_file_suffix(path) {
+        Some(suffix) if suffix.ends_with(".gz") => Box::new(MultiGzDecoder::new(file)),
+        Some(suffix) if suffix.ends_with(".zst") => Box::new(zstd::Decoder::new(file)?),
+        _ => Box::new(file),
+    })
 }
 
-fn collect_json_files(root: &Path) -> Vec<PathBuf> {
+fn collect_files_with_extension(root: &Path, wanted: &str) -> Vec<PathBuf> {
     let mut files = Vec::new();
     let mut stack = vec![root.to_path_buf()];
 
@@ -487,7 +3013,7 @@ fn collect_json_files(root: &Path) -> Vec<PathBuf> {
             } else if path
                 .extension()
                 .and_then(|ext| ext.to_str())
-                .map(|ext| ext.eq_ignore_ascii_case("json"))
+                .map(|ext| ext.eq_ignore_ascii_case(wanted))
                 .unwrap_or(false)
             {
                 files.push(path);
@@ -498,7 +3024,12 @@ fn collect_json_files(root: &Path) -> Vec<PathBuf> {
     files
 }
 
-fn build_dataframe(rows: Vec<PlayerRow>) -> Result<DataFrame, PolarsError> {
+fn build_dataframe(
+    rows: Vec<PlayerRow>,
+    challenge_keys: &[String],
+    fields: &[FieldSpec],
+    positions: bool,
+) -> Result<DataFrame, PolarsError> {
     let mut match_id: Vec<String> = Vec::new();
     let mut game_creation: Vec<i64> = Vec::new();
     let mut game_duration: Vec<i32> = Vec::new();
@@ -506,10 +3037,15 @@ fn build_dataframe(rows: Vec<PlayerRow>) -> Result<DataFrame, PolarsError> {
     let mut game_version: Vec<String> = Vec::new();
     let mut team_id: Vec<i32> = Vec::new();
     let mut puuid: Vec<String> = Vec::new();
+    let mut riot_id_game_name: Vec<Option<String>> = Vec::new();
+    let mut riot_id_tagline: Vec<Option<String>> = Vec::new();
+    let mut summoner_name: Vec<Option<String>> = Vec::new();
     let mut champion_id: Vec<i32> = Vec::new();
     let mut champion_name: Vec<String> = Vec::new();
     let mut role: Vec<String> = Vec::new();
     let mut win: Vec<bool> = Vec::new();
+    let mut game_ended_in_surrender: Vec<bool> = Vec::new();
+    let mut game_ended_in_early_surrender: Vec<bool> = Vec::new();
     let mut kills: Vec<i32> = Vec::new();
     let mut deaths: Vec<i32> = Vec::new();
     let mut assists: Vec<i32> = Vec::new();
@@ -522,12 +3058,49 @@ fn build_dataframe(rows: Vec<PlayerRow>) -> Result<DataFrame, PolarsError> {
     let mut damage_to_champions: Vec<i32> = Vec::new();
     let mut damage_to_objectives: Vec<i32> = Vec::new();
     let mut damage_to_turrets: Vec<i32> = Vec::new();
+    let mut physical_damage_to_champions: Vec<i32> = Vec::new();
+    let mut magic_damage_to_champions: Vec<i32> = Vec::new();
+    let mut true_damage_to_champions: Vec<i32> = Vec::new();
+    let mut damage_taken: Vec<i32> = Vec::new();
+    let mut physical_damage_taken: Vec<i32> = Vec::new();
+    let mut magic_damage_taken: Vec<i32> = Vec::new();
+    let mut true_damage_taken: Vec<i32> = Vec::new();
+    let mut damage_self_mitigated: Vec<i32> = Vec::new();
+    let mut total_heal: Vec<i32> = Vec::new();
+    let mut heals_on_teammates: Vec<i32> = Vec::new();
+    let mut shields_on_teammates: Vec<i32> = Vec::new();
     let mut turret_takedowns: Vec<i32> = Vec::new();
     let mut inhibitor_takedowns: Vec<i32> = Vec::new();
+    let mut objectives_stolen: Vec<i32> = Vec::new();
+    let mut objectives_stolen_assists: Vec<i32> = Vec::new();
+    let mut epic_monster_steals: Vec<Option<i32>> = Vec::new();
+    let mut first_blood_kill: Vec<bool> = Vec::new();
+    let mut first_blood_assist: Vec<bool> = Vec::new();
+    let mut first_tower_kill: Vec<bool> = Vec::new();
+    let mut first_tower_assist: Vec<bool> = Vec::new();
     let mut vision_score: Vec<i32> = Vec::new();
     let mut wards_placed: Vec<i32> = Vec::new();
     let mut wards_killed: Vec<i32> = Vec::new();
     let mut control_wards_placed: Vec<i32> = Vec::new();
+    let mut item0: Vec<i32> = Vec::new();
+    let mut item1: Vec<i32> = Vec::new();
+    let mut item2: Vec<i32> = Vec::new();
+    let mut item3: Vec<i32> = Vec::new();
+    let mut item4: Vec<i32> = Vec::new();
+    let mut item5: Vec<i32> = Vec::new();
+    let mut item6: Vec<i32> = Vec::new();
+    let mut primary_style: Vec<Option<i32>> = Vec::new();
+    let mut keystone: Vec<Option<i32>> = Vec::new();
+    let mut primary_rune1: Vec<Option<i32>> = Vec::new();
+    let mut primary_rune2: Vec<Option<i32>> = Vec::new();
+    let mut primary_rune3: Vec<Option<i32>> = Vec::new();
+    let mut secondary_style: Vec<Option<i32>> = Vec::new();
+    let mut secondary_rune1: Vec<Option<i32>> = Vec::new();
+    let mut secondary_rune2: Vec<Option<i32>> = Vec::new();
+    let mut stat_offense: Vec<Option<i32>> = Vec::new();
+    let mut stat_flex: Vec<Option<i32>> = Vec::new();
+    let mut stat_defense: Vec<Option<i32>> = Vec::new();
+    let mut pings: Vec<Vec<Option<i32>>> = vec![Vec::new(); PING_COLUMNS.len()];
     let mut damage_per_min: Vec<Option<f64>> = Vec::new();
     let mut gold_per_min: Vec<Option<f64>> = Vec::new();
     let mut team_damage_percentage: Vec<Option<f64>> = Vec::new();
@@ -536,6 +3109,14 @@ fn build_dataframe(rows: Vec<PlayerRow>) -> Result<DataFrame, PolarsError> {
     let mut vision_score_per_min: Vec<Option<f64>> = Vec::new();
     let mut lane_minions_first10: Vec<Option<f64>> = Vec::new();
     let mut jungle_cs_before10: Vec<Option<f64>> = Vec::new();
+    let mut challenges_derived: Vec<bool> = Vec::new();
+    let mut lane_diffs: Vec<Vec<Option<LaneDiff>>> = vec![Vec::new(); LANE_DIFF_MINUTES.len()];
+    let mut cs_at: Vec<Vec<Option<i32>>> = vec![Vec::new(); LANE_DIFF_MINUTES.len()];
+    let mut build_order: Vec<Option<String>> = Vec::new();
+    let mut items_sold: Vec<Option<String>> = Vec::new();
+    let mut position_features: Vec<PositionFeatures> = Vec::new();
+    let mut extra_challenges: Vec<HashMap<String, f64>> = Vec::new();
+    let mut field_values: Vec<Vec<Value>> = vec![Vec::new(); fields.len()];
 
     for row in rows {
         match_id.push(row.match_id);
@@ -545,10 +3126,15 @@ fn build_dataframe(rows: Vec<PlayerRow>) -> Result<DataFrame, PolarsError> {
         game_version.push(row.game_version);
         team_id.push(row.team_id);
         puuid.push(row.puuid);
+        riot_id_game_name.push(row.riot_id_game_name);
+        riot_id_tagline.push(row.riot_id_tagline);
+        summoner_name.push(row.summoner_name);
         champion_id.push(row.champion_id);
         champion_name.push(row.champion_name);
         role.push(row.role);
         win.push(row.win);
+        game_ended_in_surrender.push(row.game_ended_in_surrender);
+        game_ended_in_early_surrender.push(row.game_ended_in_early_surrender);
         kills.push(row.kills);
         deaths.push(row.deaths);
         assists.push(row.assists);
@@ -561,12 +3147,51 @@ fn build_dataframe(rows: Vec<PlayerRow>) -> Result<DataFrame, PolarsError> {
         damage_to_champions.push(row.damage_to_champions);
         damage_to_objectives.push(row.damage_to_objectives);
         damage_to_turrets.push(row.damage_to_turrets);
+        physical_damage_to_champions.push(row.physical_damage_to_champions);
+        magic_damage_to_champions.push(row.magic_damage_to_champions);
+        true_damage_to_champions.push(row.true_damage_to_champions);
+        damage_taken.push(row.damage_taken);
+        physical_damage_taken.push(row.physical_damage_taken);
+        magic_damage_taken.push(row.magic_damage_taken);
+        true_damage_taken.push(row.true_damage_taken);
+        damage_self_mitigated.push(row.damage_self_mitigated);
+        total_heal.push(row.total_heal);
+        heals_on_teammates.push(row.heals_on_teammates);
+        shields_on_teammates.push(row.shields_on_teammates);
         turret_takedowns.push(row.turret_takedowns);
         inhibitor_takedowns.push(row.inhibitor_takedowns);
+        objectives_stolen.push(row.objectives_stolen);
+        objectives_stolen_assists.push(row.objectives_stolen_assists);
+        epic_monster_steals.push(row.epic_monster_steals);
+        first_blood_kill.push(row.first_blood_kill);
+        first_blood_assist.push(row.first_blood_assist);
+        first_tower_kill.push(row.first_tower_kill);
+        first_tower_assist.push(row.first_tower_assist);
         vision_score.push(row.vision_score);
         wards_placed.push(row.wards_placed);
         wards_killed.push(row.wards_killed);
         control_wards_placed.push(row.control_wards_placed);
+        item0.push(row.item0);
+        item1.push(row.item1);
+        item2.push(row.item2);
+        item3.push(row.item3);
+        item4.push(row.item4);
+        item5.push(row.item5);
+        item6.push(row.item6);
+        primary_style.push(row.runes.primary_style);
+        keystone.push(row.runes.keystone);
+        primary_rune1.push(row.runes.primary_rune1);
+        primary_rune2.push(row.runes.primary_rune2);
+        primary_rune3.push(row.runes.primary_rune3);
+        secondary_style.push(row.runes.secondary_style);
+        secondary_rune1.push(row.runes.secondary_rune1);
+        secondary_rune2.push(row.runes.secondary_rune2);
+        stat_offense.push(row.runes.stat_offense);
+        stat_flex.push(row.runes.stat_flex);
+        stat_defense.push(row.runes.stat_defense);
+        for (column, count) in pings.iter_mut().zip(row.pings) {
+            column.push(count);
+        }
         damage_per_min.push(row.damage_per_min);
         gold_per_min.push(row.gold_per_min);
         team_damage_percentage.push(row.team_damage_percentage);
@@ -575,9 +3200,27 @@ fn build_dataframe(rows: Vec<PlayerRow>) -> Result<DataFrame, PolarsError> {
         vision_score_per_min.push(row.vision_score_per_min);
         lane_minions_first10.push(row.lane_minions_first10);
         jungle_cs_before10.push(row.jungle_cs_before10);
+        challenges_derived.push(row.challenges_derived);
+        for (column, diff) in lane_diffs.iter_mut().zip(row.lane_diffs) {
+            column.push(diff);
+        }
+        for (column, cs) in cs_at.iter_mut().zip(row.cs_at) {
+            column.push(cs);
+        }
+        build_order.push(
+            row.item_build
+                .as_ref()
+                .map(|build| join_ids(&build.purchased)),
+        );
+        items_sold.push(row.item_build.as_ref().map(|build| join_ids(&build.sold)));
+        position_features.push(row.positions);
+        extra_challenges.push(row.extra_challenges);
+        for (values, value) in field_values.iter_mut().zip(row.extra_fields) {
+            values.push(value);
+        }
     }
 
-    DataFrame::new(vec![
+    let mut columns = vec![
         Series::new("match_id", match_id),
         Series::new("game_creation", game_creation),
         Series::new("game_duration", game_duration),
@@ -585,10 +3228,18 @@ fn build_dataframe(rows: Vec<PlayerRow>) -> Result<DataFrame, PolarsError> {
         Series::new("game_version", game_version),
         Series::new("team_id", team_id),
         Series::new("puuid", puuid),
+        Series::new("riot_id_game_name", riot_id_game_name),
+        Series::new("riot_id_tagline", riot_id_tagline),
+        Series::new("summoner_name", summoner_name),
         Series::new("champion_id", champion_id),
         Series::new("champion_name", champion_name),
         Series::new("role", role),
         Series::new("win", win),
+        Series::new("game_ended_in_surrender", game_ended_in_surrender),
+        Series::new(
+            "game_ended_in_early_surrender",
+            game_ended_in_early_surrender,
+        ),
         Series::new("kills", kills),
         Series::new("deaths", deaths),
         Series::new("assists", assists),
@@ -601,12 +3252,56 @@ fn build_dataframe(rows: Vec<PlayerRow>) -> Result<DataFrame, PolarsError> {
         Series::new("damage_to_champions", damage_to_champions),
         Series::new("damage_to_objectives", damage_to_objectives),
         Series::new("damage_to_turrets", damage_to_turrets),
+        Series::new("physical_damage_to_champions", physical_damage_to_champions),
+        Series::new("magic_damage_to_champions", magic_damage_to_champions),
+        Series::new("true_damage_to_champions", true_damage_to_champions),
+        Series::new("damage_taken", damage_taken),
+        Series::new("physical_damage_taken", physical_damage_taken),
+        Series::new("magic_damage_taken", magic_damage_taken),
+        Series::new("true_damage_taken", true_damage_taken),
+        Series::new("damage_self_mitigated", damage_self_mitigated),
+        Series::new("total_heal", total_heal),
+        Series::new("heals_on_teammates", heals_on_teammates),
+        Series::new("shields_on_teammates", shields_on_teammates),
         Series::new("turret_takedowns", turret_takedowns),
         Series::new("inhibitor_takedowns", inhibitor_takedowns),
+        Series::new("objectives_stolen", objectives_stolen),
+        Series::new("objectives_stolen_assists", objectives_stolen_assists),
+        Series::new("epic_monster_steals", epic_monster_steals),
+        Series::new("first_blood_kill", first_blood_kill),
+        Series::new("first_blood_assist", first_blood_assist),
+        Series::new("first_tower_kill", first_tower_kill),
+        Series::new("first_tower_assist", first_tower_assist),
         Series::new("vision_score", vision_score),
         Series::new("wards_placed", wards_placed),
         Series::new("wards_killed", wards_killed),
         Series::new("control_wards_placed", control_wards_placed),
+        Series::new("item0", item0),
+        Series::new("item1", item1),
+        Series::new("item2", item2),
+        Series::new("item3", item3),
+        Series::new("item4", item4),
+        Series::new("item5", item5),
+        Series::new("item6", item6),
+        Series::new("primary_style", primary_style),
+        Series::new("keystone", keystone),
+        Series::new("primary_rune1", primary_rune1),
+        Series::new("primary_rune2", primary_rune2),
+        Series::new("primary_rune3", primary_rune3),
+        Series::new("secondary_style", secondary_style),
+        Series::new("secondary_rune1", secondary_rune1),
+        Series::new("secondary_rune2", secondary_rune2),
+        Series::new("stat_offense", stat_offense),
+        Series::new("stat_flex", stat_flex),
+        Series::new("stat_defense", stat_defense),
+    ];
+    columns.extend(
+        PING_COLUMNS
+            .iter()
+            .zip(pings)
+            .map(|(name, counts)| Series::new(name, counts)),
+    );
+    columns.extend([
         Series::new("damage_per_min", damage_per_min),
         Series::new("gold_per_min", gold_per_min),
         Series::new("team_damage_percentage", team_damage_percentage),
@@ -615,16 +3310,101 @@ fn build_dataframe(rows: Vec<PlayerRow>) -> Result<DataFrame, PolarsError> {
         Series::new("vision_score_per_min", vision_score_per_min),
         Series::new("lane_minions_first10", lane_minions_first10),
         Series::new("jungle_cs_before10", jungle_cs_before10),
-    ])
+        Series::new("challenges_derived", challenges_derived),
+    ]);
+    for (minute, diffs) in LANE_DIFF_MINUTES.iter().zip(&lane_diffs) {
+        let column = |stat: &str, value: fn(&LaneDiff) -> i32| {
+            let values: Vec<Option<i32>> =
+                diffs.iter().map(|diff| diff.as_ref().map(value)).collect();
+            Series::new(&format!("{}_diff_at{}", stat, minute), values)
+        };
+        columns.extend([
+            column("gold", |diff| diff.gold),
+            column("xp", |diff| diff.xp),
+            column("cs", |diff| diff.cs),
+        ]);
+    }
+    for (minute, cs) in LANE_DIFF_MINUTES.iter().zip(cs_at) {
+        columns.push(Series::new(&format!("cs_at{}", minute), cs));
+    }
+    columns.extend([
+        Series::new("build_order", build_order),
+        Series::new("items_sold", items_sold),
+    ]);
+    if positions {
+        let column = |name: &str, value: fn(&PositionFeatures) -> Option<f64>| {
+            let values: Vec<Option<f64>> = position_features.iter().map(value).collect();
+            Series::new(name, values)
+        };
+        let values: [fn(&PositionFeatures) -> Option<f64>; POSITION_COLUMNS.len()] = [
+            |p| p.own_jungle_share,
+            |p| p.enemy_jungle_share,
+            |p| p.river_share_before10,
+            |p| p.avg_lane_distance,
+        ];
+        columns.extend(
+            POSITION_COLUMNS
+                .iter()
+                .zip(values)
+                .map(|(name, value)| column(name, value)),
+        );
+    }
+    let mut df = DataFrame::new(columns)?;
+
+    for key in challenge_keys {
+        let name = snake_case(key);
+        // e.g. `kda` is already a fixed column with the same value.
+        if df.get_column_names().contains(&name.as_str()) {
+            continue;
+        }
+        let values: Vec<Option<f64>> = extra_challenges
+            .iter()
+            .map(|challenges| challenges.get(key).copied())
+            .collect();
+        df.with_column(Series::new(&name, values))?;
+    }
+    for (field, values) in fields.iter().zip(&field_values) {
+        df.with_column(field.series(values))?;
+    }
+
+    Ok(df)
+}
+
+/// `skillshotsDodged` -> `skillshots_dodged`, `twentyMinionsIn3SecondsCount` ->
+/// `twenty_minions_in_3_seconds_count`.
+pub fn snake_case(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    let mut out = String::with_capacity(key.len() + 4);
+    for (idx, &ch) in chars.iter().enumerate() {
+        if idx > 0 {
+            let prev = chars[idx - 1];
+            let next_lower = chars.get(idx + 1).is_some_and(|c| c.is_lowercase());
+            let boundary = (ch.is_uppercase()
+                && (prev.is_lowercase()
+                    || prev.is_ascii_digit()
+                    || (prev.is_uppercase() && next_lower)))
+                || (ch.is_ascii_digit() && prev.is_alphabetic());
+            if boundary {
+                out.push('_');
+            }
+        }
+        out.extend(ch.to_lowercase());
+    }
+    out
 }
 
-fn build_team_dataframe(rows: Vec<TeamRow>) -> Result<DataFrame, PolarsError> {
+fn build_team_dataframe(
+    rows: Vec<TeamRow>,
+    fields: &[FieldSpec],
+) -> Result<DataFrame, PolarsError> {
     let mut match_id: Vec<String> = Vec::new();
     let mut platform_id: Vec<Option<String>> = Vec::new();
     let mut queue_id: Vec<i32> = Vec::new();
     let mut game_version: Vec<String> = Vec::new();
     let mut game_creation: Vec<i64> = Vec::new();
     let mut game_duration: Vec<i32> = Vec::new();
+    let mut game_ended_in_surrender: Vec<bool> = Vec::new();
+    let mut game_ended_in_early_surrender: Vec<bool> = Vec::new();
     let mut team_id: Vec<i16> = Vec::new();
     let mut team_side: Vec<String> = Vec::new();
     let mut team_win: Vec<i32> = Vec::new();
@@ -633,6 +3413,8 @@ fn build_team_dataframe(rows: Vec<TeamRow>) -> Result<DataFrame, PolarsError> {
     let mut middle_champion_id: Vec<Option<i32>> = Vec::new();
     let mut bottom_champion_id: Vec<Option<i32>> = Vec::new();
     let mut utility_champion_id: Vec<Option<i32>> = Vec::new();
+    let mut ban_champion_ids: [Vec<Option<i32>>; BAN_SLOTS] = Default::default();
+    let mut ban_pick_turns: [Vec<Option<i32>>; BAN_SLOTS] = Default::default();
     let mut team_kills: Vec<i32> = Vec::new();
     let mut team_deaths: Vec<i32> = Vec::new();
     let mut team_assists: Vec<i32> = Vec::new();
@@ -650,12 +3432,16 @@ fn build_team_dataframe(rows: Vec<TeamRow>) -> Result<DataFrame, PolarsError> {
     let mut team_barons: Vec<i32> = Vec::new();
     let mut team_heralds: Vec<i32> = Vec::new();
     let mut team_plates: Vec<Option<i32>> = Vec::new();
+    let mut team_objectives_stolen: Vec<i32> = Vec::new();
+    let mut team_elder_dragons: Vec<Option<i32>> = Vec::new();
+    let mut team_dragon_soul: Vec<Option<bool>> = Vec::new();
     let mut first_blood: Vec<Option<bool>> = Vec::new();
     let mut first_tower: Vec<Option<bool>> = Vec::new();
     let mut first_inhibitor: Vec<Option<bool>> = Vec::new();
     let mut first_baron: Vec<Option<bool>> = Vec::new();
     let mut first_dragon: Vec<Option<bool>> = Vec::new();
     let mut first_herald: Vec<Option<bool>> = Vec::new();
+    let mut field_values: Vec<Vec<Value>> = vec![Vec::new(); fields.len()];
 
     for row in rows {
         match_id.push(row.match_id);
@@ -664,6 +3450,8 @@ fn build_team_dataframe(rows: Vec<TeamRow>) -> Result<DataFrame, PolarsError> {
         game_version.push(row.game_version);
         game_creation.push(row.game_creation);
         game_duration.push(row.game_duration);
+        game_ended_in_surrender.push(row.game_ended_in_surrender);
+        game_ended_in_early_surrender.push(row.game_ended_in_early_surrender);
         team_id.push(row.team_id);
         team_side.push(row.team_side);
         team_win.push(row.team_win);
@@ -672,6 +3460,10 @@ fn build_team_dataframe(rows: Vec<TeamRow>) -> Result<DataFrame, PolarsError> {
         middle_champion_id.push(row.middle_champion_id);
         bottom_champion_id.push(row.bottom_champion_id);
         utility_champion_id.push(row.utility_champion_id);
+        for slot in 0..BAN_SLOTS {
+            ban_champion_ids[slot].push(row.ban_champion_ids[slot]);
+            ban_pick_turns[slot].push(row.ban_pick_turns[slot]);
+        }
         team_kills.push(row.team_kills);
         team_deaths.push(row.team_deaths);
         team_assists.push(row.team_assists);
@@ -689,21 +3481,32 @@ fn build_team_dataframe(rows: Vec<TeamRow>) -> Result<DataFrame, PolarsError> {
         team_barons.push(row.team_barons);
         team_heralds.push(row.team_heralds);
         team_plates.push(row.team_plates);
+        team_objectives_stolen.push(row.team_objectives_stolen);
+        team_elder_dragons.push(row.team_elder_dragons);
+        team_dragon_soul.push(row.team_dragon_soul);
         first_blood.push(row.first_blood);
         first_tower.push(row.first_tower);
         first_inhibitor.push(row.first_inhibitor);
         first_baron.push(row.first_baron);
         first_dragon.push(row.first_dragon);
         first_herald.push(row.first_herald);
+        for (values, value) in field_values.iter_mut().zip(row.extra_fields) {
+            values.push(value);
+        }
     }
 
-    DataFrame::new(vec![
+    let mut columns = vec![
         Series::new("match_id", match_id),
         Series::new("platform_id", platform_id),
         Series::new("queue_id", queue_id),
         Series::new("game_version", game_version),
         Series::new("game_creation", game_creation),
         Series::new("game_duration", game_duration),
+        Series::new("game_ended_in_surrender", game_ended_in_surrender),
+        Series::new(
+            "game_ended_in_early_surrender",
+            game_ended_in_early_surrender,
+        ),
         Series::new("team_id", team_id),
         Series::new("team_side", team_side),
         Series::new("team_win", team_win),
@@ -712,6 +3515,20 @@ fn build_team_dataframe(rows: Vec<TeamRow>) -> Result<DataFrame, PolarsError> {
         Series::new("middle_champion_id", middle_champion_id),
         Series::new("bottom_champion_id", bottom_champion_id),
         Series::new("utility_champion_id", utility_champion_id),
+    ];
+    for (slot, champion_ids) in ban_champion_ids.into_iter().enumerate() {
+        columns.push(Series::new(
+            &format!("ban{}_champion_id", slot + 1),
+            champion_ids,
+        ));
+    }
+    for (slot, pick_turns) in ban_pick_turns.into_iter().enumerate() {
+        columns.push(Series::new(
+            &format!("ban{}_pick_turn", slot + 1),
+            pick_turns,
+        ));
+    }
+    columns.extend([
         Series::new("team_kills", team_kills),
         Series::new("team_deaths", team_deaths),
         Series::new("team_assists", team_assists),
@@ -729,44 +3546,289 @@ fn build_team_dataframe(rows: Vec<TeamRow>) -> Result<DataFrame, PolarsError> {
         Series::new("team_barons", team_barons),
         Series::new("team_heralds", team_heralds),
         Series::new("team_plates", team_plates),
+        Series::new("team_objectives_stolen", team_objectives_stolen),
+        Series::new("team_elder_dragons", team_elder_dragons),
+        Series::new("team_dragon_soul", team_dragon_soul),
         Series::new("first_blood", first_blood),
         Series::new("first_tower", first_tower),
         Series::new("first_inhibitor", first_inhibitor),
         Series::new("first_baron", first_baron),
         Series::new("first_dragon", first_dragon),
         Series::new("first_herald", first_herald),
+    ]);
+
+    let mut df = DataFrame::new(columns)?;
+    for (field, values) in fields.iter().zip(&field_values) {
+        df.with_column(field.series(values))?;
+    }
+    Ok(df)
+}
+
+/// Team-table columns that describe the whole match and appear once per `--level match` row.
+const MATCH_COLUMNS: [&str; 8] = [
+    "match_id",
+    "platform_id",
+    "queue_id",
+    "game_version",
+    "game_creation",
+    "game_duration",
+    "game_ended_in_surrender",
+    "game_ended_in_early_surrender",
+];
+
+/// One row per match: the match-wide columns once, then every team column for each side with a
+/// `blue_`/`red_` prefix (`team_kills` -> `blue_kills`, `top_champion_id` ->
+/// `red_top_champion_id`). Matches missing a side are dropped.
+fn build_match_dataframe(teams: &DataFrame) -> Result<DataFrame, PolarsError> {
+    let side = |team_id: i16, prefix: &str| -> Result<DataFrame, PolarsError> {
+        let mask = teams.column("team_id")?.i16()?.equal(team_id);
+        let mut side = teams.filter(&mask)?.drop_many(&["team_id", "team_side"]);
+        for name in side.get_column_names_owned() {
+            if MATCH_COLUMNS.contains(&name.as_str()) {
+                continue;
+            }
+            let renamed = format!("{}_{}", prefix, name.strip_prefix("team_").unwrap_or(&name));
+            side.rename(&name, &renamed)?;
+        }
+        Ok(side)
+    };
+
+    let blue = side(100, "blue")?;
+    let red = side(200, "red")?.drop_many(&MATCH_COLUMNS[1..]);
+    blue.join(
+        &red,
+        ["match_id"],
+        ["match_id"],
+        JoinArgs::new(JoinType::Inner),
+    )
+}
+
+/// Adds the lane opponent's `columns` as `opp_<column>` to every player with a standard
+/// position (`opp_team_id` identifies the other side). Players whose opposite number is missing
+/// keep null opponent columns.
+pub fn with_lane_opponent(players: LazyFrame, columns: &[&str]) -> LazyFrame {
+    let base = players
+        .filter(
+            col("role")
+                .eq(lit("TOP"))
+                .or(col("role").eq(lit("JUNGLE")))
+                .or(col("role").eq(lit("MIDDLE")))
+                .or(col("role").eq(lit("BOTTOM")))
+                .or(col("role").eq(lit("UTILITY"))),
+        )
+        .with_column(
+            when(col("team_id").eq(lit(100)))
+                .then(lit(200))
+                .otherwise(lit(100))
+                .alias("opp_team_id"),
+        );
+
+    let mut selection = vec![col("match_id"), col("role"), col("team_id")];
+    selection.extend(
+        columns
+            .iter()
+            .map(|name| col(name).alias(&format!("opp_{}", name))),
+    );
+    let opponents = base.clone().select(selection);
+
+    base.join(
+        opponents,
+        [col("match_id"), col("role"), col("opp_team_id")],
+        [col("match_id"), col("role"), col("team_id")],
+        JoinArgs::new(JoinType::Left),
+    )
+}
+
+/// One row per participant and lane opponent (each matchup appears once from either side).
+fn build_matchup_dataframe(players: DataFrame) -> Result<DataFrame, PolarsError> {
+    with_lane_opponent(
+        players.lazy(),
+        &[
+            "puuid",
+            "champion_id",
+            "champion_name",
+            "kills",
+            "deaths",
+            "assists",
+            "gold_earned",
+            "total_cs",
+            "vision_score",
+            "damage_to_champions",
+        ],
+    )
+    .filter(col("opp_puuid").is_not_null())
+    .select([
+        col("match_id"),
+        col("game_creation"),
+        col("game_duration"),
+        col("queue_id"),
+        col("game_version"),
+        col("role"),
+        col("team_id"),
+        col("win"),
+        col("puuid"),
+        col("champion_id"),
+        col("champion_name"),
+        col("opp_puuid"),
+        col("opp_champion_id"),
+        col("opp_champion_name"),
+        col("kills"),
+        col("deaths"),
+        col("assists"),
+        col("opp_kills"),
+        col("opp_deaths"),
+        col("opp_assists"),
+        col("gold_earned"),
+        col("opp_gold_earned"),
+        (col("gold_earned") - col("opp_gold_earned")).alias("gold_diff"),
+        col("total_cs"),
+        col("opp_total_cs"),
+        (col("total_cs") - col("opp_total_cs")).alias("cs_diff"),
+        col("vision_score"),
+        col("opp_vision_score"),
+        (col("vision_score") - col("opp_vision_score")).alias("vision_diff"),
+        col("damage_to_champions"),
+        col("opp_damage_to_champions"),
+        (col("damage_to_champions") - col("opp_damage_to_champions")).alias("damage_diff"),
     ])
+    .collect()
+}
+
+/// Rows of `--level player-champion` are keyed by these player columns.
+const PLAYER_CHAMPION_KEYS: [&str; 4] = ["puuid", "champion_id", "champion_name", "role"];
+
+/// Player columns averaged by `--level player-champion`, each into `avg_<column>`. Nullable ones
+/// are averaged over the games that have them.
+const PLAYER_CHAMPION_METRICS: [&str; 14] = [
+    "kills",
+    "deaths",
+    "assists",
+    "kda",
+    "kill_participation",
+    "gold_per_min",
+    "damage_per_min",
+    "team_damage_percentage",
+    "total_cs",
+    "vision_score_per_min",
+    "game_duration",
+    "gold_diff_at10",
+    "xp_diff_at10",
+    "cs_diff_at10",
+];
+
+/// Game and win counts plus the sum and non-null count of every metric per key, added to the
+/// running `sums` of earlier chunks. Sums can be added up chunk by chunk where means cannot.
+fn champion_sums_of(players: LazyFrame, sums: Option<DataFrame>) -> Result<DataFrame, PolarsError> {
+    let keys: Vec<Expr> = PLAYER_CHAMPION_KEYS.iter().map(|key| col(key)).collect();
+    let mut aggregates = vec![
+        col("puuid").count().cast(DataType::Int64).alias("games"),
+        col("win").cast(DataType::Int64).sum().alias("wins"),
+    ];
+    for metric in PLAYER_CHAMPION_METRICS {
+        aggregates.push(
+            col(metric)
+                .cast(DataType::Float64)
+                .sum()
+                .alias(&format!("{}_sum", metric)),
+        );
+        aggregates.push(
+            col(metric)
+                .is_not_null()
+                .cast(DataType::Int64)
+                .sum()
+                .alias(&format!("{}_games", metric)),
+        );
+    }
+    let chunk = players.group_by(keys.clone()).agg(aggregates).collect()?;
+
+    let Some(mut sums) = sums else {
+        return Ok(chunk);
+    };
+    sums.vstack_mut(&chunk)?;
+    sums.lazy().group_by(keys).agg([all().sum()]).collect()
+}
+
+/// One row per player, champion and role from the sums of [`champion_sums_of`].
+fn build_player_champion_dataframe(sums: DataFrame) -> Result<DataFrame, PolarsError> {
+    let mut columns: Vec<Expr> = PLAYER_CHAMPION_KEYS.iter().map(|key| col(key)).collect();
+    columns.extend([
+        col("games").cast(DataType::Int32),
+        col("wins").cast(DataType::Int32),
+        (col("wins").cast(DataType::Float64) / col("games").cast(DataType::Float64))
+            .alias("win_rate"),
+    ]);
+    for metric in PLAYER_CHAMPION_METRICS {
+        let games = col(&format!("{}_games", metric));
+        columns.push(
+            when(games.clone().gt(lit(0)))
+                .then(col(&format!("{}_sum", metric)) / games.cast(DataType::Float64))
+                .otherwise(lit(NULL).cast(DataType::Float64))
+                .alias(&format!("avg_{}", metric)),
+        );
+    }
+    sums.lazy()
+        .select(columns)
+        .sort_by_exprs(
+            [col("puuid"), col("role"), col("champion_id")],
+            [false, false, false],
+            false,
+            true,
+        )
+        .collect()
 }
 
-fn as_i32(value: Option<&Value>) -> i32 {
-    value
-        .and_then(|v| v.as_i64())
-        .unwrap_or_default()
-        .try_into()
-        .unwrap_or_default()
+fn rune_page(perks: Option<&Perks>) -> RunePage {
+    let Some(perks) = perks else {
+        return RunePage::default();
+    };
+
+    let primary = perks.style("primaryStyle");
+    let secondary = perks.style("subStyle");
+    let selection = |style: Option<&PerkStyle>, slot: usize| style?.selection(slot);
+    let shards = perks.stat_perks.as_ref();
+    RunePage {
+        primary_style: primary.and_then(|s| s.style),
+        keystone: selection(primary, 0),
+        primary_rune1: selection(primary, 1),
+        primary_rune2: selection(primary, 2),
+        primary_rune3: selection(primary, 3),
+        secondary_style: secondary.and_then(|s| s.style),
+        secondary_rune1: selection(secondary, 0),
+        secondary_rune2: selection(secondary, 1),
+        stat_offense: shards.and_then(|s| s.offense),
+        stat_flex: shards.and_then(|s| s.flex),
+        stat_defense: shards.and_then(|s| s.defense),
+    }
 }
 
-fn as_f64(container: Option<&Value>, key: &str) -> Option<f64> {
-    container.and_then(|c| c.get(key)).and_then(|v| v.as_f64())
+/// Champion and pick turn of each ban slot, ordered by pick turn. Riot reports skipped bans
+/// as champion `-1`.
+fn team_bans(team: &Team) -> ([Option<i32>; BAN_SLOTS], [Option<i32>; BAN_SLOTS]) {
+    let mut bans: Vec<(Option<i32>, Option<i32>)> = team
+        .bans
+        .iter()
+        .map(|ban| (ban.champion_id.filter(|id| *id > 0), ban.pick_turn))
+        .collect();
+    bans.sort_by_key(|(_, pick_turn)| pick_turn.unwrap_or(i32::MAX));
+
+    let mut champion_ids = [None; BAN_SLOTS];
+    let mut pick_turns = [None; BAN_SLOTS];
+    for (slot, (champion_id, pick_turn)) in bans.into_iter().take(BAN_SLOTS).enumerate() {
+        champion_ids[slot] = champion_id;
+        pick_turns[slot] = pick_turn;
+    }
+    (champion_ids, pick_turns)
 }
 
-fn find_role_champion(participants: &[&Value], role: &str) -> Option<i32> {
+fn find_role_champion(participants: &[&Participant], role: &str) -> Option<i32> {
     participants
         .iter()
-        .find(|p| {
-            p.get("teamPosition")
-                .and_then(|v| v.as_str())
-                .map(|s| s.eq_ignore_ascii_case(role))
-                .unwrap_or(false)
-        })
-        .and_then(|p| p.get("championId"))
-        .and_then(|v| v.as_i64())
-        .map(|id| id as i32)
+        .find(|p| p.team_position.eq_ignore_ascii_case(role))
+        .map(|p| p.champion_id)
 }
 
-fn team_objectives(
-    team: &Value,
-) -> (
+/// (towers, inhibitors, dragons, barons, heralds, plates, then the six `first*` flags).
+type TeamObjectives = (
     i32,
     i32,
     i32,
@@ -779,62 +3841,389 @@ fn team_objectives(
     Option<bool>,
     Option<bool>,
     Option<bool>,
-) {
-    let objectives = team.get("objectives");
-
-    let tower = objective_kills(objectives, "tower");
-    let inhibitor = objective_kills(objectives, "inhibitor");
-    let dragon = objective_kills(objectives, "dragon");
-    let baron = objective_kills(objectives, "baron");
-    let herald = objective_kills(objectives, "riftHerald");
-    let plates = objectives
-        .and_then(|o| o.get("tower"))
-        .and_then(|t| t.get("plates"))
-        .and_then(|v| v.as_i64())
-        .map(|v| v as i32);
-
-    let first_blood = objective_first(objectives, "champion");
-    let first_tower = objective_first(objectives, "tower");
-    let first_inhibitor = objective_first(objectives, "inhibitor");
-    let first_baron = objective_first(objectives, "baron");
-    let first_dragon = objective_first(objectives, "dragon");
-    let first_herald = objective_first(objectives, "riftHerald");
+);
+
+fn team_objectives(team: &Team) -> TeamObjectives {
+    let objectives = team.objectives.as_ref();
+    let objective =
+        |pick: fn(&Objectives) -> &Option<Objective>| objectives.and_then(|o| pick(o).as_ref());
+    let kills = |pick| objective(pick).map_or(0, |o| o.kills);
+    let first = |pick| objective(pick).and_then(|o| o.first);
 
     (
-        tower,
-        inhibitor,
-        dragon,
-        baron,
-        herald,
-        plates,
-        first_blood,
-        first_tower,
-        first_inhibitor,
-        first_baron,
-        first_dragon,
-        first_herald,
+        kills(|o| &o.tower),
+        kills(|o| &o.inhibitor),
+        kills(|o| &o.dragon),
+        kills(|o| &o.baron),
+        kills(|o| &o.rift_herald),
+        objective(|o| &o.tower).and_then(|o| o.plates),
+        first(|o| &o.champion),
+        first(|o| &o.tower),
+        first(|o| &o.inhibitor),
+        first(|o| &o.baron),
+        first(|o| &o.dragon),
+        first(|o| &o.rift_herald),
     )
 }
 
-fn objective_kills(objectives: Option<&Value>, key: &str) -> i32 {
-    objectives
-        .and_then(|o| o.get(key))
-        .and_then(|obj| obj.get("kills"))
-        .and_then(|v| v.as_i64())
-        .unwrap_or_default() as i32
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value.clone().filter(|value| !value.is_empty())
 }
 
-fn objective_first(objectives: Option<&Value>, key: &str) -> Option<bool> {
-    objectives
-        .and_then(|o| o.get(key))
-        .and_then(|obj| obj.get("first"))
-        .and_then(|v| v.as_bool())
+/// `part` as a fraction of `total`; `None` when the total is zero.
+fn share(part: i64, total: i64) -> Option<f64> {
+    if total <= 0 {
+        return None;
+    }
+
+    Some(part as f64 / total as f64)
 }
 
-fn per_min(total: i64, duration_secs: i32) -> Option<f64> {
+pub fn per_min(total: i64, duration_secs: i32) -> Option<f64> {
     if duration_secs <= 0 {
         return None;
     }
 
     Some(total as f64 / (duration_secs as f64 / 60.0))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use serde_json::json;
+
+    fn match_json(match_id: &str, game_creation: i64, challenges: Value) -> Value {
+        json!({
+            "metadata": { "matchId": match_id },
+            "info": {
+                "gameCreation": game_creation,
+                "gameDuration": 1800,
+                "queueId": 420,
+                "gameVersion": "14.19.1.1",
+                "participants": [{
+                    "puuid": "p1",
+                    "teamId": 100,
+                    "teamPosition": "MIDDLE",
+                    "win": true,
+                    "challenges": challenges,
+                }],
+            },
+        })
+    }
+
+    fn player_args(matches: &Path, out: &Path) -> ExtractParquetArgs {
+        ExtractParquetArgs {
+            matches_dirs: vec![matches.to_string_lossy().to_string()],
+            out_parquet: out.to_path_buf(),
+            level: "player".to_string(),
+            incremental: false,
+            merge: false,
+            partition_by: None,
+            challenge_cols: None,
+            queues: None,
+            since: None,
+            until: None,
+            patches: None,
+            format: OutputFormat::Parquet,
+            enrich_champions: None,
+            min_duration_secs: 0,
+            schema_version_column: false,
+            columns: None,
+            timelines_dir: None,
+            timeline_positions: false,
+            parquet: ParquetOptions::default(),
+        }
+    }
+
+    #[test]
+    fn challenge_cols_all_keeps_keys_first_seen_in_a_later_chunk() {
+        let dir = TempDir::new("extract-challenges");
+        let matches = dir.path().join("matches");
+        fs::create_dir_all(&matches).unwrap();
+        fs::write(
+            matches.join("EUW1_1.json"),
+            match_json("EUW1_1", 1_727_900_000_000, json!({ "soloKills": 1 })).to_string(),
+        )
+        .unwrap();
+        fs::write(
+            matches.join("EUW1_2.json"),
+            match_json(
+                "EUW1_2",
+                1_727_900_000_000,
+                json!({ "soloKills": 2, "skillshotsDodged": 7 }),
+            )
+            .to_string(),
+        )
+        .unwrap();
+        let out = dir.path().join("players.parquet");
+        let args = ExtractParquetArgs {
+            challenge_cols: Some("all".to_string()),
+            ..player_args(&matches, &out)
+        };
+
+        // One match per chunk: `skillshotsDodged` only shows up in the second one.
+        extract_in_chunks(&args, 1).unwrap();
+        let df = ParquetReader::new(File::open(&out).unwrap())
+            .finish()
+            .unwrap()
+            .sort(["match_id"], false, false)
+            .unwrap();
+        let staging_left = with_suffix(&out, ".challenges.tmp").exists();
+
+        let column = |name: &str| -> Vec<Option<f64>> {
+            df.column(name)
+                .unwrap()
+                .f64()
+                .unwrap()
+                .into_iter()
+                .collect()
+        };
+        assert_eq!(column("solo_kills"), vec![Some(1.0), Some(2.0)]);
+        assert_eq!(column("skillshots_dodged"), vec![None, Some(7.0)]);
+        assert!(!staging_left);
+    }
+
+    #[test]
+    fn rows_are_sorted_across_chunks() {
+        let dir = TempDir::new("extract-sort");
+        let matches = dir.path().join("matches");
+        fs::create_dir_all(&matches).unwrap();
+        // Path order is the reverse of creation order.
+        for (match_id, game_creation) in [
+            ("EUW1_1", 1_727_900_300_000),
+            ("EUW1_2", 1_727_900_200_000),
+            ("EUW1_3", 1_727_900_100_000),
+        ] {
+            fs::write(
+                matches.join(format!("{}.json", match_id)),
+                match_json(match_id, game_creation, json!({})).to_string(),
+            )
+            .unwrap();
+        }
+        let out = dir.path().join("players.parquet");
+
+        extract_in_chunks(&player_args(&matches, &out), 1).unwrap();
+        let df = ParquetReader::new(File::open(&out).unwrap())
+            .finish()
+            .unwrap();
+        let (version, level) = stored_schema(&out).unwrap();
+
+        let match_ids: Vec<Option<&str>> = df
+            .column("match_id")
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            match_ids,
+            vec![Some("EUW1_3"), Some("EUW1_2"), Some("EUW1_1")]
+        );
+        assert_eq!(
+            (version, level.as_deref()),
+            (SCHEMA_VERSION, Some("player"))
+        );
+    }
+
+    #[test]
+    fn partitioned_append_adds_a_part_per_partition() {
+        let dir = TempDir::new("extract-partitions");
+        let matches = dir.path().join("matches");
+        fs::create_dir_all(&matches).unwrap();
+        let write_match = |match_id: &str| {
+            fs::write(
+                matches.join(format!("{}.json", match_id)),
+                match_json(match_id, 1_727_900_000_000, json!({ "soloKills": 1 })).to_string(),
+            )
+            .unwrap();
+        };
+        let out = dir.path().join("players");
+        let args = ExtractParquetArgs {
+            partition_by: Some(vec!["patch".to_string()]),
+            incremental: true,
+            ..player_args(&matches, &out)
+        };
+
+        write_match("EUW1_1");
+        extract_in_chunks(&args, 100).unwrap();
+        write_match("EUW1_2");
+        extract_in_chunks(&args, 100).unwrap();
+
+        let partition = out.join("patch=14.19");
+        let match_ids: Vec<String> = ["part-0.parquet", "part-1.parquet"]
+            .into_iter()
+            .flat_map(|part| existing_match_ids(&partition.join(part)).unwrap())
+            .collect();
+        let third_part = partition.join("part-2.parquet").exists();
+
+        // Appending with other columns is refused instead of adding a mismatched part.
+        write_match("EUW1_3");
+        let widened = ExtractParquetArgs {
+            challenge_cols: Some("all".to_string()),
+            ..args.clone()
+        };
+        let mismatch = extract_in_chunks(&widened, 100).unwrap_err().to_string();
+        let mismatched_part = partition.join("part-2.parquet").exists();
+
+        assert_eq!(match_ids, vec!["EUW1_1".to_string(), "EUW1_2".to_string()]);
+        assert!(!third_part);
+        assert!(mismatch.contains("its columns differ"), "{}", mismatch);
+        assert!(!mismatched_part);
+    }
+
+    #[test]
+    fn partitioned_rewrite_without_rows_keeps_the_dataset() {
+        let dir = TempDir::new("extract-empty-rewrite");
+        let matches = dir.path().join("matches");
+        fs::create_dir_all(&matches).unwrap();
+        fs::write(
+            matches.join("EUW1_1.json"),
+            match_json("EUW1_1", 1_727_900_000_000, json!({})).to_string(),
+        )
+        .unwrap();
+        let out = dir.path().join("players");
+        let args = ExtractParquetArgs {
+            partition_by: Some(vec!["patch".to_string()]),
+            ..player_args(&matches, &out)
+        };
+
+        extract_in_chunks(&args, 100).unwrap();
+        // Every match is filtered out of the rewrite.
+        let flex_only = ExtractParquetArgs {
+            queues: Some("flex".to_string()),
+            ..args.clone()
+        };
+        extract_in_chunks(&flex_only, 100).unwrap();
+        let kept = out.join("patch=14.19").join("part-0.parquet").exists();
+        let staging_left = with_suffix(&out, ".tmp").exists();
+
+        assert!(kept);
+        assert!(!staging_left);
+    }
+
+    #[test]
+    fn merge_into_replaces_rows_of_reextracted_matches() {
+        let dir = TempDir::new("extract-merge");
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        for match_id in ["EUW1_1", "EUW1_2"] {
+            fs::write(
+                first.join(format!("{}.json", match_id)),
+                match_json(match_id, 1_727_900_000_000, json!({})).to_string(),
+            )
+            .unwrap();
+        }
+        let mut updated = match_json("EUW1_1", 1_727_900_000_000, json!({}));
+        updated["info"]["gameDuration"] = json!(2000);
+        fs::write(second.join("EUW1_1.json"), updated.to_string()).unwrap();
+        let out = dir.path().join("players.parquet");
+
+        extract_in_chunks(&player_args(&first, &out), 100).unwrap();
+        let args = ExtractParquetArgs {
+            merge: true,
+            ..player_args(&second, &out)
+        };
+        extract_in_chunks(&args, 100).unwrap();
+        let df = ParquetReader::new(File::open(&out).unwrap())
+            .finish()
+            .unwrap()
+            .sort(["match_id"], false, false)
+            .unwrap();
+
+        let durations: Vec<Option<i32>> = df
+            .column("game_duration")
+            .unwrap()
+            .i32()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(durations, vec![Some(2000), Some(1800)]);
+    }
+
+    #[test]
+    fn migrate_dataset_upgrades_an_unversioned_player_file() {
+        let dir = TempDir::new("extract-migrate");
+        let old = dir.path().join("old.parquet");
+        // Written before schema versioning, with a challenge column the level does not know.
+        let mut df = df!(
+            "match_id" => ["EUW1_1"],
+            "puuid" => ["p1"],
+            "kills" => [3i64],
+            "solo_kills" => [1.0],
+        )
+        .unwrap();
+        ParquetWriter::new(File::create(&old).unwrap())
+            .finish(&mut df)
+            .unwrap();
+        let new = dir.path().join("new.parquet");
+
+        migrate_dataset(&MigrateDatasetArgs {
+            input: old,
+            output: Some(new.clone()),
+            level: Some("player".to_string()),
+            parquet: ParquetOptions::default(),
+        })
+        .unwrap();
+        let migrated = ParquetReader::new(File::open(&new).unwrap())
+            .finish()
+            .unwrap();
+        let (version, level) = stored_schema(&new).unwrap();
+
+        let current = empty_level_frame("player", false).unwrap().schema();
+        let names = migrated.get_column_names();
+        assert_eq!(names.len(), current.len() + 1);
+        assert_eq!(names.last(), Some(&"solo_kills"));
+        assert_eq!(
+            migrated.column("kills").unwrap().dtype(),
+            current.get("kills").unwrap()
+        );
+        assert_eq!(migrated.column("win").unwrap().null_count(), 1);
+        assert_eq!(
+            (version, level.as_deref()),
+            (SCHEMA_VERSION, Some("player"))
+        );
+    }
+
+    #[test]
+    fn champion_metadata_skips_match_level_bans() {
+        let champions = ChampionTable {
+            version: "14.19.1".to_string(),
+            champions: BTreeMap::from([(
+                1,
+                ChampionInfo {
+                    name: "Annie".to_string(),
+                    class: Some("Mage".to_string()),
+                    secondary_class: None,
+                    resource: Some("Mana".to_string()),
+                    release_patch: None,
+                },
+            )]),
+        };
+        let df = df!(
+            "match_id" => ["EUW1_1"],
+            "blue_top_champion_id" => [1],
+            "blue_ban1_champion_id" => [1],
+            "red_ban5_champion_id" => [1],
+        )
+        .unwrap();
+
+        let df = with_champion_metadata(df, &champions).unwrap();
+
+        assert_eq!(
+            df.get_column_names(),
+            vec![
+                "match_id",
+                "blue_top_champion_id",
+                "blue_ban1_champion_id",
+                "red_ban5_champion_id",
+                "blue_top_champion_class",
+                "blue_top_champion_secondary_class",
+                "blue_top_champion_resource",
+                "blue_top_champion_release_patch",
+            ]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::parquet_extract::{ParquetOptions, with_lane_opponent};
 use anyhow::Result;
 use polars::prelude::*;
 use std::fs::{self, File};
@@ -8,8 +9,12 @@ pub struct PlayerProfileArgs<'a> {
     pub out_parquet: &'a Path,
     pub history_size: usize,
     pub min_matches: usize,
+    pub parquet: ParquetOptions,
 }
 
+/// One row per (puuid, role) over the player's `history_size` most recent SoloQ games in that
+/// role: win rate, per-game and per-minute averages, and lane diffs against the opponent in the
+/// same role. Players with fewer than `min_matches` games in the role are left out.
 pub fn build_player_profiles(args: PlayerProfileArgs) -> Result<()> {
     let mut df = LazyFrame::scan_parquet(
         args.player_parquet.to_string_lossy().to_string(),
@@ -17,88 +22,67 @@ pub fn build_player_profiles(args: PlayerProfileArgs) -> Result<()> {
     )?
     .collect()?;
 
+    // Challenge columns are only extracted with --challenge-cols (listing their camelCase keys,
+    // or `all`); profiles get nulls without them.
     ensure_column(
         &mut df,
-        "earlyLaningPhaseGoldExpAdvantage",
+        "early_laning_phase_gold_exp_advantage",
         DataType::Float64,
     )?;
-    ensure_column(&mut df, "laningPhaseGoldExpAdvantage", DataType::Float64)?;
-    ensure_column(&mut df, "maxCsAdvantageOnLaneOpponent", DataType::Float64)?;
     ensure_column(
         &mut df,
-        "visionScoreAdvantageLaneOpponent",
+        "laning_phase_gold_exp_advantage",
+        DataType::Float64,
+    )?;
+    ensure_column(
+        &mut df,
+        "max_cs_advantage_on_lane_opponent",
+        DataType::Float64,
+    )?;
+    ensure_column(
+        &mut df,
+        "vision_score_advantage_lane_opponent",
         DataType::Float64,
     )?;
 
-    let base = df
-        .lazy()
-        .filter(col("queue_id").eq(lit(420)))
-        .filter(
-            col("role")
-                .eq(lit("TOP"))
-                .or(col("role").eq(lit("JUNGLE")))
-                .or(col("role").eq(lit("MIDDLE")))
-                .or(col("role").eq(lit("BOTTOM")))
-                .or(col("role").eq(lit("UTILITY"))),
-        )
-        .with_column(
-            when(col("team_id").eq(lit(100)))
-                .then(lit(200))
-                .otherwise(lit(100))
-                .alias("opp_team_id"),
-        );
-
-    let opponents = base.clone().select([
-        col("match_id"),
-        col("role"),
-        col("team_id"),
-        col("gold_earned").alias("opp_gold_earned"),
-        col("total_cs").alias("opp_total_cs"),
-        col("vision_score").alias("opp_vision_score"),
+    let with_opponent = with_lane_opponent(
+        df.lazy().filter(col("queue_id").eq(lit(420))),
+        &["gold_earned", "total_cs", "vision_score"],
+    )
+    .with_columns([
+        (col("gold_earned") - col("opp_gold_earned")).alias("gold_diff_vs_lane"),
+        (col("total_cs") - col("opp_total_cs")).alias("cs_diff_vs_lane"),
+        (col("vision_score") - col("opp_vision_score")).alias("vision_diff_vs_lane"),
+        col("early_laning_phase_gold_exp_advantage").alias("early_gold_xp_adv"),
+        col("laning_phase_gold_exp_advantage").alias("laning_gold_xp_adv"),
+        col("max_cs_advantage_on_lane_opponent").alias("max_cs_adv_lane"),
+        col("vision_score_advantage_lane_opponent").alias("vision_score_adv_lane"),
+    ])
+    .with_columns([
+        col("game_creation")
+            .rank(
+                RankOptions {
+                    method: RankMethod::Dense,
+                    descending: true,
+                },
+                None,
+            )
+            .over([col("puuid"), col("role")])
+            .alias("recent_rank"),
+        col("match_id")
+            .count()
+            .over([col("puuid"), col("role")])
+            .alias("games_available"),
     ]);
 
-    let with_opponent = base
-        .join(
-            opponents,
-            [col("match_id"), col("role"), col("opp_team_id")],
-            [col("match_id"), col("role"), col("team_id")],
-            JoinArgs::new(JoinType::Left),
-        )
-        .with_columns([
-            (col("gold_earned") - col("opp_gold_earned")).alias("gold_diff_vs_lane"),
-            (col("total_cs") - col("opp_total_cs")).alias("cs_diff_vs_lane"),
-            (col("vision_score") - col("opp_vision_score")).alias("vision_diff_vs_lane"),
-            col("earlyLaningPhaseGoldExpAdvantage").alias("early_gold_xp_adv"),
-            col("laningPhaseGoldExpAdvantage").alias("laning_gold_xp_adv"),
-            col("maxCsAdvantageOnLaneOpponent").alias("max_cs_adv_lane"),
-            col("visionScoreAdvantageLaneOpponent").alias("vision_score_adv_lane"),
-        ])?
-        .with_columns([
-            col("game_creation")
-                .rank(
-                    RankOptions {
-                        method: RankMethod::Dense,
-                        descending: true,
-                        ..Default::default()
-                    },
-                    None,
-                )
-                .over([col("puuid"), col("role")])
-                .alias("recent_rank"),
-            col("match_id")
-                .count()
-                .over([col("puuid"), col("role")])
-                .alias("games_available"),
-        ])?;
-
     let recent_subset = with_opponent
-        .filter(col("recent_rank").le(lit(args.history_size as u32)))
+        .filter(col("recent_rank").lt_eq(lit(args.history_size as u32)))
         .with_column(
             col("match_id")
                 .count()
                 .over([col("puuid"), col("role")])
                 .alias("games_used"),
-        )?;
+        );
 
     let aggregated = recent_subset
         .group_by([col("puuid"), col("role")])
@@ -156,18 +140,18 @@ pub fn build_player_profiles(args: PlayerProfileArgs) -> Result<()> {
                 .mean()
                 .alias("avg_vision_score_adv_lane"),
         ])
-        .filter(col("games_used").ge(lit(args.min_matches as i32)));
+        .filter(col("games_used").gt_eq(lit(args.min_matches as i32)));
 
     let mut result = aggregated.collect()?;
 
-    if let Some(parent) = args.out_parquet.parent() {
-        if !parent.as_os_str().is_empty() {
-            fs::create_dir_all(parent)?;
-        }
+    if let Some(parent) = args.out_parquet.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
     }
 
     let mut file = File::create(args.out_parquet)?;
-    ParquetWriter::new(&mut file).finish(&mut result)?;
+    args.parquet.writer(&mut file).finish(&mut result)?;
 
     println!(
         "Built {} player profiles (history_size={}, min_matches={})",
@@ -180,7 +164,7 @@ pub fn build_player_profiles(args: PlayerProfileArgs) -> Result<()> {
 }
 
 fn ensure_column(df: &mut DataFrame, name: &str, dtype: DataType) -> Result<()> {
-    if !df.get_column_names().iter().any(|c| *c == name) {
+    if !df.get_column_names().contains(&name) {
         let series = Series::full_null(name, df.height(), &dtype);
         df.with_column(series)?;
     }
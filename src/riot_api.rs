@@ -4,19 +4,53 @@ use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue, RETRY_AFTER};
 use serde::Deserialize;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::error::Error;
+use std::fmt;
 use std::fs;
 use std::path::Path;
-use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 
-const BASE_URL: &str = "https://europe.api.riotgames.com";
+const DEFAULT_PLATFORM: &str = "euw1";
 const DEFAULT_MAX_REQS_PER_2MIN: usize = 80;
 const DEFAULT_MAX_REQS_PER_SEC: usize = 20;
-static GLOBAL_RATE_LIMITER: OnceLock<Mutex<RateLimiter>> = OnceLock::new();
+// Adaptive throttling: 429s inside this window count against the effective limit, which
+// shrinks by THROTTLE_BACKOFF per 429 and grows back by THROTTLE_RECOVERY of the configured
+// limit every THROTTLE_RECOVERY_EVERY once the window has been clean.
+const THROTTLE_WINDOW: Duration = Duration::from_secs(5 * 60);
+const THROTTLE_BACKOFF: f64 = 0.75;
+const THROTTLE_RECOVERY: f64 = 0.05;
+const THROTTLE_RECOVERY_EVERY: Duration = Duration::from_secs(30);
+const THROTTLE_FLOOR: f64 = 0.2;
+
+type SharedLimiter = Arc<Mutex<RateLimiter>>;
+
+static GLOBAL_RATE_LIMITER: OnceLock<Arc<Mutex<RateLimiter>>> = OnceLock::new();
+/// Regional routes (`europe`, `americas`, ...) serve every platform routed to them under one
+/// match-v5/account-v1 limit, so their limiters are shared between platform clients.
+static REGIONAL_RATE_LIMITERS: OnceLock<Mutex<HashMap<&'static str, SharedLimiter>>> =
+    OnceLock::new();
+
+/// Most requests the per-second limit lets through in two minutes; a larger
+/// `--max-req-per-2min` is never reached.
+pub fn max_sustained_reqs_per_2min() -> usize {
+    DEFAULT_MAX_REQS_PER_SEC * 120
+}
+
+/// Maps a platform (e.g. `euw1`, `kr`) to the regional routing value used by match-v5.
+pub fn regional_route(platform: &str) -> Option<&'static str> {
+    match platform.to_lowercase().as_str() {
+        "euw1" | "eun1" | "tr1" | "ru" | "me1" => Some("europe"),
+        "na1" | "br1" | "la1" | "la2" => Some("americas"),
+        "kr" | "jp1" => Some("asia"),
+        "oc1" | "ph2" | "sg2" | "th2" | "tw2" | "vn2" => Some("sea"),
+        _ => None,
+    }
+}
 
 #[derive(Deserialize)]
 pub struct AccountResponse {
@@ -28,6 +62,15 @@ struct LeagueEntry {
     #[serde(rename = "queueType")]
     queue_type: String,
     tier: String,
+    #[serde(default)]
+    rank: String,
+}
+
+/// Solo/duo standing of a player: tier (e.g. `EMERALD`) and division (`I` to `IV`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankedEntry {
+    pub tier: String,
+    pub division: String,
 }
 
 fn build_headers() -> Result<HeaderMap, Box<dyn Error>> {
@@ -40,24 +83,54 @@ fn build_headers() -> Result<HeaderMap, Box<dyn Error>> {
     Ok(headers)
 }
 
+/// A request Riot answered with an error status, after any retries of a 429.
+#[derive(Debug)]
+pub struct StatusError {
+    pub url: String,
+    pub status: StatusCode,
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.status == StatusCode::TOO_MANY_REQUESTS {
+            write!(f, "Too many requests for URL {}", self.url)
+        } else {
+            write!(
+                f,
+                "Request to {} failed with status {}",
+                self.url, self.status
+            )
+        }
+    }
+}
+
+impl Error for StatusError {}
+
 pub struct RiotClient {
     client: Client,
     headers: HeaderMap,
+    platform: String,
+    regional_url: String,
+    platform_url: String,
+    /// Limits platform endpoints (league-v4, summoner-v4).
+    limiter: Arc<Mutex<RateLimiter>>,
+    /// Limits regional endpoints (match-v5, account-v1); shared by every client on the route.
+    regional_limiter: Arc<Mutex<RateLimiter>>,
+    /// Lock-free counters for dashboards; the limiter mutex can be held through long sleeps.
+    requests_sent: AtomicUsize,
+    throttled: AtomicUsize,
+    /// Suppresses the `[rate-limit]` notices, e.g. while a full-screen dashboard owns the
+    /// terminal; `throttled_count` keeps counting either way.
+    quiet: AtomicBool,
 }
 
 impl RiotClient {
     pub fn new() -> Result<Self, Box<dyn Error>> {
-        global_rate_limiter();
-
-        Ok(Self {
-            client: Client::new(),
-            headers: build_headers()?,
-        })
+        let limiter = global_rate_limiter().clone();
+        Self::with_limiters(DEFAULT_PLATFORM, limiter.clone(), limiter)
     }
 
     pub fn new_with_max(max_reqs_per_2min: usize) -> Result<Self, Box<dyn Error>> {
-        global_rate_limiter();
-
         {
             let limiter = global_rate_limiter();
             let mut guard = limiter
@@ -66,41 +139,145 @@ impl RiotClient {
             guard.set_max_reqs_per_2min(max_reqs_per_2min);
         }
 
+        Self::new()
+    }
+
+    /// Client bound to a single platform. Platform endpoints get their own rate limiter;
+    /// match-v5 calls share one limiter with every other platform on the same regional
+    /// route (e.g. euw1, eun1 and tr1 on `europe`), since Riot counts them together.
+    pub fn for_platform(platform: &str, max_reqs_per_2min: usize) -> Result<Self, Box<dyn Error>> {
+        let Some(region) = regional_route(platform) else {
+            return Err(format!("Unknown platform '{}'", platform).into());
+        };
+        let limiter = Arc::new(Mutex::new(RateLimiter::new(
+            max_reqs_per_2min,
+            DEFAULT_MAX_REQS_PER_SEC,
+        )));
+        Self::with_limiters(
+            platform,
+            limiter,
+            regional_rate_limiter(region, max_reqs_per_2min),
+        )
+    }
+
+    fn with_limiters(
+        platform: &str,
+        limiter: Arc<Mutex<RateLimiter>>,
+        regional_limiter: Arc<Mutex<RateLimiter>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let platform = platform.to_lowercase();
+        let Some(region) = regional_route(&platform) else {
+            return Err(format!("Unknown platform '{}'", platform).into());
+        };
+
         Ok(Self {
             client: Client::new(),
             headers: build_headers()?,
+            regional_url: format!("https://{}.api.riotgames.com", region),
+            platform_url: format!("https://{}.api.riotgames.com", platform),
+            platform,
+            limiter,
+            regional_limiter,
+            requests_sent: AtomicUsize::new(0),
+            throttled: AtomicUsize::new(0),
+            quiet: AtomicBool::new(false),
         })
     }
 
+    pub fn platform(&self) -> &str {
+        &self.platform
+    }
+
+    /// Requests sent by this client so far, including retries.
+    pub fn requests_sent(&self) -> usize {
+        self.requests_sent.load(Ordering::Relaxed)
+    }
+
+    /// 429 responses received by this client so far.
+    pub fn throttled_count(&self) -> usize {
+        self.throttled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_quiet(&self, quiet: bool) {
+        self.quiet.store(quiet, Ordering::Relaxed);
+    }
+
     pub fn get_match_ids_by_puuid(
         &self,
         puuid: &str,
         count: usize,
     ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let url = format!(
-            "{}/lol/match/v5/matches/by-puuid/{}/ids?start=0&count={}",
-            BASE_URL, puuid, count
+        self.get_match_ids_by_puuid_since(puuid, count, None)
+    }
+
+    /// Like `get_match_ids_by_puuid`, restricted to games started at or after `start_time`
+    /// (Unix seconds) when given.
+    pub fn get_match_ids_by_puuid_since(
+        &self,
+        puuid: &str,
+        count: usize,
+        start_time: Option<i64>,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        self.get_match_ids_by_puuid_between(puuid, count, start_time, None)
+    }
+
+    /// Match IDs of games started inside `[start_time, end_time]` (Unix seconds; either bound
+    /// optional), so the `count` budget is not spent on games outside a target window.
+    pub fn get_match_ids_by_puuid_between(
+        &self,
+        puuid: &str,
+        count: usize,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        self.get_match_ids_page(puuid, 0, count, start_time, end_time)
+    }
+
+    /// One page (newest first, skipping the first `start` IDs) of a windowed match history.
+    pub fn get_match_ids_page(
+        &self,
+        puuid: &str,
+        start: usize,
+        count: usize,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut url = format!(
+            "{}/lol/match/v5/matches/by-puuid/{}/ids?start={}&count={}",
+            self.regional_url, puuid, start, count
         );
+        if let Some(start_time) = start_time {
+            url.push_str(&format!("&startTime={}", start_time));
+        }
+        if let Some(end_time) = end_time {
+            url.push_str(&format!("&endTime={}", end_time));
+        }
 
-        self.get_json(&url)
+        self.get_json(&url, &self.regional_limiter)
     }
 
     pub fn get_match_json(&self, match_id: &str) -> Result<Value, Box<dyn std::error::Error>> {
-        let url = format!("{}/lol/match/v5/matches/{}", BASE_URL, match_id);
+        let url = format!("{}/lol/match/v5/matches/{}", self.regional_url, match_id);
 
-        self.get_json(&url)
+        self.get_json(&url, &self.regional_limiter)
     }
 
-    pub fn get_ranked_tier_by_puuid(
+    pub fn get_ranked_entry_by_puuid(
         &self,
         puuid: &str,
-    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
-        let url = format!("{}/lol/league/v4/entries/by-puuid/{}", BASE_URL, puuid);
+    ) -> Result<Option<RankedEntry>, Box<dyn std::error::Error>> {
+        let url = format!(
+            "{}/lol/league/v4/entries/by-puuid/{}",
+            self.platform_url, puuid
+        );
 
-        let entries: Vec<LeagueEntry> = self.get_json(&url)?;
+        let entries: Vec<LeagueEntry> = self.get_json(&url, &self.limiter)?;
         for entry in entries {
             if entry.queue_type == "RANKED_SOLO_5x5" {
-                return Ok(Some(entry.tier));
+                return Ok(Some(RankedEntry {
+                    tier: entry.tier.to_uppercase(),
+                    division: entry.rank.to_uppercase(),
+                }));
             }
         }
 
@@ -114,31 +291,65 @@ impl RiotClient {
     ) -> Result<AccountResponse, Box<dyn Error>> {
         let url = format!(
             "{}/riot/account/v1/accounts/by-riot-id/{}/{}",
-            BASE_URL, game_name, tag_line
+            self.account_url(),
+            game_name,
+            tag_line
         );
 
-        self.get_json(&url)
+        self.get_json(&url, &self.regional_limiter)
     }
 
-    fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, Box<dyn Error>> {
-        let response = self.request_with_retry(url)?;
+    // account-v1 is not served from the sea cluster; those accounts resolve through asia.
+    fn account_url(&self) -> String {
+        self.regional_url.replace("://sea.", "://asia.")
+    }
+
+    fn get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        limiter: &Mutex<RateLimiter>,
+    ) -> Result<T, Box<dyn Error>> {
+        let response = self.request_with_retry(url, limiter)?;
         Ok(response.json()?)
     }
 
-    fn request_with_retry(&self, url: &str) -> Result<reqwest::blocking::Response, Box<dyn Error>> {
+    fn request_with_retry(
+        &self,
+        url: &str,
+        limiter: &Mutex<RateLimiter>,
+    ) -> Result<reqwest::blocking::Response, Box<dyn Error>> {
         const MAX_ATTEMPTS: usize = 2;
         let mut attempt = 0;
 
         loop {
             attempt += 1;
 
-            wait_global_rate_limit();
+            limiter
+                .lock()
+                .expect("Rate limiter mutex poisoned while waiting")
+                .wait();
 
+            self.requests_sent.fetch_add(1, Ordering::Relaxed);
             let response = self.client.get(url).headers(self.headers.clone()).send()?;
 
             if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                self.throttled.fetch_add(1, Ordering::Relaxed);
+                let notice = {
+                    let mut limiter = limiter
+                        .lock()
+                        .expect("Rate limiter mutex poisoned while throttling");
+                    limiter.record_throttled();
+                    limiter.throttle_notice()
+                };
+                if !self.quiet.load(Ordering::Relaxed) {
+                    eprintln!("{}", notice);
+                }
+
                 if attempt >= MAX_ATTEMPTS {
-                    return Err(format!("Too many requests for URL {}", url).into());
+                    return Err(Box::new(StatusError {
+                        url: url.to_string(),
+                        status: response.status(),
+                    }));
                 }
 
                 if let Some(retry_after) = parse_retry_after(&response) {
@@ -151,12 +362,10 @@ impl RiotClient {
             }
 
             if !response.status().is_success() {
-                return Err(format!(
-                    "Request to {} failed with status {}",
-                    url,
-                    response.status()
-                )
-                .into());
+                return Err(Box::new(StatusError {
+                    url: url.to_string(),
+                    status: response.status(),
+                }));
             }
 
             return Ok(response);
@@ -164,49 +373,102 @@ impl RiotClient {
     }
 }
 
+/// Sliding-window limiter. The 2-minute limit adapts to observed 429s: it is the configured
+/// maximum until Riot starts throttling, then shrinks and slowly recovers.
 pub struct RateLimiter {
     max_reqs_per_2min: usize,
+    effective_reqs_per_2min: usize,
     max_reqs_per_sec: usize,
     timestamps_2min: VecDeque<Instant>,
     timestamps_1s: VecDeque<Instant>,
+    throttled_at: VecDeque<Instant>,
+    last_adjusted: Instant,
 }
 
 impl RateLimiter {
     pub fn new(max_reqs_per_2min: usize, max_reqs_per_sec: usize) -> Self {
         Self {
             max_reqs_per_2min,
+            effective_reqs_per_2min: max_reqs_per_2min,
             max_reqs_per_sec,
             timestamps_2min: VecDeque::new(),
             timestamps_1s: VecDeque::new(),
+            throttled_at: VecDeque::new(),
+            last_adjusted: Instant::now(),
         }
     }
 
     pub fn set_max_reqs_per_2min(&mut self, max_reqs_per_2min: usize) {
         self.max_reqs_per_2min = max_reqs_per_2min;
+        self.effective_reqs_per_2min = max_reqs_per_2min;
+    }
+
+    /// Registers a 429 and lowers the effective 2-minute limit.
+    pub fn record_throttled(&mut self) {
+        let now = Instant::now();
+        self.throttled_at.push_back(now);
+        self.last_adjusted = now;
+
+        let floor = ((self.max_reqs_per_2min as f64 * THROTTLE_FLOOR).ceil() as usize).max(1);
+        let lowered = (self.effective_reqs_per_2min as f64 * THROTTLE_BACKOFF) as usize;
+        self.effective_reqs_per_2min = lowered.max(floor);
+    }
+
+    fn throttle_notice(&self) -> String {
+        format!(
+            "[rate-limit] {} 429s in the last {} min; lowering limit to {} req/2min",
+            self.throttled_at.len(),
+            THROTTLE_WINDOW.as_secs() / 60,
+            self.effective_reqs_per_2min
+        )
+    }
+
+    fn recover(&mut self, now: Instant) {
+        while let Some(front) = self.throttled_at.front() {
+            if now.duration_since(*front) > THROTTLE_WINDOW {
+                self.throttled_at.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if !self.throttled_at.is_empty()
+            || self.effective_reqs_per_2min >= self.max_reqs_per_2min
+            || now.duration_since(self.last_adjusted) < THROTTLE_RECOVERY_EVERY
+        {
+            return;
+        }
+
+        let step = ((self.max_reqs_per_2min as f64 * THROTTLE_RECOVERY).ceil() as usize).max(1);
+        self.effective_reqs_per_2min =
+            (self.effective_reqs_per_2min + step).min(self.max_reqs_per_2min);
+        self.last_adjusted = now;
     }
 
     pub fn wait(&mut self) {
         loop {
             let now = Instant::now();
             self.prune(now);
+            self.recover(now);
 
             let mut sleep_duration: Option<Duration> = None;
 
-            if self.timestamps_1s.len() >= self.max_reqs_per_sec {
-                if let Some(oldest) = self.timestamps_1s.front() {
-                    let elapsed = now.duration_since(*oldest);
-                    if elapsed < Duration::from_secs(1) {
-                        sleep_duration = Some(Duration::from_secs(1) - elapsed);
-                    }
+            if self.timestamps_1s.len() >= self.max_reqs_per_sec
+                && let Some(oldest) = self.timestamps_1s.front()
+            {
+                let elapsed = now.duration_since(*oldest);
+                if elapsed < Duration::from_secs(1) {
+                    sleep_duration = Some(Duration::from_secs(1) - elapsed);
                 }
             }
 
-            if sleep_duration.is_none() && self.timestamps_2min.len() >= self.max_reqs_per_2min {
-                if let Some(oldest) = self.timestamps_2min.front() {
-                    let elapsed = now.duration_since(*oldest);
-                    if elapsed < Duration::from_secs(120) {
-                        sleep_duration = Some(Duration::from_secs(120) - elapsed);
-                    }
+            if sleep_duration.is_none()
+                && self.timestamps_2min.len() >= self.effective_reqs_per_2min
+                && let Some(oldest) = self.timestamps_2min.front()
+            {
+                let elapsed = now.duration_since(*oldest);
+                if elapsed < Duration::from_secs(120) {
+                    sleep_duration = Some(Duration::from_secs(120) - elapsed);
                 }
             }
 
@@ -241,21 +503,30 @@ impl RateLimiter {
     }
 }
 
-fn global_rate_limiter() -> &'static Mutex<RateLimiter> {
+fn global_rate_limiter() -> &'static Arc<Mutex<RateLimiter>> {
     GLOBAL_RATE_LIMITER.get_or_init(|| {
-        Mutex::new(RateLimiter::new(
+        Arc::new(Mutex::new(RateLimiter::new(
             DEFAULT_MAX_REQS_PER_2MIN,
             DEFAULT_MAX_REQS_PER_SEC,
-        ))
+        )))
     })
 }
 
-fn wait_global_rate_limit() {
-    let limiter = global_rate_limiter();
-    let mut guard = limiter
+/// The limiter shared by every platform client on `region`, created with the first client's
+/// `max_reqs_per_2min`.
+fn regional_rate_limiter(region: &'static str, max_reqs_per_2min: usize) -> SharedLimiter {
+    REGIONAL_RATE_LIMITERS
+        .get_or_init(|| Mutex::new(HashMap::new()))
         .lock()
-        .expect("Rate limiter mutex poisoned while waiting");
-    guard.wait();
+        .expect("Regional rate limiter registry poisoned")
+        .entry(region)
+        .or_insert_with(|| {
+            Arc::new(Mutex::new(RateLimiter::new(
+                max_reqs_per_2min,
+                DEFAULT_MAX_REQS_PER_SEC,
+            )))
+        })
+        .clone()
 }
 
 fn parse_retry_after(response: &reqwest::blocking::Response) -> Option<Duration> {
@@ -1,12 +1,109 @@
-use csv::Writer;
+use crate::kraken::TimeBound;
+use crate::kraken_manifest::parse_queues;
+use crate::match_data::{MatchInfo, Participant};
+use crate::parquet_extract::{
+    collect_match_files, file_match_id, lane_opponent, per_min, read_match_documents,
+};
+use chrono::Utc;
+use csv::{ReaderBuilder, Writer, WriterBuilder};
 use serde::Serialize;
 use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::error::Error;
-use std::fs;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
 
-#[derive(Serialize)]
+/// File format of the stats output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsFormat {
+    Csv,
+    /// One JSON array of row objects.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+impl StatsFormat {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        match raw.to_lowercase().as_str() {
+            "csv" => Ok(StatsFormat::Csv),
+            "json" => Ok(StatsFormat::Json),
+            "ndjson" | "jsonl" => Ok(StatsFormat::Ndjson),
+            other => Err(format!(
+                "Invalid format '{}' (use csv, json or ndjson)",
+                other
+            )),
+        }
+    }
+}
+
+/// Writes rows one at a time in any [`StatsFormat`].
+enum RowWriter {
+    Csv(Box<Writer<File>>),
+    Json { out: BufWriter<File>, rows: usize },
+    Ndjson(BufWriter<File>),
+}
+
+impl RowWriter {
+    fn create(path: &Path, format: StatsFormat) -> Result<Self, Box<dyn Error>> {
+        Ok(match format {
+            StatsFormat::Csv => RowWriter::Csv(Box::new(Writer::from_path(path)?)),
+            StatsFormat::Json => {
+                let mut out = BufWriter::new(File::create(path)?);
+                out.write_all(b"[")?;
+                RowWriter::Json { out, rows: 0 }
+            }
+            StatsFormat::Ndjson => RowWriter::Ndjson(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    /// Adds rows after those already in `path`, which must be CSV or NDJSON. A CSV file keeps
+    /// its header.
+    fn append(path: &Path, format: StatsFormat) -> Result<Self, Box<dyn Error>> {
+        let file = OpenOptions::new().append(true).open(path)?;
+        Ok(match format {
+            StatsFormat::Csv => RowWriter::Csv(Box::new(
+                WriterBuilder::new().has_headers(false).from_writer(file),
+            )),
+            StatsFormat::Ndjson => RowWriter::Ndjson(BufWriter::new(file)),
+            StatsFormat::Json => return Err("--append does not support --format json".into()),
+        })
+    }
+
+    fn write(&mut self, row: &impl Serialize) -> Result<(), Box<dyn Error>> {
+        match self {
+            RowWriter::Csv(writer) => writer.serialize(row)?,
+            RowWriter::Json { out, rows } => {
+                out.write_all(if *rows == 0 { b"\n  " } else { b",\n  " })?;
+                serde_json::to_writer(&mut *out, row)?;
+                *rows += 1;
+            }
+            RowWriter::Ndjson(out) => {
+                serde_json::to_writer(&mut *out, row)?;
+                out.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Box<dyn Error>> {
+        match self {
+            RowWriter::Csv(mut writer) => writer.flush()?,
+            RowWriter::Json { mut out, rows } => {
+                out.write_all(if rows == 0 { b"]\n" } else { b"\n]\n" })?;
+                out.flush()?;
+            }
+            RowWriter::Ndjson(mut out) => out.flush()?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default, Serialize)]
 struct BasicStatsRow {
+    puuid: String,
     match_id: String,
     game_creation: i64,
     queue_id: i64,
@@ -16,140 +113,910 @@ struct BasicStatsRow {
     kills: i64,
     deaths: i64,
     assists: i64,
+    /// (kills + assists) / deaths, with deathless games divided by 1.
+    kda: f64,
+    champ_level: i64,
     cs_total: i64,
     gold_earned: i64,
+    gold_per_min: Option<f64>,
+    damage_to_champions: i64,
+    damage_per_min: Option<f64>,
+    vision_score: i64,
     game_duration: i64,
+    /// Champion of the other team's player in the same position; empty without one.
+    opp_champion_name: Option<String>,
+    /// End-of-game differences to that opponent, named like the player-profile columns; empty
+    /// without one.
+    gold_diff_vs_lane: Option<i64>,
+    cs_diff_vs_lane: Option<i64>,
+    vision_diff_vs_lane: Option<i64>,
 }
 
-pub fn extract_basic_stats_for_puuid(
-    puuid: &str,
+/// Writes one row per game of each player in `puuids`, in match file order. With `append`, an
+/// existing `out_file` is kept and only games it lacks are added to it. With `summary`, a short
+/// aggregate of every matched game (written now or before) is printed afterwards.
+pub fn extract_basic_stats(
+    puuids: &[String],
     matches_dir: &Path,
+    filter: &GameFilter,
     out_file: &Path,
+    format: StatsFormat,
+    append: bool,
+    summary: bool,
 ) -> Result<(), Box<dyn Error>> {
-    if let Some(parent) = out_file.parent() {
-        if !parent.as_os_str().is_empty() {
-            fs::create_dir_all(parent)?;
+    if let Some(parent) = out_file.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+
+    if append && format == StatsFormat::Json {
+        return Err("--append does not support --format json; use csv or ndjson".into());
+    }
+    // A CSV without rows has no header yet either, so it is rewritten from scratch.
+    let existing = if append && fs::metadata(out_file).is_ok_and(|meta| meta.len() > 0) {
+        Some(existing_stats_keys(out_file, format)?)
+    } else {
+        None
+    };
+    let mut writer = match existing {
+        Some(_) => RowWriter::append(out_file, format)?,
+        None => RowWriter::create(out_file, format)?,
+    };
+    let mut added = 0usize;
+    let mut write_error: Option<Box<dyn Error>> = None;
+    let mut totals = ChampionTotals::default();
+    let mut champions: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+    for_each_game(
+        puuids,
+        matches_dir,
+        filter,
+        |match_id, info, participants, participant| {
+            totals.games += 1;
+            totals.wins += participant.win as i64;
+            totals.kills += participant.kills as i64;
+            totals.deaths += participant.deaths as i64;
+            totals.assists += participant.assists as i64;
+            totals.cs += participant.total_cs() as i64;
+            totals.seconds += info.game_duration;
+            let champion = champions
+                .entry(participant.champion_name.clone())
+                .or_default();
+            champion.0 += 1;
+            champion.1 += participant.win as i64;
+
+            if write_error.is_some()
+                || existing.as_ref().is_some_and(|keys| {
+                    keys.contains(&(participant.puuid.clone(), match_id.to_string()))
+                })
+            {
+                return;
+            }
+            let opponent = lane_opponent(participants, participant);
+            let row = BasicStatsRow {
+                puuid: participant.puuid.clone(),
+                match_id: match_id.to_string(),
+                game_creation: info.game_creation,
+                queue_id: info.queue_id,
+                champion_name: participant.champion_name.clone(),
+                role: participant.team_position.clone(),
+                win: if participant.win { 1 } else { 0 },
+                kills: participant.kills as i64,
+                deaths: participant.deaths as i64,
+                assists: participant.assists as i64,
+                kda: (participant.kills + participant.assists) as f64
+                    / participant.deaths.max(1) as f64,
+                champ_level: participant.champ_level as i64,
+                cs_total: participant.total_cs() as i64,
+                gold_earned: participant.gold_earned as i64,
+                gold_per_min: per_min(participant.gold_earned as i64, info.game_duration as i32),
+                damage_to_champions: participant.total_damage_dealt_to_champions as i64,
+                damage_per_min: per_min(
+                    participant.total_damage_dealt_to_champions as i64,
+                    info.game_duration as i32,
+                ),
+                vision_score: participant.vision_score as i64,
+                game_duration: info.game_duration,
+                opp_champion_name: opponent.map(|opponent| opponent.champion_name.clone()),
+                gold_diff_vs_lane: opponent
+                    .map(|opponent| participant.gold_earned as i64 - opponent.gold_earned as i64),
+                cs_diff_vs_lane: opponent
+                    .map(|opponent| participant.total_cs() as i64 - opponent.total_cs() as i64),
+                vision_diff_vs_lane: opponent
+                    .map(|opponent| participant.vision_score as i64 - opponent.vision_score as i64),
+            };
+            match writer.write(&row) {
+                Ok(()) => added += 1,
+                Err(err) => write_error = Some(err),
+            }
+        },
+    );
+
+    if let Some(err) = write_error {
+        return Err(format!("failed to write {}: {}", out_file.display(), err).into());
+    }
+    writer.finish()?;
+    if existing.is_some() {
+        println!("Appended {} new rows to {}", added, out_file.display());
+    }
+    if summary {
+        print_stats_summary(&totals, champions);
+    }
+    Ok(())
+}
+
+/// Number of champions listed by `extract-stats --summary`.
+const SUMMARY_TOP_CHAMPIONS: usize = 3;
+
+fn print_stats_summary(totals: &ChampionTotals, champions: BTreeMap<String, (i64, i64)>) {
+    if totals.games == 0 {
+        println!("No games matched");
+        return;
+    }
+    println!(
+        "Games: {} ({}W {}L), win rate {:.1}%",
+        totals.games,
+        totals.wins,
+        totals.games - totals.wins,
+        totals.wins as f64 / totals.games as f64 * 100.0
+    );
+    println!(
+        "KDA: {:.2}, CS/min: {:.2}",
+        (totals.kills + totals.assists) as f64 / totals.deaths.max(1) as f64,
+        per_min(totals.cs, totals.seconds as i32).unwrap_or(0.0)
+    );
+    let mut champions: Vec<(String, (i64, i64))> = champions.into_iter().collect();
+    champions.sort_by_key(|(_, (games, _))| Reverse(*games));
+    let top: Vec<String> = champions
+        .iter()
+        .take(SUMMARY_TOP_CHAMPIONS)
+        .map(|(name, (games, wins))| {
+            format!(
+                "{} {} games ({:.1}%)",
+                name,
+                games,
+                *wins as f64 / *games as f64 * 100.0
+            )
+        })
+        .collect();
+    println!("Top champions: {}", top.join(", "));
+}
+
+/// `(puuid, match_id)` of every row of a stats file written earlier.
+fn existing_stats_keys(
+    path: &Path,
+    format: StatsFormat,
+) -> Result<HashSet<(String, String)>, Box<dyn Error>> {
+    let outdated = || {
+        format!(
+            "{} has no puuid or match_id column; rebuild it without --append",
+            path.display()
+        )
+    };
+    let mut keys = HashSet::new();
+    match format {
+        StatsFormat::Csv => {
+            let mut reader = ReaderBuilder::new().from_path(path)?;
+            let headers = reader.headers()?.clone();
+            // Appended rows would not line up under another header.
+            if headers.iter().ne(basic_stats_columns()?.iter()) {
+                return Err(format!(
+                    "{} was written with other columns; rebuild it without --append",
+                    path.display()
+                )
+                .into());
+            }
+            let column = |name: &str| headers.iter().position(|header| header == name);
+            let (Some(puuid), Some(match_id)) = (column("puuid"), column("match_id")) else {
+                return Err(outdated().into());
+            };
+            for record in reader.records() {
+                let record = record?;
+                keys.insert((record[puuid].to_string(), record[match_id].to_string()));
+            }
+        }
+        StatsFormat::Ndjson => {
+            for line in BufReader::new(File::open(path)?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let row: Value = serde_json::from_str(&line)?;
+                let (Some(puuid), Some(match_id)) =
+                    (row["puuid"].as_str(), row["match_id"].as_str())
+                else {
+                    return Err(outdated().into());
+                };
+                keys.insert((puuid.to_string(), match_id.to_string()));
+            }
         }
+        StatsFormat::Json => return Err("--append does not support --format json".into()),
     }
+    Ok(keys)
+}
 
-    let mut writer = Writer::from_path(out_file)?;
+/// CSV header of [`BasicStatsRow`].
+fn basic_stats_columns() -> Result<Vec<String>, Box<dyn Error>> {
+    let mut writer = Writer::from_writer(Vec::new());
+    writer.serialize(BasicStatsRow::default())?;
+    let csv = writer.into_inner().map_err(|err| err.to_string())?;
+    let mut reader = ReaderBuilder::new().from_reader(csv.as_slice());
+    Ok(reader.headers()?.iter().map(str::to_string).collect())
+}
 
-    for entry in fs::read_dir(matches_dir)? {
-        let entry = match entry {
-            Ok(entry) => entry,
-            Err(_) => continue,
+/// Queue and date restrictions on the games read, mirroring the extract-parquet filters.
+#[derive(Debug, Default)]
+pub struct GameFilter {
+    queues: Option<BTreeSet<i64>>,
+    /// `gameCreation` bounds in Unix milliseconds.
+    since_ms: Option<i64>,
+    until_ms: Option<i64>,
+}
+
+impl GameFilter {
+    /// `queues` takes IDs or labels (`soloq,flex`).
+    pub fn new(
+        queues: Option<&str>,
+        since: Option<TimeBound>,
+        until: Option<TimeBound>,
+    ) -> Result<Self, String> {
+        let now = Utc::now().timestamp();
+        Ok(Self {
+            queues: queues.map(parse_queues).transpose()?,
+            since_ms: since.map(|bound| bound.resolve(now) * 1000),
+            until_ms: until.map(|bound| bound.resolve(now) * 1000),
+        })
+    }
+
+    fn accepts(&self, info: &MatchInfo) -> bool {
+        self.queues
+            .as_ref()
+            .is_none_or(|queues| queues.contains(&info.queue_id))
+            && self
+                .since_ms
+                .is_none_or(|since| info.game_creation >= since)
+            && self.until_ms.is_none_or(|until| info.game_creation < until)
+    }
+}
+
+/// Calls `visit` with every game of a player in `puuids` found under `matches_dir`, in match
+/// file order, skipping those `filter` rejects. Later copies of an already visited match are
+/// ignored.
+fn for_each_game(
+    puuids: &[String],
+    matches_dir: &Path,
+    filter: &GameFilter,
+    mut visit: impl FnMut(&str, &MatchInfo, &[Participant], &Participant),
+) {
+    let wanted: HashSet<&str> = puuids.iter().map(String::as_str).collect();
+    let mut seen: HashSet<String> = HashSet::new();
+
+    // Searched recursively, like extract-parquet, so kraken and partitioned layouts work too.
+    let files = collect_match_files(&[matches_dir.to_path_buf()]);
+    let documents = files
+        .iter()
+        .flat_map(|path| read_match_documents(path).map(move |document| (path, document)));
+
+    for (path, document) in documents {
+        let Some(info) = &document.info else {
+            continue;
         };
 
-        let path = entry.path();
-        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+        if !filter.accepts(info) {
             continue;
         }
 
-        let contents = match fs::read_to_string(&path) {
-            Ok(data) => data,
-            Err(_) => continue,
+        let Some(participants) = &info.participants else {
+            continue;
         };
 
-        let parsed: Value = match serde_json::from_str(&contents) {
-            Ok(value) => value,
-            Err(_) => continue,
+        let Some(match_id) = document.match_id().or_else(|| file_match_id(path)) else {
+            continue;
         };
 
-        let info = match parsed.get("info") {
-            Some(value) => value,
-            None => continue,
-        };
+        if !seen.insert(match_id.to_string()) {
+            continue;
+        }
 
-        let participants = match info.get("participants").and_then(|p| p.as_array()) {
-            Some(list) => list,
-            None => continue,
-        };
+        for participant in participants
+            .iter()
+            .filter(|p| wanted.contains(p.puuid.as_str()))
+        {
+            visit(match_id, info, participants, participant);
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChampionSummaryRow {
+    champion_name: String,
+    role: String,
+    games: i64,
+    wins: i64,
+    win_rate: f64,
+    /// Summed (kills + assists) over summed deaths, with deathless totals divided by 1.
+    kda: f64,
+    cs_per_min: f64,
+    /// Mean share of the team's damage to champions.
+    damage_share: f64,
+}
+
+#[derive(Default)]
+struct ChampionTotals {
+    games: i64,
+    wins: i64,
+    kills: i64,
+    deaths: i64,
+    assists: i64,
+    cs: i64,
+    seconds: i64,
+    damage_share: f64,
+}
+
+/// Aggregates the games of the players in `puuids` into one row per champion and role, most
+/// played first. Prints the table and, with `out_file`, also writes it in `format`.
+pub fn champion_summary(
+    puuids: &[String],
+    matches_dir: &Path,
+    out_file: Option<&Path>,
+    format: StatsFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut totals: BTreeMap<(String, String), ChampionTotals> = BTreeMap::new();
+    for_each_game(
+        puuids,
+        matches_dir,
+        &GameFilter::default(),
+        |_, info, participants, participant| {
+            let team_damage: i64 = participants
+                .iter()
+                .filter(|other| other.team_id == participant.team_id)
+                .map(|other| other.total_damage_dealt_to_champions as i64)
+                .sum();
+            let entry = totals
+                .entry((
+                    participant.champion_name.clone(),
+                    participant.team_position.clone(),
+                ))
+                .or_default();
+            entry.games += 1;
+            entry.wins += participant.win as i64;
+            entry.kills += participant.kills as i64;
+            entry.deaths += participant.deaths as i64;
+            entry.assists += participant.assists as i64;
+            entry.cs += participant.total_cs() as i64;
+            entry.seconds += info.game_duration;
+            if team_damage > 0 {
+                entry.damage_share +=
+                    participant.total_damage_dealt_to_champions as f64 / team_damage as f64;
+            }
+        },
+    );
+
+    let mut rows: Vec<ChampionSummaryRow> = totals
+        .into_iter()
+        .map(|((champion_name, role), totals)| ChampionSummaryRow {
+            champion_name,
+            role,
+            games: totals.games,
+            wins: totals.wins,
+            win_rate: totals.wins as f64 / totals.games as f64,
+            kda: (totals.kills + totals.assists) as f64 / totals.deaths.max(1) as f64,
+            cs_per_min: per_min(totals.cs, totals.seconds as i32).unwrap_or(0.0),
+            damage_share: totals.damage_share / totals.games as f64,
+        })
+        .collect();
+    rows.sort_by_key(|row| Reverse(row.games));
+
+    println!(
+        "{:<16} {:<8} {:>5} {:>6} {:>5} {:>7} {:>8}",
+        "Champion", "Role", "Games", "Win%", "KDA", "CS/min", "Dmg%"
+    );
+    for row in &rows {
+        println!(
+            "{:<16} {:<8} {:>5} {:>5.1}% {:>5.2} {:>7.2} {:>7.1}%",
+            row.champion_name,
+            row.role,
+            row.games,
+            row.win_rate * 100.0,
+            row.kda,
+            row.cs_per_min,
+            row.damage_share * 100.0
+        );
+    }
+    if rows.is_empty() {
+        println!(
+            "No games found for the given players in {}",
+            matches_dir.display()
+        );
+    }
 
-        let participant = match participants.iter().find(|p| {
-            p.get("puuid")
-                .and_then(|value| value.as_str())
-                .map(|value| value == puuid)
-                .unwrap_or(false)
-        }) {
-            Some(p) => p,
-            None => continue,
+    if let Some(out_file) = out_file {
+        if let Some(parent) = out_file.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = RowWriter::create(out_file, format)?;
+        for row in &rows {
+            writer.write(row)?;
+        }
+        writer.finish()?;
+    }
+    Ok(())
+}
+
+/// Rolling game counts of the `form` rows.
+const FORM_WINDOWS: [usize; 2] = [10, 20];
+
+#[derive(Serialize)]
+struct FormRow {
+    puuid: String,
+    match_id: String,
+    game_creation: i64,
+    champion_name: String,
+    win: u8,
+    /// Win rate over this game and the previous 9 (or 19); empty until that many were played.
+    winrate_last10: Option<f64>,
+    winrate_last20: Option<f64>,
+    /// Consecutive results up to this game: +3 after three wins, -2 after two losses.
+    streak: i64,
+}
+
+/// Rolling win rates and streaks over each player's games, oldest first. Prints every player's
+/// current form and, with `out_file`, writes one row per game in `format`.
+pub fn form(
+    puuids: &[String],
+    matches_dir: &Path,
+    out_file: Option<&Path>,
+    format: StatsFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut games: BTreeMap<String, Vec<(i64, String, String, bool)>> = BTreeMap::new();
+    for_each_game(
+        puuids,
+        matches_dir,
+        &GameFilter::default(),
+        |match_id, info, _, participant| {
+            games.entry(participant.puuid.clone()).or_default().push((
+                info.game_creation,
+                match_id.to_string(),
+                participant.champion_name.clone(),
+                participant.win,
+            ));
+        },
+    );
+
+    let mut rows: Vec<FormRow> = Vec::new();
+    for puuid in puuids {
+        let Some(mut player_games) = games.remove(puuid) else {
+            println!("{}: no games found in {}", puuid, matches_dir.display());
+            continue;
         };
+        player_games.sort();
 
-        let match_id = parsed
-            .get("metadata")
-            .and_then(|metadata| metadata.get("matchId"))
-            .and_then(|value| value.as_str())
-            .map(|value| value.to_string())
-            .or_else(|| {
-                path.file_stem()
-                    .and_then(|name| name.to_str())
-                    .map(|value| value.to_string())
+        let first = rows.len();
+        let mut streak = 0i64;
+        for (idx, (game_creation, match_id, champion_name, win)) in player_games.iter().enumerate()
+        {
+            streak = match (*win, streak) {
+                (true, s) if s > 0 => s + 1,
+                (true, _) => 1,
+                (false, s) if s < 0 => s - 1,
+                (false, _) => -1,
+            };
+            let winrate = |window: usize| {
+                (idx + 1 >= window).then(|| {
+                    let wins = player_games[idx + 1 - window..=idx]
+                        .iter()
+                        .filter(|game| game.3)
+                        .count();
+                    wins as f64 / window as f64
+                })
+            };
+            rows.push(FormRow {
+                puuid: puuid.clone(),
+                match_id: match_id.clone(),
+                game_creation: *game_creation,
+                champion_name: champion_name.clone(),
+                win: *win as u8,
+                winrate_last10: winrate(FORM_WINDOWS[0]),
+                winrate_last20: winrate(FORM_WINDOWS[1]),
+                streak,
             });
+        }
 
-        let Some(match_id) = match_id else {
-            continue;
+        let player_rows = &rows[first..];
+        let recent = |window: usize| {
+            let last = &player_rows[player_rows.len().saturating_sub(window)..];
+            let wins = last.iter().filter(|row| row.win == 1).count();
+            format!("{}-{}", wins, last.len() - wins)
         };
+        println!(
+            "{}: {} games, last {} {}, last {} {}, streak {}",
+            puuid,
+            player_rows.len(),
+            FORM_WINDOWS[0],
+            recent(FORM_WINDOWS[0]),
+            FORM_WINDOWS[1],
+            recent(FORM_WINDOWS[1]),
+            match streak {
+                s if s > 0 => format!("{}W", s),
+                s => format!("{}L", -s),
+            }
+        );
+    }
+
+    if let Some(out_file) = out_file {
+        if let Some(parent) = out_file.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = RowWriter::create(out_file, format)?;
+        for row in &rows {
+            writer.write(row)?;
+        }
+        writer.finish()?;
+    }
+    Ok(())
+}
+
+/// The other player of `head-to-head`.
+#[derive(Debug, Clone)]
+pub enum Opponent {
+    Puuid(String),
+    /// `game_name#tag_line`, matched against the Riot IDs recorded in the matches.
+    RiotId {
+        game_name: String,
+        tag_line: String,
+    },
+}
+
+impl Opponent {
+    pub fn parse_riot_id(raw: &str) -> Result<Self, String> {
+        match raw.split_once('#') {
+            Some((game_name, tag_line)) if !game_name.is_empty() && !tag_line.is_empty() => {
+                Ok(Opponent::RiotId {
+                    game_name: game_name.to_string(),
+                    tag_line: tag_line.to_string(),
+                })
+            }
+            _ => Err(format!("Invalid Riot ID '{}' (use Name#TAG)", raw)),
+        }
+    }
+
+    fn is(&self, participant: &Participant) -> bool {
+        match self {
+            Opponent::Puuid(puuid) => participant.puuid == *puuid,
+            Opponent::RiotId {
+                game_name,
+                tag_line,
+            } => {
+                participant
+                    .riot_id_game_name
+                    .as_deref()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(game_name))
+                    && participant
+                        .riot_id_tagline
+                        .as_deref()
+                        .is_some_and(|tag| tag.eq_ignore_ascii_case(tag_line))
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct HeadToHeadRow {
+    match_id: String,
+    game_creation: i64,
+    /// `with` when on the same team, `against` otherwise.
+    relation: &'static str,
+    win: u8,
+    champion_name: String,
+    role: String,
+    other_champion_name: String,
+    other_role: String,
+    /// Differences to the other player (yours minus theirs).
+    kills_diff: i64,
+    deaths_diff: i64,
+    gold_diff: i64,
+    cs_diff: i64,
+    damage_diff: i64,
+}
+
+/// Finds the matches `puuid` shared with `other` and reports the record with and against them,
+/// and the lane stats of the games where they played the same position on opposite teams.
+pub fn head_to_head(
+    puuid: &str,
+    other: &Opponent,
+    matches_dir: &Path,
+    out_file: Option<&Path>,
+    format: StatsFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut rows: Vec<HeadToHeadRow> = Vec::new();
+    for_each_game(
+        &[puuid.to_string()],
+        matches_dir,
+        &GameFilter::default(),
+        |match_id, info, participants, player| {
+            let Some(them) = participants
+                .iter()
+                .find(|p| p.puuid != player.puuid && other.is(p))
+            else {
+                return;
+            };
+            rows.push(HeadToHeadRow {
+                match_id: match_id.to_string(),
+                game_creation: info.game_creation,
+                relation: if them.team_id == player.team_id {
+                    "with"
+                } else {
+                    "against"
+                },
+                win: player.win as u8,
+                champion_name: player.champion_name.clone(),
+                role: player.team_position.clone(),
+                other_champion_name: them.champion_name.clone(),
+                other_role: them.team_position.clone(),
+                kills_diff: (player.kills - them.kills) as i64,
+                deaths_diff: (player.deaths - them.deaths) as i64,
+                gold_diff: (player.gold_earned - them.gold_earned) as i64,
+                cs_diff: (player.total_cs() - them.total_cs()) as i64,
+                damage_diff: (player.total_damage_dealt_to_champions
+                    - them.total_damage_dealt_to_champions) as i64,
+            });
+        },
+    );
+    rows.sort_by_key(|row| row.game_creation);
+
+    let record = |rows: &[&HeadToHeadRow]| {
+        let wins = rows.iter().filter(|row| row.win == 1).count();
+        format!("{}-{}", wins, rows.len() - wins)
+    };
+    let with: Vec<&HeadToHeadRow> = rows.iter().filter(|row| row.relation == "with").collect();
+    let against: Vec<&HeadToHeadRow> = rows
+        .iter()
+        .filter(|row| row.relation == "against")
+        .collect();
+    let lane: Vec<&HeadToHeadRow> = against
+        .iter()
+        .copied()
+        .filter(|row| !row.role.is_empty() && row.role == row.other_role)
+        .collect();
 
-        let row = BasicStatsRow {
-            match_id,
-            game_creation: info
-                .get("gameCreation")
-                .and_then(|value| value.as_i64())
-                .unwrap_or(0),
-            queue_id: info
-                .get("queueId")
-                .and_then(|value| value.as_i64())
-                .unwrap_or(0),
-            champion_name: participant
-                .get("championName")
-                .and_then(|value| value.as_str())
-                .unwrap_or("")
-                .to_string(),
-            role: participant
-                .get("teamPosition")
-                .and_then(|value| value.as_str())
-                .unwrap_or("")
-                .to_string(),
-            win: participant
-                .get("win")
-                .and_then(|value| value.as_bool())
-                .map(|won| if won { 1 } else { 0 })
-                .unwrap_or(0),
-            kills: participant
-                .get("kills")
-                .and_then(|value| value.as_i64())
-                .unwrap_or(0),
-            deaths: participant
-                .get("deaths")
-                .and_then(|value| value.as_i64())
-                .unwrap_or(0),
-            assists: participant
-                .get("assists")
-                .and_then(|value| value.as_i64())
-                .unwrap_or(0),
-            cs_total: participant
-                .get("totalMinionsKilled")
-                .and_then(|value| value.as_i64())
-                .unwrap_or(0)
-                + participant
-                    .get("neutralMinionsKilled")
-                    .and_then(|value| value.as_i64())
-                    .unwrap_or(0),
-            gold_earned: participant
-                .get("goldEarned")
-                .and_then(|value| value.as_i64())
-                .unwrap_or(0),
-            game_duration: info
-                .get("gameDuration")
-                .and_then(|value| value.as_i64())
-                .unwrap_or(0),
+    println!("Shared matches: {}", rows.len());
+    println!("Together: {} games, record {}", with.len(), record(&with));
+    println!(
+        "Against:  {} games, record {}",
+        against.len(),
+        record(&against)
+    );
+    if !lane.is_empty() {
+        let mean = |value: fn(&HeadToHeadRow) -> i64| {
+            lane.iter().map(|row| value(row)).sum::<i64>() as f64 / lane.len() as f64
         };
+        println!(
+            "Lane matchups: {} games, record {}, avg diff: gold {:+.0}, cs {:+.1}, kills {:+.1}, deaths {:+.1}, damage {:+.0}",
+            lane.len(),
+            record(&lane),
+            mean(|row| row.gold_diff),
+            mean(|row| row.cs_diff),
+            mean(|row| row.kills_diff),
+            mean(|row| row.deaths_diff),
+            mean(|row| row.damage_diff)
+        );
+    }
 
-        if writer.serialize(row).is_err() {
-            continue;
+    if let Some(out_file) = out_file {
+        if let Some(parent) = out_file.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = RowWriter::create(out_file, format)?;
+        for row in &rows {
+            writer.write(row)?;
+        }
+        writer.finish()?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct DuoPartnerRow {
+    partner_puuid: String,
+    /// Riot ID of the partner in their latest shared game, when recorded.
+    partner_riot_id: Option<String>,
+    games: i64,
+    wins: i64,
+    win_rate: f64,
+    /// The player's win rate in their games without this partner.
+    win_rate_without: Option<f64>,
+    /// Most frequent `player_role/partner_role` pairing, e.g. `BOTTOM/UTILITY`.
+    top_role_pair: String,
+    top_role_pair_games: i64,
+}
+
+#[derive(Default)]
+struct PartnerTotals {
+    games: i64,
+    wins: i64,
+    last_seen: i64,
+    riot_id: Option<String>,
+    role_pairs: BTreeMap<String, i64>,
+}
+
+/// Teammates `puuid` played with at least `min_games` times, most frequent first, with the win
+/// rate together against the player's win rate without them.
+pub fn duo_report(
+    puuid: &str,
+    matches_dir: &Path,
+    min_games: usize,
+    out_file: Option<&Path>,
+    format: StatsFormat,
+) -> Result<(), Box<dyn Error>> {
+    let (mut games, mut wins) = (0i64, 0i64);
+    let mut partners: BTreeMap<String, PartnerTotals> = BTreeMap::new();
+    for_each_game(
+        &[puuid.to_string()],
+        matches_dir,
+        &GameFilter::default(),
+        |_, info, participants, player| {
+            games += 1;
+            wins += player.win as i64;
+            let teammates = participants
+                .iter()
+                .filter(|p| p.team_id == player.team_id && p.puuid != player.puuid);
+            for teammate in teammates {
+                let totals = partners.entry(teammate.puuid.clone()).or_default();
+                totals.games += 1;
+                totals.wins += player.win as i64;
+                if info.game_creation >= totals.last_seen {
+                    totals.last_seen = info.game_creation;
+                    totals.riot_id = teammate
+                        .riot_id_game_name
+                        .as_ref()
+                        .zip(teammate.riot_id_tagline.as_ref())
+                        .map(|(name, tag)| format!("{}#{}", name, tag));
+                }
+                *totals
+                    .role_pairs
+                    .entry(format!("{}/{}", player.role(), teammate.role()))
+                    .or_default() += 1;
+            }
+        },
+    );
+
+    let mut rows: Vec<DuoPartnerRow> = partners
+        .into_iter()
+        .filter(|(_, totals)| totals.games >= min_games as i64)
+        .map(|(partner_puuid, totals)| {
+            let (top_role_pair, top_role_pair_games) = totals
+                .role_pairs
+                .iter()
+                .max_by_key(|(_, count)| **count)
+                .map(|(pair, count)| (pair.clone(), *count))
+                .unwrap_or_default();
+            let games_without = games - totals.games;
+            DuoPartnerRow {
+                partner_puuid,
+                partner_riot_id: totals.riot_id,
+                games: totals.games,
+                wins: totals.wins,
+                win_rate: totals.wins as f64 / totals.games as f64,
+                win_rate_without: (games_without > 0)
+                    .then(|| (wins - totals.wins) as f64 / games_without as f64),
+                top_role_pair,
+                top_role_pair_games,
+            }
+        })
+        .collect();
+    rows.sort_by_key(|row| Reverse(row.games));
+
+    println!(
+        "{} games, win rate {:.1}%",
+        games,
+        if games > 0 {
+            wins as f64 / games as f64 * 100.0
+        } else {
+            0.0
         }
+    );
+    if rows.is_empty() {
+        println!("No teammate appears in {} or more games", min_games);
+    }
+    for row in &rows {
+        println!(
+            "{:<24} {:>4} games  {:>5.1}% together  {:>5.1}% without  {} ({} games)",
+            row.partner_riot_id.as_deref().unwrap_or(&row.partner_puuid),
+            row.games,
+            row.win_rate * 100.0,
+            row.win_rate_without.unwrap_or(0.0) * 100.0,
+            row.top_role_pair,
+            row.top_role_pair_games
+        );
     }
 
-    writer.flush()?;
+    if let Some(out_file) = out_file {
+        if let Some(parent) = out_file.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = RowWriter::create(out_file, format)?;
+        for row in &rows {
+            writer.write(row)?;
+        }
+        writer.finish()?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use serde_json::json;
+
+    fn write_match(dir: &Path, match_id: &str, game_creation: i64) {
+        let document = json!({
+            "metadata": { "matchId": match_id },
+            "info": {
+                "gameCreation": game_creation,
+                "gameDuration": 1800,
+                "queueId": 420,
+                "gameVersion": "14.19.1.1",
+                "participants": [{
+                    "puuid": "p1",
+                    "teamId": 100,
+                    "teamPosition": "MIDDLE",
+                    "championName": "Annie",
+                    "win": true,
+                }],
+            },
+        });
+        fs::write(dir.join(format!("{}.json", match_id)), document.to_string()).unwrap();
+    }
+
+    #[test]
+    fn append_only_adds_games_missing_from_the_file() {
+        let dir = TempDir::new("stats-append");
+        let matches = dir.path().join("matches");
+        fs::create_dir_all(&matches).unwrap();
+        let out = dir.path().join("stats.csv");
+        let puuids = vec!["p1".to_string()];
+        let filter = GameFilter::default();
+
+        write_match(&matches, "EUW1_1", 1_727_900_000_000);
+        extract_basic_stats(
+            &puuids,
+            &matches,
+            &filter,
+            &out,
+            StatsFormat::Csv,
+            true,
+            false,
+        )
+        .unwrap();
+        write_match(&matches, "EUW1_2", 1_727_900_100_000);
+        extract_basic_stats(
+            &puuids,
+            &matches,
+            &filter,
+            &out,
+            StatsFormat::Csv,
+            true,
+            false,
+        )
+        .unwrap();
+        let keys = existing_stats_keys(&out, StatsFormat::Csv).unwrap();
+        let rows = ReaderBuilder::new()
+            .from_path(&out)
+            .unwrap()
+            .records()
+            .count();
+
+        assert_eq!(rows, 2);
+        assert!(keys.contains(&("p1".to_string(), "EUW1_1".to_string())));
+        assert!(keys.contains(&("p1".to_string(), "EUW1_2".to_string())));
+    }
+}